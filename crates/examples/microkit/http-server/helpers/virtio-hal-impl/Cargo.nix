@@ -17,5 +17,6 @@ mk {
       sel4-externally-shared
       sel4-bounce-buffer-allocator
     ;
+    sel4-microkit = localCrates.sel4-microkit // { default-features = false; };
   };
 }
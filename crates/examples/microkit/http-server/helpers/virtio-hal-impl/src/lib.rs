@@ -7,13 +7,14 @@
 #![no_std]
 
 use core::alloc::Layout;
-use core::ptr::{self, NonNull};
+use core::ptr::NonNull;
 
 use virtio_drivers::{BufferDirection, Hal, PhysAddr, PAGE_SIZE};
 
 use sel4_bounce_buffer_allocator::{Basic, BounceBufferAllocator};
-use sel4_externally_shared::{ExternallySharedRef, ExternallySharedRefExt};
+use sel4_externally_shared::ExternallySharedRef;
 use sel4_immediate_sync_once_cell::ImmediateSyncOnceCell;
+use sel4_microkit::MemoryRegion;
 use sel4_sync::{lock_api::Mutex, GenericRawMutex, PanickingMutexSyncOps};
 
 static GLOBAL_STATE: ImmediateSyncOnceCell<Mutex<GenericRawMutex<PanickingMutexSyncOps>, State>> =
@@ -21,46 +22,35 @@ static GLOBAL_STATE: ImmediateSyncOnceCell<Mutex<GenericRawMutex<PanickingMutexS
 
 struct State {
     dma_region: ExternallySharedRef<'static, [u8]>,
-    dma_region_paddr: usize,
+    dma_region_info: MemoryRegion,
     bounce_buffer_allocator: BounceBufferAllocator<Basic>,
 }
 
 impl State {
     fn offset_to_paddr(&self, offset: usize) -> PhysAddr {
-        self.dma_region_paddr.checked_add(offset).unwrap()
+        self.dma_region_info.offset_to_paddr(offset).unwrap()
     }
 
     fn paddr_to_offset(&self, paddr: PhysAddr) -> usize {
-        paddr.checked_sub(self.dma_region_paddr).unwrap()
+        self.dma_region_info.paddr_to_offset(paddr).unwrap()
     }
 }
 
 pub struct HalImpl;
 
 impl HalImpl {
-    pub fn init(dma_region_size: usize, dma_region_vaddr: usize, dma_region_paddr: usize) {
-        let dma_region_ptr = NonNull::new(ptr::slice_from_raw_parts_mut(
-            dma_region_vaddr as *mut _,
-            dma_region_size,
-        ))
-        .unwrap();
-
-        let dma_region = unsafe { ExternallySharedRef::new(dma_region_ptr) };
-
-        let max_alignment = 1
-            << dma_region_vaddr
-                .trailing_zeros()
-                .min(dma_region_paddr.trailing_zeros());
+    pub fn init(dma_region: MemoryRegion) {
+        assert!(dma_region.paddr().is_some());
 
         let bounce_buffer_allocator =
-            BounceBufferAllocator::new(Basic::new(dma_region_size), max_alignment);
+            BounceBufferAllocator::new(Basic::new(dma_region.size()), dma_region.max_alignment());
 
         GLOBAL_STATE
             .set(Mutex::const_new(
                 GenericRawMutex::new(PanickingMutexSyncOps::new()),
                 State {
-                    dma_region,
-                    dma_region_paddr,
+                    dma_region: unsafe { dma_region.as_externally_shared() },
+                    dma_region_info: dma_region,
                     bounce_buffer_allocator,
                 },
             ))
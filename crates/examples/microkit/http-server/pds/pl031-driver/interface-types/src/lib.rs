@@ -6,16 +6,15 @@
 
 #![no_std]
 
-use serde::{Deserialize, Serialize};
+use sel4_microkit::MessageLabel;
 
 pub type Seconds = u32;
 
-#[derive(Debug, Serialize, Deserialize)]
-pub enum Request {
-    Now,
-}
+/// Requests are identified by their labels. Their arguments and results fit in the message
+/// registers which are passed in CPU registers, so neither side touches its IPC buffer.
+pub mod labels {
+    use super::MessageLabel;
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct NowResponse {
-    pub unix_time: Seconds,
+    /// No arguments. Replies with the current Unix time in seconds, in a single message register.
+    pub const NOW: MessageLabel = 1;
 }
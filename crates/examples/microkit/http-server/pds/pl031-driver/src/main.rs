@@ -8,7 +8,8 @@
 #![no_main]
 
 use sel4_microkit::{
-    memory_region_symbol, protection_domain, Channel, Handler, Infallible, MessageInfo,
+    memory_region_symbol, protection_domain, Channel, FastMessageRegisters, Handler, Infallible,
+    MessageInfo,
 };
 use sel4_microkit_message::MessageInfoExt as _;
 
@@ -32,23 +33,22 @@ struct HandlerImpl {
 impl Handler for HandlerImpl {
     type Error = Infallible;
 
-    fn protected(
+    fn protected_with_mrs(
         &mut self,
         channel: Channel,
         msg_info: MessageInfo,
-    ) -> Result<MessageInfo, Self::Error> {
+        _msg: FastMessageRegisters,
+    ) -> Result<(MessageInfo, FastMessageRegisters), Self::Error> {
         Ok(match channel {
-            channels::CLIENT => match msg_info.recv_using_postcard::<Request>() {
-                Ok(req) => match req {
-                    Request::Now => {
-                        let now = self.driver.now();
-                        MessageInfo::send_using_postcard(NowResponse {
-                            unix_time: now.as_secs().try_into().unwrap(),
-                        })
-                        .unwrap()
-                    }
-                },
-                Err(_) => MessageInfo::send_unspecified_error(),
+            channels::CLIENT => match (msg_info.label(), msg_info.count()) {
+                (labels::NOW, 0) => {
+                    let now = self.driver.now();
+                    let now = Seconds::try_from(now.as_secs()).unwrap();
+                    let mut mrs = FastMessageRegisters::default();
+                    mrs[0] = now.into();
+                    (MessageInfo::new(0, 1), mrs)
+                }
+                _ => (MessageInfo::send_unspecified_error(), Default::default()),
             },
             _ => {
                 unreachable!()
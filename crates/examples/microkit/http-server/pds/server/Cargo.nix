@@ -56,7 +56,7 @@ mk {
       ];
     };
 
-    sel4-microkit = localCrates.sel4-microkit // { default-features = false; features = [ "alloc" ]; };
+    sel4-microkit = localCrates.sel4-microkit // { default-features = false; features = [ "async" ]; };
     sel4-externally-shared = localCrates.sel4-externally-shared // { features = [ "unstable" ]; };
 
     microkit-http-server-example-server-core = localCrates.microkit-http-server-example-server-core // {
@@ -41,7 +41,7 @@ mod timer_client;
 
 use block_client::BlockClient;
 use config::channels;
-use handler::HandlerImpl;
+use handler::new_handler;
 use net_client::NetClient;
 use rtc_client::RTCClient;
 use timer_client::TimerClient;
@@ -159,10 +159,7 @@ fn init() -> impl Handler {
         )
     };
 
-    HandlerImpl::new(
-        channels::TIMER_DRIVER,
-        channels::NET_DRIVER,
-        channels::BLOCK_DRIVER,
+    new_handler(
         timer_client,
         net_device,
         net_config,
@@ -5,7 +5,6 @@
 //
 
 use sel4_microkit::MessageInfo;
-use sel4_microkit_message::MessageInfoExt as _;
 
 use microkit_http_server_example_pl031_driver_interface_types::*;
 
@@ -19,12 +18,10 @@ impl RTCClient {
     }
 
     pub fn now(&self) -> Seconds {
-        let req = Request::Now;
-        let resp: NowResponse = self
+        let (resp_info, resp) = self
             .channel
-            .pp_call(MessageInfo::send_using_postcard(req).unwrap())
-            .recv_using_postcard()
-            .unwrap();
-        resp.unix_time
+            .pp_call_with_mrs(MessageInfo::new(labels::NOW, 0), []);
+        assert_eq!((resp_info.label(), resp_info.count()), (0, 1));
+        resp[0].try_into().unwrap()
     }
 }
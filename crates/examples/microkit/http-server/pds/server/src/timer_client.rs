@@ -19,28 +19,32 @@ impl TimerClient {
     }
 
     pub fn now(&self) -> Microseconds {
-        let req = Request::Now;
-        let resp: NowResponse = self
+        let (resp_info, resp) = self
             .channel
-            .pp_call(MessageInfo::send_using_postcard(req).unwrap())
-            .recv_using_postcard()
-            .unwrap();
-        resp.micros
+            .pp_call_with_mrs(MessageInfo::new(labels::NOW, 0), []);
+        assert_eq!(
+            (resp_info.label(), resp_info.count()),
+            (0, MICROSECONDS_NUM_MRS)
+        );
+        decode_microseconds(&resp[..MICROSECONDS_NUM_MRS])
     }
 
     pub fn set_timeout(&self, relative_micros: Microseconds) {
-        let req = Request::SetTimeout { relative_micros };
         self.channel
-            .pp_call(MessageInfo::send_using_postcard(req).unwrap())
+            .pp_call_with_mrs(
+                MessageInfo::new(labels::SET_TIMEOUT, MICROSECONDS_NUM_MRS),
+                encode_microseconds(relative_micros),
+            )
+            .0
             .recv_empty()
             .unwrap();
     }
 
     #[allow(dead_code)]
     pub fn clear_timeout(&self) {
-        let req = Request::ClearTimeout;
         self.channel
-            .pp_call(MessageInfo::send_using_postcard(req).unwrap())
+            .pp_call_with_mrs(MessageInfo::new(labels::CLEAR_TIMEOUT, 0), [])
+            .0
             .recv_empty()
             .unwrap();
     }
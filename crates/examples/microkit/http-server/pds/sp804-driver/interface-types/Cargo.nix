@@ -4,11 +4,11 @@
 # SPDX-License-Identifier: BSD-2-Clause
 #
 
-{ mk, serdeWith }:
+{ mk, localCrates }:
 
 mk {
   package.name = "microkit-http-server-example-sp804-driver-interface-types";
   dependencies = {
-    serde = serdeWith [];
+    sel4-microkit = localCrates.sel4-microkit // { default-features = false; };
   };
 }
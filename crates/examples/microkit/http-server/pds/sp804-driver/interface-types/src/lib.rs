@@ -6,18 +6,46 @@
 
 #![no_std]
 
-use serde::{Deserialize, Serialize};
+use core::mem;
+
+use sel4_microkit::{MessageLabel, MessageRegisterValue};
 
 pub type Microseconds = u64;
 
-#[derive(Debug, Serialize, Deserialize)]
-pub enum Request {
-    Now,
-    SetTimeout { relative_micros: Microseconds },
-    ClearTimeout,
+/// Requests are identified by their labels. Their arguments and results fit in the message
+/// registers which are passed in CPU registers, so neither side touches its IPC buffer.
+///
+/// [`Microseconds`] values span [`MICROSECONDS_NUM_MRS`] message registers, which depends on the
+/// word size. See [`encode_microseconds`] and [`decode_microseconds`].
+pub mod labels {
+    use super::MessageLabel;
+
+    /// No arguments. Replies with the current time in microseconds.
+    pub const NOW: MessageLabel = 1;
+
+    /// Takes a timeout in microseconds, relative to now. Replies with an empty message.
+    pub const SET_TIMEOUT: MessageLabel = 2;
+
+    /// No arguments. Replies with an empty message.
+    pub const CLEAR_TIMEOUT: MessageLabel = 3;
+}
+
+/// Number of message registers spanned by a [`Microseconds`] value.
+pub const MICROSECONDS_NUM_MRS: usize =
+    mem::size_of::<Microseconds>().div_ceil(mem::size_of::<MessageRegisterValue>());
+
+/// Splits `value` into message registers, least significant word first.
+pub fn encode_microseconds(value: Microseconds) -> [MessageRegisterValue; MICROSECONDS_NUM_MRS] {
+    let mut mrs = [0; MICROSECONDS_NUM_MRS];
+    for (i, mr) in mrs.iter_mut().enumerate() {
+        *mr = (value >> (i * mem::size_of::<MessageRegisterValue>() * 8)) as MessageRegisterValue;
+    }
+    mrs
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct NowResponse {
-    pub micros: Microseconds,
+/// Inverse of [`encode_microseconds`].
+pub fn decode_microseconds(mrs: &[MessageRegisterValue]) -> Microseconds {
+    mrs.iter().enumerate().fold(0, |acc, (i, mr)| {
+        acc | ((*mr as Microseconds) << (i * mem::size_of::<MessageRegisterValue>() * 8))
+    })
 }
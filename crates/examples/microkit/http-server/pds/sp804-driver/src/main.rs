@@ -10,7 +10,8 @@
 use core::time::Duration;
 
 use sel4_microkit::{
-    memory_region_symbol, protection_domain, Channel, Handler, Infallible, MessageInfo,
+    memory_region_symbol, protection_domain, Channel, FastMessageRegisters, Handler, Infallible,
+    MessageInfo,
 };
 use sel4_microkit_message::MessageInfoExt as _;
 
@@ -53,32 +54,31 @@ impl Handler for HandlerImpl {
         Ok(())
     }
 
-    fn protected(
+    fn protected_with_mrs(
         &mut self,
         channel: Channel,
         msg_info: MessageInfo,
-    ) -> Result<MessageInfo, Self::Error> {
+        msg: FastMessageRegisters,
+    ) -> Result<(MessageInfo, FastMessageRegisters), Self::Error> {
         Ok(match channel {
-            channels::CLIENT => match msg_info.recv_using_postcard::<Request>() {
-                Ok(req) => match req {
-                    Request::Now => {
-                        let now = self.driver.now();
-                        MessageInfo::send_using_postcard(NowResponse {
-                            micros: now.as_micros().try_into().unwrap(),
-                        })
-                        .unwrap()
-                    }
-                    Request::SetTimeout { relative_micros } => {
-                        self.driver
-                            .set_timeout(Duration::from_micros(relative_micros));
-                        MessageInfo::send_empty()
-                    }
-                    Request::ClearTimeout => {
-                        self.driver.clear_timeout();
-                        MessageInfo::send_empty()
-                    }
-                },
-                Err(_) => MessageInfo::send_unspecified_error(),
+            channels::CLIENT => match (msg_info.label(), msg_info.count()) {
+                (labels::NOW, 0) => {
+                    let now = self.driver.now().as_micros().try_into().unwrap();
+                    let mut mrs = FastMessageRegisters::default();
+                    mrs[..MICROSECONDS_NUM_MRS].copy_from_slice(&encode_microseconds(now));
+                    (MessageInfo::new(0, MICROSECONDS_NUM_MRS), mrs)
+                }
+                (labels::SET_TIMEOUT, MICROSECONDS_NUM_MRS) => {
+                    let relative_micros = decode_microseconds(&msg[..MICROSECONDS_NUM_MRS]);
+                    self.driver
+                        .set_timeout(Duration::from_micros(relative_micros));
+                    (MessageInfo::send_empty(), Default::default())
+                }
+                (labels::CLEAR_TIMEOUT, 0) => {
+                    self.driver.clear_timeout();
+                    (MessageInfo::send_empty(), Default::default())
+                }
+                _ => (MessageInfo::send_unspecified_error(), Default::default()),
             },
             _ => {
                 unreachable!()
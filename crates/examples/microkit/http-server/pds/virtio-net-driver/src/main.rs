@@ -19,7 +19,8 @@ use virtio_drivers::{
 
 use sel4_externally_shared::{ExternallySharedRef, ExternallySharedRefExt};
 use sel4_microkit::{
-    memory_region_symbol, protection_domain, var, Channel, Handler, Infallible, MessageInfo,
+    memory_region, memory_region_symbol, protection_domain, var, Channel, Handler, Infallible,
+    MessageInfo,
 };
 use sel4_microkit_message::MessageInfoExt as _;
 use sel4_shared_ring_buffer::{roles::Use, RingBuffers};
@@ -38,11 +39,11 @@ const NET_BUFFER_LEN: usize = 2048;
     heap_size = 512 * 1024,
 )]
 fn init() -> HandlerImpl {
-    HalImpl::init(
-        config::VIRTIO_NET_DRIVER_DMA_SIZE,
-        *var!(virtio_net_driver_dma_vaddr: usize = 0),
-        *var!(virtio_net_driver_dma_paddr: usize = 0),
-    );
+    HalImpl::init(memory_region!(
+        virtio_net_driver_dma_vaddr,
+        n = config::VIRTIO_NET_DRIVER_DMA_SIZE,
+        paddr = virtio_net_driver_dma_paddr,
+    ));
 
     let mut dev = {
         let header = NonNull::new(
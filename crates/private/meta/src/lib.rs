@@ -19,8 +19,8 @@
 //!   used by all targets (i.e. in all of: application code, build scripts, and build-time tools).
 //! - [`sel4_platform_info`]: Constants corresponding to the contents of `platform_info.h`. Can be
 //!   used by all targets.
-//! - [`sel4_sync`]: Synchronization constructs using seL4 IPC. Currently only supports
-//!   notification-based mutexes.
+//! - [`sel4_sync`]: Synchronization constructs using seL4 IPC. Supports notification-based mutexes,
+//!   reader-writer locks, semaphores, condition variables, and barriers.
 //! - [`sel4_logging`]: Log implementation for the [`log`] crate.
 //! - [`sel4_externally_shared`]: Abstractions for interacting with data structures in shared
 //!   memory.
@@ -0,0 +1,17 @@
+#
+# Copyright 2023, Colias Group, LLC
+#
+# SPDX-License-Identifier: BSD-2-Clause
+#
+
+{ mk, localCrates, versions }:
+
+mk {
+  package.name = "sel4-arm-vmm";
+  dependencies = {
+    inherit (versions) log;
+    inherit (localCrates)
+      sel4
+    ;
+  };
+}
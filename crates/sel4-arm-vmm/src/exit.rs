@@ -0,0 +1,90 @@
+//
+// Copyright 2023, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use sel4::Fault;
+
+use crate::syndrome::{DataAbort, DecodedSyndrome, Syndrome};
+
+/// The reason for which a guest vCPU exited to its VMM, decoded from a [`sel4::Fault`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VmExit {
+    /// A stage 2 data abort on a guest physical address which is not backed by memory, which is
+    /// typically an access to an emulated device.
+    Mmio {
+        addr: usize,
+        data_abort: DataAbort,
+    },
+    Hvc {
+        imm: u16,
+    },
+    Smc {
+        imm: u16,
+    },
+    Wfx {
+        is_wfe: bool,
+    },
+    /// The guest has completed an interrupt injected into list register `idx`.
+    VgicMaintenance {
+        idx: Option<usize>,
+    },
+    /// A virtual PPI (for example, the virtual timer) has fired for the vCPU.
+    Vppi {
+        irq: usize,
+    },
+    /// A stage 2 fault which could not be decoded as an MMIO access.
+    UnhandledVmFault {
+        addr: usize,
+        syndrome: Syndrome,
+    },
+    /// A trap whose syndrome this crate does not decode.
+    UnhandledSyndrome(Syndrome),
+    /// A fault which is not specific to virtualization, such as a `CapFault`.
+    Other(Fault),
+}
+
+impl VmExit {
+    pub fn decode(fault: &Fault) -> Self {
+        match fault {
+            Fault::VMFault(f) => {
+                let addr = f.addr().try_into().unwrap();
+                let syndrome = Syndrome::new(f.fsr());
+                match syndrome.decode() {
+                    DecodedSyndrome::DataAbort(data_abort) if data_abort.is_valid() => {
+                        Self::Mmio { addr, data_abort }
+                    }
+                    _ => Self::UnhandledVmFault { addr, syndrome },
+                }
+            }
+            Fault::VCPUFault(f) => {
+                let syndrome = Syndrome::new(f.hsr());
+                match syndrome.decode() {
+                    DecodedSyndrome::Hvc { imm } => Self::Hvc { imm },
+                    DecodedSyndrome::Smc { imm } => Self::Smc { imm },
+                    DecodedSyndrome::Wfx { is_wfe } => Self::Wfx { is_wfe },
+                    _ => Self::UnhandledSyndrome(syndrome),
+                }
+            }
+            Fault::VGICMaintenance(f) => Self::VgicMaintenance {
+                idx: f.idx().map(|idx| idx.try_into().unwrap()),
+            },
+            Fault::VPPIEvent(f) => Self::Vppi {
+                irq: f.irq().try_into().unwrap(),
+            },
+            _ => Self::Other(fault.clone()),
+        }
+    }
+
+    /// Whether the guest's PC must be advanced past the trapping instruction before resuming it.
+    ///
+    /// The preferred return address of an `HVC` is already the following instruction, whereas
+    /// trapped `SMC`s, `WFx`s, and data aborts return to the trapping instruction itself.
+    pub fn skips_instruction(&self) -> bool {
+        matches!(
+            self,
+            Self::Mmio { .. } | Self::Smc { .. } | Self::Wfx { .. }
+        )
+    }
+}
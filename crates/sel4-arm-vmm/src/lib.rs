@@ -0,0 +1,43 @@
+//
+// Copyright 2023, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+//! Building blocks for Arm virtual machine monitors.
+//!
+//! This crate decodes the syndromes carried by the `VMFault` and `VCPUFault` faults of guest vCPUs
+//! into [`VmExit`]s, emulates MMIO accesses using [`MmioDevice`]s, and models a GICv2 distributor
+//! with [`VirtualGicDistributor`]. [`Vm`] ties these together into a minimal event loop for a
+//! single-vCPU AArch64 guest, whose policy is provided by a [`VmmHandler`].
+//!
+//! Everything other than the syndrome and MMIO abstractions requires a kernel configured with
+//! `ARM_HYPERVISOR_SUPPORT`.
+
+#![no_std]
+
+extern crate alloc;
+
+mod mmio;
+mod syndrome;
+
+pub use mmio::{MmioAccess, MmioAccessKind, MmioBus, MmioDevice, MmioResult, Width};
+pub use syndrome::{DataAbort, DecodedSyndrome, ExceptionClass, Syndrome, SysRegAccess};
+
+sel4::sel4_cfg_if! {
+    if #[cfg(ARM_HYPERVISOR_SUPPORT)] {
+        mod exit;
+        mod vgic;
+
+        pub use exit::VmExit;
+        pub use vgic::{VirtualGicDistributor, GICD_SIZE, NUM_IRQS, NUM_LIST_REGISTERS};
+    }
+}
+
+sel4::sel4_cfg_if! {
+    if #[cfg(all(ARCH_AARCH64, ARM_HYPERVISOR_SUPPORT))] {
+        mod vm;
+
+        pub use vm::{Error, Vm, VmmHandler, PSCI_NOT_SUPPORTED};
+    }
+}
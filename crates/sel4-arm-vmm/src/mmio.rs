@@ -0,0 +1,202 @@
+//
+// Copyright 2023, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+/// The width of a guest memory access.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub enum Width {
+    Byte,
+    HalfWord,
+    Word,
+    DoubleWord,
+}
+
+impl Width {
+    pub fn bytes(&self) -> usize {
+        match self {
+            Self::Byte => 1,
+            Self::HalfWord => 2,
+            Self::Word => 4,
+            Self::DoubleWord => 8,
+        }
+    }
+
+    pub fn bits(&self) -> u32 {
+        (self.bytes() * 8) as u32
+    }
+
+    pub fn truncate(&self, value: u64) -> u64 {
+        match self {
+            Self::DoubleWord => value,
+            _ => value & ((1 << self.bits()) - 1),
+        }
+    }
+}
+
+/// A single guest access to an emulated device register.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct MmioAccess {
+    /// Offset of the access relative to the start of the device's region.
+    pub offset: usize,
+    pub width: Width,
+    pub kind: MmioAccessKind,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum MmioAccessKind {
+    Read,
+    Write(u64),
+}
+
+/// Outcome of an emulated access.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum MmioResult {
+    /// The access was handled. For reads, this carries the value to be returned to the guest.
+    Handled(u64),
+    /// The access did not correspond to any register of the device.
+    Unhandled,
+}
+
+/// An emulated memory-mapped device.
+pub trait MmioDevice {
+    fn read(&mut self, offset: usize, width: Width) -> MmioResult;
+
+    fn write(&mut self, offset: usize, width: Width, value: u64) -> MmioResult;
+
+    fn access(&mut self, access: &MmioAccess) -> MmioResult {
+        match access.kind {
+            MmioAccessKind::Read => self.read(access.offset, access.width),
+            MmioAccessKind::Write(value) => {
+                self.write(access.offset, access.width, access.width.truncate(value))
+            }
+        }
+    }
+}
+
+/// A collection of emulated devices, each occupying a range of guest physical addresses.
+pub struct MmioBus {
+    devices: Vec<(Range<usize>, Box<dyn MmioDevice>)>,
+}
+
+impl MmioBus {
+    pub fn new() -> Self {
+        Self {
+            devices: Vec::new(),
+        }
+    }
+
+    /// Panics if `range` overlaps with that of a device which has already been added.
+    pub fn add_device(&mut self, range: Range<usize>, device: Box<dyn MmioDevice>) {
+        assert!(!self
+            .devices
+            .iter()
+            .any(|(other, _)| range.start < other.end && other.start < range.end));
+        self.devices.push((range, device));
+    }
+
+    /// Dispatches an access at guest physical address `addr` to the device whose range contains
+    /// it. Returns `None` if no such device exists.
+    pub fn access(
+        &mut self,
+        addr: usize,
+        width: Width,
+        kind: MmioAccessKind,
+    ) -> Option<MmioResult> {
+        self.devices
+            .iter_mut()
+            .find(|(range, _)| range.contains(&addr))
+            .map(|(range, device)| {
+                device.access(&MmioAccess {
+                    offset: addr - range.start,
+                    width,
+                    kind,
+                })
+            })
+    }
+}
+
+impl Default for MmioBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use alloc::rc::Rc;
+    use core::cell::RefCell;
+
+    type LastWrite = Rc<RefCell<Option<(usize, u64)>>>;
+
+    struct Scratch {
+        last_write: LastWrite,
+    }
+
+    impl MmioDevice for Scratch {
+        fn read(&mut self, offset: usize, _width: Width) -> MmioResult {
+            match offset {
+                0 => MmioResult::Handled(0xdead_beef),
+                _ => MmioResult::Unhandled,
+            }
+        }
+
+        fn write(&mut self, offset: usize, _width: Width, value: u64) -> MmioResult {
+            *self.last_write.borrow_mut() = Some((offset, value));
+            MmioResult::Handled(0)
+        }
+    }
+
+    fn bus() -> (MmioBus, LastWrite) {
+        let last_write = Rc::new(RefCell::new(None));
+        let mut bus = MmioBus::new();
+        bus.add_device(
+            0x1000..0x2000,
+            Box::new(Scratch {
+                last_write: last_write.clone(),
+            }),
+        );
+        (bus, last_write)
+    }
+
+    #[test]
+    fn truncate() {
+        assert_eq!(Width::Byte.truncate(0x1234), 0x34);
+        assert_eq!(Width::HalfWord.truncate(0x12_3456), 0x3456);
+        assert_eq!(Width::Word.truncate(u64::MAX), 0xffff_ffff);
+        assert_eq!(Width::DoubleWord.truncate(u64::MAX), u64::MAX);
+    }
+
+    #[test]
+    fn dispatch() {
+        let (mut bus, last_write) = bus();
+        assert_eq!(
+            bus.access(0x1000, Width::Word, MmioAccessKind::Read),
+            Some(MmioResult::Handled(0xdead_beef))
+        );
+        assert_eq!(
+            bus.access(0x1004, Width::Word, MmioAccessKind::Read),
+            Some(MmioResult::Unhandled)
+        );
+        assert_eq!(bus.access(0x2000, Width::Word, MmioAccessKind::Read), None);
+        assert_eq!(
+            bus.access(0x1010, Width::Byte, MmioAccessKind::Write(0x1ff)),
+            Some(MmioResult::Handled(0))
+        );
+        assert_eq!(*last_write.borrow(), Some((0x10, 0xff)));
+    }
+
+    #[test]
+    #[should_panic]
+    fn overlap() {
+        let (mut bus, last_write) = bus();
+        bus.add_device(0x1800..0x2800, Box::new(Scratch { last_write }));
+    }
+}
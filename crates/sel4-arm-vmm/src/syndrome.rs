@@ -0,0 +1,287 @@
+//
+// Copyright 2023, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use crate::mmio::Width;
+
+/// The exception class field (`EC`) of an `ESR_EL2`/`HSR` value.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ExceptionClass {
+    Unknown,
+    Wfx,
+    Hvc32,
+    Smc32,
+    Hvc64,
+    Smc64,
+    SysReg64,
+    InstructionAbortLowerEl,
+    DataAbortLowerEl,
+    Other(u8),
+}
+
+impl ExceptionClass {
+    pub fn from_bits(bits: u8) -> Self {
+        match bits {
+            0x00 => Self::Unknown,
+            0x01 => Self::Wfx,
+            0x12 => Self::Hvc32,
+            0x13 => Self::Smc32,
+            0x16 => Self::Hvc64,
+            0x17 => Self::Smc64,
+            0x18 => Self::SysReg64,
+            0x20 => Self::InstructionAbortLowerEl,
+            0x24 => Self::DataAbortLowerEl,
+            _ => Self::Other(bits),
+        }
+    }
+}
+
+/// A raw syndrome value, as found in `ESR_EL2` (or `HSR` on AArch32).
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Syndrome(u32);
+
+impl Syndrome {
+    const EC_SHIFT: u32 = 26;
+    const IL_BIT: u32 = 25;
+    const ISS_MASK: u32 = (1 << 25) - 1;
+
+    pub fn new(bits: sel4::Word) -> Self {
+        Self(bits as u32)
+    }
+
+    pub fn bits(&self) -> u32 {
+        self.0
+    }
+
+    pub fn exception_class(&self) -> ExceptionClass {
+        ExceptionClass::from_bits((self.0 >> Self::EC_SHIFT) as u8)
+    }
+
+    /// Whether the trapped instruction was 32 bits wide (as opposed to a 16-bit T32 instruction).
+    pub fn is_32_bit_instruction(&self) -> bool {
+        self.0 & (1 << Self::IL_BIT) != 0
+    }
+
+    /// The size in bytes of the trapped instruction, by which the guest's PC must be advanced in
+    /// order to skip it.
+    pub fn instruction_len(&self) -> usize {
+        if self.is_32_bit_instruction() {
+            4
+        } else {
+            2
+        }
+    }
+
+    pub fn iss(&self) -> u32 {
+        self.0 & Self::ISS_MASK
+    }
+
+    pub fn decode(&self) -> DecodedSyndrome {
+        match self.exception_class() {
+            ExceptionClass::DataAbortLowerEl => {
+                DecodedSyndrome::DataAbort(DataAbort::new(self.iss()))
+            }
+            ExceptionClass::Hvc32 | ExceptionClass::Hvc64 => DecodedSyndrome::Hvc {
+                imm: self.iss() as u16,
+            },
+            ExceptionClass::Smc32 | ExceptionClass::Smc64 => DecodedSyndrome::Smc {
+                imm: self.iss() as u16,
+            },
+            ExceptionClass::Wfx => DecodedSyndrome::Wfx {
+                is_wfe: self.iss() & 1 != 0,
+            },
+            ExceptionClass::SysReg64 => DecodedSyndrome::SysReg(SysRegAccess::new(self.iss())),
+            _ => DecodedSyndrome::Other(*self),
+        }
+    }
+}
+
+/// A [`Syndrome`] decoded according to its exception class.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum DecodedSyndrome {
+    DataAbort(DataAbort),
+    Hvc { imm: u16 },
+    Smc { imm: u16 },
+    Wfx { is_wfe: bool },
+    SysReg(SysRegAccess),
+    Other(Syndrome),
+}
+
+/// The instruction specific syndrome of a data abort taken from a lower exception level.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct DataAbort(u32);
+
+impl DataAbort {
+    const ISV_BIT: u32 = 24;
+    const SAS_SHIFT: u32 = 22;
+    const SSE_BIT: u32 = 21;
+    const SRT_SHIFT: u32 = 16;
+    const SF_BIT: u32 = 15;
+    const WNR_BIT: u32 = 6;
+
+    pub fn new(iss: u32) -> Self {
+        Self(iss)
+    }
+
+    /// Whether the syndrome holds a valid instruction decoding (`ISV`). If not, the faulting
+    /// instruction must be decoded by hand, which this crate does not support.
+    pub fn is_valid(&self) -> bool {
+        self.bit(Self::ISV_BIT)
+    }
+
+    pub fn width(&self) -> Width {
+        match (self.0 >> Self::SAS_SHIFT) & 0b11 {
+            0b00 => Width::Byte,
+            0b01 => Width::HalfWord,
+            0b10 => Width::Word,
+            0b11 => Width::DoubleWord,
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn is_sign_extended(&self) -> bool {
+        self.bit(Self::SSE_BIT)
+    }
+
+    /// The general purpose register transferred by the faulting instruction. On AArch64, `31`
+    /// denotes the zero register.
+    pub fn register(&self) -> usize {
+        ((self.0 >> Self::SRT_SHIFT) & 0b1_1111) as usize
+    }
+
+    /// Whether the register is 64 bits wide (as opposed to 32).
+    pub fn is_64_bit_register(&self) -> bool {
+        self.bit(Self::SF_BIT)
+    }
+
+    pub fn is_write(&self) -> bool {
+        self.bit(Self::WNR_BIT)
+    }
+
+    /// Sign- or zero-extends a value read from an emulated device according to this syndrome.
+    pub fn extend_read_value(&self, value: u64) -> u64 {
+        let value = self.width().truncate(value);
+        let value = if self.is_sign_extended() {
+            let shift = 64 - self.width().bits();
+            (((value << shift) as i64) >> shift) as u64
+        } else {
+            value
+        };
+        if self.is_64_bit_register() {
+            value
+        } else {
+            value & u64::from(u32::MAX)
+        }
+    }
+
+    fn bit(&self, i: u32) -> bool {
+        self.0 & (1 << i) != 0
+    }
+}
+
+/// The instruction specific syndrome of a trapped `MSR`/`MRS` instruction.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct SysRegAccess(u32);
+
+impl SysRegAccess {
+    pub fn new(iss: u32) -> Self {
+        Self(iss)
+    }
+
+    pub fn is_read(&self) -> bool {
+        self.0 & 1 != 0
+    }
+
+    pub fn register(&self) -> usize {
+        ((self.0 >> 5) & 0b1_1111) as usize
+    }
+
+    /// The `(op0, op1, crn, crm, op2)` encoding of the accessed system register.
+    pub fn encoding(&self) -> (u8, u8, u8, u8, u8) {
+        (
+            ((self.0 >> 20) & 0b11) as u8,
+            ((self.0 >> 14) & 0b111) as u8,
+            ((self.0 >> 10) & 0b1111) as u8,
+            ((self.0 >> 1) & 0b1111) as u8,
+            ((self.0 >> 17) & 0b111) as u8,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IL: u32 = 1 << 25;
+
+    fn syndrome(ec: u32, iss: u32) -> Syndrome {
+        Syndrome::new(((ec << 26) | IL | iss).into())
+    }
+
+    #[test]
+    fn data_abort() {
+        // ISV, SAS = word, SRT = x3, WnR
+        let iss = (1 << 24) | (0b10 << 22) | (3 << 16) | (1 << 6);
+        let s = syndrome(0x24, iss);
+        assert_eq!(s.exception_class(), ExceptionClass::DataAbortLowerEl);
+        assert_eq!(s.instruction_len(), 4);
+        let DecodedSyndrome::DataAbort(data_abort) = s.decode() else {
+            panic!()
+        };
+        assert!(data_abort.is_valid());
+        assert_eq!(data_abort.width(), Width::Word);
+        assert_eq!(data_abort.register(), 3);
+        assert!(data_abort.is_write());
+        assert!(!data_abort.is_64_bit_register());
+    }
+
+    #[test]
+    fn extend_read_value() {
+        // SAS = byte, SSE
+        let narrow = DataAbort::new((1 << 24) | (1 << 21));
+        assert_eq!(narrow.extend_read_value(0x1_80), 0xffff_ff80);
+        assert_eq!(narrow.extend_read_value(0x7f), 0x7f);
+        let wide = DataAbort::new((1 << 24) | (1 << 21) | (1 << 15));
+        assert_eq!(wide.extend_read_value(0x80), 0xffff_ffff_ffff_ff80);
+        // SAS = halfword, no SSE
+        let unsigned = DataAbort::new((1 << 24) | (0b01 << 22) | (1 << 15));
+        assert_eq!(unsigned.extend_read_value(0x1_8000), 0x8000);
+    }
+
+    #[test]
+    fn hvc_smc_wfx() {
+        assert_eq!(
+            syndrome(0x16, 0x1234).decode(),
+            DecodedSyndrome::Hvc { imm: 0x1234 }
+        );
+        assert_eq!(syndrome(0x13, 0).decode(), DecodedSyndrome::Smc { imm: 0 });
+        assert_eq!(
+            syndrome(0x01, 1).decode(),
+            DecodedSyndrome::Wfx { is_wfe: true }
+        );
+        let s = Syndrome::new((0x01u32 << 26).into());
+        assert_eq!(s.instruction_len(), 2);
+        assert_eq!(s.decode(), DecodedSyndrome::Wfx { is_wfe: false });
+    }
+
+    #[test]
+    fn sys_reg() {
+        // MRS x5, SCTLR_EL1: op0 = 3, op1 = 0, CRn = 1, CRm = 0, op2 = 0
+        let iss = (3 << 20) | (1 << 10) | (5 << 5) | 1;
+        let DecodedSyndrome::SysReg(access) = syndrome(0x18, iss).decode() else {
+            panic!()
+        };
+        assert!(access.is_read());
+        assert_eq!(access.register(), 5);
+        assert_eq!(access.encoding(), (3, 0, 1, 0, 0));
+    }
+
+    #[test]
+    fn other() {
+        let s = syndrome(0x3c, 0);
+        assert_eq!(s.exception_class(), ExceptionClass::Other(0x3c));
+        assert_eq!(s.decode(), DecodedSyndrome::Other(s));
+    }
+}
@@ -0,0 +1,381 @@
+//
+// Copyright 2023, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use crate::mmio::{MmioDevice, MmioResult, Width};
+
+/// Number of interrupt lines modeled by [`VirtualGicDistributor`], including SGIs and PPIs.
+pub const NUM_IRQS: usize = 256;
+
+/// Number of list registers assumed to be implemented by the physical GIC's virtual CPU
+/// interface. Four is the architectural minimum.
+pub const NUM_LIST_REGISTERS: usize = 4;
+
+const NUM_SGIS: usize = 16;
+
+const GICD_CTLR: usize = 0x000;
+const GICD_TYPER: usize = 0x004;
+const GICD_IIDR: usize = 0x008;
+const GICD_IGROUPR: usize = 0x080;
+const GICD_ISENABLER: usize = 0x100;
+const GICD_ICENABLER: usize = 0x180;
+const GICD_ISPENDR: usize = 0x200;
+const GICD_ICPENDR: usize = 0x280;
+const GICD_ISACTIVER: usize = 0x300;
+const GICD_ICACTIVER: usize = 0x380;
+const GICD_IPRIORITYR: usize = 0x400;
+const GICD_ITARGETSR: usize = 0x800;
+const GICD_ICFGR: usize = 0xc00;
+const GICD_SGIR: usize = 0xf00;
+const GICD_PIDR2: usize = 0xfe8;
+
+const BITMAP_BANK_SIZE: usize = NUM_IRQS / 8;
+
+const GIC_V2_ARCH_REV: u64 = 2 << 4;
+
+/// Size of the GICv2 distributor register frame.
+pub const GICD_SIZE: usize = 0x1000;
+
+#[derive(Debug, Copy, Clone, Default)]
+struct IrqState {
+    group: bool,
+    enabled: bool,
+    pending: bool,
+    active: bool,
+    priority: u8,
+    targets: u8,
+    config: u8,
+}
+
+/// A model of a GICv2 distributor for a single-vCPU guest.
+///
+/// Guest accesses to the distributor's register frame are emulated via [`MmioDevice`], and
+/// interrupts which become deliverable are injected into the guest's vCPU using the list
+/// registers of the physical GIC's virtual CPU interface.
+pub struct VirtualGicDistributor {
+    vcpu: sel4::VCPU,
+    enabled: bool,
+    irqs: [IrqState; NUM_IRQS],
+    list_registers: [Option<u16>; NUM_LIST_REGISTERS],
+}
+
+impl VirtualGicDistributor {
+    pub fn new(vcpu: sel4::VCPU) -> Self {
+        let mut irqs = [IrqState::default(); NUM_IRQS];
+        for irq in irqs.iter_mut().take(NUM_SGIS) {
+            irq.enabled = true;
+            irq.config = 0b10;
+        }
+        for irq in irqs.iter_mut() {
+            irq.targets = 1;
+        }
+        Self {
+            vcpu,
+            enabled: false,
+            irqs,
+            list_registers: [None; NUM_LIST_REGISTERS],
+        }
+    }
+
+    /// Marks `irq` as pending, injecting it if it is deliverable.
+    ///
+    /// This is how devices, both emulated and passed-through, raise interrupts in the guest.
+    pub fn set_pending(&mut self, irq: u16) -> sel4::Result<()> {
+        self.irqs[usize::from(irq)].pending = true;
+        self.flush()
+    }
+
+    /// Handles a `VGICMaintenance` fault, which signals that the guest has completed the interrupt
+    /// in list register `idx`. Returns that interrupt, so that the caller can acknowledge the
+    /// corresponding physical interrupt, if any.
+    pub fn maintenance(&mut self, idx: Option<usize>) -> sel4::Result<Option<u16>> {
+        let completed = idx.and_then(|idx| self.list_registers[idx].take());
+        if let Some(irq) = completed {
+            self.irqs[usize::from(irq)].active = false;
+        }
+        self.flush()?;
+        Ok(completed)
+    }
+
+    fn is_deliverable(&self, irq: usize) -> bool {
+        let state = &self.irqs[irq];
+        self.enabled && state.enabled && state.pending && !state.active
+    }
+
+    fn flush(&mut self) -> sel4::Result<()> {
+        for irq in 0..NUM_IRQS {
+            if !self.is_deliverable(irq) {
+                continue;
+            }
+            let Some(index) = self.list_registers.iter().position(Option::is_none) else {
+                break;
+            };
+            let state = &mut self.irqs[irq];
+            self.vcpu.vcpu_inject_irq(
+                irq.try_into().unwrap(),
+                state.priority >> 3,
+                state.group.into(),
+                index.try_into().unwrap(),
+            )?;
+            state.pending = false;
+            state.active = true;
+            self.list_registers[index] = Some(irq.try_into().unwrap());
+        }
+        Ok(())
+    }
+
+    fn read_bitmap(&self, bank_offset: usize, f: impl Fn(&IrqState) -> bool) -> u64 {
+        let first = bank_offset * 8;
+        (0..32)
+            .filter(|i| first + i < NUM_IRQS && f(&self.irqs[first + i]))
+            .fold(0, |acc, i| acc | (1 << i))
+    }
+
+    fn write_bitmap(&mut self, bank_offset: usize, value: u64, f: impl Fn(&mut IrqState)) {
+        let first = bank_offset * 8;
+        for i in 0..32 {
+            if first + i < NUM_IRQS && value & (1 << i) != 0 {
+                f(&mut self.irqs[first + i]);
+            }
+        }
+    }
+
+    fn read_bytes(&self, byte_offset: usize, width: Width, f: impl Fn(&IrqState) -> u8) -> u64 {
+        (0..width.bytes())
+            .filter(|i| byte_offset + i < NUM_IRQS)
+            .fold(0, |acc, i| {
+                acc | (u64::from(f(&self.irqs[byte_offset + i])) << (i * 8))
+            })
+    }
+
+    fn write_bytes(
+        &mut self,
+        byte_offset: usize,
+        width: Width,
+        value: u64,
+        f: impl Fn(&mut IrqState, u8),
+    ) {
+        for i in 0..width.bytes() {
+            if byte_offset + i < NUM_IRQS {
+                f(&mut self.irqs[byte_offset + i], (value >> (i * 8)) as u8);
+            }
+        }
+    }
+
+    fn read_config(&self, offset: usize) -> u64 {
+        let first = offset * 4;
+        (0..16).filter(|i| first + i < NUM_IRQS).fold(0, |acc, i| {
+            acc | (u64::from(self.irqs[first + i].config) << (i * 2))
+        })
+    }
+
+    fn write_config(&mut self, offset: usize, value: u64) {
+        let first = offset * 4;
+        // SGI configuration is read-only
+        for i in 0..16 {
+            if first + i >= NUM_SGIS && first + i < NUM_IRQS {
+                self.irqs[first + i].config = ((value >> (i * 2)) & 0b11) as u8;
+            }
+        }
+    }
+
+    fn write_sgir(&mut self, value: u64) {
+        let sgi = value & 0b1111;
+        let target_list_filter = (value >> 24) & 0b11;
+        let target_list = (value >> 16) & 0xff;
+        // Only one vCPU is modeled, so the SGI is delivered unless the guest targets other CPUs
+        // exclusively.
+        let delivered = match target_list_filter {
+            0b00 => target_list & 1 != 0,
+            0b01 => false,
+            0b10 => true,
+            _ => false,
+        };
+        if delivered {
+            self.irqs[sgi as usize].pending = true;
+        }
+    }
+}
+
+fn in_bank(offset: usize, base: usize) -> Option<usize> {
+    (base..base + BITMAP_BANK_SIZE)
+        .contains(&offset)
+        .then(|| offset - base)
+}
+
+fn in_byte_bank(offset: usize, base: usize) -> Option<usize> {
+    (base..base + NUM_IRQS)
+        .contains(&offset)
+        .then(|| offset - base)
+}
+
+fn in_config_bank(offset: usize) -> Option<usize> {
+    (GICD_ICFGR..GICD_ICFGR + NUM_IRQS / 4)
+        .contains(&offset)
+        .then(|| offset - GICD_ICFGR)
+}
+
+impl MmioDevice for VirtualGicDistributor {
+    fn read(&mut self, offset: usize, width: Width) -> MmioResult {
+        let value = match offset {
+            GICD_CTLR => self.enabled.into(),
+            GICD_TYPER => ((NUM_IRQS / 32) - 1) as u64,
+            GICD_IIDR => 0x43b,
+            GICD_PIDR2 => GIC_V2_ARCH_REV,
+            GICD_SGIR => 0,
+            _ => {
+                if let Some(off) = in_bank(offset, GICD_IGROUPR) {
+                    self.read_bitmap(off, |s| s.group)
+                } else if let Some(off) =
+                    in_bank(offset, GICD_ISENABLER).or_else(|| in_bank(offset, GICD_ICENABLER))
+                {
+                    self.read_bitmap(off, |s| s.enabled)
+                } else if let Some(off) =
+                    in_bank(offset, GICD_ISPENDR).or_else(|| in_bank(offset, GICD_ICPENDR))
+                {
+                    self.read_bitmap(off, |s| s.pending)
+                } else if let Some(off) =
+                    in_bank(offset, GICD_ISACTIVER).or_else(|| in_bank(offset, GICD_ICACTIVER))
+                {
+                    self.read_bitmap(off, |s| s.active)
+                } else if let Some(off) = in_byte_bank(offset, GICD_IPRIORITYR) {
+                    self.read_bytes(off, width, |s| s.priority)
+                } else if let Some(off) = in_byte_bank(offset, GICD_ITARGETSR) {
+                    self.read_bytes(off, width, |s| s.targets)
+                } else if let Some(off) = in_config_bank(offset) {
+                    self.read_config(off)
+                } else if offset < GICD_SIZE {
+                    0
+                } else {
+                    return MmioResult::Unhandled;
+                }
+            }
+        };
+        MmioResult::Handled(value)
+    }
+
+    fn write(&mut self, offset: usize, width: Width, value: u64) -> MmioResult {
+        match offset {
+            GICD_CTLR => self.enabled = value & 1 != 0,
+            GICD_TYPER | GICD_IIDR | GICD_PIDR2 => {}
+            GICD_SGIR => self.write_sgir(value),
+            _ => {
+                if let Some(off) = in_bank(offset, GICD_IGROUPR) {
+                    for i in 0..32 {
+                        let irq = off * 8 + i;
+                        if irq < NUM_IRQS {
+                            self.irqs[irq].group = value & (1 << i) != 0;
+                        }
+                    }
+                } else if let Some(off) = in_bank(offset, GICD_ISENABLER) {
+                    self.write_bitmap(off, value, |s| s.enabled = true)
+                } else if let Some(off) = in_bank(offset, GICD_ICENABLER) {
+                    self.write_bitmap(off, value, |s| s.enabled = false)
+                } else if let Some(off) = in_bank(offset, GICD_ISPENDR) {
+                    self.write_bitmap(off, value, |s| s.pending = true)
+                } else if let Some(off) = in_bank(offset, GICD_ICPENDR) {
+                    self.write_bitmap(off, value, |s| s.pending = false)
+                } else if let Some(off) = in_bank(offset, GICD_ISACTIVER) {
+                    self.write_bitmap(off, value, |s| s.active = true)
+                } else if let Some(off) = in_bank(offset, GICD_ICACTIVER) {
+                    self.write_bitmap(off, value, |s| s.active = false)
+                } else if let Some(off) = in_byte_bank(offset, GICD_IPRIORITYR) {
+                    self.write_bytes(off, width, value, |s, b| s.priority = b)
+                } else if let Some(off) = in_byte_bank(offset, GICD_ITARGETSR) {
+                    self.write_bytes(off, width, value, |s, b| s.targets = b)
+                } else if let Some(off) = in_config_bank(offset) {
+                    self.write_config(off, value)
+                } else if offset >= GICD_SIZE {
+                    return MmioResult::Unhandled;
+                }
+            }
+        }
+        if self.flush().is_err() {
+            log::warn!("failed to inject virtual interrupt");
+        }
+        MmioResult::Handled(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The distributor is left disabled, so that no interrupt is ever deliverable and the vCPU is
+    // never invoked.
+    fn vgic() -> VirtualGicDistributor {
+        VirtualGicDistributor::new(sel4::VCPU::from_bits(0))
+    }
+
+    fn read(vgic: &mut VirtualGicDistributor, offset: usize) -> u64 {
+        match vgic.read(offset, Width::Word) {
+            MmioResult::Handled(value) => value,
+            MmioResult::Unhandled => panic!(),
+        }
+    }
+
+    fn write(vgic: &mut VirtualGicDistributor, offset: usize, width: Width, value: u64) {
+        assert_eq!(vgic.write(offset, width, value), MmioResult::Handled(0));
+    }
+
+    #[test]
+    fn id_registers() {
+        let mut vgic = vgic();
+        assert_eq!(read(&mut vgic, GICD_TYPER), 7);
+        assert_eq!(read(&mut vgic, GICD_PIDR2), GIC_V2_ARCH_REV);
+        assert_eq!(read(&mut vgic, GICD_CTLR), 0);
+        assert_eq!(vgic.read(GICD_SIZE, Width::Word), MmioResult::Unhandled);
+        assert_eq!(vgic.write(GICD_SIZE, Width::Word, 0), MmioResult::Unhandled);
+    }
+
+    #[test]
+    fn set_and_clear_enable() {
+        let mut vgic = vgic();
+        // SGIs are always enabled
+        assert_eq!(read(&mut vgic, GICD_ISENABLER), 0xffff);
+        write(&mut vgic, GICD_ISENABLER + 4, Width::Word, 0b101);
+        assert_eq!(read(&mut vgic, GICD_ISENABLER + 4), 0b101);
+        assert_eq!(read(&mut vgic, GICD_ICENABLER + 4), 0b101);
+        write(&mut vgic, GICD_ICENABLER + 4, Width::Word, 0b1);
+        assert_eq!(read(&mut vgic, GICD_ISENABLER + 4), 0b100);
+    }
+
+    #[test]
+    fn priorities_and_targets() {
+        let mut vgic = vgic();
+        write(&mut vgic, GICD_IPRIORITYR + 32, Width::Word, 0x4030_2010);
+        write(&mut vgic, GICD_IPRIORITYR + 33, Width::Byte, 0xff);
+        assert_eq!(read(&mut vgic, GICD_IPRIORITYR + 32), 0x4030_ff10);
+        assert_eq!(read(&mut vgic, GICD_ITARGETSR), 0x0101_0101);
+        // The last bank is truncated at NUM_IRQS
+        assert_eq!(
+            vgic.read(GICD_IPRIORITYR + NUM_IRQS - 2, Width::Word),
+            MmioResult::Handled(0)
+        );
+    }
+
+    #[test]
+    fn config() {
+        let mut vgic = vgic();
+        write(&mut vgic, GICD_ICFGR, Width::Word, 0);
+        assert_eq!(read(&mut vgic, GICD_ICFGR), 0xaaaa_aaaa);
+        write(&mut vgic, GICD_ICFGR + 8, Width::Word, 0b10_00);
+        assert_eq!(read(&mut vgic, GICD_ICFGR + 8), 0b10_00);
+    }
+
+    #[test]
+    fn sgir() {
+        let mut vgic = vgic();
+        // Targeted at this CPU
+        write(&mut vgic, GICD_SGIR, Width::Word, (1 << 16) | 3);
+        // Targeted at other CPUs only
+        write(&mut vgic, GICD_SGIR, Width::Word, (0b01 << 24) | 4);
+        // Targeted at this CPU only
+        write(&mut vgic, GICD_SGIR, Width::Word, (0b10 << 24) | 5);
+        assert_eq!(read(&mut vgic, GICD_ISPENDR), 0b10_1000);
+        write(&mut vgic, GICD_ICPENDR, Width::Word, 0b1000);
+        assert_eq!(read(&mut vgic, GICD_ISPENDR), 0b10_0000);
+    }
+}
@@ -0,0 +1,298 @@
+//
+// Copyright 2023, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use core::fmt;
+use core::ops::Range;
+
+use sel4::{ConveysReplyAuthority, Fault, UserContext, Word};
+
+use crate::exit::VmExit;
+use crate::mmio::{MmioAccess, MmioAccessKind, MmioBus, MmioDevice, MmioResult};
+use crate::syndrome::DataAbort;
+use crate::vgic::{VirtualGicDistributor, GICD_SIZE};
+
+/// PSCI `NOT_SUPPORTED`, returned by the default [`VmmHandler::hvc`] and [`VmmHandler::smc`].
+pub const PSCI_NOT_SUPPORTED: Word = -1i64 as Word;
+
+const ZERO_REGISTER: usize = 31;
+
+const PPI_RANGE: Range<u16> = 16..32;
+
+/// Trait for the application-specific part of a VMM's event loop.
+pub trait VmmHandler {
+    type Error: fmt::Debug;
+
+    /// Handles a hypercall. The default implementation fails it with [`PSCI_NOT_SUPPORTED`].
+    fn hvc(&mut self, regs: &mut UserContext, imm: u16) -> Result<(), Self::Error> {
+        log::debug!("unhandled hvc #{imm:#x}, function id {:#x}", regs.gpr(0));
+        *regs.gpr_mut(0) = PSCI_NOT_SUPPORTED;
+        Ok(())
+    }
+
+    /// Handles a trapped secure monitor call. The default implementation fails it with
+    /// [`PSCI_NOT_SUPPORTED`].
+    fn smc(&mut self, regs: &mut UserContext, imm: u16) -> Result<(), Self::Error> {
+        log::debug!("unhandled smc #{imm:#x}, function id {:#x}", regs.gpr(0));
+        *regs.gpr_mut(0) = PSCI_NOT_SUPPORTED;
+        Ok(())
+    }
+
+    /// Handles a notification delivered to the VMM thread via its bound notification, for example
+    /// a passed-through device interrupt.
+    ///
+    /// The default implementation just panics.
+    fn notified(
+        &mut self,
+        vgic: &mut VirtualGicDistributor,
+        badge: sel4::Badge,
+    ) -> Result<(), Self::Error> {
+        let _ = vgic;
+        panic!("unexpected notification with badge {badge:#x}")
+    }
+
+    /// Called once the guest has completed the virtual interrupt `irq`, so that the corresponding
+    /// physical interrupt, if any, can be acknowledged.
+    fn completed(&mut self, irq: u16) -> Result<(), Self::Error> {
+        let _ = irq;
+        Ok(())
+    }
+
+    /// Handles an exit which the event loop does not know how to handle. Returns whether the guest
+    /// should be resumed.
+    ///
+    /// The default implementation logs the exit and leaves the guest stopped.
+    fn unhandled(&mut self, exit: &VmExit) -> Result<bool, Self::Error> {
+        log::error!("unhandled vm exit: {exit:?}");
+        Ok(false)
+    }
+}
+
+/// Error type returned by [`Vm::run`].
+#[derive(Debug)]
+pub enum Error<E> {
+    Sel4(sel4::Error),
+    Handler(E),
+    /// A guest access to an address not covered by any emulated device.
+    UnmappedMmio {
+        addr: usize,
+    },
+}
+
+impl<E> From<sel4::Error> for Error<E> {
+    fn from(err: sel4::Error) -> Self {
+        Self::Sel4(err)
+    }
+}
+
+impl<E: fmt::Debug> fmt::Display for Error<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Sel4(err) => write!(f, "seL4 error: {err:?}"),
+            Self::Handler(err) => write!(f, "handler error: {err:?}"),
+            Self::UnmappedMmio { addr } => write!(f, "unmapped mmio access at {addr:#x}"),
+        }
+    }
+}
+
+/// A single-vCPU virtual machine and the state its VMM needs to drive it.
+pub struct Vm<H> {
+    tcb: sel4::TCB,
+    vcpu: sel4::VCPU,
+    fault_ep: sel4::Endpoint,
+    gicd_base: usize,
+    vgic: VirtualGicDistributor,
+    bus: MmioBus,
+    vppis_awaiting_ack: u32,
+    handler: H,
+}
+
+impl<H: VmmHandler> Vm<H> {
+    /// Binds `vcpu` to `tcb`, whose faults must be delivered on `fault_ep`. A virtual GICv2
+    /// distributor is emulated at guest physical address `gicd_base`.
+    pub fn new(
+        tcb: sel4::TCB,
+        vcpu: sel4::VCPU,
+        fault_ep: sel4::Endpoint,
+        gicd_base: usize,
+        handler: H,
+    ) -> sel4::Result<Self> {
+        vcpu.vcpu_set_tcb(tcb)?;
+        Ok(Self {
+            tcb,
+            vcpu,
+            fault_ep,
+            gicd_base,
+            vgic: VirtualGicDistributor::new(vcpu),
+            bus: MmioBus::new(),
+            vppis_awaiting_ack: 0,
+            handler,
+        })
+    }
+
+    pub fn tcb(&self) -> sel4::TCB {
+        self.tcb
+    }
+
+    pub fn vcpu(&self) -> sel4::VCPU {
+        self.vcpu
+    }
+
+    pub fn vgic(&mut self) -> &mut VirtualGicDistributor {
+        &mut self.vgic
+    }
+
+    pub fn bus(&mut self) -> &mut MmioBus {
+        &mut self.bus
+    }
+
+    pub fn handler(&mut self) -> &mut H {
+        &mut self.handler
+    }
+
+    /// Sets the guest's entry point and boot argument in `x0`, as expected by Linux and most
+    /// baremetal images, and resumes it.
+    pub fn start(&mut self, entry: usize, x0: Word) -> Result<(), Error<H::Error>> {
+        let mut regs = self.tcb.tcb_read_all_registers(false)?;
+        *regs.pc_mut() = entry.try_into().unwrap();
+        *regs.gpr_mut(0) = x0;
+        // EL1h with all exceptions masked
+        *regs.spsr_mut() = 0x3c5;
+        self.tcb.tcb_write_all_registers(true, &mut regs)?;
+        Ok(())
+    }
+
+    /// Runs the VMM's event loop, handling guest exits until an error occurs or the guest is left
+    /// stopped by [`VmmHandler::unhandled`].
+    pub fn run(
+        &mut self,
+        reply_authority: impl ConveysReplyAuthority + Copy,
+    ) -> Result<(), Error<H::Error>> {
+        let mut reply = false;
+        loop {
+            let (info, badge) = if reply {
+                self.fault_ep
+                    .reply_recv(sel4::MessageInfoBuilder::default().build(), reply_authority)
+            } else {
+                self.fault_ep.recv(reply_authority)
+            };
+
+            let fault = sel4::with_ipc_buffer(|ipc_buffer| Fault::new(ipc_buffer, &info));
+
+            reply = match fault {
+                Fault::NullFault(_) => {
+                    self.handler
+                        .notified(&mut self.vgic, badge)
+                        .map_err(Error::Handler)?;
+                    false
+                }
+                _ => {
+                    let exit = VmExit::decode(&fault);
+                    let resume = self.handle_exit(&exit)?;
+                    if !resume {
+                        return Ok(());
+                    }
+                    true
+                }
+            };
+        }
+    }
+
+    fn handle_exit(&mut self, exit: &VmExit) -> Result<bool, Error<H::Error>> {
+        match exit {
+            VmExit::Mmio { addr, data_abort } => {
+                self.with_regs(exit, |this, regs| this.handle_mmio(regs, *addr, data_abort))?;
+            }
+            VmExit::Hvc { imm } => {
+                self.with_regs(exit, |this, regs| {
+                    this.handler.hvc(regs, *imm).map_err(Error::Handler)
+                })?;
+            }
+            VmExit::Smc { imm } => {
+                self.with_regs(exit, |this, regs| {
+                    this.handler.smc(regs, *imm).map_err(Error::Handler)
+                })?;
+            }
+            VmExit::Wfx { .. } => {
+                // The guest will be woken by the next injected interrupt, so there is no need to
+                // keep it blocked here.
+                self.with_regs(exit, |_, _| Ok(()))?;
+            }
+            VmExit::VgicMaintenance { idx } => {
+                if let Some(irq) = self.vgic.maintenance(*idx)? {
+                    let ppi_bit = 1 << (irq % 32);
+                    if PPI_RANGE.contains(&irq) && self.vppis_awaiting_ack & ppi_bit != 0 {
+                        self.vppis_awaiting_ack &= !ppi_bit;
+                        self.vcpu.vcpu_ack_vppi(irq.into())?;
+                    } else {
+                        self.handler.completed(irq).map_err(Error::Handler)?;
+                    }
+                }
+            }
+            VmExit::Vppi { irq } => {
+                let irq: u16 = (*irq).try_into().unwrap();
+                self.vppis_awaiting_ack |= 1 << (irq % 32);
+                self.vgic.set_pending(irq)?;
+            }
+            _ => return self.handler.unhandled(exit).map_err(Error::Handler),
+        }
+        Ok(true)
+    }
+
+    fn with_regs(
+        &mut self,
+        exit: &VmExit,
+        f: impl FnOnce(&mut Self, &mut UserContext) -> Result<(), Error<H::Error>>,
+    ) -> Result<(), Error<H::Error>> {
+        let mut regs = self.tcb.tcb_read_all_registers(false)?;
+        f(self, &mut regs)?;
+        if exit.skips_instruction() {
+            // All trapping AArch64 instructions are 32 bits wide
+            *regs.pc_mut() += 4;
+        }
+        self.tcb.tcb_write_all_registers(false, &mut regs)?;
+        Ok(())
+    }
+
+    fn handle_mmio(
+        &mut self,
+        regs: &mut UserContext,
+        addr: usize,
+        data_abort: &DataAbort,
+    ) -> Result<(), Error<H::Error>> {
+        let reg = data_abort.register();
+        let width = data_abort.width();
+        let kind = if data_abort.is_write() {
+            MmioAccessKind::Write(if reg == ZERO_REGISTER {
+                0
+            } else {
+                (*regs.gpr(reg.try_into().unwrap())).into()
+            })
+        } else {
+            MmioAccessKind::Read
+        };
+
+        let result = if (self.gicd_base..self.gicd_base + GICD_SIZE).contains(&addr) {
+            Some(self.vgic.access(&MmioAccess {
+                offset: addr - self.gicd_base,
+                width,
+                kind,
+            }))
+        } else {
+            self.bus.access(addr, width, kind)
+        };
+
+        match result {
+            Some(MmioResult::Handled(value)) => {
+                if kind == MmioAccessKind::Read && reg != ZERO_REGISTER {
+                    *regs.gpr_mut(reg.try_into().unwrap()) =
+                        data_abort.extend_read_value(value).try_into().unwrap();
+                }
+                Ok(())
+            }
+            Some(MmioResult::Unhandled) | None => Err(Error::UnmappedMmio { addr }),
+        }
+    }
+}
@@ -20,7 +20,8 @@ mk {
     postcard = postcardWith [ "alloc" ];
     inherit (localCrates)
       sel4-render-elf-with-data
+      sel4-capdl-initializer-cdl
     ;
-    sel4-capdl-initializer-types = localCrates.sel4-capdl-initializer-types // { features = [ "std" "serde" "deflate" ]; };
+    sel4-capdl-initializer-types = localCrates.sel4-capdl-initializer-types // { features = [ "std" "serde" "deflate" "digest" ]; };
   };
 }
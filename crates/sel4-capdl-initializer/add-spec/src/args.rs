@@ -12,11 +12,14 @@ use sel4_capdl_initializer_types::ObjectNamesLevel;
 #[derive(Debug)]
 pub struct Args {
     pub initializer_elf_path: String,
-    pub spec_json_path: String,
+    pub spec_path: String,
+    pub object_sizes_path: Option<String>,
     pub fill_dir_path: String,
     pub out_file_path: String,
+    pub digest_out_file_path: Option<String>,
     pub object_names_level: ObjectNamesLevel,
     pub embed_frames: bool,
+    pub no_deflate: bool,
     pub verbose: bool,
 }
 
@@ -30,11 +33,16 @@ impl Args {
                     .required(true),
             )
             .arg(
-                Arg::new("spec_json")
+                Arg::new("spec")
                     .short('f')
                     .value_name("SPEC_FILE")
                     .required(true),
             )
+            .arg(
+                Arg::new("object_sizes")
+                    .long("object-sizes")
+                    .value_name("OBJECT_SIZES_FILE"),
+            )
             .arg(
                 Arg::new("fill_dir")
                     .short('d')
@@ -47,6 +55,11 @@ impl Args {
                     .value_name("OUT_FILE")
                     .required(true),
             )
+            .arg(
+                Arg::new("digest_out_file")
+                    .long("digest-out")
+                    .value_name("DIGEST_OUT_FILE"),
+            )
             .arg(
                 Arg::new("object_names_level")
                     .long("object-names-level")
@@ -60,6 +73,11 @@ impl Args {
                     .value_name("EMBED_FRAMES")
                     .action(ArgAction::SetTrue),
             )
+            .arg(
+                Arg::new("no_deflate")
+                    .long("no-deflate")
+                    .action(ArgAction::SetTrue),
+            )
             .arg(Arg::new("verbose").short('v').action(ArgAction::SetTrue))
             .get_matches();
 
@@ -67,9 +85,11 @@ impl Args {
             .get_one::<String>("initializer_elf")
             .unwrap()
             .to_owned();
-        let spec_json_path = matches.get_one::<String>("spec_json").unwrap().to_owned();
+        let spec_path = matches.get_one::<String>("spec").unwrap().to_owned();
+        let object_sizes_path = matches.get_one::<String>("object_sizes").cloned();
         let fill_dir_path = matches.get_one::<String>("fill_dir").unwrap().to_owned();
         let out_file_path = matches.get_one::<String>("out_file").unwrap().to_owned();
+        let digest_out_file_path = matches.get_one::<String>("digest_out_file").cloned();
 
         let object_names_level = matches
             .get_one::<u32>("object_names_level")
@@ -83,15 +103,20 @@ impl Args {
 
         let embed_frames = *matches.get_one::<bool>("embed_frames").unwrap();
 
+        let no_deflate = *matches.get_one::<bool>("no_deflate").unwrap();
+
         let verbose = *matches.get_one::<bool>("verbose").unwrap();
 
         Ok(Self {
             initializer_elf_path,
-            spec_json_path,
+            spec_path,
+            object_sizes_path,
             fill_dir_path,
             out_file_path,
+            digest_out_file_path,
             object_names_level,
             embed_frames,
+            no_deflate,
             verbose,
         })
     }
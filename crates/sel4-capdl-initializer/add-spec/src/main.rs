@@ -8,7 +8,8 @@ use std::fs;
 
 use anyhow::Result;
 
-use sel4_capdl_initializer_types::{Footprint, InputSpec};
+use sel4_capdl_initializer_cdl::{read_input_spec, read_object_sizes};
+use sel4_capdl_initializer_types::Footprint;
 use sel4_render_elf_with_data::{ConcreteFileHeader32, ConcreteFileHeader64, ElfBitWidth};
 
 mod args;
@@ -28,19 +29,24 @@ fn main() -> Result<()> {
     }
 
     let initializer_elf = fs::read(&args.initializer_elf_path)?;
-    let spec_json = fs::read_to_string(&args.spec_json_path)?;
     let fill_dir_path = &args.fill_dir_path;
     let out_file_path = &args.out_file_path;
     let object_names_level = &args.object_names_level;
     let embed_frames = args.embed_frames;
 
-    let input_spec = InputSpec::parse(&spec_json);
+    let object_sizes = args
+        .object_sizes_path
+        .as_ref()
+        .map(read_object_sizes)
+        .transpose()?;
+    let input_spec = read_input_spec(&args.spec_path, object_sizes.as_ref())?;
 
-    let (final_spec, serialized_spec) = reserialize_spec::reserialize_spec(
+    let (final_spec, digest, serialized_spec) = reserialize_spec::reserialize_spec(
         &input_spec,
         fill_dir_path,
         object_names_level,
         embed_frames,
+        !args.no_deflate,
         GRANULE_SIZE_BITS,
         args.verbose,
     );
@@ -68,5 +74,10 @@ fn main() -> Result<()> {
     };
 
     fs::write(out_file_path, rendered_initializer_elf)?;
+
+    if let Some(digest_out_file_path) = &args.digest_out_file_path {
+        fs::write(digest_out_file_path, format!("{}\n", digest))?;
+    }
+
     Ok(())
 }
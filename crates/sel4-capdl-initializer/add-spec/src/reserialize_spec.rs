@@ -4,6 +4,7 @@
 // SPDX-License-Identifier: BSD-2-Clause
 //
 
+use std::collections::HashMap;
 use std::ops::Range;
 use std::path::Path;
 
@@ -14,44 +15,122 @@ pub fn reserialize_spec<'a>(
     fill_dir_path: impl AsRef<Path>,
     object_names_level: &ObjectNamesLevel,
     embed_frames: bool,
+    deflate: bool,
     granule_size_bits: usize,
     verbose: bool,
-) -> (SpecWithIndirection<'a>, Vec<u8>) {
+) -> (SpecWithIndirection<'a>, SpecDigest, Vec<u8>) {
     let granule_size = 1 << granule_size_bits;
 
     let fill_map = input_spec.collect_fill(&[fill_dir_path]);
 
+    let digest = fill_map.spec_digest(input_spec, |absurdity| match *absurdity {});
+
     let mut sources = SourcesBuilder::new();
-    let mut num_embedded_frames = 0;
+    let mut sizes = Sizes::default();
+    // Identical chunks, such as copies of the same ELF segment in different components, are
+    // stored once.
+    let mut packed_chunks = HashMap::<&[u8], IndirectMaybeDeflatedBytesContent>::new();
     let final_spec: SpecWithIndirection<'a> = input_spec
         .traverse_names_with_context(|named_obj| {
-            object_names_level
-                .apply(named_obj)
-                .map(|s| IndirectObjectName {
+            object_names_level.apply(named_obj).map(|s| {
+                sizes.object_names += s.len();
+                IndirectObjectName {
                     range: sources.append(s.as_bytes()),
-                })
+                }
+            })
         })
         .split_embedded_frames(embed_frames, granule_size_bits)
         .traverse_data(|key| {
-            let compressed = DeflatedBytesContent::pack(fill_map.get(key));
-            IndirectDeflatedBytesContent {
-                deflated_bytes_range: sources.append(&compressed),
-            }
+            let raw = fill_map.get(key);
+            sizes.fill_chunks += 1;
+            sizes.fill_raw += raw.len();
+            packed_chunks
+                .entry(raw)
+                .or_insert_with(|| {
+                    sizes.unique_fill_chunks += 1;
+                    sizes.unique_fill_raw += raw.len();
+                    let deflated = if deflate {
+                        Some(DeflatedBytesContent::pack(raw))
+                    } else {
+                        None
+                    };
+                    match deflated {
+                        Some(deflated) if deflated.len() < raw.len() => {
+                            sizes.deflated_fill_chunks += 1;
+                            sizes.deflated_fill_stored += deflated.len();
+                            IndirectMaybeDeflatedBytesContent::Deflated(
+                                IndirectDeflatedBytesContent {
+                                    deflated_bytes_range: sources.append(&deflated),
+                                },
+                            )
+                        }
+                        _ => {
+                            sizes.raw_fill_stored += raw.len();
+                            IndirectMaybeDeflatedBytesContent::Bytes(IndirectBytesContent {
+                                bytes_range: sources.append(&BytesContent::pack(raw)),
+                            })
+                        }
+                    }
+                })
+                .clone()
         })
         .traverse_embedded_frames(|fill| {
-            num_embedded_frames += 1;
-            sources.align_to(granule_size);
+            sizes.embedded_frames += 1;
+            sizes.embedded_frame_padding += sources.align_to(granule_size);
             let range = sources.append(&fill_map.get_frame(granule_size, fill));
             IndirectEmbeddedFrame::new(range.start)
         });
 
+    let mut blob = postcard::to_allocvec(&(&final_spec, &digest)).unwrap();
+    sizes.spec = blob.len();
+    blob.extend(sources.build());
+
     if verbose {
-        eprintln!("embedded frames count: {}", num_embedded_frames);
+        sizes.report(granule_size, blob.len());
+        eprintln!("spec digest: {}", digest);
     }
 
-    let mut blob = postcard::to_allocvec(&final_spec).unwrap();
-    blob.extend(sources.build());
-    (final_spec, blob)
+    (final_spec, digest, blob)
+}
+
+#[derive(Default)]
+struct Sizes {
+    spec: usize,
+    object_names: usize,
+    fill_chunks: usize,
+    fill_raw: usize,
+    unique_fill_chunks: usize,
+    unique_fill_raw: usize,
+    deflated_fill_chunks: usize,
+    deflated_fill_stored: usize,
+    raw_fill_stored: usize,
+    embedded_frames: usize,
+    embedded_frame_padding: usize,
+}
+
+impl Sizes {
+    fn report(&self, granule_size: usize, total: usize) {
+        eprintln!("serialized spec: {} bytes", self.spec);
+        eprintln!("object names: {} bytes", self.object_names);
+        eprintln!(
+            "fill chunks: {} ({} bytes), of which {} unique ({} bytes)",
+            self.fill_chunks, self.fill_raw, self.unique_fill_chunks, self.unique_fill_raw
+        );
+        eprintln!(
+            "stored fill chunks: {} deflated ({} bytes), {} as is ({} bytes)",
+            self.deflated_fill_chunks,
+            self.deflated_fill_stored,
+            self.unique_fill_chunks - self.deflated_fill_chunks,
+            self.raw_fill_stored
+        );
+        eprintln!(
+            "embedded frames: {} ({} bytes, plus {} bytes of padding)",
+            self.embedded_frames,
+            self.embedded_frames * granule_size,
+            self.embedded_frame_padding
+        );
+        eprintln!("total: {} bytes", total);
+    }
 }
 
 struct SourcesBuilder {
@@ -67,9 +146,11 @@ impl SourcesBuilder {
         self.buf
     }
 
-    fn align_to(&mut self, align: usize) {
+    fn align_to(&mut self, align: usize) -> usize {
         assert!(align.is_power_of_two());
-        self.buf.resize(self.buf.len().next_multiple_of(align), 0);
+        let start = self.buf.len();
+        self.buf.resize(start.next_multiple_of(align), 0);
+        self.buf.len() - start
     }
 
     fn append(&mut self, bytes: &[u8]) -> Range<usize> {
@@ -79,3 +160,147 @@ impl SourcesBuilder {
         start..end
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::*;
+
+    const ZEROS_LEN: usize = 4096;
+    const NOISE_LEN: usize = 256;
+
+    // A page of zeros, which deflates well, followed by bytes which do not.
+    fn fill_contents() -> Vec<u8> {
+        let mut state = 0x2545_f491_u32;
+        let noise = (0..NOISE_LEN).map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state.to_le_bytes()[0]
+        });
+        [0; ZEROS_LEN].into_iter().chain(noise).collect()
+    }
+
+    fn fill_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "sel4-capdl-initializer-add-spec-{}-{}",
+            name,
+            std::process::id()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("fill"), fill_contents()).unwrap();
+        dir
+    }
+
+    fn frame(
+        file_offset: usize,
+        file_length: usize,
+    ) -> NamedObject<'static, String, FileContentRange, NeverEmbedded> {
+        let entry = FillEntry {
+            range: 0..file_length,
+            content: FillEntryContent::Data(FileContentRange {
+                file: "fill".to_owned(),
+                file_offset,
+                file_length,
+            }),
+        };
+        NamedObject {
+            name: "frame".to_owned(),
+            object: Object::Frame(object::Frame {
+                size_bits: 12,
+                paddr: None,
+                init: FrameInit::Fill(Fill {
+                    entries: Indirect::from_owned(vec![entry].into_boxed_slice()),
+                }),
+            }),
+        }
+    }
+
+    // Two frames with the same contents, and one with contents which do not deflate well.
+    fn input_spec() -> InputSpec {
+        Spec {
+            objects: [
+                frame(0, ZEROS_LEN),
+                frame(0, ZEROS_LEN),
+                frame(ZEROS_LEN, NOISE_LEN),
+            ]
+            .into_iter()
+            .collect(),
+            irqs: [].into_iter().collect(),
+            asid_slots: [].into_iter().collect(),
+            root_objects: 0..3,
+            untyped_covers: [].into_iter().collect(),
+            resource_handoff: None,
+        }
+    }
+
+    fn content(spec: &SpecWithIndirection, obj_id: ObjectId) -> IndirectMaybeDeflatedBytesContent {
+        match &spec.objects[obj_id].object {
+            Object::Frame(object::Frame {
+                init: FrameInit::Fill(fill),
+                ..
+            }) => fill.entries[0].content.as_data().unwrap().clone(),
+            _ => panic!(),
+        }
+    }
+
+    fn reserialize(name: &str, deflate: bool) -> (SpecWithIndirection<'static>, Vec<u8>) {
+        let dir = fill_dir(name);
+        let (spec, digest, blob) = reserialize_spec(
+            &input_spec(),
+            &dir,
+            &ObjectNamesLevel::None,
+            false,
+            deflate,
+            12,
+            false,
+        );
+        fs::remove_dir_all(dir).unwrap();
+        let spec_len = postcard::to_allocvec(&(&spec, &digest)).unwrap().len();
+        let sources = blob[spec_len..].to_vec();
+        (spec, sources)
+    }
+
+    fn copy_out(
+        content: &IndirectMaybeDeflatedBytesContent,
+        sources: &[u8],
+        len: usize,
+    ) -> Vec<u8> {
+        let mut buf = vec![0xff; len];
+        content.copy_out(sources, &mut buf);
+        buf
+    }
+
+    #[test]
+    fn dedup_and_deflate() {
+        let (spec, sources) = reserialize("deflate", true);
+        let zeros = content(&spec, 0);
+        let noise = content(&spec, 2);
+        assert_eq!(zeros, content(&spec, 1));
+        assert!(matches!(
+            zeros,
+            IndirectMaybeDeflatedBytesContent::Deflated(_)
+        ));
+        assert!(matches!(noise, IndirectMaybeDeflatedBytesContent::Bytes(_)));
+        assert!(sources.len() < NOISE_LEN + ZEROS_LEN / 2);
+
+        let file = fill_contents();
+        assert_eq!(copy_out(&zeros, &sources, ZEROS_LEN), file[..ZEROS_LEN]);
+        assert_eq!(copy_out(&noise, &sources, NOISE_LEN), file[ZEROS_LEN..]);
+    }
+
+    #[test]
+    fn dedup_without_deflate() {
+        let (spec, sources) = reserialize("raw", false);
+        assert_eq!(content(&spec, 0), content(&spec, 1));
+        for obj_id in 0..3 {
+            assert!(matches!(
+                content(&spec, obj_id),
+                IndirectMaybeDeflatedBytesContent::Bytes(_)
+            ));
+        }
+        assert_eq!(sources, fill_contents());
+    }
+}
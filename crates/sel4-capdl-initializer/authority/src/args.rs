@@ -0,0 +1,131 @@
+//
+// Copyright 2023, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use anyhow::Result;
+use clap::{Arg, ArgAction, Command};
+
+use sel4_capdl_initializer_authority::Config;
+
+#[derive(Debug)]
+pub struct Args {
+    pub spec_path: String,
+    pub object_sizes_path: Option<String>,
+    pub config: Config,
+    pub query: Query,
+    pub verbose: bool,
+}
+
+#[derive(Debug)]
+pub enum Query {
+    Dot,
+    Json,
+    CanSend { from: String, to: String },
+    FlowPath { from: String, to: String },
+    GrantPath { from: String, to: String },
+    Writers { frame: String },
+    Readers { frame: String },
+}
+
+impl Args {
+    pub fn parse() -> Result<Self> {
+        let pair = |name: &'static str, about: &'static str| {
+            Command::new(name)
+                .about(about)
+                .arg(Arg::new("from").value_name("FROM").required(true))
+                .arg(Arg::new("to").value_name("TO").required(true))
+        };
+        let frame = |name: &'static str, about: &'static str| {
+            Command::new(name)
+                .about(about)
+                .arg(Arg::new("frame").value_name("FRAME").required(true))
+        };
+        let matches = Command::new("")
+            .arg(
+                Arg::new("spec")
+                    .short('f')
+                    .value_name("SPEC_FILE")
+                    .required(true),
+            )
+            .arg(
+                Arg::new("object_sizes")
+                    .long("object-sizes")
+                    .value_name("OBJECT_SIZES_FILE"),
+            )
+            .arg(
+                Arg::new("arch")
+                    .long("arch")
+                    .value_parser(["aarch64", "riscv64", "x86_64"])
+                    .default_value("aarch64"),
+            )
+            .arg(Arg::new("verbose").short('v').action(ArgAction::SetTrue))
+            .subcommand_required(true)
+            .subcommand(Command::new("dot").about("Print the authority graph in Graphviz format"))
+            .subcommand(Command::new("json").about("Print the authority graph as JSON"))
+            .subcommand(pair(
+                "can-send",
+                "List the channels through which FROM can send to TO",
+            ))
+            .subcommand(pair(
+                "flow-path",
+                "Find a path of information flow from FROM to TO",
+            ))
+            .subcommand(pair(
+                "grant-path",
+                "Find a path through which FROM can transfer caps to TO",
+            ))
+            .subcommand(frame(
+                "writers",
+                "List the components which can write FRAME",
+            ))
+            .subcommand(frame("readers", "List the components which can read FRAME"))
+            .get_matches();
+
+        let spec_path = matches.get_one::<String>("spec").unwrap().to_owned();
+        let object_sizes_path = matches.get_one::<String>("object_sizes").cloned();
+
+        let config = match matches.get_one::<String>("arch").unwrap().as_str() {
+            "aarch64" => Config::AARCH64,
+            "riscv64" => Config::RISCV64_SV39,
+            "x86_64" => Config::X86_64,
+            _ => unreachable!(),
+        };
+
+        let get = |sub: &clap::ArgMatches, id| sub.get_one::<String>(id).unwrap().to_owned();
+        let query = match matches.subcommand().unwrap() {
+            ("dot", _) => Query::Dot,
+            ("json", _) => Query::Json,
+            ("can-send", sub) => Query::CanSend {
+                from: get(sub, "from"),
+                to: get(sub, "to"),
+            },
+            ("flow-path", sub) => Query::FlowPath {
+                from: get(sub, "from"),
+                to: get(sub, "to"),
+            },
+            ("grant-path", sub) => Query::GrantPath {
+                from: get(sub, "from"),
+                to: get(sub, "to"),
+            },
+            ("writers", sub) => Query::Writers {
+                frame: get(sub, "frame"),
+            },
+            ("readers", sub) => Query::Readers {
+                frame: get(sub, "frame"),
+            },
+            _ => unreachable!(),
+        };
+
+        let verbose = *matches.get_one::<bool>("verbose").unwrap();
+
+        Ok(Self {
+            spec_path,
+            object_sizes_path,
+            config,
+            query,
+            verbose,
+        })
+    }
+}
@@ -0,0 +1,90 @@
+//
+// Copyright 2023, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use crate::{AuthorityGraph, FlowKind};
+
+impl AuthorityGraph {
+    /// Renders the graph in Graphviz format.
+    ///
+    /// Subjects are boxes, and the objects through which information flows between them are
+    /// ellipses. Flows are drawn from their sender to the object, and from the object to their
+    /// receiver. Control of one subject by another is drawn in bold, and grants are dashed.
+    pub fn to_dot(&self) -> String {
+        let mut edges = BTreeSet::new();
+        let mut objects = BTreeSet::new();
+        for flow in self.flows.iter() {
+            match flow.kind {
+                FlowKind::Control => {
+                    if flow.object == flow.to {
+                        edges.insert((flow.from, flow.to, "control".to_owned(), "bold"));
+                    }
+                }
+                FlowKind::Reply => {
+                    objects.insert(flow.object);
+                    edges.insert((flow.from, flow.object, "reply".to_owned(), "dotted"));
+                    edges.insert((flow.object, flow.to, "reply".to_owned(), "dotted"));
+                }
+                kind => {
+                    objects.insert(flow.object);
+                    let label = match flow.badge {
+                        Some(badge) if badge != 0 => format!("{} ({:#x})", kind_label(kind), badge),
+                        _ => kind_label(kind).to_owned(),
+                    };
+                    edges.insert((flow.from, flow.object, label, "solid"));
+                    edges.insert((flow.object, flow.to, String::new(), "solid"));
+                }
+            }
+        }
+        for grant in self.grants.iter() {
+            let label = format!(
+                "grant{} via {}",
+                if grant.reply { " (reply)" } else { "" },
+                self.name(grant.endpoint)
+            );
+            edges.insert((grant.from, grant.to, label, "dashed"));
+        }
+
+        let mut s = String::new();
+        writeln!(s, "digraph authority {{").unwrap();
+        for (id, subject) in self.subjects.iter() {
+            writeln!(s, "    n{} [label={:?}, shape=box];", id, subject.name).unwrap();
+        }
+        for id in objects.iter() {
+            let info = &self.objects[id];
+            writeln!(
+                s,
+                "    n{} [label={:?}, shape=ellipse];",
+                id,
+                format!("{}\n({})", info.name, info.kind)
+            )
+            .unwrap();
+        }
+        for (from, to, label, style) in edges.iter() {
+            writeln!(
+                s,
+                "    n{} -> n{} [label={:?}, style={}];",
+                from, to, label, style
+            )
+            .unwrap();
+        }
+        writeln!(s, "}}").unwrap();
+        s
+    }
+}
+
+fn kind_label(kind: FlowKind) -> &'static str {
+    match kind {
+        FlowKind::Send => "send",
+        FlowKind::Reply => "reply",
+        FlowKind::Signal => "signal",
+        FlowKind::Fault => "fault",
+        FlowKind::SharedMemory => "write",
+        FlowKind::Control => "control",
+    }
+}
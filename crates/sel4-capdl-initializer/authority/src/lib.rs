@@ -0,0 +1,901 @@
+//
+// Copyright 2023, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+//! Authority and information-flow analysis of CapDL specs.
+//!
+//! The subjects of the analysis are the TCBs of a spec. A subject's authority is the set of caps
+//! reachable through its CSpace, the caps in its TCB's own slots, and the frames mapped into its
+//! VSpace. From these, [`AuthorityGraph`] derives the direct information flows and cap-transfer
+//! (grant) relationships between subjects.
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+use serde::Serialize;
+
+use sel4_capdl_initializer_types::{
+    cap, object, Badge, CPtr, Cap, CapSlot, HasCapTable, Object, ObjectId, PageTableEntry, Rights,
+    SelfContainedObjectName, Spec, Word,
+};
+
+mod dot;
+
+const PAGE_BITS: usize = 12;
+const LEVEL_BITS: usize = 9;
+
+/// Architecture parameters needed to interpret CSpaces and VSpaces.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Config {
+    pub word_bits: usize,
+    pub vspace_levels: usize,
+}
+
+impl Config {
+    pub const AARCH64: Self = Self::new(64, 4);
+    pub const RISCV64_SV39: Self = Self::new(64, 3);
+    pub const X86_64: Self = Self::new(64, 4);
+
+    pub const fn new(word_bits: usize, vspace_levels: usize) -> Self {
+        Self {
+            word_bits,
+            vspace_levels,
+        }
+    }
+
+    fn entry_bits(&self, level: usize) -> usize {
+        (self.vspace_levels - level - 1) * LEVEL_BITS + PAGE_BITS
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AuthorityGraph {
+    pub subjects: BTreeMap<ObjectId, Subject>,
+    /// Names and kinds of all objects referred to by `subjects`.
+    pub objects: BTreeMap<ObjectId, ObjectInfo>,
+    pub flows: Vec<Flow>,
+    pub grants: Vec<Grant>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Subject {
+    pub name: String,
+    pub accesses: Vec<Access>,
+    pub mappings: Vec<Mapping>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ObjectInfo {
+    pub name: String,
+    pub kind: &'static str,
+}
+
+/// A cap held by a subject.
+#[derive(Debug, Clone, Serialize)]
+pub struct Access {
+    pub cap: Cap,
+    pub via: Via,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub enum Via {
+    /// A slot of a CNode reachable from the subject's CSpace root. `cptr` is present if the slot
+    /// can be addressed by a full-depth lookup.
+    #[serde(rename = "cspace")]
+    CSpace {
+        cnode: ObjectId,
+        slot: CapSlot,
+        cptr: Option<CPtr>,
+    },
+    /// A slot of the subject's TCB, such as its bound notification.
+    #[serde(rename = "tcb")]
+    TCB { slot: CapSlot },
+}
+
+/// A frame mapped into a subject's VSpace.
+#[derive(Debug, Clone, Serialize)]
+pub struct Mapping {
+    pub frame: ObjectId,
+    pub vaddr: Word,
+    pub rights: Rights,
+}
+
+/// A direct flow of information from one subject to another.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct Flow {
+    pub from: ObjectId,
+    pub to: ObjectId,
+    /// The object through which the information flows.
+    pub object: ObjectId,
+    pub kind: FlowKind,
+    /// The badge that the receiver observes, for IPC and notifications.
+    pub badge: Option<Badge>,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FlowKind {
+    Send,
+    Reply,
+    Signal,
+    Fault,
+    SharedMemory,
+    Control,
+}
+
+/// The ability of one subject to transfer caps to another through an endpoint.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct Grant {
+    pub from: ObjectId,
+    pub to: ObjectId,
+    pub endpoint: ObjectId,
+    /// Whether the transfer happens through a reply cap, rather than the endpoint itself.
+    pub reply: bool,
+}
+
+// What a subject can do with an object, accumulated over all of its caps and mappings.
+#[derive(Debug, Clone, Default)]
+struct Capabilities {
+    send: BTreeSet<Badge>,
+    // Whether calls, which create reply caps, are possible.
+    call: bool,
+    send_grant: bool,
+    receive: bool,
+    receive_grant: bool,
+    fault: BTreeSet<Badge>,
+    bound: bool,
+    read: bool,
+    write: bool,
+    control: bool,
+}
+
+impl AuthorityGraph {
+    pub fn new<N: SelfContainedObjectName, D, M>(spec: &Spec<N, D, M>, config: &Config) -> Self {
+        let mut graph = Self {
+            subjects: BTreeMap::new(),
+            objects: BTreeMap::new(),
+            flows: Vec::new(),
+            grants: Vec::new(),
+        };
+
+        for (tcb_id, tcb) in spec.filter_objects::<&object::TCB>() {
+            let mut accesses = Vec::new();
+            let mut fault_ep = None;
+            if let Some(Cap::CNode(cspace)) = tcb.maybe_slot(object::TCB::SLOT_CSPACE) {
+                fault_ep = walk_cspace(
+                    spec,
+                    cspace,
+                    config,
+                    tcb.extra.master_fault_ep,
+                    &mut accesses,
+                );
+            }
+            for (slot, cap) in tcb.slots() {
+                accesses.push(Access {
+                    cap: cap.clone(),
+                    via: Via::TCB { slot: *slot },
+                });
+            }
+            let mut mappings = Vec::new();
+            if let Some(Cap::PageTable(vspace)) = tcb.maybe_slot(object::TCB::SLOT_VSPACE) {
+                walk_vspace(spec, vspace.object, 0, 0, config, &mut mappings);
+            }
+            if let Some(cap) = fault_ep {
+                accesses.push(Access {
+                    cap: Cap::Endpoint(cap),
+                    via: Via::TCB {
+                        slot: object::TCB::SLOT_FAULT_EP,
+                    },
+                });
+            }
+            graph.subjects.insert(
+                tcb_id,
+                Subject {
+                    name: name_of(spec, tcb_id),
+                    accesses,
+                    mappings,
+                },
+            );
+        }
+
+        let mut caps_by_object = BTreeMap::<ObjectId, BTreeMap<ObjectId, Capabilities>>::new();
+        for (subject_id, subject) in graph.subjects.iter() {
+            for access in subject.accesses.iter() {
+                let caps = caps_by_object
+                    .entry(access.cap.obj())
+                    .or_default()
+                    .entry(*subject_id)
+                    .or_default();
+                accumulate(caps, access);
+            }
+            for mapping in subject.mappings.iter() {
+                let caps = caps_by_object
+                    .entry(mapping.frame)
+                    .or_default()
+                    .entry(*subject_id)
+                    .or_default();
+                caps.read |= mapping.rights.read;
+                caps.write |= mapping.rights.write;
+            }
+        }
+
+        for (obj_id, holders) in caps_by_object.iter() {
+            graph.objects.insert(
+                *obj_id,
+                ObjectInfo {
+                    name: name_of(spec, *obj_id),
+                    kind: kind_of(spec.object(*obj_id)),
+                },
+            );
+            graph.add_flows(*obj_id, spec.object(*obj_id), holders);
+        }
+
+        graph
+    }
+
+    fn add_flows<D, M>(
+        &mut self,
+        obj_id: ObjectId,
+        obj: &Object<D, M>,
+        holders: &BTreeMap<ObjectId, Capabilities>,
+    ) {
+        for (from, from_caps) in holders.iter() {
+            for (to, to_caps) in holders.iter() {
+                if from == to {
+                    continue;
+                }
+                let mut flow = |from: &ObjectId, to: &ObjectId, kind, badge| {
+                    self.flows.push(Flow {
+                        from: *from,
+                        to: *to,
+                        object: obj_id,
+                        kind,
+                        badge,
+                    })
+                };
+                match obj {
+                    Object::Endpoint if to_caps.receive => {
+                        for badge in from_caps.send.iter() {
+                            flow(from, to, FlowKind::Send, Some(*badge));
+                        }
+                        for badge in from_caps.fault.iter() {
+                            flow(from, to, FlowKind::Fault, Some(*badge));
+                        }
+                        // The receiver of a call or a fault gets a reply cap.
+                        if from_caps.call || !from_caps.fault.is_empty() {
+                            flow(to, from, FlowKind::Reply, None);
+                        }
+                        if from_caps.send_grant {
+                            self.grants.push(Grant {
+                                from: *from,
+                                to: *to,
+                                endpoint: obj_id,
+                                reply: false,
+                            });
+                        }
+                        if from_caps.call && to_caps.receive_grant {
+                            self.grants.push(Grant {
+                                from: *to,
+                                to: *from,
+                                endpoint: obj_id,
+                                reply: true,
+                            });
+                        }
+                    }
+                    Object::Notification if to_caps.receive || to_caps.bound => {
+                        for badge in from_caps.send.iter() {
+                            flow(from, to, FlowKind::Signal, Some(*badge));
+                        }
+                    }
+                    Object::Frame(_) if from_caps.write && to_caps.read => {
+                        flow(from, to, FlowKind::SharedMemory, None);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        // Control of a TCB allows both reading and writing its registers.
+        if let Object::TCB(_) = obj {
+            for (holder, caps) in holders.iter() {
+                if caps.control && *holder != obj_id {
+                    for (from, to) in [(*holder, obj_id), (obj_id, *holder)] {
+                        self.flows.push(Flow {
+                            from,
+                            to,
+                            object: obj_id,
+                            kind: FlowKind::Control,
+                            badge: None,
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    /// Looks up a subject by the name of its TCB.
+    pub fn subject_by_name(&self, name: &str) -> Option<ObjectId> {
+        self.subjects
+            .iter()
+            .find(|(_, subject)| subject.name == name)
+            .map(|(id, _)| *id)
+    }
+
+    /// Looks up an object referred to by any subject by its name.
+    pub fn object_by_name(&self, name: &str) -> Option<ObjectId> {
+        self.objects
+            .iter()
+            .find(|(_, info)| info.name == name)
+            .map(|(id, _)| *id)
+    }
+
+    /// Returns the direct flows from `from` to `to`.
+    pub fn flows_between(&self, from: ObjectId, to: ObjectId) -> impl Iterator<Item = &Flow> {
+        self.flows
+            .iter()
+            .filter(move |flow| flow.from == from && flow.to == to)
+    }
+
+    /// Returns the IPC, notification, and fault flows through which `from` can send to `to`.
+    pub fn can_send(&self, from: ObjectId, to: ObjectId) -> Vec<&Flow> {
+        self.flows_between(from, to)
+            .filter(|flow| {
+                matches!(
+                    flow.kind,
+                    FlowKind::Send | FlowKind::Reply | FlowKind::Signal | FlowKind::Fault
+                )
+            })
+            .collect()
+    }
+
+    /// Returns the subjects to which information can flow from `from`, directly or indirectly.
+    pub fn reachable_flows(&self, from: ObjectId) -> BTreeSet<ObjectId> {
+        reachable(from, self.flows.iter().map(|flow| (flow.from, flow.to)))
+            .into_keys()
+            .collect()
+    }
+
+    /// Returns a shortest path of information flow from `from` to `to`, excluding `from`.
+    pub fn flow_path(&self, from: ObjectId, to: ObjectId) -> Option<Vec<ObjectId>> {
+        path(from, to, self.flows.iter().map(|flow| (flow.from, flow.to)))
+    }
+
+    /// Returns a shortest path of cap transfers from `from` to `to`, excluding `from`.
+    pub fn grant_path(&self, from: ObjectId, to: ObjectId) -> Option<Vec<ObjectId>> {
+        path(
+            from,
+            to,
+            self.grants.iter().map(|grant| (grant.from, grant.to)),
+        )
+    }
+
+    /// Returns the subjects to which `from` can transfer caps, directly or indirectly.
+    pub fn reachable_grants(&self, from: ObjectId) -> BTreeSet<ObjectId> {
+        reachable(from, self.grants.iter().map(|grant| (grant.from, grant.to)))
+            .into_keys()
+            .collect()
+    }
+
+    /// Returns the subjects which can write to `frame`, either through a mapping, at the given
+    /// vaddr, or through a frame cap, which they could map.
+    pub fn writers(&self, frame: ObjectId) -> Vec<(ObjectId, Option<Word>)> {
+        self.frame_users(frame, |rights| rights.write)
+    }
+
+    /// Like [`writers`](Self::writers), but for reading.
+    pub fn readers(&self, frame: ObjectId) -> Vec<(ObjectId, Option<Word>)> {
+        self.frame_users(frame, |rights| rights.read)
+    }
+
+    fn frame_users(
+        &self,
+        frame: ObjectId,
+        f: impl Fn(&Rights) -> bool,
+    ) -> Vec<(ObjectId, Option<Word>)> {
+        let mut users = Vec::new();
+        for (subject_id, subject) in self.subjects.iter() {
+            for mapping in subject.mappings.iter() {
+                if mapping.frame == frame && f(&mapping.rights) {
+                    users.push((*subject_id, Some(mapping.vaddr)));
+                }
+            }
+            for access in subject.accesses.iter() {
+                if let (Cap::Frame(cap), Via::CSpace { .. }) = (&access.cap, &access.via) {
+                    if cap.object == frame && f(&cap.rights) {
+                        users.push((*subject_id, None));
+                    }
+                }
+            }
+        }
+        users.dedup();
+        users
+    }
+
+    pub fn name(&self, obj_id: ObjectId) -> &str {
+        self.subjects
+            .get(&obj_id)
+            .map(|subject| subject.name.as_str())
+            .or_else(|| self.objects.get(&obj_id).map(|info| info.name.as_str()))
+            .unwrap()
+    }
+}
+
+fn accumulate(caps: &mut Capabilities, access: &Access) {
+    let tcb_slot = match access.via {
+        Via::TCB { slot } => Some(slot),
+        Via::CSpace { .. } => None,
+    };
+    match (&access.cap, tcb_slot) {
+        (
+            Cap::Endpoint(cap),
+            Some(object::TCB::SLOT_FAULT_EP | object::TCB::SLOT_TEMP_FAULT_EP),
+        ) => {
+            caps.fault.insert(cap.badge);
+        }
+        (Cap::Endpoint(cap), None) => {
+            if cap.rights.write {
+                caps.send.insert(cap.badge);
+                caps.call |= cap.rights.grant || cap.rights.grant_reply;
+                caps.send_grant |= cap.rights.grant;
+            }
+            if cap.rights.read {
+                caps.receive = true;
+                caps.receive_grant |= cap.rights.grant;
+            }
+        }
+        (Cap::Notification(_), Some(object::TCB::SLOT_BOUND_NOTIFICATION)) => {
+            caps.bound = true;
+        }
+        (Cap::Notification(cap), None) => {
+            if cap.rights.write {
+                caps.send.insert(cap.badge);
+            }
+            caps.receive |= cap.rights.read;
+        }
+        (Cap::Frame(cap), None) => {
+            caps.read |= cap.rights.read;
+            caps.write |= cap.rights.write;
+        }
+        (Cap::TCB(_), None) => {
+            caps.control = true;
+        }
+        _ => {}
+    }
+}
+
+// Visits every CNode reachable from `root`, including those whose slots cannot be addressed by a
+// full-depth lookup, since a subject can still copy caps out of them. Returns the cap that
+// `master_fault_ep` resolves to, if it is an endpoint cap.
+fn walk_cspace<N, D, M>(
+    spec: &Spec<N, D, M>,
+    root: &cap::CNode,
+    config: &Config,
+    master_fault_ep: Option<CPtr>,
+    accesses: &mut Vec<Access>,
+) -> Option<cap::Endpoint> {
+    let mut fault_ep = None;
+    let mut visited = BTreeSet::new();
+    // CNode caps, with the bits of the path to them and the number of those bits, if the path
+    // fits in a word.
+    let mut stack = vec![(root, Some((0, 0)))];
+    while let Some((cnode_cap, prefix)) = stack.pop() {
+        if !visited.insert(cnode_cap.object) {
+            continue;
+        }
+        let cnode = match spec.object(cnode_cap.object) {
+            Object::CNode(cnode) => cnode,
+            _ => continue,
+        };
+        let radix = cnode.size_bits;
+        let guard_size = usize::try_from(cnode_cap.guard_size).unwrap();
+        let prefix = prefix.and_then(|(bits, depth): (CPtr, usize)| {
+            let depth = depth + guard_size + radix;
+            (depth <= config.word_bits).then(|| {
+                let bits = shl(shl(bits, guard_size) | cnode_cap.guard, radix);
+                (bits, depth)
+            })
+        });
+        for (slot, cap) in cnode.slots() {
+            let path = prefix.map(|(bits, depth)| (bits | Word::try_from(*slot).unwrap(), depth));
+            let cptr = path.map(|(bits, depth)| shl(bits, config.word_bits - depth));
+            if let (Some((bits, depth)), Some(target), Cap::Endpoint(cap)) =
+                (path, master_fault_ep, cap)
+            {
+                if shr(target, config.word_bits - depth) == bits {
+                    fault_ep = Some(cap.clone());
+                }
+            }
+            accesses.push(Access {
+                cap: cap.clone(),
+                via: Via::CSpace {
+                    cnode: cnode_cap.object,
+                    slot: *slot,
+                    cptr,
+                },
+            });
+            if let Cap::CNode(child) = cap {
+                stack.push((child, path.filter(|(_, depth)| *depth < config.word_bits)));
+            }
+        }
+    }
+    fault_ep
+}
+
+fn walk_vspace<N, D, M>(
+    spec: &Spec<N, D, M>,
+    page_table: ObjectId,
+    level: usize,
+    base: Word,
+    config: &Config,
+    mappings: &mut Vec<Mapping>,
+) {
+    if level >= config.vspace_levels {
+        return;
+    }
+    let obj = match spec.object(page_table) {
+        Object::PageTable(obj) => obj,
+        _ => return,
+    };
+    for (slot, entry) in obj.entries() {
+        let vaddr = base + (Word::try_from(slot).unwrap() << config.entry_bits(level));
+        match entry {
+            PageTableEntry::Frame(cap) => mappings.push(Mapping {
+                frame: cap.object,
+                vaddr,
+                rights: cap.rights,
+            }),
+            PageTableEntry::PageTable(cap) => {
+                walk_vspace(spec, cap.object, level + 1, vaddr, config, mappings)
+            }
+        }
+    }
+}
+
+fn shl(x: Word, n: usize) -> Word {
+    x.checked_shl(n.try_into().unwrap()).unwrap_or(0)
+}
+
+fn shr(x: Word, n: usize) -> Word {
+    x.checked_shr(n.try_into().unwrap()).unwrap_or(0)
+}
+
+// Breadth-first, so that paths found by walking back through predecessors are shortest.
+fn reachable(
+    from: ObjectId,
+    edges: impl Iterator<Item = (ObjectId, ObjectId)>,
+) -> BTreeMap<ObjectId, ObjectId> {
+    let mut succs = BTreeMap::<ObjectId, BTreeSet<ObjectId>>::new();
+    for (a, b) in edges {
+        succs.entry(a).or_default().insert(b);
+    }
+    let mut preds = BTreeMap::new();
+    let mut queue = VecDeque::from([from]);
+    while let Some(node) = queue.pop_front() {
+        for succ in succs.get(&node).into_iter().flatten() {
+            if *succ != from && !preds.contains_key(succ) {
+                preds.insert(*succ, node);
+                queue.push_back(*succ);
+            }
+        }
+    }
+    preds
+}
+
+fn path(
+    from: ObjectId,
+    to: ObjectId,
+    edges: impl Iterator<Item = (ObjectId, ObjectId)>,
+) -> Option<Vec<ObjectId>> {
+    let preds = reachable(from, edges);
+    let mut path = vec![to];
+    let mut node = to;
+    while let Some(pred) = preds.get(&node) {
+        if *pred == from {
+            path.reverse();
+            return Some(path);
+        }
+        path.push(*pred);
+        node = *pred;
+    }
+    None
+}
+
+fn name_of<N: SelfContainedObjectName, D, M>(spec: &Spec<N, D, M>, obj_id: ObjectId) -> String {
+    match spec.name(obj_id).self_contained_object_name() {
+        Some(name) => name.to_owned(),
+        None => format!("{}_{}", kind_of(spec.object(obj_id)), obj_id),
+    }
+}
+
+fn kind_of<D, M>(obj: &Object<D, M>) -> &'static str {
+    match obj {
+        Object::Untyped(_) => "untyped",
+        Object::Endpoint => "endpoint",
+        Object::Notification => "notification",
+        Object::CNode(_) => "cnode",
+        Object::TCB(_) => "tcb",
+        Object::IRQ(_) => "irq",
+        Object::VCPU => "vcpu",
+        Object::Frame(_) => "frame",
+        Object::PageTable(_) => "page_table",
+        Object::ASIDPool(_) => "asid_pool",
+        Object::ArmIRQ(_) => "arm_irq",
+        Object::SchedContext(_) => "sched_context",
+        Object::Reply => "reply",
+        Object::IRQIOAPIC(_) => "ioapic_irq",
+        Object::IRQMSI(_) => "msi_irq",
+        Object::RiscvIRQ(_) => "riscv_irq",
+        Object::IOPorts(_) => "io_ports",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sel4_capdl_initializer_types::builder::{
+        kind, BuilderSpec, ObjectHandle, ObjectSizes, SpecBuilder, VSpace, VSpaceLayout,
+    };
+    use sel4_capdl_initializer_types::object::TCBExtraInfo;
+    use sel4_capdl_initializer_types::Indirect;
+
+    use super::*;
+
+    const SHARED: usize = 0x10_0000;
+
+    fn object_sizes() -> ObjectSizes {
+        [
+            ("seL4_EndpointObject", 4),
+            ("seL4_NotificationObject", 5),
+            ("seL4_TCBObject", 11),
+            ("seL4_Slot", 5),
+            ("seL4_PageTableObject", 12),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    fn rights(read: bool, write: bool, grant: bool, grant_reply: bool) -> Rights {
+        Rights {
+            read,
+            write,
+            grant,
+            grant_reply,
+        }
+    }
+
+    // A TCB whose CSpace is a single-level CNode of 16 slots, resolved with a full-depth lookup.
+    fn subject(
+        builder: &mut SpecBuilder,
+        name: &str,
+        master_fault_ep: Option<CPtr>,
+    ) -> (ObjectHandle<kind::TCB>, ObjectHandle<kind::CNode>, VSpace) {
+        let vspace = builder.vspace(format!("{name}_vspace"), VSpaceLayout::AARCH64);
+        let cspace = builder.cnode(format!("{name}_cspace"), 4);
+        let tcb = builder.tcb(
+            name,
+            TCBExtraInfo {
+                ipc_buffer_addr: 0,
+                affinity: 0,
+                prio: 0,
+                max_prio: 0,
+                domain: None,
+                resume: true,
+                ip: 0,
+                sp: 0,
+                spsr: 0,
+                gprs: Indirect::from_owned(Vec::new().into_boxed_slice()),
+                master_fault_ep,
+            },
+        );
+        builder.set_slot(tcb, object::TCB::SLOT_CSPACE, cspace.cap(0, 60));
+        builder.set_slot(tcb, object::TCB::SLOT_VSPACE, vspace.root().cap());
+        (tcb, cspace, vspace)
+    }
+
+    // A client calls a server, which signals it back and reads memory the client writes. A
+    // monitor handles the client's faults and controls its TCB. A fourth subject has no caps.
+    fn system() -> BuilderSpec {
+        let mut builder = SpecBuilder::new();
+        let (client, client_cspace, mut client_vspace) = subject(&mut builder, "client", Some(3));
+        let (server, server_cspace, mut server_vspace) = subject(&mut builder, "server", None);
+        let (_, monitor_cspace, _) = subject(&mut builder, "monitor", None);
+        subject(&mut builder, "other", None);
+
+        let ep = builder.endpoint("ep");
+        let ntfn = builder.notification("ntfn");
+        let fault_ep = builder.endpoint("fault_ep");
+        let shared = builder.frame("shared", 12, None, Vec::new());
+
+        builder.set_slot(
+            client_cspace,
+            1,
+            ep.cap(1, rights(false, true, false, true)),
+        );
+        builder.set_slot(
+            client_cspace,
+            3,
+            fault_ep.cap(5, rights(false, true, false, false)),
+        );
+        builder.set_slot(
+            client,
+            object::TCB::SLOT_BOUND_NOTIFICATION,
+            ntfn.cap(0, Rights::read_only()),
+        );
+        builder.map(
+            &mut client_vspace,
+            SHARED,
+            shared,
+            Rights::read_write(),
+            true,
+        );
+
+        builder.set_slot(
+            server_cspace,
+            1,
+            ep.cap(0, rights(true, false, true, false)),
+        );
+        builder.set_slot(
+            server_cspace,
+            2,
+            ntfn.cap(2, rights(false, true, false, false)),
+        );
+        builder.map(
+            &mut server_vspace,
+            SHARED,
+            shared,
+            Rights::read_only(),
+            true,
+        );
+        let _ = server;
+
+        builder.set_slot(monitor_cspace, 1, fault_ep.cap(0, Rights::read_only()));
+        builder.set_slot(monitor_cspace, 2, client.cap());
+
+        builder.build(&object_sizes()).unwrap()
+    }
+
+    fn kinds<'a>(flows: impl IntoIterator<Item = &'a Flow>) -> Vec<(FlowKind, Option<Badge>)> {
+        let mut kinds = flows
+            .into_iter()
+            .map(|flow| (flow.kind, flow.badge))
+            .collect::<Vec<_>>();
+        kinds.sort();
+        kinds
+    }
+
+    #[test]
+    fn flows() {
+        let spec = system();
+        let graph = AuthorityGraph::new(&spec, &Config::AARCH64);
+        let subject = |name| graph.subject_by_name(name).unwrap();
+        let (client, server, monitor, other) = (
+            subject("client"),
+            subject("server"),
+            subject("monitor"),
+            subject("other"),
+        );
+
+        assert_eq!(
+            kinds(graph.flows_between(client, server)),
+            [(FlowKind::Send, Some(1)), (FlowKind::SharedMemory, None)]
+        );
+        assert_eq!(
+            kinds(graph.can_send(server, client)),
+            [(FlowKind::Reply, None), (FlowKind::Signal, Some(2))]
+        );
+        assert_eq!(
+            kinds(graph.flows_between(client, monitor)),
+            [
+                (FlowKind::Send, Some(5)),
+                (FlowKind::Fault, Some(5)),
+                (FlowKind::Control, None)
+            ]
+        );
+        assert_eq!(
+            kinds(graph.flows_between(monitor, client)),
+            [(FlowKind::Reply, None), (FlowKind::Control, None)]
+        );
+        assert!(graph
+            .flows
+            .iter()
+            .all(|flow| flow.from != other && flow.to != other));
+
+        assert_eq!(
+            graph.flow_path(server, monitor),
+            Some(vec![client, monitor])
+        );
+        assert_eq!(graph.flow_path(other, client), None);
+        assert_eq!(
+            graph.reachable_flows(server),
+            BTreeSet::from([client, monitor])
+        );
+    }
+
+    #[test]
+    fn grants() {
+        let spec = system();
+        let graph = AuthorityGraph::new(&spec, &Config::AARCH64);
+        let client = graph.subject_by_name("client").unwrap();
+        let server = graph.subject_by_name("server").unwrap();
+
+        // The server can grant through its replies to the client's calls, but not the reverse.
+        assert_eq!(
+            graph.grants,
+            [Grant {
+                from: server,
+                to: client,
+                endpoint: graph.object_by_name("ep").unwrap(),
+                reply: true,
+            }]
+        );
+        assert_eq!(graph.grant_path(server, client), Some(vec![client]));
+        assert_eq!(graph.grant_path(client, server), None);
+        assert!(graph.reachable_grants(client).is_empty());
+    }
+
+    #[test]
+    fn frames() {
+        let spec = system();
+        let graph = AuthorityGraph::new(&spec, &Config::AARCH64);
+        let client = graph.subject_by_name("client").unwrap();
+        let server = graph.subject_by_name("server").unwrap();
+        let shared = graph.object_by_name("shared").unwrap();
+        let vaddr = Some(Word::try_from(SHARED).unwrap());
+
+        assert_eq!(graph.writers(shared), [(client, vaddr)]);
+        assert_eq!(graph.readers(shared), [(client, vaddr), (server, vaddr)]);
+    }
+
+    #[test]
+    fn cspace_paths() {
+        let mut builder = SpecBuilder::new();
+        let (_, root, _) = subject(&mut builder, "tcb", Some(3));
+        let child = builder.cnode("child", 4);
+        let deep = builder.cnode("deep", 4);
+        let fault_ep = builder.endpoint("fault_ep");
+        let ep = builder.endpoint("ep");
+        builder.set_slot(root, 1, child.cap(0, 0));
+        // The root CNode's guard uses up the remaining bits, so nothing below it is addressable.
+        builder.set_slot(root, 2, deep.cap(0, 0));
+        builder.set_slot(root, 3, fault_ep.cap(0, Rights::all()));
+        builder.set_slot(child, 1, ep.cap(0, Rights::all()));
+        builder.set_slot(deep, 1, ep.cap(0, Rights::all()));
+        let spec = builder.build(&object_sizes()).unwrap();
+
+        let graph = AuthorityGraph::new(&spec, &Config::AARCH64);
+        let tcb = &graph.subjects[&graph.subject_by_name("tcb").unwrap()];
+        let cptrs = tcb
+            .accesses
+            .iter()
+            .filter_map(|access| match access.via {
+                Via::CSpace { slot, cptr, .. } => Some((graph.name(access.cap.obj()), slot, cptr)),
+                Via::TCB { .. } => None,
+            })
+            .collect::<BTreeSet<_>>();
+        assert_eq!(
+            cptrs,
+            BTreeSet::from([
+                ("child", 1, Some(1)),
+                ("deep", 2, Some(2)),
+                ("fault_ep", 3, Some(3)),
+                ("ep", 1, None),
+            ])
+        );
+        // The master fault endpoint is resolved through the CSpace.
+        assert!(tcb.accesses.iter().any(|access| matches!(
+            access.via,
+            Via::TCB {
+                slot: object::TCB::SLOT_FAULT_EP
+            }
+        ) && graph.name(access.cap.obj()) == "fault_ep"));
+    }
+
+    #[test]
+    fn dot() {
+        let spec = system();
+        let graph = AuthorityGraph::new(&spec, &Config::AARCH64);
+        let dot = graph.to_dot();
+        assert!(dot.starts_with("digraph authority {"));
+        assert!(dot.contains("[label=\"grant (reply) via ep\", style=dashed]"));
+        assert!(dot.contains("[label=\"control\", style=bold]"));
+    }
+}
@@ -0,0 +1,107 @@
+//
+// Copyright 2023, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use std::process::ExitCode;
+
+use anyhow::{anyhow, Result};
+
+use sel4_capdl_initializer_authority::AuthorityGraph;
+use sel4_capdl_initializer_cdl::{read_input_spec, read_object_sizes};
+use sel4_capdl_initializer_types::{ObjectId, Word};
+
+mod args;
+
+use args::{Args, Query};
+
+fn main() -> Result<ExitCode> {
+    let args = Args::parse()?;
+
+    if args.verbose {
+        eprintln!("{:#?}", args);
+    }
+
+    let object_sizes = args
+        .object_sizes_path
+        .as_ref()
+        .map(read_object_sizes)
+        .transpose()?;
+
+    let spec = read_input_spec(&args.spec_path, object_sizes.as_ref())?;
+
+    let graph = AuthorityGraph::new(&spec, &args.config);
+
+    let subject = |name: &str| {
+        graph
+            .subject_by_name(name)
+            .ok_or_else(|| anyhow!("no TCB named {name:?}"))
+    };
+    let object = |name: &str| {
+        graph
+            .object_by_name(name)
+            .ok_or_else(|| anyhow!("no object named {name:?} is accessible to any TCB"))
+    };
+
+    let found = match &args.query {
+        Query::Dot => {
+            print!("{}", graph.to_dot());
+            true
+        }
+        Query::Json => {
+            println!("{}", serde_json::to_string_pretty(&graph)?);
+            true
+        }
+        Query::CanSend { from, to } => {
+            let flows = graph.can_send(subject(from)?, subject(to)?);
+            for flow in flows.iter() {
+                println!(
+                    "{:?} via {}{}",
+                    flow.kind,
+                    graph.name(flow.object),
+                    match flow.badge {
+                        Some(badge) => format!(" (badge {badge:#x})"),
+                        None => String::new(),
+                    }
+                );
+            }
+            !flows.is_empty()
+        }
+        Query::FlowPath { from, to } => {
+            print_path(&graph, graph.flow_path(subject(from)?, subject(to)?))
+        }
+        Query::GrantPath { from, to } => {
+            print_path(&graph, graph.grant_path(subject(from)?, subject(to)?))
+        }
+        Query::Writers { frame } => print_users(&graph, graph.writers(object(frame)?)),
+        Query::Readers { frame } => print_users(&graph, graph.readers(object(frame)?)),
+    };
+
+    Ok(if found {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+fn print_path(graph: &AuthorityGraph, path: Option<Vec<ObjectId>>) -> bool {
+    match path {
+        Some(path) => {
+            let names = path.iter().map(|id| graph.name(*id)).collect::<Vec<_>>();
+            println!("{}", names.join(" -> "));
+            true
+        }
+        None => false,
+    }
+}
+
+fn print_users(graph: &AuthorityGraph, users: Vec<(ObjectId, Option<Word>)>) -> bool {
+    for (subject, vaddr) in users.iter() {
+        match vaddr {
+            Some(vaddr) => println!("{} (mapped at {:#x})", graph.name(*subject), vaddr),
+            None => println!("{} (by cap)", graph.name(*subject)),
+        }
+    }
+    !users.is_empty()
+}
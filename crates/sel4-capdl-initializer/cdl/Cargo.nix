@@ -0,0 +1,14 @@
+#
+# Copyright 2023, Colias Group, LLC
+#
+# SPDX-License-Identifier: BSD-2-Clause
+#
+
+{ mk, localCrates }:
+
+mk {
+  package.name = "sel4-capdl-initializer-cdl";
+  dependencies = {
+    sel4-capdl-initializer-types = localCrates.sel4-capdl-initializer-types // { features = [ "builder" "std" "serde" ]; };
+  };
+}
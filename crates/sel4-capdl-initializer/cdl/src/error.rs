@@ -0,0 +1,110 @@
+//
+// Copyright 2023, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use std::error::Error;
+use std::fmt;
+use std::ops::Range;
+
+pub type Span = Range<usize>;
+
+/// An error in a CapDL spec, located by a span of byte offsets into its source.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ParseError {
+    span: Span,
+    message: String,
+}
+
+impl ParseError {
+    pub(crate) fn new(span: Span, message: impl Into<String>) -> Self {
+        Self {
+            span,
+            message: message.into(),
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        self.span.clone()
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Returns the 1-based line and column of the start of the span within `source`.
+    pub fn line_and_column(&self, source: &str) -> (usize, usize) {
+        let start = self.span.start.min(source.len());
+        let line_start = source[..start].rfind('\n').map(|i| i + 1).unwrap_or(0);
+        let line = source[..start].matches('\n').count() + 1;
+        let column = source[line_start..start].chars().count() + 1;
+        (line, column)
+    }
+
+    /// Renders this error in the style of `rustc`, with the offending line of `source` and the span
+    /// underlined. `path` is only used for display.
+    pub fn render(&self, path: &str, source: &str) -> String {
+        let (line, column) = self.line_and_column(source);
+        let start = self.span.start.min(source.len());
+        let line_start = source[..start].rfind('\n').map(|i| i + 1).unwrap_or(0);
+        let line_end = source[start..]
+            .find('\n')
+            .map(|i| start + i)
+            .unwrap_or(source.len());
+        let end = self.span.end.clamp(start, line_end);
+        let line_text = &source[line_start..line_end];
+        let gutter = line.to_string();
+        format!(
+            "{path}:{line}:{column}: error: {message}\n{pad} |\n{gutter} | {line_text}\n{pad} | {indent}{carets}\n",
+            message = self.message,
+            pad = " ".repeat(gutter.len()),
+            indent = " ".repeat(column - 1),
+            carets = "^".repeat(source[start..end].chars().count().max(1)),
+        )
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} (at bytes {}..{})",
+            self.message, self.span.start, self.span.end
+        )
+    }
+}
+
+impl Error for ParseError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "objects {\n  x = bogus\n}\n";
+
+    #[test]
+    fn line_and_column() {
+        assert_eq!(ParseError::new(0..7, "").line_and_column(SOURCE), (1, 1));
+        assert_eq!(ParseError::new(16..21, "").line_and_column(SOURCE), (2, 7));
+        assert_eq!(ParseError::new(24..24, "").line_and_column(SOURCE), (4, 1));
+        assert_eq!(ParseError::new(99..99, "").line_and_column(SOURCE), (4, 1));
+    }
+
+    #[test]
+    fn render() {
+        let err = ParseError::new(16..21, "unknown object type `bogus`");
+        assert_eq!(
+            err.render("a.cdl", SOURCE),
+            "a.cdl:2:7: error: unknown object type `bogus`\n  |\n2 |   x = bogus\n  |       ^^^^^\n"
+        );
+        // Spans which run past the end of the line are cut off there, and empty spans get one
+        // caret.
+        assert!(ParseError::new(8..99, "")
+            .render("a.cdl", SOURCE)
+            .ends_with("1 | objects {\n  |         ^\n"));
+        assert!(ParseError::new(24..24, "")
+            .render("a.cdl", SOURCE)
+            .ends_with("4 | \n  | ^\n"));
+    }
+}
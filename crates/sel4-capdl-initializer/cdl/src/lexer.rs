@@ -0,0 +1,180 @@
+//
+// Copyright 2023, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use crate::error::{ParseError, Span};
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) enum TokenKind {
+    // Identifiers, keywords, numbers, and size literals such as `4k`.
+    Word(String),
+    Str(String),
+    Punct(char),
+    Eof,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) struct Token {
+    pub(crate) kind: TokenKind,
+    pub(crate) span: Span,
+}
+
+const PUNCTUATION: &[char] = &['=', '(', ')', '{', '}', '[', ']', ':', ','];
+
+fn is_word_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '-'
+}
+
+pub(crate) fn tokenize(src: &str) -> Result<Vec<Token>, ParseError> {
+    let mut tokens = vec![];
+    let mut chars = src.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        let rest = &src[start..];
+        if c.is_whitespace() {
+            chars.next();
+        } else if rest.starts_with("--") {
+            while chars.next_if(|&(_, c)| c != '\n').is_some() {}
+        } else if rest.starts_with("{-") {
+            let end = rest
+                .find("-}")
+                .ok_or_else(|| ParseError::new(start..start + 2, "unterminated block comment"))?;
+            let end = start + end + 2;
+            while chars.next_if(|&(i, _)| i < end).is_some() {}
+        } else if c == '"' {
+            chars.next();
+            let mut value = String::new();
+            loop {
+                match chars.next() {
+                    Some((i, '"')) => {
+                        tokens.push(Token {
+                            kind: TokenKind::Str(value),
+                            span: start..i + 1,
+                        });
+                        break;
+                    }
+                    Some((_, '\n')) | None => {
+                        return Err(ParseError::new(start..start + 1, "unterminated string"));
+                    }
+                    Some((_, c)) => value.push(c),
+                }
+            }
+        } else if PUNCTUATION.contains(&c) {
+            chars.next();
+            tokens.push(Token {
+                kind: TokenKind::Punct(c),
+                span: start..start + 1,
+            });
+        } else if is_word_char(c) {
+            let mut end = start;
+            // A word may not contain the start of a comment.
+            while let Some((i, c)) =
+                chars.next_if(|&(i, c)| is_word_char(c) && !src[i..].starts_with("--"))
+            {
+                end = i + c.len_utf8();
+            }
+            tokens.push(Token {
+                kind: TokenKind::Word(src[start..end].to_owned()),
+                span: start..end,
+            });
+        } else {
+            return Err(ParseError::new(
+                start..start + c.len_utf8(),
+                format!("unexpected character {c:?}"),
+            ));
+        }
+    }
+    tokens.push(Token {
+        kind: TokenKind::Eof,
+        span: src.len()..src.len(),
+    });
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(src: &str) -> Vec<TokenKind> {
+        tokenize(src)
+            .unwrap()
+            .into_iter()
+            .map(|token| token.kind)
+            .collect()
+    }
+
+    fn word(s: &str) -> TokenKind {
+        TokenKind::Word(s.to_owned())
+    }
+
+    #[test]
+    fn tokens() {
+        assert_eq!(
+            kinds("x[2] = frame (4k, fill: [{0 4 CDL_FrameFill_FileData \"a b\" 0}])"),
+            [
+                word("x"),
+                TokenKind::Punct('['),
+                word("2"),
+                TokenKind::Punct(']'),
+                TokenKind::Punct('='),
+                word("frame"),
+                TokenKind::Punct('('),
+                word("4k"),
+                TokenKind::Punct(','),
+                word("fill"),
+                TokenKind::Punct(':'),
+                TokenKind::Punct('['),
+                TokenKind::Punct('{'),
+                word("0"),
+                word("4"),
+                word("CDL_FrameFill_FileData"),
+                TokenKind::Str("a b".to_owned()),
+                word("0"),
+                TokenKind::Punct('}'),
+                TokenKind::Punct(']'),
+                TokenKind::Punct(')'),
+                TokenKind::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn comments() {
+        assert_eq!(
+            kinds("a -- line\nb{- block\n-}c d--e\n[0..255]"),
+            [
+                word("a"),
+                word("b"),
+                word("c"),
+                word("d"),
+                TokenKind::Punct('['),
+                word("0..255"),
+                TokenKind::Punct(']'),
+                TokenKind::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn spans() {
+        let tokens = tokenize("ep = ep \"s\"").unwrap();
+        let spans = tokens
+            .iter()
+            .map(|token| token.span.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(spans, [0..2, 3..4, 5..7, 8..11, 11..11]);
+    }
+
+    #[test]
+    fn errors() {
+        let err = |src| tokenize(src).unwrap_err();
+        assert_eq!(err("a {- b").span(), 2..4);
+        assert_eq!(err("a {- b").message(), "unterminated block comment");
+        assert_eq!(err("a \"b\nc\"").span(), 2..3);
+        assert_eq!(err("a \"b").message(), "unterminated string");
+        assert_eq!(err("a ; b").span(), 2..3);
+        assert_eq!(err("a é").span(), 2..4);
+        assert_eq!(err("a é").message(), "unexpected character 'é'");
+    }
+}
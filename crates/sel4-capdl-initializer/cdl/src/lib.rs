@@ -0,0 +1,48 @@
+//
+// Copyright 2023, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+//! A parser for the CapDL text language (`.cdl` files).
+//!
+//! This is an alternative to `parse-capDL --json` for producing specs for
+//! `sel4-capdl-initializer-add-spec`. The result is the same [`Spec`] that
+//! [`InputSpec::parse`](sel4_capdl_initializer_types::InputSpec::parse) deserializes from JSON,
+//! with objects ordered the way the initializer expects.
+//!
+//! The supported subset of the language covers what is needed to describe systems for the
+//! initializer: object declarations (including arrays, frame fills, and untyped covers), cap
+//! tables (including named TCB slots), and IRQ maps. `cdt` sections are accepted but ignored.
+//!
+//! A `handoff` section, which is specific to the initializer, names a CNode to which leftover
+//! resources are handed off:
+//!
+//! ```text
+//! handoff rm_cnode (untyped_slots: [16..255], info: rm_info, irq_control: 1, asid_control: 2)
+//! ```
+//!
+//! [`read_input_spec`] reads specs for host tools, which accept both JSON and `.cdl` specs.
+
+use sel4_capdl_initializer_types::builder::BuilderSpec;
+
+mod error;
+mod lexer;
+mod lower;
+mod object_sizes;
+mod parser;
+mod read;
+
+pub use error::{ParseError, Span};
+pub use object_sizes::parse_object_sizes;
+pub use read::{read_input_spec, read_object_sizes, ReadError};
+pub use sel4_capdl_initializer_types::builder::ObjectSizes;
+
+pub type CdlSpec = BuilderSpec;
+
+/// Parses the CapDL spec `src`.
+pub fn parse(src: &str, object_sizes: &ObjectSizes) -> Result<CdlSpec, ParseError> {
+    let tokens = lexer::tokenize(src)?;
+    let ast = parser::parse(tokens)?;
+    lower::lower(&ast, object_sizes)
+}
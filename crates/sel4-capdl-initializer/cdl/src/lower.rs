@@ -0,0 +1,1491 @@
+//
+// Copyright 2023, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+// Resolves names in the syntax tree and hands the result to a `SpecBuilder`, which lays objects
+// out the way the initializer expects.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use sel4_capdl_initializer_types::builder::{BuildError, SpecBuilder};
+use sel4_capdl_initializer_types::{
+    cap, object, Cap, CapSlot, CapTableEntry, FileContent, Fill, FillEntry, FillEntryContent,
+    FillEntryContentBootInfo, FillEntryContentBootInfoId, FrameInit, HandoffInfo, Indirect, Object,
+    ResourceHandoff, Rights, Word,
+};
+
+use crate::error::{ParseError, Span};
+use crate::parser::{
+    element_name, Ast, CoverEntry, HandoffDecl, ObjectDecl, ObjectRef, Param, Spanned, Value,
+};
+use crate::{CdlSpec, ObjectSizes};
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum ObjectType {
+    Untyped,
+    Endpoint,
+    Notification,
+    CNode,
+    TCB,
+    IRQ,
+    ArmIRQ,
+    VCPU,
+    Frame,
+    PageTable(PageTableType),
+    ASIDPool,
+    SchedContext,
+    Reply,
+    IRQIOAPIC,
+    IRQMSI,
+    RiscvIRQ,
+    IOPorts,
+}
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+enum PageTableType {
+    PT,
+    PD,
+    PUD,
+    PGD,
+    PDPT,
+    PML4,
+}
+
+impl ObjectType {
+    fn from_keyword(keyword: &str) -> Option<Self> {
+        Some(match keyword {
+            "ut" => Self::Untyped,
+            "ep" => Self::Endpoint,
+            "notification" => Self::Notification,
+            "cnode" => Self::CNode,
+            "tcb" => Self::TCB,
+            "irq" => Self::IRQ,
+            "arm_irq" => Self::ArmIRQ,
+            "vcpu" => Self::VCPU,
+            "frame" => Self::Frame,
+            "pt" => Self::PageTable(PageTableType::PT),
+            "pd" => Self::PageTable(PageTableType::PD),
+            "pud" => Self::PageTable(PageTableType::PUD),
+            "pgd" => Self::PageTable(PageTableType::PGD),
+            "pdpt" => Self::PageTable(PageTableType::PDPT),
+            "pml4" => Self::PageTable(PageTableType::PML4),
+            "asid_pool" => Self::ASIDPool,
+            "sc" => Self::SchedContext,
+            "rtreply" => Self::Reply,
+            "ioapic_irq" => Self::IRQIOAPIC,
+            "msi_irq" => Self::IRQMSI,
+            "riscv_irq" => Self::RiscvIRQ,
+            "io_ports" => Self::IOPorts,
+            _ => return None,
+        })
+    }
+
+    fn has_slots(self) -> bool {
+        matches!(self, Self::CNode | Self::TCB | Self::PageTable(_)) || self.is_irq()
+    }
+
+    fn is_irq(self) -> bool {
+        matches!(
+            self,
+            Self::IRQ | Self::ArmIRQ | Self::IRQIOAPIC | Self::IRQMSI | Self::RiscvIRQ
+        )
+    }
+}
+
+impl PageTableType {
+    fn x86_64_level(self) -> u8 {
+        match self {
+            Self::PML4 | Self::PGD => 0,
+            Self::PDPT | Self::PUD => 1,
+            Self::PD => 2,
+            Self::PT => 3,
+        }
+    }
+
+    fn is_top_level(self) -> bool {
+        matches!(self, Self::PGD | Self::PML4)
+    }
+}
+
+const TCB_SLOT_NAMES: &[(&str, CapSlot)] = &[
+    ("cspace", 0),
+    ("vspace", 1),
+    ("reply_slot", 2),
+    ("caller_slot", 3),
+    ("ipc_buffer_slot", 4),
+    ("fault_ep_slot", 5),
+    ("sc_slot", 6),
+    ("temp_fault_ep_slot", 7),
+    ("bound_notification", 8),
+    ("vcpu", 9),
+];
+
+// Guards against specs which would otherwise exhaust memory while being lowered.
+const MAX_ARRAY_LEN: u64 = 1 << 20;
+
+// Cap parameters which only matter to other consumers of CapDL specs.
+const IGNORED_CAP_PARAMS: &[&str] = &["asid", "reply", "master_reply", "ports", "mapping"];
+
+struct Decl<'a> {
+    name: String,
+    span: Span,
+    ty: ObjectType,
+    params: &'a [Param],
+    parent: Option<usize>,
+    children: Vec<usize>,
+}
+
+struct Lowerer<'a> {
+    object_sizes: &'a ObjectSizes,
+    x86_64: bool,
+    decls: Vec<Decl<'a>>,
+    names: BTreeMap<String, usize>,
+    arrays: BTreeMap<String, u64>,
+    cover_refs: Vec<(usize, &'a ObjectRef)>,
+}
+
+// Objects are identified by their index in `Lowerer::decls`, which is also their ID in the
+// builder.
+
+pub(crate) fn lower(ast: &Ast, object_sizes: &ObjectSizes) -> Result<CdlSpec, ParseError> {
+    let mut lowerer = Lowerer {
+        object_sizes,
+        x86_64: matches!(&ast.arch, Some(arch) if arch.value == "x86_64"),
+        decls: vec![],
+        names: BTreeMap::new(),
+        arrays: BTreeMap::new(),
+        cover_refs: vec![],
+    };
+    for decl in &ast.objects {
+        lowerer.collect(decl, None)?;
+    }
+    lowerer.resolve_cover_refs()?;
+    lowerer.lower_spec(ast)
+}
+
+impl<'a> Lowerer<'a> {
+    fn collect(&mut self, decl: &'a ObjectDecl, parent: Option<usize>) -> Result<(), ParseError> {
+        let ty = ObjectType::from_keyword(&decl.ty.value).ok_or_else(|| {
+            ParseError::new(
+                decl.ty.span.clone(),
+                format!("unknown object type `{}`", decl.ty.value),
+            )
+        })?;
+        if !decl.cover.is_empty() && ty != ObjectType::Untyped {
+            return Err(ParseError::new(
+                decl.name.span.clone(),
+                "only untyped objects may cover other objects",
+            ));
+        }
+        match &decl.count {
+            None => {
+                let id = self.add(&decl.name.value, decl, ty, parent)?;
+                for entry in &decl.cover {
+                    match entry {
+                        CoverEntry::Decl(child) => self.collect(child, Some(id))?,
+                        CoverEntry::Ref(child) => self.cover_refs.push((id, child)),
+                    }
+                }
+            }
+            Some(count) => {
+                if !decl.cover.is_empty() {
+                    return Err(ParseError::new(
+                        decl.name.span.clone(),
+                        "arrays of objects may not cover other objects",
+                    ));
+                }
+                self.check_unique(&decl.name.value, &decl.name.span)?;
+                if count.value > MAX_ARRAY_LEN {
+                    return Err(ParseError::new(
+                        count.span.clone(),
+                        format!("arrays of objects may have at most {MAX_ARRAY_LEN} elements"),
+                    ));
+                }
+                self.arrays.insert(decl.name.value.clone(), count.value);
+                for i in 0..count.value {
+                    self.add(&element_name(&decl.name.value, i), decl, ty, parent)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn check_unique(&self, name: &str, span: &Span) -> Result<(), ParseError> {
+        if self.names.contains_key(name) || self.arrays.contains_key(name) {
+            return Err(ParseError::new(
+                span.clone(),
+                format!("object `{name}` is declared more than once"),
+            ));
+        }
+        Ok(())
+    }
+
+    fn add(
+        &mut self,
+        name: &str,
+        decl: &'a ObjectDecl,
+        ty: ObjectType,
+        parent: Option<usize>,
+    ) -> Result<usize, ParseError> {
+        self.check_unique(name, &decl.name.span)?;
+        let id = self.decls.len();
+        self.decls.push(Decl {
+            name: name.to_owned(),
+            span: decl.name.span.clone(),
+            ty,
+            params: &decl.params,
+            parent,
+            children: vec![],
+        });
+        self.names.insert(name.to_owned(), id);
+        if let Some(parent) = parent {
+            self.decls[parent].children.push(id);
+        }
+        Ok(id)
+    }
+
+    fn resolve(&self, object_ref: &ObjectRef) -> Result<usize, ParseError> {
+        if let Some(id) = self.names.get(&object_ref.key()) {
+            return Ok(*id);
+        }
+        let message = match (object_ref.index, self.arrays.get(&object_ref.name)) {
+            (None, Some(_)) => format!("`{}` is an array and must be indexed", object_ref.name),
+            (Some(index), Some(len)) => format!(
+                "index {index} is out of bounds for `{}`, which has {len} elements",
+                object_ref.name
+            ),
+            _ => format!("no object named `{}`", object_ref.key()),
+        };
+        Err(ParseError::new(object_ref.span.clone(), message))
+    }
+
+    fn resolve_cover_refs(&mut self) -> Result<(), ParseError> {
+        for (parent, child_ref) in std::mem::take(&mut self.cover_refs) {
+            let child = self.resolve(child_ref)?;
+            if let Some(existing) = self.decls[child].parent {
+                return Err(ParseError::new(
+                    child_ref.span.clone(),
+                    format!(
+                        "`{}` is already covered by `{}`",
+                        self.decls[child].name, self.decls[existing].name
+                    ),
+                ));
+            }
+            self.decls[child].parent = Some(parent);
+            self.decls[parent].children.push(child);
+        }
+        Ok(())
+    }
+
+    fn lower_spec(&self, ast: &Ast) -> Result<CdlSpec, ParseError> {
+        let mut objects = self
+            .decls
+            .iter()
+            .map(|decl| self.lower_object(decl))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.assign_asid_highs(&mut objects);
+
+        // Adding objects in declaration order makes builder IDs coincide with declaration indices.
+        let mut builder = SpecBuilder::new();
+        for (decl, object) in self.decls.iter().zip(objects) {
+            builder.add_object(decl.name.clone(), object);
+        }
+        for (id, decl) in self.decls.iter().enumerate() {
+            for child in &decl.children {
+                builder.cover_by_id(id, *child);
+            }
+        }
+
+        for block in &ast.caps {
+            let holder = self.resolve(&block.holder)?;
+            if !self.decls[holder].ty.has_slots() {
+                return Err(ParseError::new(
+                    block.holder.span.clone(),
+                    format!(
+                        "`{}` is of a type of object which does not hold caps",
+                        self.decls[holder].name
+                    ),
+                ));
+            }
+            let mut next_slot = 0;
+            for cap_decl in &block.caps {
+                let slot = match &cap_decl.slot {
+                    Some(slot) => self.resolve_slot(holder, &slot.value, &slot.span)?,
+                    None => next_slot,
+                };
+                let slot_span = cap_decl
+                    .slot
+                    .as_ref()
+                    .map(|slot| slot.span.clone())
+                    .unwrap_or(cap_decl.target.span.clone());
+                if let Object::CNode(cnode) = &builder.object(holder).object {
+                    if checked_pow2(cnode.size_bits).is_some_and(|num_slots| slot >= num_slots) {
+                        return Err(ParseError::new(
+                            slot_span,
+                            format!(
+                                "slot {slot} is out of bounds for a {} bit CNode",
+                                cnode.size_bits
+                            ),
+                        ));
+                    }
+                }
+                let target = self.resolve(&cap_decl.target)?;
+                let cap = self.lower_cap(target, &cap_decl.params)?;
+                if builder.set_slot_by_id(holder, slot, cap).is_some() {
+                    return Err(ParseError::new(
+                        slot_span,
+                        format!(
+                            "slot {slot} of `{}` is filled more than once",
+                            self.decls[holder].name
+                        ),
+                    ));
+                }
+                next_slot = slot + 1;
+            }
+        }
+
+        let mut seen_irqs = BTreeSet::new();
+        for mapping in &ast.irq_maps {
+            let handler = self.resolve(&mapping.handler)?;
+            if !self.decls[handler].ty.is_irq() {
+                return Err(ParseError::new(
+                    mapping.handler.span.clone(),
+                    format!("`{}` is not an IRQ object", self.decls[handler].name),
+                ));
+            }
+            if !seen_irqs.insert(mapping.irq.value) {
+                return Err(ParseError::new(
+                    mapping.irq.span.clone(),
+                    format!("IRQ {} is mapped more than once", mapping.irq.value),
+                ));
+            }
+            builder.add_irq(mapping.irq.value, handler);
+        }
+
+        if let Some(decl) = &ast.handoff {
+            let handoff = self.lower_handoff(decl, &builder)?;
+            builder.set_resource_handoff(handoff);
+        }
+
+        builder
+            .build(self.object_sizes)
+            .map_err(|err| self.lower_build_error(&err))
+    }
+
+    fn lower_handoff(
+        &self,
+        decl: &HandoffDecl,
+        builder: &SpecBuilder,
+    ) -> Result<ResourceHandoff, ParseError> {
+        let cnode = self.resolve(&decl.cnode)?;
+        let size_bits = match &builder.object(cnode).object {
+            Object::CNode(obj) => obj.size_bits,
+            _ => {
+                return Err(ParseError::new(
+                    decl.cnode.span.clone(),
+                    format!("`{}` is not a CNode", self.decls[cnode].name),
+                ))
+            }
+        };
+        let mut untyped_slots = None;
+        let mut info_frame = None;
+        let mut irq_control_slot = None;
+        let mut asid_control_slot = None;
+        let mut init_cnode_slot = None;
+        let mut claimed = vec![];
+        for param in &decl.params {
+            let Param::KeyValue { key, value } = param else {
+                return Err(ParseError::new(
+                    param.span(),
+                    "unexpected parameter for `handoff`",
+                ));
+            };
+            match key.value.as_str() {
+                "untyped_slots" => {
+                    let (first, last) = lower_inclusive_range(value, "slot")?;
+                    let end = to_usize(last, &value.span)?
+                        .checked_add(1)
+                        .ok_or_else(|| ParseError::new(value.span.clone(), "value is too large"))?;
+                    let slots = to_usize(first, &value.span)?..end;
+                    claimed.push((slots.clone(), value.span.clone()));
+                    untyped_slots = Some(slots);
+                }
+                "info" => {
+                    let frame = self.resolve(&ObjectRef {
+                        name: value.as_word()?.to_owned(),
+                        index: None,
+                        span: value.span.clone(),
+                    })?;
+                    if self.decls[frame].ty != ObjectType::Frame {
+                        return Err(ParseError::new(
+                            value.span.clone(),
+                            format!("`{}` is not a frame", self.decls[frame].name),
+                        ));
+                    }
+                    info_frame = Some(frame);
+                }
+                "irq_control" | "asid_control" | "init_cnode" => {
+                    let slot = to_usize(value.as_number()?, &value.span)?;
+                    let end = slot
+                        .checked_add(1)
+                        .ok_or_else(|| ParseError::new(value.span.clone(), "value is too large"))?;
+                    claimed.push((slot..end, value.span.clone()));
+                    match key.value.as_str() {
+                        "irq_control" => irq_control_slot = Some(slot),
+                        "asid_control" => asid_control_slot = Some(slot),
+                        _ => init_cnode_slot = Some(slot),
+                    }
+                }
+                _ => {
+                    return Err(ParseError::new(
+                        param.span(),
+                        "unexpected parameter for `handoff`",
+                    ))
+                }
+            }
+        }
+        let untyped_slots = untyped_slots.ok_or_else(|| {
+            ParseError::new(
+                decl.cnode.span.clone(),
+                "`handoff` is missing `untyped_slots`",
+            )
+        })?;
+        for (slots, span) in claimed.iter() {
+            if checked_pow2(size_bits).is_some_and(|num_slots| slots.end > num_slots) {
+                return Err(ParseError::new(
+                    span.clone(),
+                    format!("slots are out of bounds for a {size_bits} bit CNode"),
+                ));
+            }
+        }
+        // The slots of large CNodes are too many to iterate over, so compare ranges instead.
+        claimed.sort_by_key(|(slots, _)| slots.start);
+        let mut claimed_end = 0;
+        for (slots, span) in claimed {
+            let slot = if slots.start < claimed_end {
+                Some(slots.start)
+            } else {
+                builder
+                    .slots_in(cnode, slots.clone())
+                    .next()
+                    .map(|(slot, _)| slot)
+            };
+            if let Some(slot) = slot {
+                return Err(ParseError::new(
+                    span,
+                    format!(
+                        "slot {slot} of `{}` is filled more than once",
+                        self.decls[cnode].name
+                    ),
+                ));
+            }
+            claimed_end = claimed_end.max(slots.end);
+        }
+        if let Some(frame) = info_frame {
+            let Object::Frame(obj) = &builder.object(frame).object else {
+                unreachable!()
+            };
+            if untyped_slots.len() > HandoffInfo::max_untypeds(obj.size_bits) {
+                return Err(ParseError::new(
+                    decl.cnode.span.clone(),
+                    format!(
+                        "`{}` is too small to describe {} untypeds",
+                        self.decls[frame].name,
+                        untyped_slots.len()
+                    ),
+                ));
+            }
+        }
+        Ok(ResourceHandoff {
+            cnode,
+            untyped_slots,
+            info_frame,
+            irq_control_slot,
+            asid_control_slot,
+            init_cnode_slot,
+        })
+    }
+
+    // Pools without an explicit asid_high are made in declaration order, after those with one.
+    fn assign_asid_highs(&self, objects: &mut [Object<'static, FileContent, ()>]) {
+        let mut next_high = objects
+            .iter()
+            .filter_map(|object| match object {
+                Object::ASIDPool(pool) if pool.high != Word::MAX => Some(pool.high + 1),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(1);
+        for object in objects {
+            if let Object::ASIDPool(pool) = object {
+                if pool.high == Word::MAX {
+                    pool.high = next_high;
+                    next_high += 1;
+                }
+            }
+        }
+    }
+
+    fn lower_build_error(&self, err: &BuildError) -> ParseError {
+        let decl = &self.decls[err.object()];
+        let message = match err {
+            BuildError::MissingObjectSize { key, .. } => format!(
+                "the size of `{}` is unknown, because the object sizes have no entry for `{key}`",
+                decl.name
+            ),
+            BuildError::NotAllocatable { .. } => {
+                format!("`{}` cannot be allocated from untyped memory", decl.name)
+            }
+            BuildError::CoverCycle { .. } => {
+                format!("`{}` is part of a cycle of untyped covers", decl.name)
+            }
+        };
+        ParseError::new(decl.span.clone(), message)
+    }
+
+    fn resolve_slot(&self, holder: usize, slot: &str, span: &Span) -> Result<CapSlot, ParseError> {
+        if let Some(n) = crate::parser::parse_number(slot) {
+            return usize::try_from(n)
+                .map_err(|_| ParseError::new(span.clone(), "slot number is too large"));
+        }
+        if self.decls[holder].ty == ObjectType::TCB {
+            if let Some((_, n)) = TCB_SLOT_NAMES.iter().find(|(name, _)| *name == slot) {
+                return Ok(*n);
+            }
+        }
+        Err(ParseError::new(
+            span.clone(),
+            format!("unknown slot `{slot}` for `{}`", self.decls[holder].name),
+        ))
+    }
+
+    fn lower_object(&self, decl: &Decl) -> Result<Object<'static, FileContent, ()>, ParseError> {
+        let mut size_bits = None;
+        let mut paddr = None;
+        let object = match decl.ty {
+            ObjectType::Untyped => {
+                for param in decl.params {
+                    match param {
+                        Param::Bits(bits) => size_bits = Some(to_size_bits(bits)?),
+                        Param::KeyValue { key, value } if key.value == "paddr" => {
+                            paddr = Some(to_usize(value.as_number()?, &value.span)?)
+                        }
+                        _ => return Err(unexpected_param(param, decl)),
+                    }
+                }
+                Object::Untyped(object::Untyped {
+                    size_bits: require_size_bits(size_bits, decl)?,
+                    paddr,
+                })
+            }
+            ObjectType::Endpoint => self.lower_simple(decl, Object::Endpoint)?,
+            ObjectType::Notification => self.lower_simple(decl, Object::Notification)?,
+            ObjectType::VCPU => self.lower_simple(decl, Object::VCPU)?,
+            ObjectType::Reply => self.lower_simple(decl, Object::Reply)?,
+            ObjectType::IRQ => {
+                self.lower_simple(decl, Object::IRQ(object::IRQ { slots: no_slots() }))?
+            }
+            ObjectType::CNode => {
+                for param in decl.params {
+                    match param {
+                        Param::Bits(bits) => size_bits = Some(to_size_bits(bits)?),
+                        _ => return Err(unexpected_param(param, decl)),
+                    }
+                }
+                Object::CNode(object::CNode {
+                    size_bits: require_size_bits(size_bits, decl)?,
+                    slots: no_slots(),
+                })
+            }
+            ObjectType::TCB => Object::TCB(object::TCB {
+                slots: no_slots(),
+                extra: Indirect::from_owned(Box::new(self.lower_tcb_extra(decl)?)),
+            }),
+            ObjectType::ArmIRQ => {
+                let mut extra = object::ArmIRQExtraInfo {
+                    trigger: 0,
+                    target: 0,
+                };
+                for param in decl.params {
+                    match param {
+                        Param::KeyValue { key, value } if key.value == "trigger" => {
+                            extra.trigger = value.as_number()?
+                        }
+                        Param::KeyValue { key, value } if key.value == "target" => {
+                            extra.target = value.as_number()?
+                        }
+                        _ => return Err(unexpected_param(param, decl)),
+                    }
+                }
+                Object::ArmIRQ(object::ArmIRQ {
+                    slots: no_slots(),
+                    extra: Indirect::from_owned(Box::new(extra)),
+                })
+            }
+            ObjectType::IRQIOAPIC => {
+                let mut extra = object::IRQIOAPICExtraInfo {
+                    ioapic: 0,
+                    pin: 0,
+                    level: 0,
+                    polarity: 0,
+                };
+                for param in decl.params {
+                    match param {
+                        Param::KeyValue { key, value } => match key.value.as_str() {
+                            "ioapic" => extra.ioapic = value.as_number()?,
+                            "ioapic_pin" => extra.pin = value.as_number()?,
+                            "level" => extra.level = value.as_number()?,
+                            "polarity" => extra.polarity = value.as_number()?,
+                            _ => return Err(unexpected_param(param, decl)),
+                        },
+                        _ => return Err(unexpected_param(param, decl)),
+                    }
+                }
+                Object::IRQIOAPIC(object::IRQIOAPIC {
+                    slots: no_slots(),
+                    extra: Indirect::from_owned(Box::new(extra)),
+                })
+            }
+            ObjectType::IRQMSI => {
+                let mut extra = object::IRQMSIExtraInfo {
+                    handle: 0,
+                    pci_bus: 0,
+                    pci_dev: 0,
+                    pci_func: 0,
+                };
+                for param in decl.params {
+                    match param {
+                        Param::KeyValue { key, value } => match key.value.as_str() {
+                            "msi_handle" => extra.handle = value.as_number()?,
+                            "msi_pci_bus" => extra.pci_bus = value.as_number()?,
+                            "msi_pci_dev" => extra.pci_dev = value.as_number()?,
+                            "msi_pci_func" => extra.pci_func = value.as_number()?,
+                            _ => return Err(unexpected_param(param, decl)),
+                        },
+                        _ => return Err(unexpected_param(param, decl)),
+                    }
+                }
+                Object::IRQMSI(object::IRQMSI {
+                    slots: no_slots(),
+                    extra: Indirect::from_owned(Box::new(extra)),
+                })
+            }
+            ObjectType::RiscvIRQ => {
+                let mut extra = object::RiscvIRQExtraInfo { trigger: 0 };
+                for param in decl.params {
+                    match param {
+                        Param::KeyValue { key, value } if key.value == "trigger" => {
+                            extra.trigger = value.as_number()?
+                        }
+                        _ => return Err(unexpected_param(param, decl)),
+                    }
+                }
+                Object::RiscvIRQ(object::RiscvIRQ {
+                    slots: no_slots(),
+                    extra: Indirect::from_owned(Box::new(extra)),
+                })
+            }
+            ObjectType::IOPorts => {
+                let mut ports = None;
+                for param in decl.params {
+                    match param {
+                        Param::KeyValue { key, value } if key.value == "ports" => {
+                            ports = Some(lower_port_range(value)?)
+                        }
+                        _ => return Err(unexpected_param(param, decl)),
+                    }
+                }
+                let (first_port, last_port) = ports.ok_or_else(|| {
+                    ParseError::new(
+                        decl.span.clone(),
+                        format!("`{}` has no `ports` parameter", decl.name),
+                    )
+                })?;
+                Object::IOPorts(object::IOPorts {
+                    first_port,
+                    last_port,
+                })
+            }
+            ObjectType::Frame => {
+                let mut fill = None;
+                for param in decl.params {
+                    match param {
+                        Param::Bits(bits) => size_bits = Some(to_size_bits(bits)?),
+                        Param::Flag(flag) => match frame_size_bits(&flag.value) {
+                            Some(bits) => size_bits = Some(bits),
+                            None => return Err(unexpected_param(param, decl)),
+                        },
+                        Param::KeyValue { key, value } if key.value == "paddr" => {
+                            paddr = Some(to_usize(value.as_number()?, &value.span)?)
+                        }
+                        Param::KeyValue { key, value } if key.value == "fill" => fill = Some(value),
+                        _ => return Err(unexpected_param(param, decl)),
+                    }
+                }
+                let size_bits = require_size_bits(size_bits, decl)?;
+                let entries = match fill {
+                    Some(fill) => fill
+                        .as_list()?
+                        .iter()
+                        .map(|entry| lower_fill_entry(entry, size_bits))
+                        .collect::<Result<Vec<_>, _>>()?,
+                    None => vec![],
+                };
+                Object::Frame(object::Frame {
+                    size_bits,
+                    paddr,
+                    init: FrameInit::Fill(Fill {
+                        entries: Indirect::from_owned(entries.into_boxed_slice()),
+                    }),
+                })
+            }
+            ObjectType::PageTable(ty) => self.lower_simple(
+                decl,
+                Object::PageTable(object::PageTable {
+                    // Page tables in the VSpace slots of TCBs are also marked as roots by the
+                    // builder.
+                    is_root: ty.is_top_level(),
+                    level: if self.x86_64 {
+                        Some(ty.x86_64_level())
+                    } else {
+                        None
+                    },
+                    slots: no_slots(),
+                }),
+            )?,
+            ObjectType::ASIDPool => {
+                let mut high = None;
+                for param in decl.params {
+                    match param {
+                        Param::KeyValue { key, value } if key.value == "asid_high" => {
+                            high = Some(value.as_number()?)
+                        }
+                        _ => return Err(unexpected_param(param, decl)),
+                    }
+                }
+                Object::ASIDPool(object::ASIDPool {
+                    // Replaced by `assign_asid_highs`.
+                    high: high.unwrap_or(Word::MAX),
+                })
+            }
+            ObjectType::SchedContext => {
+                let mut extra = object::SchedContextExtraInfo {
+                    period: 0,
+                    budget: 0,
+                    badge: 0,
+                };
+                for param in decl.params {
+                    match param {
+                        Param::Bits(bits) => size_bits = Some(to_size_bits(bits)?),
+                        Param::KeyValue { key, value } => match key.value.as_str() {
+                            "period" => extra.period = value.as_number()?,
+                            "budget" => extra.budget = value.as_number()?,
+                            "data" => extra.badge = value.as_number()?,
+                            _ => return Err(unexpected_param(param, decl)),
+                        },
+                        _ => return Err(unexpected_param(param, decl)),
+                    }
+                }
+                let size_bits = match size_bits {
+                    Some(size_bits) => size_bits,
+                    None => self.object_size("seL4_SchedContextObject", decl)?,
+                };
+                Object::SchedContext(object::SchedContext { size_bits, extra })
+            }
+        };
+        Ok(object)
+    }
+
+    fn lower_simple(
+        &self,
+        decl: &Decl,
+        object: Object<'static, FileContent, ()>,
+    ) -> Result<Object<'static, FileContent, ()>, ParseError> {
+        match decl.params.first() {
+            Some(param) => Err(unexpected_param(param, decl)),
+            None => Ok(object),
+        }
+    }
+
+    fn lower_tcb_extra(&self, decl: &Decl) -> Result<object::TCBExtraInfo<'static>, ParseError> {
+        let mut extra = object::TCBExtraInfo {
+            ipc_buffer_addr: 0,
+            affinity: 0,
+            prio: 0,
+            max_prio: 0,
+            domain: None,
+            resume: true,
+            ip: 0,
+            sp: 0,
+            spsr: 0,
+            gprs: Indirect::from_owned(Vec::new().into_boxed_slice()),
+            master_fault_ep: None,
+        };
+        for param in decl.params {
+            let (key, value) = match param {
+                Param::KeyValue { key, value } => (key, value),
+                _ => return Err(unexpected_param(param, decl)),
+            };
+            match key.value.as_str() {
+                "addr" => extra.ipc_buffer_addr = value.as_number()?,
+                "ip" => extra.ip = value.as_number()?,
+                "sp" => extra.sp = value.as_number()?,
+                "spsr" => extra.spsr = value.as_number()?,
+                "prio" => extra.prio = to_u8(value)?,
+                "max_prio" => extra.max_prio = to_u8(value)?,
+                "affinity" => extra.affinity = value.as_number()?,
+                "dom" => extra.domain = Some(to_u8(value)?),
+                "fault_ep" => extra.master_fault_ep = Some(value.as_number()?),
+                "init" => {
+                    extra.gprs = Indirect::from_owned(
+                        value
+                            .as_list()?
+                            .iter()
+                            .map(Value::as_number)
+                            .collect::<Result<Vec<_>, _>>()?
+                            .into_boxed_slice(),
+                    )
+                }
+                "resume" => {
+                    extra.resume = match value.as_word()? {
+                        "true" => true,
+                        "false" => false,
+                        _ => {
+                            return Err(ParseError::new(
+                                value.span.clone(),
+                                "expected `true` or `false`",
+                            ))
+                        }
+                    }
+                }
+                // Not used by the initializer.
+                "crit" | "max_crit" | "elf" => {}
+                _ => return Err(unexpected_param(param, decl)),
+            }
+        }
+        Ok(extra)
+    }
+
+    fn lower_cap(&self, target: usize, params: &[Param]) -> Result<Cap, ParseError> {
+        let decl = &self.decls[target];
+        let object = target;
+        let mut rights = None;
+        let mut badge = 0;
+        let mut guard = 0;
+        let mut guard_size = 0;
+        let mut cached = true;
+        for param in params {
+            let ok = match param {
+                Param::Flag(flag) if is_rights(&flag.value) => {
+                    rights = Some(parse_rights(&flag.value));
+                    matches!(
+                        decl.ty,
+                        ObjectType::Endpoint | ObjectType::Notification | ObjectType::Frame
+                    )
+                }
+                Param::Flag(flag) if flag.value == "cached" || flag.value == "uncached" => {
+                    cached = flag.value == "cached";
+                    decl.ty == ObjectType::Frame
+                }
+                Param::Flag(flag) if IGNORED_CAP_PARAMS.contains(&flag.value.as_str()) => true,
+                Param::KeyValue { key, value } => match key.value.as_str() {
+                    "badge" => {
+                        badge = value.as_number()?;
+                        matches!(decl.ty, ObjectType::Endpoint | ObjectType::Notification)
+                    }
+                    "guard" => {
+                        guard = value.as_number()?;
+                        decl.ty == ObjectType::CNode
+                    }
+                    "guard_size" => {
+                        guard_size = value.as_number()?;
+                        decl.ty == ObjectType::CNode
+                    }
+                    key => IGNORED_CAP_PARAMS.contains(&key),
+                },
+                _ => false,
+            };
+            if !ok {
+                return Err(ParseError::new(
+                    param.span(),
+                    format!("unexpected parameter for a cap to `{}`", decl.name),
+                ));
+            }
+        }
+        let rights = rights.unwrap_or(Rights::none());
+        Ok(match decl.ty {
+            ObjectType::Untyped => Cap::Untyped(cap::Untyped { object }),
+            ObjectType::Endpoint => Cap::Endpoint(cap::Endpoint {
+                object,
+                badge,
+                rights,
+            }),
+            ObjectType::Notification => Cap::Notification(cap::Notification {
+                object,
+                badge,
+                rights,
+            }),
+            ObjectType::CNode => Cap::CNode(cap::CNode {
+                object,
+                guard,
+                guard_size,
+            }),
+            ObjectType::TCB => Cap::TCB(cap::TCB { object }),
+            ObjectType::IRQ => Cap::IRQHandler(cap::IRQHandler { object }),
+            ObjectType::ArmIRQ => Cap::ArmIRQHandler(cap::ArmIRQHandler { object }),
+            ObjectType::VCPU => Cap::VCPU(cap::VCPU { object }),
+            ObjectType::Frame => Cap::Frame(cap::Frame {
+                object,
+                rights,
+                cached,
+            }),
+            ObjectType::PageTable(_) => Cap::PageTable(cap::PageTable { object }),
+            ObjectType::ASIDPool => Cap::ASIDPool(cap::ASIDPool { object }),
+            ObjectType::SchedContext => Cap::SchedContext(cap::SchedContext { object }),
+            ObjectType::Reply => Cap::Reply(cap::Reply { object }),
+            ObjectType::IRQIOAPIC => Cap::IRQIOAPICHandler(cap::IRQIOAPICHandler { object }),
+            ObjectType::IRQMSI => Cap::IRQMSIHandler(cap::IRQMSIHandler { object }),
+            ObjectType::RiscvIRQ => Cap::RiscvIRQHandler(cap::RiscvIRQHandler { object }),
+            ObjectType::IOPorts => Cap::IOPorts(cap::IOPorts { object }),
+        })
+    }
+
+    fn object_size(&self, key: &str, decl: &Decl) -> Result<usize, ParseError> {
+        self.object_sizes.get(key).ok_or_else(|| {
+            ParseError::new(
+                decl.span.clone(),
+                format!("the size of `{}` is unknown, because the object sizes have no entry for `{key}`", decl.name),
+            )
+        })
+    }
+}
+
+fn no_slots() -> Indirect<'static, [CapTableEntry]> {
+    Indirect::from_owned(Vec::new().into_boxed_slice())
+}
+
+fn lower_port_range(value: &Value) -> Result<(Word, Word), ParseError> {
+    let (first, last) = lower_inclusive_range(value, "port")?;
+    if last > 0xffff {
+        return Err(ParseError::new(value.span.clone(), "invalid port range"));
+    }
+    Ok((first, last))
+}
+
+// `[first..last]`, where both ends are inclusive.
+fn lower_inclusive_range(value: &Value, what: &str) -> Result<(u64, u64), ParseError> {
+    let err = || {
+        ParseError::new(
+            value.span.clone(),
+            format!("expected a {what} range `[first..last]`"),
+        )
+    };
+    let range = match value.as_list()? {
+        [range] => range.as_word()?,
+        _ => return Err(err()),
+    };
+    let (first, last) = range.split_once("..").ok_or_else(err)?;
+    let first = crate::parser::parse_number(first).ok_or_else(err)?;
+    let last = crate::parser::parse_number(last).ok_or_else(err)?;
+    if first > last {
+        return Err(ParseError::new(
+            value.span.clone(),
+            format!("invalid {what} range"),
+        ));
+    }
+    Ok((first, last))
+}
+
+fn lower_fill_entry(
+    entry: &Value,
+    frame_size_bits: usize,
+) -> Result<FillEntry<FileContent>, ParseError> {
+    let items = entry.as_braced()?;
+    let item = |i: usize| {
+        items
+            .get(i)
+            .ok_or_else(|| ParseError::new(entry.span.clone(), "incomplete fill entry"))
+    };
+    let dest_offset = to_usize(item(0)?.as_number()?, &item(0)?.span)?;
+    let length = to_usize(item(1)?.as_number()?, &item(1)?.span)?;
+    let content = match item(2)?.as_word()? {
+        "CDL_FrameFill_FileData" => FillEntryContent::Data(FileContent {
+            file: item(3)?.as_str()?.to_owned(),
+            file_offset: to_usize(item(4)?.as_number()?, &item(4)?.span)?,
+        }),
+        "CDL_FrameFill_BootInfo" => FillEntryContent::BootInfo(FillEntryContentBootInfo {
+            id: match item(3)?.as_word()? {
+                "CDL_FrameFill_BootInfo_FDT" => FillEntryContentBootInfoId::Fdt,
+                "CDL_FrameFill_BootInfo_SpecDigest" => FillEntryContentBootInfoId::SpecDigest,
+                _ => {
+                    return Err(ParseError::new(
+                        item(3)?.span.clone(),
+                        "unsupported bootinfo fill type",
+                    ))
+                }
+            },
+            offset: to_usize(item(4)?.as_number()?, &item(4)?.span)?,
+        }),
+        _ => {
+            return Err(ParseError::new(
+                item(2)?.span.clone(),
+                "expected `CDL_FrameFill_FileData` or `CDL_FrameFill_BootInfo`",
+            ))
+        }
+    };
+    if let Some(extra) = items.get(5) {
+        return Err(ParseError::new(
+            extra.span.clone(),
+            "unexpected item in fill entry",
+        ));
+    }
+    let range = dest_offset..dest_offset.saturating_add(length);
+    if checked_pow2(frame_size_bits).is_some_and(|frame_size| range.end > frame_size) {
+        return Err(ParseError::new(
+            entry.span.clone(),
+            "fill entry extends past the end of the frame",
+        ));
+    }
+    Ok(FillEntry { range, content })
+}
+
+fn frame_size_bits(literal: &str) -> Option<usize> {
+    Some(match literal {
+        "4k" => 12,
+        "64k" => 16,
+        "1M" => 20,
+        "2M" => 21,
+        "16M" => 24,
+        "1G" => 30,
+        _ => return None,
+    })
+}
+
+fn is_rights(word: &str) -> bool {
+    !word.is_empty() && word.chars().all(|c| "RWXGP".contains(c))
+}
+
+// Execute rights are conveyed by the absence of XN in mapping attributes, which the initializer
+// does not need.
+fn parse_rights(word: &str) -> Rights {
+    Rights {
+        read: word.contains('R'),
+        write: word.contains('W'),
+        grant: word.contains('G'),
+        grant_reply: word.contains('P'),
+    }
+}
+
+fn require_size_bits(size_bits: Option<usize>, decl: &Decl) -> Result<usize, ParseError> {
+    size_bits.ok_or_else(|| {
+        ParseError::new(
+            decl.span.clone(),
+            format!("`{}` is missing a size", decl.name),
+        )
+    })
+}
+
+fn unexpected_param(param: &Param, decl: &Decl) -> ParseError {
+    ParseError::new(
+        param.span(),
+        format!("unexpected parameter for `{}`", decl.name),
+    )
+}
+
+fn to_size_bits(bits: &Spanned<u64>) -> Result<usize, ParseError> {
+    match usize::try_from(bits.value) {
+        Ok(size_bits) if size_bits < usize::BITS as usize => Ok(size_bits),
+        _ => Err(ParseError::new(
+            bits.span.clone(),
+            format!("sizes must be less than {} bits", usize::BITS),
+        )),
+    }
+}
+
+// `1 << bits`, or `None` if that does not fit in a `usize`.
+fn checked_pow2(bits: usize) -> Option<usize> {
+    u32::try_from(bits)
+        .ok()
+        .and_then(|bits| 1usize.checked_shl(bits))
+}
+
+fn to_usize(value: u64, span: &Span) -> Result<usize, ParseError> {
+    usize::try_from(value).map_err(|_| ParseError::new(span.clone(), "value is too large"))
+}
+
+fn to_u8(value: &Value) -> Result<u8, ParseError> {
+    u8::try_from(value.as_number()?)
+        .map_err(|_| ParseError::new(value.span.clone(), "value must be less than 256"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::parse_object_sizes;
+
+    const OBJECT_SIZES: &str = "
+seL4_EndpointObject: 4
+seL4_NotificationObject: 6
+seL4_TCBObject: 11
+seL4_Slot: 5
+seL4_PageTableObject: 12
+seL4_VSpaceObject: 12
+";
+
+    fn lower_str(src: &str) -> Result<CdlSpec, ParseError> {
+        crate::parse(src, &parse_object_sizes(OBJECT_SIZES).unwrap())
+    }
+
+    fn err(src: &str) -> (String, Span) {
+        let err = lower_str(src).unwrap_err();
+        (err.message().to_owned(), err.span())
+    }
+
+    fn find<'a>(spec: &'a CdlSpec, name: &str) -> &'a Object<'static, FileContent, ()> {
+        &spec
+            .objects
+            .iter()
+            .find(|object| object.name == name)
+            .unwrap()
+            .object
+    }
+
+    #[test]
+    fn lower() {
+        let spec = lower_str(
+            "objects {
+                ut = ut (16 bits) { ep = ep }
+                cnode = cnode (4 bits)
+                tcb = tcb (prio: 100, ip: 0x1000, dom: 1)
+                frames[2] = frame (4k, fill: [{0 8 CDL_FrameFill_BootInfo CDL_FrameFill_BootInfo_FDT 0}])
+                irq = arm_irq (trigger: 1)
+            }
+            caps {
+                cnode { 1: ep (RW, badge: 3) frames[1] (R, uncached) }
+                tcb { cspace: cnode (guard: 0, guard_size: 60) }
+            }
+            irq maps { 33: irq }",
+        )
+        .unwrap();
+
+        let Object::CNode(cnode) = find(&spec, "cnode") else {
+            panic!()
+        };
+        assert_eq!(cnode.size_bits, 4);
+        let [(1, Cap::Endpoint(ep)), (2, Cap::Frame(frame))] = &cnode.slots[..] else {
+            panic!("{:?}", cnode.slots)
+        };
+        assert_eq!(spec.objects[ep.object].name, "ep");
+        assert_eq!(ep.badge, 3);
+        assert!(ep.rights.read && ep.rights.write && !ep.rights.grant);
+        assert_eq!(spec.objects[frame.object].name, "frames[1]");
+        assert!(!frame.cached);
+
+        let Object::TCB(tcb) = find(&spec, "tcb") else {
+            panic!()
+        };
+        assert_eq!(tcb.extra.prio, 100);
+        assert_eq!(tcb.extra.ip, 0x1000);
+        assert_eq!(tcb.extra.domain, Some(1));
+        assert!(matches!(&tcb.slots[..], [(0, Cap::CNode(cap))] if cap.guard_size == 60));
+
+        let Object::Frame(frame) = find(&spec, "frames[0]") else {
+            panic!()
+        };
+        assert_eq!(frame.size_bits, 12);
+        let FrameInit::Fill(fill) = &frame.init else {
+            panic!()
+        };
+        assert_eq!(fill.entries[0].range, 0..8);
+
+        let [cover] = &spec.untyped_covers[..] else {
+            panic!()
+        };
+        assert_eq!(spec.objects[cover.parent].name, "ut");
+        assert_eq!(spec.objects[cover.children.start].name, "ep");
+
+        assert_eq!(spec.irqs.len(), 1);
+        assert_eq!(spec.irqs[0].irq, 33);
+        assert_eq!(spec.objects[spec.irqs[0].handler].name, "irq");
+    }
+
+    #[test]
+    fn x86_and_riscv_kinds() {
+        let spec = lower_str(
+            "objects {
+                ioapic = ioapic_irq (ioapic: 1, ioapic_pin: 2, level: 1, polarity: 0)
+                msi = msi_irq (msi_handle: 3, msi_pci_bus: 4, msi_pci_dev: 5, msi_pci_func: 6)
+                riscv = riscv_irq (trigger: 1)
+                ports = io_ports (ports: [0x60..0x64])
+                cnode = cnode (2 bits)
+            }
+            caps { cnode { ioapic msi riscv ports } }
+            irq maps { 48: ioapic 49: msi 50: riscv }",
+        )
+        .unwrap();
+        assert_eq!(spec.check(), []);
+
+        let Object::IRQIOAPIC(ioapic) = find(&spec, "ioapic") else {
+            panic!()
+        };
+        assert_eq!(
+            *ioapic.extra,
+            object::IRQIOAPICExtraInfo {
+                ioapic: 1,
+                pin: 2,
+                level: 1,
+                polarity: 0,
+            }
+        );
+        let Object::IRQMSI(msi) = find(&spec, "msi") else {
+            panic!()
+        };
+        assert_eq!(
+            *msi.extra,
+            object::IRQMSIExtraInfo {
+                handle: 3,
+                pci_bus: 4,
+                pci_dev: 5,
+                pci_func: 6,
+            }
+        );
+        let Object::RiscvIRQ(riscv) = find(&spec, "riscv") else {
+            panic!()
+        };
+        assert_eq!(riscv.extra.trigger, 1);
+        let Object::IOPorts(ports) = find(&spec, "ports") else {
+            panic!()
+        };
+        assert_eq!((ports.first_port, ports.last_port), (0x60, 0x64));
+
+        let Object::CNode(cnode) = find(&spec, "cnode") else {
+            panic!()
+        };
+        assert!(matches!(
+            &cnode.slots[..],
+            [
+                (0, Cap::IRQIOAPICHandler(_)),
+                (1, Cap::IRQMSIHandler(_)),
+                (2, Cap::RiscvIRQHandler(_)),
+                (3, Cap::IOPorts(_)),
+            ]
+        ));
+
+        let irqs = spec
+            .irqs
+            .iter()
+            .map(|entry| (entry.irq, spec.objects[entry.handler].name.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(irqs, [(48, "ioapic"), (49, "msi"), (50, "riscv")]);
+    }
+
+    #[test]
+    fn x86_and_riscv_kinds_errors() {
+        for (src, param) in [
+            ("objects { x = ioapic_irq (ioapic: 1, pin: 2) }", "pin: 2"),
+            ("objects { x = ioapic_irq (edge) }", "edge"),
+            (
+                "objects { x = msi_irq (msi_handle: 1, handle: 2) }",
+                "handle: 2",
+            ),
+            (
+                "objects { x = riscv_irq (trigger: 1, target: 2) }",
+                "target: 2",
+            ),
+            (
+                "objects { x = io_ports (ports: [0..1], size: 2) }",
+                "size: 2",
+            ),
+        ] {
+            let start = src.find(param).unwrap();
+            assert_eq!(
+                err(src),
+                (
+                    "unexpected parameter for `x`".to_owned(),
+                    start..start + param.len()
+                )
+            );
+        }
+        assert_eq!(
+            err("objects { x = io_ports }").0,
+            "`x` has no `ports` parameter"
+        );
+        assert_eq!(
+            err("objects { x = io_ports (ports: [0..0x10000]) }"),
+            ("invalid port range".to_owned(), 31..43)
+        );
+        assert_eq!(
+            err("objects { x = io_ports (ports: [2..1]) }").0,
+            "invalid port range"
+        );
+        assert_eq!(
+            err("objects { x = io_ports (ports: [2]) }").0,
+            "expected a port range `[first..last]`"
+        );
+    }
+
+    #[test]
+    fn handoff() {
+        let spec = lower_str(
+            "objects {
+                cnode = cnode (8 bits)
+                info = frame (4k)
+            }
+            handoff cnode (untyped_slots: [16..31], info: info, irq_control: 1, asid_control: 2,
+                init_cnode: 3)",
+        )
+        .unwrap();
+        let handoff = spec.resource_handoff.as_ref().unwrap();
+        assert_eq!(spec.objects[handoff.cnode].name, "cnode");
+        assert_eq!(handoff.untyped_slots, 16..32);
+        assert_eq!(spec.objects[handoff.info_frame.unwrap()].name, "info");
+        assert_eq!(handoff.irq_control_slot, Some(1));
+        assert_eq!(handoff.asid_control_slot, Some(2));
+        assert_eq!(handoff.init_cnode_slot, Some(3));
+
+        // Slots are claimed without iterating over them.
+        let spec = lower_str(
+            "objects { c = cnode (60 bits) ep = ep }
+            caps { c { 0xffffffffffffff: ep } }
+            handoff c (untyped_slots: [16..0xfffffffffffffe], irq_control: 1)",
+        )
+        .unwrap();
+        let handoff = spec.resource_handoff.as_ref().unwrap();
+        assert_eq!(handoff.untyped_slots, 16..0xff_ffff_ffff_ffff);
+        assert_eq!(spec.check(), []);
+    }
+
+    #[test]
+    fn handoff_slots_claimed_twice() {
+        let message = |slot: usize| format!("slot {slot} of `c` is filled more than once");
+        assert_eq!(
+            err("objects { c = cnode (60 bits) ep = ep }
+                caps { c { 0xffffffffffff: ep } }
+                handoff c (untyped_slots: [16..0xfffffffffffffe])"),
+            (message(0xffff_ffff_ffff), 132..154)
+        );
+        assert_eq!(
+            err("objects { c = cnode (4 bits) } handoff c (untyped_slots: [4..7], asid_control: 8, init_cnode: 6)"),
+            (message(6), 94..95)
+        );
+        assert_eq!(
+            err("objects { c = cnode (4 bits) } handoff c (untyped_slots: [4..8], irq_control: 1, init_cnode: 1)"),
+            (message(1), 93..94)
+        );
+    }
+
+    #[test]
+    fn sizes_too_large() {
+        let message = format!("sizes must be less than {} bits", usize::BITS);
+        assert_eq!(
+            err("objects { f = frame (64 bits, fill: [{0 1 x \"a\" 0}]) }"),
+            (message.clone(), 21..28)
+        );
+        assert_eq!(
+            err("objects { c = cnode (70 bits) } caps { c { 0: c } }"),
+            (message.clone(), 21..28)
+        );
+        assert_eq!(
+            err("objects { u = ut (18446744073709551615 bits) }"),
+            (message, 18..43)
+        );
+        assert_eq!(
+            err("objects { x[99999999999] = ep }"),
+            (
+                format!("arrays of objects may have at most {MAX_ARRAY_LEN} elements"),
+                12..23
+            )
+        );
+    }
+
+    #[test]
+    fn out_of_bounds() {
+        assert_eq!(
+            err("objects { c = cnode (2 bits) ep = ep } caps { c { 4: ep } }"),
+            (
+                "slot 4 is out of bounds for a 2 bit CNode".to_owned(),
+                50..51
+            )
+        );
+        assert_eq!(
+            err("objects { c = cnode (63 bits) ep = ep } caps { c { 0xffffffffffffffff: ep } }").1,
+            51..69
+        );
+        assert_eq!(
+            err("objects { f = frame (4k, fill: [{4095 2 CDL_FrameFill_FileData \"a\" 0}]) }"),
+            (
+                "fill entry extends past the end of the frame".to_owned(),
+                32..69
+            )
+        );
+        assert_eq!(
+            err("objects { c = cnode (4 bits) } handoff c (untyped_slots: [8..16])"),
+            (
+                "slots are out of bounds for a 4 bit CNode".to_owned(),
+                57..64
+            )
+        );
+        assert_eq!(
+            err(
+                "objects { c = cnode (4 bits) } handoff c (untyped_slots: [0..0xffffffffffffffff])"
+            )
+            .0,
+            "value is too large"
+        );
+        assert_eq!(
+            err("objects { xs[2] = ep c = cnode (2 bits) } caps { c { xs[2] } }"),
+            (
+                "index 2 is out of bounds for `xs`, which has 2 elements".to_owned(),
+                53..58
+            )
+        );
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            err("objects { x = bogus }"),
+            ("unknown object type `bogus`".to_owned(), 14..19)
+        );
+        assert_eq!(
+            err("objects { x = ep x = ep }"),
+            ("object `x` is declared more than once".to_owned(), 17..18)
+        );
+        assert_eq!(
+            err("objects { x = ep } caps { x { y } }"),
+            (
+                "`x` is of a type of object which does not hold caps".to_owned(),
+                26..27
+            )
+        );
+        assert_eq!(
+            err("objects { c = cnode (2 bits) } caps { c { y } }"),
+            ("no object named `y`".to_owned(), 42..43)
+        );
+        assert_eq!(
+            err("objects { c = cnode (2 bits) ep = ep } caps { c { 1: ep 1: ep } }"),
+            ("slot 1 of `c` is filled more than once".to_owned(), 56..57)
+        );
+        assert_eq!(
+            err("objects { c = cnode } "),
+            ("`c` is missing a size".to_owned(), 10..11)
+        );
+        assert_eq!(
+            err("objects { e = ep (RW) }"),
+            ("unexpected parameter for `e`".to_owned(), 18..20)
+        );
+        assert_eq!(
+            err("objects { t = tcb } caps { t { t (badge: 1) } }"),
+            ("unexpected parameter for a cap to `t`".to_owned(), 34..42)
+        );
+        assert_eq!(
+            err("objects { v = vcpu }").0,
+            "the size of `v` is unknown, because the object sizes have no entry for `seL4_VCPU`"
+        );
+    }
+}
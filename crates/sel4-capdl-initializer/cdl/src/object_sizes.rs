@@ -0,0 +1,43 @@
+//
+// Copyright 2023, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use sel4_capdl_initializer_types::builder::ObjectSizes;
+
+use crate::error::ParseError;
+
+/// Parses object sizes in the format of the `object_sizes.yaml` file generated by the seL4 build
+/// system, which `parse-capDL` also consumes:
+///
+/// ```yaml
+/// ---
+/// seL4_TCBObject: 11
+/// seL4_EndpointObject: 4
+/// seL4_Slot: 5
+/// ```
+pub fn parse_object_sizes(src: &str) -> Result<ObjectSizes, ParseError> {
+    let mut sizes = ObjectSizes::new();
+    let mut offset = 0;
+    for line in src.split_inclusive('\n') {
+        let line_offset = offset;
+        offset += line.len();
+        let content = line.split('#').next().unwrap().trim_end();
+        let trimmed = content.trim_start();
+        if trimmed.is_empty() || trimmed == "---" || trimmed == "..." {
+            continue;
+        }
+        let start = line_offset + (content.len() - trimmed.len());
+        let span = start..start + trimmed.len();
+        let (key, value) = trimmed
+            .split_once(':')
+            .ok_or_else(|| ParseError::new(span.clone(), "expected `key: value`"))?;
+        let value = value
+            .trim()
+            .parse()
+            .map_err(|_| ParseError::new(span.clone(), "expected a number of bits"))?;
+        sizes.insert(key.trim(), value);
+    }
+    Ok(sizes)
+}
@@ -0,0 +1,657 @@
+//
+// Copyright 2023, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+// Parses the token stream into a syntax tree which still refers to objects by name.
+
+use crate::error::{ParseError, Span};
+use crate::lexer::{Token, TokenKind};
+
+#[derive(Debug, Clone)]
+pub(crate) struct Spanned<T> {
+    pub(crate) value: T,
+    pub(crate) span: Span,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Ast {
+    pub(crate) arch: Option<Spanned<String>>,
+    pub(crate) objects: Vec<ObjectDecl>,
+    pub(crate) caps: Vec<CapBlock>,
+    pub(crate) irq_maps: Vec<IrqMapping>,
+    pub(crate) handoff: Option<HandoffDecl>,
+}
+
+#[derive(Debug)]
+pub(crate) struct ObjectDecl {
+    pub(crate) name: Spanned<String>,
+    pub(crate) count: Option<Spanned<u64>>,
+    pub(crate) ty: Spanned<String>,
+    pub(crate) params: Vec<Param>,
+    pub(crate) cover: Vec<CoverEntry>,
+}
+
+#[derive(Debug)]
+pub(crate) enum CoverEntry {
+    Decl(ObjectDecl),
+    Ref(ObjectRef),
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct ObjectRef {
+    pub(crate) name: String,
+    pub(crate) index: Option<u64>,
+    pub(crate) span: Span,
+}
+
+impl ObjectRef {
+    pub(crate) fn key(&self) -> String {
+        match self.index {
+            Some(index) => element_name(&self.name, index),
+            None => self.name.clone(),
+        }
+    }
+}
+
+pub(crate) fn element_name(array: &str, index: u64) -> String {
+    format!("{array}[{index}]")
+}
+
+#[derive(Debug)]
+pub(crate) struct CapBlock {
+    pub(crate) holder: ObjectRef,
+    pub(crate) caps: Vec<CapDecl>,
+}
+
+#[derive(Debug)]
+pub(crate) struct CapDecl {
+    pub(crate) slot: Option<Spanned<String>>,
+    pub(crate) target: ObjectRef,
+    pub(crate) params: Vec<Param>,
+}
+
+#[derive(Debug)]
+pub(crate) struct IrqMapping {
+    pub(crate) irq: Spanned<u64>,
+    pub(crate) handler: ObjectRef,
+}
+
+// `handoff <cnode> (<params>)`, which is specific to the initializer
+#[derive(Debug)]
+pub(crate) struct HandoffDecl {
+    pub(crate) cnode: ObjectRef,
+    pub(crate) params: Vec<Param>,
+}
+
+#[derive(Debug)]
+pub(crate) enum Param {
+    KeyValue { key: Spanned<String>, value: Value },
+    Bits(Spanned<u64>),
+    Flag(Spanned<String>),
+}
+
+impl Param {
+    pub(crate) fn span(&self) -> Span {
+        match self {
+            Self::KeyValue { key, value } => key.span.start..value.span.end,
+            Self::Bits(bits) => bits.span.clone(),
+            Self::Flag(flag) => flag.span.clone(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Value {
+    pub(crate) kind: ValueKind,
+    pub(crate) span: Span,
+}
+
+#[derive(Debug)]
+pub(crate) enum ValueKind {
+    Word(String),
+    Str(String),
+    // `[a, b]`
+    List(Vec<Value>),
+    // `(a, b)`, which only appears in parameters which the initializer does not need
+    Tuple,
+    // `{a b}`, as used for frame fill entries
+    Braced(Vec<Value>),
+}
+
+impl Value {
+    pub(crate) fn as_word(&self) -> Result<&str, ParseError> {
+        match &self.kind {
+            ValueKind::Word(word) => Ok(word),
+            _ => Err(ParseError::new(self.span.clone(), "expected a word")),
+        }
+    }
+
+    pub(crate) fn as_number(&self) -> Result<u64, ParseError> {
+        match &self.kind {
+            ValueKind::Word(word) => parse_number(word),
+            _ => None,
+        }
+        .ok_or_else(|| ParseError::new(self.span.clone(), "expected a number"))
+    }
+
+    pub(crate) fn as_str(&self) -> Result<&str, ParseError> {
+        match &self.kind {
+            ValueKind::Str(s) => Ok(s),
+            _ => Err(ParseError::new(self.span.clone(), "expected a string")),
+        }
+    }
+
+    pub(crate) fn as_list(&self) -> Result<&[Value], ParseError> {
+        match &self.kind {
+            ValueKind::List(values) => Ok(values),
+            _ => Err(ParseError::new(self.span.clone(), "expected a list")),
+        }
+    }
+
+    pub(crate) fn as_braced(&self) -> Result<&[Value], ParseError> {
+        match &self.kind {
+            ValueKind::Braced(values) => Ok(values),
+            _ => Err(ParseError::new(
+                self.span.clone(),
+                "expected a '{ ... }' group",
+            )),
+        }
+    }
+}
+
+pub(crate) fn parse_number(s: &str) -> Option<u64> {
+    if let Some(hex) = s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        u64::from_str_radix(hex, 16).ok()
+    } else if s.bytes().all(|b| b.is_ascii_digit()) {
+        s.parse().ok()
+    } else {
+        None
+    }
+}
+
+pub(crate) fn parse(tokens: Vec<Token>) -> Result<Ast, ParseError> {
+    Parser { tokens, pos: 0 }.parse_spec()
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek_nth(&self, n: usize) -> &Token {
+        let last = self.tokens.len() - 1;
+        &self.tokens[(self.pos + n).min(last)]
+    }
+
+    fn peek(&self) -> &Token {
+        self.peek_nth(0)
+    }
+
+    fn next(&mut self) -> Token {
+        let token = self.peek().clone();
+        if token.kind != TokenKind::Eof {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn is_punct(&self, c: char) -> bool {
+        self.peek().kind == TokenKind::Punct(c)
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(&self.peek().kind, TokenKind::Word(word) if word == keyword)
+    }
+
+    fn eat_punct(&mut self, c: char) -> bool {
+        let matched = self.is_punct(c);
+        if matched {
+            self.next();
+        }
+        matched
+    }
+
+    fn expected(&self, what: &str) -> ParseError {
+        let token = self.peek();
+        let found = match &token.kind {
+            TokenKind::Word(word) => format!("`{word}`"),
+            TokenKind::Str(s) => format!("{s:?}"),
+            TokenKind::Punct(c) => format!("'{c}'"),
+            TokenKind::Eof => "end of file".to_owned(),
+        };
+        ParseError::new(
+            token.span.clone(),
+            format!("expected {what}, found {found}"),
+        )
+    }
+
+    fn expect_punct(&mut self, c: char) -> Result<Span, ParseError> {
+        if self.is_punct(c) {
+            Ok(self.next().span)
+        } else {
+            Err(self.expected(&format!("'{c}'")))
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), ParseError> {
+        if self.is_keyword(keyword) {
+            self.next();
+            Ok(())
+        } else {
+            Err(self.expected(&format!("`{keyword}`")))
+        }
+    }
+
+    fn expect_word(&mut self, what: &str) -> Result<Spanned<String>, ParseError> {
+        match self.peek().kind.clone() {
+            TokenKind::Word(value) => Ok(Spanned {
+                value,
+                span: self.next().span,
+            }),
+            _ => Err(self.expected(what)),
+        }
+    }
+
+    fn expect_number(&mut self, what: &str) -> Result<Spanned<u64>, ParseError> {
+        let span = self.peek().span.clone();
+        match &self.peek().kind {
+            TokenKind::Word(word) => match parse_number(word) {
+                Some(value) => {
+                    self.next();
+                    Ok(Spanned { value, span })
+                }
+                None => Err(self.expected(what)),
+            },
+            _ => Err(self.expected(what)),
+        }
+    }
+
+    fn parse_spec(&mut self) -> Result<Ast, ParseError> {
+        let mut ast = Ast::default();
+        loop {
+            if self.peek().kind == TokenKind::Eof {
+                break;
+            } else if self.is_keyword("arch") {
+                self.next();
+                ast.arch = Some(self.expect_word("an architecture")?);
+            } else if self.is_keyword("objects") {
+                self.next();
+                self.expect_punct('{')?;
+                while !self.eat_punct('}') {
+                    ast.objects.push(self.parse_object_decl()?);
+                }
+            } else if self.is_keyword("caps") {
+                self.next();
+                self.expect_punct('{')?;
+                while !self.eat_punct('}') {
+                    ast.caps.push(self.parse_cap_block()?);
+                }
+            } else if self.is_keyword("irq") {
+                self.next();
+                self.expect_keyword("maps")?;
+                self.expect_punct('{')?;
+                while !self.eat_punct('}') {
+                    let irq = self.expect_number("an IRQ number")?;
+                    self.expect_punct(':')?;
+                    let handler = self.parse_object_ref()?;
+                    ast.irq_maps.push(IrqMapping { irq, handler });
+                }
+            } else if self.is_keyword("handoff") {
+                let span = self.next().span;
+                if ast.handoff.is_some() {
+                    return Err(ParseError::new(span, "more than one `handoff` section"));
+                }
+                let cnode = self.parse_object_ref()?;
+                let params = self.parse_params()?;
+                ast.handoff = Some(HandoffDecl { cnode, params });
+            } else if self.is_keyword("cdt") {
+                // The capability derivation tree is not used by the initializer.
+                self.next();
+                self.skip_group('{', '}')?;
+            } else {
+                return Err(
+                    self.expected("`arch`, `objects`, `caps`, `irq maps`, `handoff`, or `cdt`")
+                );
+            }
+        }
+        Ok(ast)
+    }
+
+    fn skip_group(&mut self, open: char, close: char) -> Result<(), ParseError> {
+        let open_span = self.expect_punct(open)?;
+        let mut depth = 1;
+        while depth > 0 {
+            match self.next().kind {
+                TokenKind::Punct(c) if c == open => depth += 1,
+                TokenKind::Punct(c) if c == close => depth -= 1,
+                TokenKind::Eof => {
+                    return Err(ParseError::new(open_span, format!("unclosed '{open}'")));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn parse_object_ref(&mut self) -> Result<ObjectRef, ParseError> {
+        let name = self.expect_word("an object name")?;
+        let mut span = name.span;
+        let index = if self.eat_punct('[') {
+            let index = self.expect_number("an array index")?;
+            span.end = self.expect_punct(']')?.end;
+            Some(index.value)
+        } else {
+            None
+        };
+        Ok(ObjectRef {
+            name: name.value,
+            index,
+            span,
+        })
+    }
+
+    fn parse_object_decl(&mut self) -> Result<ObjectDecl, ParseError> {
+        let name = self.expect_word("an object name")?;
+        let count = if self.eat_punct('[') {
+            let count = self.expect_number("an array length")?;
+            self.expect_punct(']')?;
+            Some(count)
+        } else {
+            None
+        };
+        self.expect_punct('=')?;
+        let ty = self.expect_word("an object type")?;
+        let params = self.parse_params()?;
+        let mut cover = vec![];
+        if self.eat_punct('{') {
+            while !self.eat_punct('}') {
+                cover.push(self.parse_cover_entry()?);
+            }
+        }
+        Ok(ObjectDecl {
+            name,
+            count,
+            ty,
+            params,
+            cover,
+        })
+    }
+
+    fn parse_cover_entry(&mut self) -> Result<CoverEntry, ParseError> {
+        let start = self.pos;
+        let object_ref = self.parse_object_ref()?;
+        if self.is_punct('=') {
+            self.pos = start;
+            Ok(CoverEntry::Decl(self.parse_object_decl()?))
+        } else {
+            Ok(CoverEntry::Ref(object_ref))
+        }
+    }
+
+    fn parse_cap_block(&mut self) -> Result<CapBlock, ParseError> {
+        let holder = self.parse_object_ref()?;
+        self.expect_punct('{')?;
+        let mut caps = vec![];
+        while !self.eat_punct('}') {
+            let slot = if matches!(self.peek().kind, TokenKind::Word(_))
+                && self.peek_nth(1).kind == TokenKind::Punct(':')
+            {
+                let slot = self.expect_word("a slot")?;
+                self.next();
+                Some(slot)
+            } else {
+                None
+            };
+            let target = self.parse_object_ref()?;
+            let params = self.parse_params()?;
+            caps.push(CapDecl {
+                slot,
+                target,
+                params,
+            });
+        }
+        Ok(CapBlock { holder, caps })
+    }
+
+    fn parse_params(&mut self) -> Result<Vec<Param>, ParseError> {
+        let mut params = vec![];
+        if !self.eat_punct('(') {
+            return Ok(params);
+        }
+        if self.eat_punct(')') {
+            return Ok(params);
+        }
+        loop {
+            params.push(self.parse_param()?);
+            if self.eat_punct(')') {
+                break;
+            }
+            self.expect_punct(',')?;
+        }
+        Ok(params)
+    }
+
+    fn parse_param(&mut self) -> Result<Param, ParseError> {
+        let word = self.expect_word("a parameter")?;
+        if self.eat_punct(':') {
+            let value = self.parse_value()?;
+            return Ok(Param::KeyValue { key: word, value });
+        }
+        if self.is_keyword("bits") {
+            let end = self.next().span.end;
+            let value = parse_number(&word.value)
+                .ok_or_else(|| ParseError::new(word.span.clone(), "expected a number of bits"))?;
+            return Ok(Param::Bits(Spanned {
+                value,
+                span: word.span.start..end,
+            }));
+        }
+        Ok(Param::Flag(word))
+    }
+
+    fn parse_value(&mut self) -> Result<Value, ParseError> {
+        let token = self.peek().clone();
+        let (kind, end) = match token.kind {
+            TokenKind::Word(word) => {
+                self.next();
+                (ValueKind::Word(word), token.span.end)
+            }
+            TokenKind::Str(s) => {
+                self.next();
+                (ValueKind::Str(s), token.span.end)
+            }
+            TokenKind::Punct('[') => {
+                let (values, end) = self.parse_sequence('[', ']', true)?;
+                (ValueKind::List(values), end)
+            }
+            TokenKind::Punct('(') => {
+                let (_values, end) = self.parse_sequence('(', ')', true)?;
+                (ValueKind::Tuple, end)
+            }
+            TokenKind::Punct('{') => {
+                let (values, end) = self.parse_sequence('{', '}', false)?;
+                (ValueKind::Braced(values), end)
+            }
+            _ => return Err(self.expected("a value")),
+        };
+        Ok(Value {
+            kind,
+            span: token.span.start..end,
+        })
+    }
+
+    fn parse_sequence(
+        &mut self,
+        open: char,
+        close: char,
+        comma_separated: bool,
+    ) -> Result<(Vec<Value>, usize), ParseError> {
+        self.expect_punct(open)?;
+        let mut values = vec![];
+        loop {
+            if self.is_punct(close) {
+                break;
+            }
+            values.push(self.parse_value()?);
+            if self.is_punct(close) {
+                break;
+            }
+            if comma_separated {
+                self.expect_punct(',')?;
+            } else {
+                self.eat_punct(',');
+            }
+        }
+        let end = self.expect_punct(close)?.end;
+        Ok((values, end))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::lexer::tokenize;
+
+    fn parse_str(src: &str) -> Result<Ast, ParseError> {
+        parse(tokenize(src)?)
+    }
+
+    #[test]
+    fn sections() {
+        let ast = parse_str(
+            "arch aarch64
+            objects {
+                ut = ut (16 bits) { ep = ep }
+                frames[2] = frame (4k, paddr: 0x1000)
+            }
+            caps {
+                cnode { 1: ep (RW, badge: 3) frames[1] (R) }
+            }
+            irq maps { 33: irq_handler }
+            cdt { ignored { nested } }
+            handoff cnode (untyped_slots: [16..31])",
+        )
+        .unwrap();
+
+        assert_eq!(ast.arch.unwrap().value, "aarch64");
+
+        let [ut, frames] = &ast.objects[..] else {
+            panic!()
+        };
+        assert_eq!(ut.ty.value, "ut");
+        assert!(matches!(&ut.params[..], [Param::Bits(bits)] if bits.value == 16));
+        assert!(matches!(&ut.cover[..], [CoverEntry::Decl(decl)] if decl.name.value == "ep"));
+        assert_eq!(frames.count.as_ref().unwrap().value, 2);
+        assert!(matches!(
+            &frames.params[..],
+            [Param::Flag(flag), Param::KeyValue { key, value }]
+                if flag.value == "4k" && key.value == "paddr" && value.as_number() == Ok(0x1000)
+        ));
+
+        let [block] = &ast.caps[..] else { panic!() };
+        assert_eq!(block.holder.key(), "cnode");
+        let [first, second] = &block.caps[..] else {
+            panic!()
+        };
+        assert_eq!(first.slot.as_ref().unwrap().value, "1");
+        assert_eq!(first.params.len(), 2);
+        assert!(second.slot.is_none());
+        assert_eq!(second.target.key(), "frames[1]");
+
+        let [mapping] = &ast.irq_maps[..] else {
+            panic!()
+        };
+        assert_eq!(mapping.irq.value, 33);
+        assert_eq!(mapping.handler.key(), "irq_handler");
+
+        let handoff = ast.handoff.unwrap();
+        assert_eq!(handoff.cnode.key(), "cnode");
+        assert!(matches!(
+            &handoff.params[..],
+            [Param::KeyValue { value, .. }] if matches!(value.as_list().unwrap(), [range] if range.as_word() == Ok("16..31"))
+        ));
+    }
+
+    #[test]
+    fn cover_refs() {
+        let ast = parse_str("objects { ut = ut (12 bits) { ep xs[3] } }").unwrap();
+        let [ut] = &ast.objects[..] else { panic!() };
+        let keys = ut
+            .cover
+            .iter()
+            .map(|entry| match entry {
+                CoverEntry::Ref(object_ref) => object_ref.key(),
+                CoverEntry::Decl(_) => panic!(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(keys, ["ep", "xs[3]"]);
+    }
+
+    #[test]
+    fn values() {
+        let ast =
+            parse_str("objects { t = tcb (init: [1, 0x2], mapping: (a, b), fill: [{0 1 x}]) }")
+                .unwrap();
+        let values = ast.objects[0]
+            .params
+            .iter()
+            .map(|param| match param {
+                Param::KeyValue { value, .. } => value,
+                _ => panic!(),
+            })
+            .collect::<Vec<_>>();
+        let numbers = values[0]
+            .as_list()
+            .unwrap()
+            .iter()
+            .map(|value| value.as_number().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(numbers, [1, 2]);
+        assert!(matches!(values[1].kind, ValueKind::Tuple));
+        assert_eq!(
+            values[2].as_list().unwrap()[0].as_braced().unwrap().len(),
+            3
+        );
+        assert_eq!(values[0].as_str().unwrap_err().span(), values[0].span);
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(parse_number("0"), Some(0));
+        assert_eq!(parse_number("0x1F"), Some(0x1f));
+        assert_eq!(parse_number("0X10"), Some(0x10));
+        assert_eq!(parse_number("18446744073709551615"), Some(u64::MAX));
+        assert_eq!(parse_number("18446744073709551616"), None);
+        assert_eq!(parse_number("4k"), None);
+        assert_eq!(parse_number("-1"), None);
+    }
+
+    #[test]
+    fn errors() {
+        let err = |src| parse_str(src).unwrap_err();
+        let e = err("objects { x = }");
+        assert_eq!(e.message(), "expected an object type, found '}'");
+        assert_eq!(e.span(), 14..15);
+        let e = err("objects { x = ep");
+        assert_eq!(e.message(), "expected an object name, found end of file");
+        assert_eq!(e.span(), 16..16);
+        let e = err("objects { x = ut (z bits) }");
+        assert_eq!(e.message(), "expected a number of bits");
+        assert_eq!(e.span(), 18..19);
+        assert_eq!(err("cdt { {").message(), "unclosed '{'");
+        assert_eq!(err("cdt { {").span(), 4..5);
+        assert_eq!(err("handoff a () handoff b ()").span(), 13..20);
+        assert_eq!(
+            err("bogus").message(),
+            "expected `arch`, `objects`, `caps`, `irq maps`, `handoff`, or `cdt`, found `bogus`"
+        );
+        assert_eq!(
+            err("irq maps { x: h }").message(),
+            "expected an IRQ number, found `x`"
+        );
+    }
+}
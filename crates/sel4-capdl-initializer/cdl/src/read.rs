@@ -0,0 +1,69 @@
+//
+// Copyright 2023, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use sel4_capdl_initializer_types::InputSpec;
+
+use crate::{parse, parse_object_sizes, ObjectSizes};
+
+/// An error from [`read_input_spec`] or [`read_object_sizes`].
+#[derive(Debug)]
+pub enum ReadError {
+    Io(io::Error),
+    /// A `.cdl` spec was read without object sizes.
+    MissingObjectSizes,
+    /// A file failed to parse. Holds the error as rendered by
+    /// [`ParseError::render`](crate::ParseError::render).
+    Parse(String),
+}
+
+impl From<io::Error> for ReadError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "{err}"),
+            Self::MissingObjectSizes => write!(f, "object sizes are required for .cdl specs"),
+            Self::Parse(rendered) => write!(f, "{}", rendered.trim_end()),
+        }
+    }
+}
+
+impl Error for ReadError {}
+
+/// Reads an `object_sizes.yaml` file (see [`parse_object_sizes`]).
+pub fn read_object_sizes(path: impl AsRef<Path>) -> Result<ObjectSizes, ReadError> {
+    let path = path.as_ref();
+    let src = fs::read_to_string(path)?;
+    parse_object_sizes(&src)
+        .map_err(|err| ReadError::Parse(err.render(&path.display().to_string(), &src)))
+}
+
+/// Reads a spec, which is JSON as emitted by `parse-capDL --json` unless its path has a `.cdl`
+/// extension. `object_sizes` is only needed for `.cdl` specs.
+pub fn read_input_spec(
+    path: impl AsRef<Path>,
+    object_sizes: Option<&ObjectSizes>,
+) -> Result<InputSpec, ReadError> {
+    let path = path.as_ref();
+    let src = fs::read_to_string(path)?;
+    if path.extension() != Some("cdl".as_ref()) {
+        return Ok(InputSpec::parse(&src));
+    }
+    let object_sizes = object_sizes.ok_or(ReadError::MissingObjectSizes)?;
+    parse(&src, object_sizes)
+        .map(|spec| InputSpec::from_file_content_spec(&spec))
+        .map_err(|err| ReadError::Parse(err.render(&path.display().to_string(), &src)))
+}
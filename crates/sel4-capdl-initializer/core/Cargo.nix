@@ -14,6 +14,6 @@ mk {
     inherit (localCrates)
       sel4
     ;
-    sel4-capdl-initializer-types = localCrates.sel4-capdl-initializer-types // { features = [ "sel4" ]; };
+    sel4-capdl-initializer-types = localCrates.sel4-capdl-initializer-types // { features = [ "sel4" "digest" ]; };
   };
 }
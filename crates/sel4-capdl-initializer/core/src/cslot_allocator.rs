@@ -4,6 +4,7 @@
 // SPDX-License-Identifier: BSD-2-Clause
 //
 
+use core::fmt;
 use core::ops::Range;
 
 use sel4::InitCSpaceSlot;
@@ -17,6 +18,14 @@ pub enum CSlotAllocatorError {
     OutOfSlots,
 }
 
+impl fmt::Display for CSlotAllocatorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::OutOfSlots => write!(f, "out of CSlots"),
+        }
+    }
+}
+
 impl CSlotAllocator {
     pub(crate) fn new(free: Range<InitCSpaceSlot>) -> Self {
         Self { free }
@@ -29,4 +38,8 @@ impl CSlotAllocator {
     pub(crate) fn alloc_or_panic(&mut self) -> InitCSpaceSlot {
         self.alloc().unwrap()
     }
+
+    pub(crate) fn free(&self) -> Range<InitCSpaceSlot> {
+        self.free.clone()
+    }
 }
@@ -10,36 +10,115 @@ use core::fmt;
 use core::num::TryFromIntError;
 use sel4_capdl_initializer_types::*;
 
+/// An error encountered by the initializer, along with whatever is known about where it happened.
 #[derive(Debug)]
-pub enum CapDLInitializerError {
+pub struct CapDLInitializerError {
+    cause: CapDLInitializerErrorCause,
+    phase: Option<Phase>,
+    object: Option<ObjectId>,
+    invocation: Option<Invocation>,
+}
+
+#[derive(Debug)]
+pub enum CapDLInitializerErrorCause {
     CSlotAllocatorError(CSlotAllocatorError),
     SeL4Error(sel4::Error),
     TryFromObjectError(TryFromObjectError),
     TryFromCapError(TryFromCapError),
     TryFromIntError(TryFromIntError),
+    SpecDigestMismatch {
+        expected: SpecDigest,
+        measured: SpecDigest,
+    },
+    /// There are more untypeds left over than slots in which to hand them off.
+    HandoffOutOfSlots,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Phase {
+    CreateObjects,
+    InitIRQs,
+    InitASIDs,
+    InitFrames,
+    MeasureSpec,
+    InitVSpaces,
+    InitSchedContexts,
+    InitTCBs,
+    InitCSpaces,
+    HandOffResources,
+    StartThreads,
+}
+
+/// A failed seL4 invocation.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Invocation {
+    pub name: &'static str,
+    /// The first two message registers, which, for some errors, the kernel uses to describe the
+    /// error in more detail.
+    pub details: [sel4::Word; 2],
+}
+
+impl CapDLInitializerError {
+    pub fn cause(&self) -> &CapDLInitializerErrorCause {
+        &self.cause
+    }
+
+    pub fn phase(&self) -> Option<Phase> {
+        self.phase
+    }
+
+    pub fn object(&self) -> Option<ObjectId> {
+        self.object
+    }
+
+    pub fn invocation(&self) -> Option<&Invocation> {
+        self.invocation.as_ref()
+    }
+
+    /// Like the `Display` implementation, but includes the name of the object involved.
+    pub fn display_with_object_name<'a>(
+        &'a self,
+        object_name: Option<&'a str>,
+    ) -> impl fmt::Display + 'a {
+        DisplayWithObjectName {
+            err: self,
+            object_name,
+        }
+    }
+}
+
+impl From<CapDLInitializerErrorCause> for CapDLInitializerError {
+    fn from(cause: CapDLInitializerErrorCause) -> Self {
+        Self {
+            cause,
+            phase: None,
+            object: None,
+            invocation: None,
+        }
+    }
 }
 
 impl From<CSlotAllocatorError> for CapDLInitializerError {
     fn from(err: CSlotAllocatorError) -> Self {
-        Self::CSlotAllocatorError(err)
+        CapDLInitializerErrorCause::CSlotAllocatorError(err).into()
     }
 }
 
 impl From<sel4::Error> for CapDLInitializerError {
     fn from(err: sel4::Error) -> Self {
-        Self::SeL4Error(err)
+        CapDLInitializerErrorCause::SeL4Error(err).into()
     }
 }
 
 impl From<TryFromObjectError> for CapDLInitializerError {
     fn from(err: TryFromObjectError) -> Self {
-        Self::TryFromObjectError(err)
+        CapDLInitializerErrorCause::TryFromObjectError(err).into()
     }
 }
 
 impl From<TryFromCapError> for CapDLInitializerError {
     fn from(err: TryFromCapError) -> Self {
-        Self::TryFromCapError(err)
+        CapDLInitializerErrorCause::TryFromCapError(err).into()
     }
 }
 
@@ -51,13 +130,139 @@ impl From<Infallible> for CapDLInitializerError {
 
 impl From<TryFromIntError> for CapDLInitializerError {
     fn from(err: TryFromIntError) -> Self {
-        Self::TryFromIntError(err)
+        CapDLInitializerErrorCause::TryFromIntError(err).into()
+    }
+}
+
+// // //
+
+/// Attaches context to errors on their way out of the initializer. The innermost context wins.
+pub(crate) trait ResultExt<T> {
+    fn phase(self, phase: Phase) -> Result<T, CapDLInitializerError>;
+
+    fn object(self, obj_id: ObjectId) -> Result<T, CapDLInitializerError>;
+
+    /// Must be applied immediately after the invocation, before the IPC buffer is reused.
+    fn invocation(self, name: &'static str) -> Result<T, CapDLInitializerError>;
+}
+
+impl<T, E: Into<CapDLInitializerError>> ResultExt<T> for Result<T, E> {
+    fn phase(self, phase: Phase) -> Result<T, CapDLInitializerError> {
+        self.map_err(|err| {
+            let mut err: CapDLInitializerError = err.into();
+            err.phase.get_or_insert(phase);
+            err
+        })
+    }
+
+    fn object(self, obj_id: ObjectId) -> Result<T, CapDLInitializerError> {
+        self.map_err(|err| {
+            let mut err: CapDLInitializerError = err.into();
+            err.object.get_or_insert(obj_id);
+            err
+        })
+    }
+
+    fn invocation(self, name: &'static str) -> Result<T, CapDLInitializerError> {
+        self.map_err(|err| {
+            let mut err: CapDLInitializerError = err.into();
+            if let CapDLInitializerErrorCause::SeL4Error(_) = err.cause {
+                err.invocation.get_or_insert_with(|| Invocation {
+                    name,
+                    details: sel4::with_ipc_buffer(|ipc_buffer| {
+                        let mrs = ipc_buffer.msg_regs();
+                        [mrs[0], mrs[1]]
+                    }),
+                });
+            }
+            err
+        })
     }
 }
 
+// // //
+
 impl fmt::Display for CapDLInitializerError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        // TODO
-        write!(f, "{self:?}")
+        fmt::Display::fmt(&self.display_with_object_name(None), f)
+    }
+}
+
+struct DisplayWithObjectName<'a> {
+    err: &'a CapDLInitializerError,
+    object_name: Option<&'a str>,
+}
+
+impl fmt::Display for DisplayWithObjectName<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let err = self.err;
+        if let Some(phase) = &err.phase {
+            write!(f, "{phase}: ")?;
+        }
+        if let Some(invocation) = &err.invocation {
+            write!(f, "{} failed", invocation.name)?;
+        } else {
+            write!(f, "failed")?;
+        }
+        if let Some(obj_id) = err.object {
+            write!(f, " for object {obj_id}")?;
+            if let Some(name) = self.object_name {
+                write!(f, " ({name:?})")?;
+            }
+        }
+        write!(f, ": {}", err.cause)?;
+        if let (CapDLInitializerErrorCause::SeL4Error(sel4_err), Some(invocation)) =
+            (&err.cause, &err.invocation)
+        {
+            let [mr0, mr1] = invocation.details;
+            match sel4_err {
+                sel4::Error::InvalidArgument => write!(f, " (argument {mr0})")?,
+                sel4::Error::InvalidCapability => write!(f, " (capability {mr0})")?,
+                sel4::Error::RangeError => write!(f, " (valid range: {mr0:#x}..={mr1:#x})")?,
+                sel4::Error::FailedLookup => write!(
+                    f,
+                    " (in {} capability, lookup failure type {mr1})",
+                    if mr0 != 0 { "source" } else { "destination" }
+                )?,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for CapDLInitializerErrorCause {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::CSlotAllocatorError(err) => write!(f, "{err}"),
+            Self::SeL4Error(err) => write!(f, "{err}"),
+            Self::TryFromObjectError(err) => write!(f, "{err}"),
+            Self::TryFromCapError(err) => write!(f, "{err}"),
+            Self::TryFromIntError(err) => write!(f, "{err}"),
+            Self::SpecDigestMismatch { expected, measured } => write!(
+                f,
+                "spec digest mismatch (expected {expected}, measured {measured})"
+            ),
+            Self::HandoffOutOfSlots => write!(f, "out of slots for untypeds handed off"),
+        }
+    }
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Self::CreateObjects => "create_objects",
+            Self::InitIRQs => "init_irqs",
+            Self::InitASIDs => "init_asids",
+            Self::InitFrames => "init_frames",
+            Self::MeasureSpec => "measure_spec",
+            Self::InitVSpaces => "init_vspaces",
+            Self::InitSchedContexts => "init_sched_contexts",
+            Self::InitTCBs => "init_tcbs",
+            Self::InitCSpaces => "init_cspaces",
+            Self::HandOffResources => "hand_off_resources",
+            Self::StartThreads => "start_threads",
+        };
+        write!(f, "{s}")
     }
 }
@@ -6,17 +6,27 @@
 
 use sel4::{AbsoluteCPtr, InitCSpaceSlot};
 
+use crate::error::ResultExt;
 use crate::{CSlotAllocator, CapDLInitializerError};
 
-const NUM_SLOTS: usize = 2;
+pub(crate) const NUM_SLOTS: usize = 2;
 
 pub(crate) struct HoldSlots<T> {
     slots: [InitCSpaceSlot; NUM_SLOTS],
-    slots_occupied: [bool; NUM_SLOTS],
+    occupants: [Option<Dummy>; NUM_SLOTS],
     which_slot: usize,
     relative_cptr_of: T,
 }
 
+/// A dummy untyped, allocated only to advance the watermark of its parent, which is still held.
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct Dummy {
+    pub(crate) slot: InitCSpaceSlot,
+    pub(crate) paddr: usize,
+    pub(crate) size_bits: usize,
+    pub(crate) is_device: bool,
+}
+
 impl<T> HoldSlots<T> {
     pub(crate) fn new(
         cslot_allocator: &mut CSlotAllocator,
@@ -28,24 +38,36 @@ impl<T> HoldSlots<T> {
                 let mut f = || cslot_allocator.alloc();
                 [f()?, f()?]
             },
-            slots_occupied: [false; NUM_SLOTS],
+            occupants: [None; NUM_SLOTS],
             which_slot: 0,
             relative_cptr_of,
         })
     }
+
+    /// Returns the dummies which are still held. Their memory is free.
+    pub(crate) fn into_occupants(self) -> [Option<Dummy>; NUM_SLOTS] {
+        self.occupants
+    }
 }
 
 impl<T: FnMut(InitCSpaceSlot) -> AbsoluteCPtr> HoldSlots<T> {
     pub(crate) fn get_slot(&mut self) -> Result<InitCSpaceSlot, CapDLInitializerError> {
-        if self.slots_occupied[self.which_slot] {
-            (self.relative_cptr_of)(self.slots[self.which_slot]).delete()?;
-            self.slots_occupied[self.which_slot] = false;
+        if self.occupants[self.which_slot].is_some() {
+            (self.relative_cptr_of)(self.slots[self.which_slot])
+                .delete()
+                .invocation("cnode_delete")?;
+            self.occupants[self.which_slot] = None;
         }
         Ok(self.slots[self.which_slot])
     }
 
-    pub(crate) fn report_used(&mut self) {
-        self.slots_occupied[self.which_slot] = true;
+    pub(crate) fn report_used(&mut self, paddr: usize, size_bits: usize, is_device: bool) {
+        self.occupants[self.which_slot] = Some(Dummy {
+            slot: self.slots[self.which_slot],
+            paddr,
+            size_bits,
+            is_device,
+        });
         self.which_slot = (self.which_slot + 1) % NUM_SLOTS;
     }
 }
@@ -35,13 +35,17 @@ mod memory;
 use arch::frame_types;
 pub use buffers::{InitializerBuffers, PerObjectBuffer};
 use cslot_allocator::{CSlotAllocator, CSlotAllocatorError};
-pub use error::CapDLInitializerError;
-use hold_slots::HoldSlots;
+use error::ResultExt;
+pub use error::{CapDLInitializerError, CapDLInitializerErrorCause, Invocation, Phase};
+use hold_slots::{Dummy, HoldSlots};
 use memory::{get_user_image_frame_slot, init_copy_addrs};
 
 #[sel4::sel4_cfg(all(ARCH_RISCV64, not(PT_LEVELS = "3")))]
 compile_error!("unsupported configuration");
 
+// `seL4_MinUntypedBits`
+const MIN_UNTYPED_SIZE_BITS: usize = 4;
+
 type Result<T> = result::Result<T, CapDLInitializerError>;
 
 pub struct Initializer<'a, N: ObjectName, D: Content, M: GetEmbeddedFrame, B> {
@@ -50,8 +54,14 @@ pub struct Initializer<'a, N: ObjectName, D: Content, M: GetEmbeddedFrame, B> {
     small_frame_copy_addr: usize,
     large_frame_copy_addr: usize,
     spec_with_sources: &'a SpecWithSources<'a, N, D, M>,
+    expected_spec_digest: &'a SpecDigest,
     cslot_allocator: &'a mut CSlotAllocator,
     buffers: &'a mut InitializerBuffers<B>,
+    // Indexed like the bootinfo untyped list. Memory below an untyped's watermark has been
+    // allocated from it.
+    untyped_watermarks: [usize; sel4::sel4_cfg_usize!(MAX_NUM_BOOTINFO_UNTYPED_CAPS)],
+    // Dummies left over from allocating objects with paddrs, whose memory can be handed off.
+    held_dummies: [Option<Dummy>; hold_slots::NUM_SLOTS],
 }
 
 impl<'a, N: ObjectName, D: Content, M: GetEmbeddedFrame, B: BorrowMut<[PerObjectBuffer]>>
@@ -61,6 +71,7 @@ impl<'a, N: ObjectName, D: Content, M: GetEmbeddedFrame, B: BorrowMut<[PerObject
         bootinfo: &BootInfo,
         user_image_bounds: Range<usize>,
         spec_with_sources: &SpecWithSources<N, D, M>,
+        expected_spec_digest: &SpecDigest,
         buffers: &mut InitializerBuffers<B>,
     ) -> ! {
         info!("Starting CapDL initializer");
@@ -70,17 +81,25 @@ impl<'a, N: ObjectName, D: Content, M: GetEmbeddedFrame, B: BorrowMut<[PerObject
 
         let mut cslot_allocator = CSlotAllocator::new(bootinfo.empty());
 
-        Initializer {
+        let mut initializer = Initializer {
             bootinfo,
             user_image_bounds,
             small_frame_copy_addr,
             large_frame_copy_addr,
             spec_with_sources,
+            expected_spec_digest,
             cslot_allocator: &mut cslot_allocator,
             buffers,
+            untyped_watermarks: array::from_fn(|_| 0),
+            held_dummies: [None; hold_slots::NUM_SLOTS],
+        };
+
+        if let Err(err) = initializer.run() {
+            let object_name = err
+                .object()
+                .and_then(|obj_id| initializer.object_name(initializer.spec().name(obj_id)));
+            panic!("Error: {}", err.display_with_object_name(object_name));
         }
-        .run()
-        .unwrap_or_else(|err| panic!("Error: {}", err));
 
         info!("CapDL initializer done, suspending");
 
@@ -100,23 +119,29 @@ impl<'a, N: ObjectName, D: Content, M: GetEmbeddedFrame, B: BorrowMut<[PerObject
     // // //
 
     fn run(&mut self) -> Result<()> {
-        self.create_objects()?;
+        self.create_objects().phase(Phase::CreateObjects)?;
 
-        self.init_irqs()?;
-        self.init_asids()?;
-        self.init_frames()?;
-        self.init_vspaces()?;
+        self.init_irqs().phase(Phase::InitIRQs)?;
+        self.init_asids().phase(Phase::InitASIDs)?;
+        self.init_frames().phase(Phase::InitFrames)?;
+        self.measure_spec().phase(Phase::MeasureSpec)?;
+        self.init_vspaces().phase(Phase::InitVSpaces)?;
 
         sel4::sel4_cfg_if! {
             if #[cfg(KERNEL_MCS)] {
-                self.init_sched_contexts()?;
+                self.init_sched_contexts().phase(Phase::InitSchedContexts)?;
             }
         }
 
-        self.init_tcbs()?;
-        self.init_cspaces()?;
+        self.init_tcbs().phase(Phase::InitTCBs)?;
+        self.init_cspaces().phase(Phase::InitCSpaces)?;
+
+        if let Some(handoff) = &self.spec().resource_handoff {
+            self.hand_off_resources(handoff)
+                .phase(Phase::HandOffResources)?;
+        }
 
-        self.start_threads()?;
+        self.start_threads().phase(Phase::StartThreads)?;
 
         Ok(())
     }
@@ -180,6 +205,7 @@ impl<'a, N: ObjectName, D: Content, M: GetEmbeddedFrame, B: BorrowMut<[PerObject
             let ut_paddr_start = ut.paddr();
             let ut_paddr_end = ut_paddr_start + ut_size_bytes;
             let mut cur_paddr = ut_paddr_start;
+            self.untyped_watermarks[*i_ut] = ut_paddr_end;
             trace!(
                 "Allocating from untyped: {:#x}..{:#x} (size_bits = {}, device = {:?})",
                 ut_paddr_start,
@@ -211,7 +237,8 @@ impl<'a, N: ObjectName, D: Content, M: GetEmbeddedFrame, B: BorrowMut<[PerObject
                                             &embedded.get_embedded_frame(
                                                 self.spec_with_sources.embedded_frame_source,
                                             ),
-                                        )?;
+                                        )
+                                        .object(*obj_id)?;
                                         *obj_id += 1;
                                         continue;
                                     }
@@ -229,12 +256,16 @@ impl<'a, N: ObjectName, D: Content, M: GetEmbeddedFrame, B: BorrowMut<[PerObject
                                     blueprint.physical_size_bits(),
                                     self.object_name(&named_obj.name).unwrap_or("<none>")
                                 );
-                                self.ut_local_cptr(*i_ut).untyped_retype(
-                                    &blueprint,
-                                    &init_thread_cnode_relative_cptr(),
-                                    self.alloc_orig_cslot(*obj_id),
-                                    1,
-                                )?;
+                                let slot = self.alloc_orig_cslot(*obj_id);
+                                self.ut_local_cptr(*i_ut)
+                                    .untyped_retype(
+                                        &blueprint,
+                                        &init_thread_cnode_relative_cptr(),
+                                        slot,
+                                        1,
+                                    )
+                                    .invocation("untyped_retype")
+                                    .object(*obj_id)?;
                                 cur_paddr += 1 << size_bits;
                                 *obj_id += 1;
                                 created = true;
@@ -250,17 +281,20 @@ impl<'a, N: ObjectName, D: Content, M: GetEmbeddedFrame, B: BorrowMut<[PerObject
                                 cur_paddr,
                                 max_size_bits
                             );
-                            self.ut_local_cptr(*i_ut).untyped_retype(
-                                &ObjectBlueprint::Untyped {
-                                    size_bits: max_size_bits,
-                                },
-                                &init_thread_cnode_relative_cptr(),
-                                hold_slot,
-                                1,
-                            )?;
-                            hold_slots.report_used();
+                            self.ut_local_cptr(*i_ut)
+                                .untyped_retype(
+                                    &ObjectBlueprint::Untyped {
+                                        size_bits: max_size_bits,
+                                    },
+                                    &init_thread_cnode_relative_cptr(),
+                                    hold_slot,
+                                    1,
+                                )
+                                .invocation("untyped_retype")?;
+                            hold_slots.report_used(cur_paddr, max_size_bits, ut.is_device());
                             cur_paddr += 1 << max_size_bits;
                         } else {
+                            self.untyped_watermarks[*i_ut] = cur_paddr;
                             cur_paddr = target;
                         }
                     }
@@ -275,12 +309,11 @@ impl<'a, N: ObjectName, D: Content, M: GetEmbeddedFrame, B: BorrowMut<[PerObject
                         blueprint.physical_size_bits(),
                         self.object_name(&named_obj.name).unwrap_or("<none>")
                     );
-                    self.ut_local_cptr(*i_ut).untyped_retype(
-                        &blueprint,
-                        &init_thread_cnode_relative_cptr(),
-                        self.alloc_orig_cslot(obj_id),
-                        1,
-                    )?;
+                    let slot = self.alloc_orig_cslot(obj_id);
+                    self.ut_local_cptr(*i_ut)
+                        .untyped_retype(&blueprint, &init_thread_cnode_relative_cptr(), slot, 1)
+                        .invocation("untyped_retype")
+                        .object(obj_id)?;
                     cur_paddr += 1 << blueprint.physical_size_bits();
                     next_obj_with_paddr += 1;
                 } else {
@@ -289,6 +322,8 @@ impl<'a, N: ObjectName, D: Content, M: GetEmbeddedFrame, B: BorrowMut<[PerObject
             }
         }
 
+        self.held_dummies = hold_slots.into_occupants();
+
         // Ensure that we've created every root object
         for bits in 0..sel4::WORD_SIZE {
             assert_eq!(by_size_start[bits], by_size_end[bits], "!!! {}", bits);
@@ -307,12 +342,16 @@ impl<'a, N: ObjectName, D: Content, M: GetEmbeddedFrame, B: BorrowMut<[PerObject
                     self.object_name(&child.name).unwrap_or("<none>"),
                     self.object_name(&parent.name).unwrap_or("<none>"),
                 );
-                parent_cptr.untyped_retype(
-                    &child.object.blueprint().unwrap(),
-                    &init_thread_cnode_relative_cptr(),
-                    self.alloc_orig_cslot(child_obj_id),
-                    1,
-                )?;
+                let slot = self.alloc_orig_cslot(child_obj_id);
+                parent_cptr
+                    .untyped_retype(
+                        &child.object.blueprint().unwrap(),
+                        &init_thread_cnode_relative_cptr(),
+                        slot,
+                        1,
+                    )
+                    .invocation("untyped_retype")
+                    .object(child_obj_id)?;
             }
         }
 
@@ -323,7 +362,10 @@ impl<'a, N: ObjectName, D: Content, M: GetEmbeddedFrame, B: BorrowMut<[PerObject
             for obj_id in self.spec().asid_slots.iter() {
                 let ut = self.orig_local_cptr(*obj_id);
                 let slot = self.cslot_alloc_or_panic();
-                BootInfo::asid_control().asid_control_make_pool(ut, &cslot_relative_cptr(slot))?;
+                BootInfo::asid_control()
+                    .asid_control_make_pool(ut, &cslot_relative_cptr(slot))
+                    .invocation("asid_control_make_pool")
+                    .object(*obj_id)?;
                 self.set_orig_cslot(*obj_id, slot);
             }
         }
@@ -336,7 +378,9 @@ impl<'a, N: ObjectName, D: Content, M: GetEmbeddedFrame, B: BorrowMut<[PerObject
                     match self.spec().object(*handler) {
                         Object::IRQ(_) => {
                             BootInfo::irq_control()
-                                .irq_control_get(*irq, &cslot_relative_cptr(slot))?;
+                                .irq_control_get(*irq, &cslot_relative_cptr(slot))
+                                .invocation("irq_control_get")
+                                .object(*handler)?;
                         }
                         #[sel4_cfg(any(ARCH_AARCH32, ARCH_AARCH64))]
                         Object::ArmIRQ(obj) => {
@@ -346,17 +390,57 @@ impl<'a, N: ObjectName, D: Content, M: GetEmbeddedFrame, B: BorrowMut<[PerObject
                                         *irq,
                                         obj.extra.trigger,
                                         &cslot_relative_cptr(slot),
-                                    )?;
+                                    )
+                                    .invocation("irq_control_get_trigger")
+                                    .object(*handler)?;
                                 } else {
                                     BootInfo::irq_control().irq_control_get_trigger_core(
                                         *irq,
                                         obj.extra.trigger,
                                         obj.extra.target,
                                         &cslot_relative_cptr(slot),
-                                    )?;
+                                    )
+                                    .invocation("irq_control_get_trigger_core")
+                                    .object(*handler)?;
                                 }
                             }
                         }
+                        #[sel4_cfg(any(ARCH_IA32, ARCH_X86_64))]
+                        Object::IRQIOAPIC(obj) => {
+                            BootInfo::irq_control().irq_control_get_ioapic(
+                                obj.extra.ioapic,
+                                obj.extra.pin,
+                                obj.extra.level,
+                                obj.extra.polarity,
+                                *irq,
+                                &cslot_relative_cptr(slot),
+                            )
+                            .invocation("irq_control_get_ioapic")
+                            .object(*handler)?;
+                        }
+                        #[sel4_cfg(any(ARCH_IA32, ARCH_X86_64))]
+                        Object::IRQMSI(obj) => {
+                            BootInfo::irq_control().irq_control_get_msi(
+                                obj.extra.pci_bus,
+                                obj.extra.pci_dev,
+                                obj.extra.pci_func,
+                                obj.extra.handle,
+                                *irq,
+                                &cslot_relative_cptr(slot),
+                            )
+                            .invocation("irq_control_get_msi")
+                            .object(*handler)?;
+                        }
+                        #[sel4_cfg(any(ARCH_RISCV32, ARCH_RISCV64))]
+                        Object::RiscvIRQ(obj) => {
+                            BootInfo::irq_control().irq_control_get_trigger(
+                                *irq,
+                                obj.extra.trigger,
+                                &cslot_relative_cptr(slot),
+                            )
+                            .invocation("irq_control_get_trigger")
+                            .object(*handler)?;
+                        }
                         _ => {
                             panic!();
                         }
@@ -366,6 +450,24 @@ impl<'a, N: ObjectName, D: Content, M: GetEmbeddedFrame, B: BorrowMut<[PerObject
             }
         }
 
+        // Create IOPort caps
+        sel4::sel4_cfg_if! {
+            if #[cfg(any(ARCH_IA32, ARCH_X86_64))] {
+                for (obj_id, obj) in self.spec().filter_objects::<&object::IOPorts>() {
+                    let slot = self.cslot_alloc_or_panic();
+                    BootInfo::io_port_control()
+                        .io_port_control_issue(
+                            obj.first_port,
+                            obj.last_port,
+                            &cslot_relative_cptr(slot),
+                        )
+                        .invocation("io_port_control_issue")
+                        .object(obj_id)?;
+                    self.set_orig_cslot(obj_id, slot);
+                }
+            }
+        }
+
         Ok(())
     }
 
@@ -386,14 +488,21 @@ impl<'a, N: ObjectName, D: Content, M: GetEmbeddedFrame, B: BorrowMut<[PerObject
 
         let irq_notifications = self
             .spec()
-            .filter_objects::<&object::IRQ>()
-            .map(|(obj_id, obj)| (obj_id, obj.notification()));
-        let arm_irq_notifications = self
-            .spec()
-            .filter_objects::<&object::ArmIRQ>()
-            .map(|(obj_id, obj)| (obj_id, obj.notification()));
+            .objects()
+            .enumerate()
+            .filter_map(|(obj_id, obj)| {
+                let notification = match obj {
+                    Object::IRQ(obj) => obj.notification(),
+                    Object::ArmIRQ(obj) => obj.notification(),
+                    Object::IRQIOAPIC(obj) => obj.notification(),
+                    Object::IRQMSI(obj) => obj.notification(),
+                    Object::RiscvIRQ(obj) => obj.notification(),
+                    _ => return None,
+                };
+                Some((obj_id, notification))
+            });
 
-        for (obj_id, notification) in irq_notifications.chain(arm_irq_notifications) {
+        for (obj_id, notification) in irq_notifications {
             let irq_handler = self.orig_local_cptr::<cap_type::IRQHandler>(obj_id);
             if let Some(logical_nfn_cap) = notification {
                 let nfn = match logical_nfn_cap.badge {
@@ -402,11 +511,16 @@ impl<'a, N: ObjectName, D: Content, M: GetEmbeddedFrame, B: BorrowMut<[PerObject
                         let orig_cptr = self.orig_relative_cptr(logical_nfn_cap.object);
                         let slot = self.cslot_alloc_or_panic();
                         let cptr = cslot_relative_cptr(slot);
-                        cptr.mint(&orig_cptr, CapRights::all(), badge)?;
+                        cptr.mint(&orig_cptr, CapRights::all(), badge)
+                            .invocation("cnode_mint")
+                            .object(obj_id)?;
                         cslot_local_cptr(slot)
                     }
                 };
-                irq_handler.irq_handler_set_notification(nfn)?;
+                irq_handler
+                    .irq_handler_set_notification(nfn)
+                    .invocation("irq_handler_set_notification")
+                    .object(obj_id)?;
             }
         }
         Ok(())
@@ -419,7 +533,10 @@ impl<'a, N: ObjectName, D: Content, M: GetEmbeddedFrame, B: BorrowMut<[PerObject
             .filter_objects_with::<&object::PageTable>(|obj| obj.is_root)
         {
             let pgd = self.orig_local_cptr::<cap_type::VSpace>(obj_id);
-            BootInfo::init_thread_asid_pool().asid_pool_assign(pgd)?;
+            BootInfo::init_thread_asid_pool()
+                .asid_pool_assign(pgd)
+                .invocation("asid_pool_assign")
+                .object(obj_id)?;
         }
         Ok(())
     }
@@ -427,76 +544,132 @@ impl<'a, N: ObjectName, D: Content, M: GetEmbeddedFrame, B: BorrowMut<[PerObject
     fn init_frames(&mut self) -> Result<()> {
         debug!("Initializing Frames");
         for (obj_id, obj) in self.spec().filter_objects::<&object::Frame<'a, D, M>>() {
-            // TODO make more platform-agnostic
             if let Some(fill) = obj.init.as_fill() {
                 let entries = &fill.entries;
                 if !entries.is_empty() {
-                    match obj.size_bits {
-                        frame_types::FRAME_SIZE_0_BITS => {
-                            let frame = self.orig_local_cptr::<frame_types::FrameType0>(obj_id);
-                            self.fill_frame(frame, entries)?;
-                        }
-                        frame_types::FRAME_SIZE_1_BITS => {
-                            let frame = self.orig_local_cptr::<frame_types::FrameType1>(obj_id);
-                            self.fill_frame(frame, entries)?;
-                        }
-                        _ => {
-                            panic!()
-                        }
-                    }
+                    self.with_frame_mapped(obj_id, obj, |frame| self.fill_frame(frame, entries))
+                        .object(obj_id)?;
                 }
             }
         }
         Ok(())
     }
 
-    fn fill_frame<U: SizedFrameType>(
-        &self,
-        frame: LocalCPtr<U>,
-        fill: &[FillEntry<D>],
-    ) -> Result<()> {
-        frame.frame_map(
-            BootInfo::init_thread_vspace(),
-            self.copy_addr::<U>(),
-            CapRights::read_write(),
-            arch::vm_attributes_from_whether_cached(false),
-        )?;
-        atomic::fence(Ordering::SeqCst); // lazy
+    fn fill_frame(&self, frame: &mut [u8], fill: &[FillEntry<D>]) {
         for entry in fill.iter() {
-            let offset = entry.range.start;
-            let length = entry.range.end - entry.range.start;
-            assert!(entry.range.end <= U::FRAME_SIZE.bytes());
-            let dst_frame = self.copy_addr::<U>() as *mut u8;
-            let dst = unsafe { slice::from_raw_parts_mut(dst_frame.add(offset), length) };
+            assert!(entry.range.end <= frame.len());
+            let dst = &mut frame[entry.range.clone()];
             match &entry.content {
                 FillEntryContent::Data(content_data) => {
                     content_data.copy_out(self.spec_with_sources.content_source, dst);
                 }
                 FillEntryContent::BootInfo(content_bootinfo) => {
+                    // Content which does not come from the kernel is written by later phases.
+                    let Some(id) = content_bootinfo.id.bootinfo_extra_id() else {
+                        continue;
+                    };
                     for extra in self.bootinfo.extra() {
-                        if extra.id == (&content_bootinfo.id).into() {
-                            let n = dst.len().min(
-                                extra
-                                    .content_with_header()
-                                    .len()
-                                    .saturating_sub(content_bootinfo.offset),
+                        if extra.id == id {
+                            copy_at_offset(
+                                dst,
+                                extra.content_with_header(),
+                                content_bootinfo.offset,
                             );
-                            if n > 0 {
-                                dst[..n].copy_from_slice(
-                                    &extra.content_with_header()
-                                        [content_bootinfo.offset..(content_bootinfo.offset + n)],
-                                );
-                            }
                         }
                     }
                 }
             }
         }
-        atomic::fence(Ordering::SeqCst); // lazy
-        frame.frame_unmap()?;
+    }
+
+    fn measure_spec(&self) -> Result<()> {
+        debug!("Measuring spec");
+        let digest = self.spec().digest(|obj_id, obj, hasher| match &obj.init {
+            FrameInit::Embedded(embedded) => {
+                let frame =
+                    embedded.get_embedded_frame(self.spec_with_sources.embedded_frame_source);
+                hasher.update(unsafe { slice::from_raw_parts(frame.ptr(), 1 << obj.size_bits) });
+                Ok(())
+            }
+            FrameInit::Fill(_) => self
+                .with_frame_mapped(obj_id, obj, |frame| hasher.update(frame))
+                .object(obj_id),
+        })?;
+        info!("Spec digest: {}", digest);
+        if digest != *self.expected_spec_digest {
+            return Err(CapDLInitializerErrorCause::SpecDigestMismatch {
+                expected: *self.expected_spec_digest,
+                measured: digest,
+            }
+            .into());
+        }
+        for (obj_id, obj) in self.spec().filter_objects::<&object::Frame<'a, D, M>>() {
+            if let Some(fill) = obj.init.as_fill() {
+                let entries = fill.entries.iter().filter(|entry| {
+                    entry.content.as_bootinfo().map(|content| content.id)
+                        == Some(FillEntryContentBootInfoId::SpecDigest)
+                });
+                if entries.clone().next().is_some() {
+                    self.with_frame_mapped(obj_id, obj, |frame| {
+                        for entry in entries {
+                            copy_at_offset(
+                                &mut frame[entry.range.clone()],
+                                digest.as_bytes(),
+                                entry.content.as_bootinfo().unwrap().offset,
+                            );
+                        }
+                    })
+                    .object(obj_id)?;
+                }
+            }
+        }
         Ok(())
     }
 
+    fn with_frame_mapped<T>(
+        &self,
+        obj_id: ObjectId,
+        obj: &object::Frame<'a, D, M>,
+        f: impl FnOnce(&mut [u8]) -> T,
+    ) -> Result<T> {
+        // TODO make more platform-agnostic
+        match obj.size_bits {
+            frame_types::FRAME_SIZE_0_BITS => {
+                let frame = self.orig_local_cptr::<frame_types::FrameType0>(obj_id);
+                self.with_sized_frame_mapped(frame, f)
+            }
+            frame_types::FRAME_SIZE_1_BITS => {
+                let frame = self.orig_local_cptr::<frame_types::FrameType1>(obj_id);
+                self.with_sized_frame_mapped(frame, f)
+            }
+            _ => {
+                panic!()
+            }
+        }
+    }
+
+    fn with_sized_frame_mapped<U: SizedFrameType, T>(
+        &self,
+        frame: LocalCPtr<U>,
+        f: impl FnOnce(&mut [u8]) -> T,
+    ) -> Result<T> {
+        frame
+            .frame_map(
+                BootInfo::init_thread_vspace(),
+                self.copy_addr::<U>(),
+                CapRights::read_write(),
+                arch::vm_attributes_from_whether_cached(false),
+            )
+            .invocation("frame_map")?;
+        atomic::fence(Ordering::SeqCst); // lazy
+        let ret = f(unsafe {
+            slice::from_raw_parts_mut(self.copy_addr::<U>() as *mut u8, U::FRAME_SIZE.bytes())
+        });
+        atomic::fence(Ordering::SeqCst); // lazy
+        frame.frame_unmap().invocation("frame_unmap")?;
+        Ok(ret)
+    }
+
     fn init_vspaces(&mut self) -> Result<()> {
         debug!("Initializing VSpaces");
         for (obj_id, obj) in self
@@ -504,7 +677,7 @@ impl<'a, N: ObjectName, D: Content, M: GetEmbeddedFrame, B: BorrowMut<[PerObject
             .filter_objects_with::<&object::PageTable>(|obj| obj.is_root)
         {
             let vspace = self.orig_local_cptr::<cap_type::VSpace>(obj_id);
-            self.init_vspace(vspace, 0, 0, obj)?;
+            self.init_vspace(vspace, 0, 0, obj).object(obj_id)?;
         }
         Ok(())
     }
@@ -522,18 +695,17 @@ impl<'a, N: ObjectName, D: Content, M: GetEmbeddedFrame, B: BorrowMut<[PerObject
                 PageTableEntry::Frame(cap) => {
                     let frame = self.orig_local_cptr::<cap_type::UnspecifiedFrame>(cap.object);
                     let rights = (&cap.rights).into();
-                    self.copy(frame)?
-                        .frame_map(vspace, vaddr, rights, cap.vm_attributes())?;
+                    self.copy(frame)
+                        .object(cap.object)?
+                        .frame_map(vspace, vaddr, rights, cap.vm_attributes())
+                        .invocation("frame_map")
+                        .object(cap.object)?;
                 }
                 PageTableEntry::PageTable(cap) => {
                     let page_table = self.orig_local_cptr::<cap_type::Unspecified>(cap.object);
-                    arch::map_page_table(
-                        vspace,
-                        level + 1,
-                        vaddr,
-                        page_table,
-                        cap.vm_attributes(),
-                    )?;
+                    arch::map_page_table(vspace, level + 1, vaddr, page_table, cap.vm_attributes())
+                        .invocation("page_table_map")
+                        .object(cap.object)?;
                     let obj = self
                         .spec()
                         .lookup_object::<&object::PageTable>(cap.object)?;
@@ -555,7 +727,10 @@ impl<'a, N: ObjectName, D: Content, M: GetEmbeddedFrame, B: BorrowMut<[PerObject
 
     #[sel4::sel4_cfg(KERNEL_MCS)]
     fn init_sched_context(&self, obj_id: ObjectId, affinity: usize) -> Result<()> {
-        let obj = self.spec().lookup_object::<&object::SchedContext>(obj_id)?;
+        let obj = self
+            .spec()
+            .lookup_object::<&object::SchedContext>(obj_id)
+            .object(obj_id)?;
         let sched_context = self.orig_local_cptr::<cap_type::SchedContext>(obj_id);
         self.bootinfo
             .sched_control(affinity)
@@ -566,7 +741,9 @@ impl<'a, N: ObjectName, D: Content, M: GetEmbeddedFrame, B: BorrowMut<[PerObject
                 0,
                 obj.extra.badge,
                 0,
-            )?;
+            )
+            .invocation("sched_control_configure_flags")
+            .object(obj_id)?;
         Ok(())
     }
 
@@ -574,134 +751,158 @@ impl<'a, N: ObjectName, D: Content, M: GetEmbeddedFrame, B: BorrowMut<[PerObject
         debug!("Initializing TCBs");
 
         for (obj_id, obj) in self.spec().filter_objects::<&object::TCB>() {
-            let tcb = self.orig_local_cptr::<cap_type::TCB>(obj_id);
+            self.init_tcb(obj_id, obj).object(obj_id)?;
+        }
+        Ok(())
+    }
 
-            if let Some(bound_notification) = obj.bound_notification() {
-                let bound_notification =
-                    self.orig_local_cptr::<cap_type::Notification>(bound_notification.object);
-                tcb.tcb_bind_notification(bound_notification)?;
-            }
+    fn init_tcb(&mut self, obj_id: ObjectId, obj: &object::TCB) -> Result<()> {
+        let tcb = self.orig_local_cptr::<cap_type::TCB>(obj_id);
 
-            sel4::sel4_cfg_if! {
-                if #[cfg(all(ARCH_AARCH64, ARM_HYPERVISOR_SUPPORT))] {
-                    if let Some(vcpu) = obj.vcpu() {
-                        let vcpu = self.orig_local_cptr::<cap_type::VCPU>(vcpu.object);
-                        vcpu.vcpu_set_tcb(tcb)?;
-                    }
+        if let Some(bound_notification) = obj.bound_notification() {
+            let bound_notification =
+                self.orig_local_cptr::<cap_type::Notification>(bound_notification.object);
+            tcb.tcb_bind_notification(bound_notification)
+                .invocation("tcb_bind_notification")?;
+        }
+
+        sel4::sel4_cfg_if! {
+            if #[cfg(all(ARCH_AARCH64, ARM_HYPERVISOR_SUPPORT))] {
+                if let Some(vcpu) = obj.vcpu() {
+                    let vcpu = self.orig_local_cptr::<cap_type::VCPU>(vcpu.object);
+                    vcpu.vcpu_set_tcb(tcb).invocation("vcpu_set_tcb")?;
                 }
             }
+        }
 
-            {
-                let cspace = self.orig_local_cptr(obj.cspace().object);
-                let cspace_root_data = CNodeCapData::new(
-                    obj.cspace().guard,
-                    obj.cspace().guard_size.try_into().unwrap(),
-                );
-                let vspace = self.orig_local_cptr(obj.vspace().object);
-                let ipc_buffer_addr = obj.extra.ipc_buffer_addr;
-                let ipc_buffer_frame = self.orig_local_cptr(obj.ipc_buffer().object);
+        {
+            let cspace = self.orig_local_cptr(obj.cspace().object);
+            let cspace_root_data = CNodeCapData::new(
+                obj.cspace().guard,
+                obj.cspace().guard_size.try_into().unwrap(),
+            );
+            let vspace = self.orig_local_cptr(obj.vspace().object);
+            let ipc_buffer_addr = obj.extra.ipc_buffer_addr;
+            let ipc_buffer_frame = self.orig_local_cptr(obj.ipc_buffer().object);
 
-                let authority = BootInfo::init_thread_tcb();
-                let max_prio = obj.extra.max_prio.into();
-                let prio = obj.extra.prio.into();
+            let authority = BootInfo::init_thread_tcb();
+            let max_prio = obj.extra.max_prio.into();
+            let prio = obj.extra.prio.into();
 
-                #[allow(unused_variables)]
-                let affinity: usize = obj.extra.affinity.try_into()?;
+            #[allow(unused_variables)]
+            let affinity: usize = obj.extra.affinity.try_into()?;
 
-                sel4::sel4_cfg_if! {
-                    if #[cfg(KERNEL_MCS)] {
-                        if let Some(sched_context_cap) = obj.sc() {
-                            self.init_sched_context(sched_context_cap.object, affinity)?;
-                        }
+            sel4::sel4_cfg_if! {
+                if #[cfg(KERNEL_MCS)] {
+                    if let Some(sched_context_cap) = obj.sc() {
+                        self.init_sched_context(sched_context_cap.object, affinity)?;
+                    }
 
-                        tcb.tcb_configure(
-                            cspace,
-                            cspace_root_data,
-                            vspace,
-                            ipc_buffer_addr,
-                            ipc_buffer_frame,
-                        )?;
-
-                        let sc = match obj.sc() {
-                            None => BootInfo::null().cast::<cap_type::SchedContext>(),
-                            Some(cap) => self.orig_local_cptr::<cap_type::SchedContext>(cap.object),
-                        };
-
-                        let fault_ep = match obj.temp_fault_ep() {
-                            None => BootInfo::null().cast::<cap_type::Endpoint>(),
-                            Some(cap) => {
-                                let orig = self.orig_local_cptr::<cap_type::Endpoint>(cap.object);
-                                let badge = cap.badge;
-                                let rights = (&cap.rights).into();
-                                if badge == 0 || rights == CapRights::all() {
-                                    orig
-                                } else {
-                                    let src = BootInfo::init_thread_cnode().relative(orig);
-                                    let new = BootInfo::init_cspace_local_cptr::<cap_type::Endpoint>(self.cslot_alloc_or_panic());
-                                    let dst = BootInfo::init_thread_cnode().relative(new);
-                                    dst.mint(&src, rights, badge)?;
-                                    new
-                                }
-                            },
-                        };
-
-                        let temp_fault_ep = match obj.temp_fault_ep() {
-                            None => BootInfo::null().cast::<cap_type::Endpoint>(),
-                            Some(cap) => {
-                                assert_eq!(cap.badge, 0); // HACK
-                                self.orig_local_cptr::<cap_type::Endpoint>(cap.object)
-                            },
-                        };
-
-                        tcb.tcb_set_sched_params(
-                            authority,
-                            max_prio,
-                            prio,
-                            sc,
-                            fault_ep,
-                        )?;
-
-                        tcb.tcb_set_timeout_endpoint(temp_fault_ep)?;
-                    } else {
-                        let fault_ep = CPtr::from_bits(obj.extra.master_fault_ep.unwrap());
-
-                        tcb.tcb_configure(
-                            fault_ep,
-                            cspace,
-                            cspace_root_data,
-                            vspace,
-                            ipc_buffer_addr,
-                            ipc_buffer_frame,
-                        )?;
-
-                        tcb.tcb_set_sched_params(
-                            authority,
-                            max_prio,
-                            prio,
-                        )?;
-
-                        sel4::sel4_cfg_if! {
-                            if #[cfg(not(MAX_NUM_NODES = "1"))] {
-                                tcb.tcb_set_affinity(affinity.try_into().unwrap())?;
-                            }
+                    tcb.tcb_configure(
+                        cspace,
+                        cspace_root_data,
+                        vspace,
+                        ipc_buffer_addr,
+                        ipc_buffer_frame,
+                    )
+                    .invocation("tcb_configure")?;
+
+                    let sc = match obj.sc() {
+                        None => BootInfo::null().cast::<cap_type::SchedContext>(),
+                        Some(cap) => self.orig_local_cptr::<cap_type::SchedContext>(cap.object),
+                    };
+
+                    // Specs which predate the distinction between the two use the timeout
+                    // endpoint as the fault endpoint too.
+                    let fault_ep = self.mint_endpoint(obj.fault_ep().or(obj.temp_fault_ep()))?;
+                    let temp_fault_ep = self.mint_endpoint(obj.temp_fault_ep())?;
+
+                    tcb.tcb_set_sched_params(
+                        authority,
+                        max_prio,
+                        prio,
+                        sc,
+                        fault_ep,
+                    )
+                    .invocation("tcb_set_sched_params")?;
+
+                    tcb.tcb_set_timeout_endpoint(temp_fault_ep)
+                        .invocation("tcb_set_timeout_endpoint")?;
+                } else {
+                    let fault_ep = CPtr::from_bits(obj.extra.master_fault_ep.unwrap());
+
+                    tcb.tcb_configure(
+                        fault_ep,
+                        cspace,
+                        cspace_root_data,
+                        vspace,
+                        ipc_buffer_addr,
+                        ipc_buffer_frame,
+                    )
+                    .invocation("tcb_configure")?;
+
+                    tcb.tcb_set_sched_params(
+                        authority,
+                        max_prio,
+                        prio,
+                    )
+                    .invocation("tcb_set_sched_params")?;
+
+                    sel4::sel4_cfg_if! {
+                        if #[cfg(not(MAX_NUM_NODES = "1"))] {
+                            tcb.tcb_set_affinity(affinity.try_into().unwrap())
+                                .invocation("tcb_set_affinity")?;
                         }
                     }
                 }
             }
+        }
 
-            {
-                let mut regs = UserContext::default();
-                arch::init_user_context(&mut regs, &obj.extra);
-                tcb.tcb_write_all_registers(false, &mut regs)?;
-            }
+        if let Some(domain) = obj.extra.domain {
+            BootInfo::domain()
+                .domain_set_set(domain, tcb)
+                .invocation("domain_set_set")?;
+        }
 
-            if let Some(name) = self.object_name(self.spec().name(obj_id)) {
-                tcb.debug_name(name.as_bytes());
-            }
+        {
+            let mut regs = UserContext::default();
+            arch::init_user_context(&mut regs, &obj.extra);
+            tcb.tcb_write_all_registers(false, &mut regs)
+                .invocation("tcb_write_all_registers")?;
+        }
+
+        if let Some(name) = self.object_name(self.spec().name(obj_id)) {
+            tcb.debug_name(name.as_bytes());
         }
         Ok(())
     }
 
+    #[sel4::sel4_cfg(KERNEL_MCS)]
+    fn mint_endpoint(
+        &mut self,
+        cap: Option<&cap::Endpoint>,
+    ) -> Result<LocalCPtr<cap_type::Endpoint>> {
+        Ok(match cap {
+            None => BootInfo::null().cast::<cap_type::Endpoint>(),
+            Some(cap) => {
+                let orig = self.orig_local_cptr::<cap_type::Endpoint>(cap.object);
+                let badge = cap.badge;
+                let rights = (&cap.rights).into();
+                if badge == 0 && rights == CapRights::all() {
+                    orig
+                } else {
+                    let src = BootInfo::init_thread_cnode().relative(orig);
+                    let new = BootInfo::init_cspace_local_cptr::<cap_type::Endpoint>(
+                        self.cslot_alloc_or_panic(),
+                    );
+                    let dst = BootInfo::init_thread_cnode().relative(new);
+                    dst.mint(&src, rights, badge).invocation("cnode_mint")?;
+                    new
+                }
+            }
+        })
+    }
+
     fn init_cspaces(&self) -> Result<()> {
         debug!("Initializing CSpaces");
 
@@ -714,20 +915,154 @@ impl<'a, N: ObjectName, D: Content, M: GetEmbeddedFrame, B: BorrowMut<[PerObject
                     .relative(self.orig_local_cptr::<cap_type::Unspecified>(cap.obj()));
                 let dst = cnode.relative_bits_with_depth((*i).try_into().unwrap(), obj.size_bits);
                 match badge {
-                    None => dst.copy(&src, rights),
-                    Some(badge) => dst.mint(&src, rights, badge),
-                }?;
+                    None => dst.copy(&src, rights).invocation("cnode_copy"),
+                    Some(badge) => dst.mint(&src, rights, badge).invocation("cnode_mint"),
+                }
+                .object(obj_id)?;
             }
         }
         Ok(())
     }
 
+    fn hand_off_resources(&self, handoff: &ResourceHandoff) -> Result<()> {
+        debug!("Handing off resources");
+
+        match handoff.info_frame {
+            Some(frame_id) => {
+                let obj = self
+                    .spec()
+                    .lookup_object::<&object::Frame<'a, D, M>>(frame_id)?;
+                self.with_frame_mapped(frame_id, obj, |frame| -> Result<()> {
+                    let num_untypeds =
+                        self.hand_off_untypeds(handoff, |i, desc| desc.write(frame, i))?;
+                    // Nothing is allocated from the initializer's CSpace after this point.
+                    let free_slots = match handoff.init_cnode_slot {
+                        Some(_) => self.cslot_allocator.free(),
+                        None => 0..0,
+                    };
+                    HandoffInfo {
+                        num_untypeds,
+                        first_untyped_slot: handoff.untyped_slots.start,
+                        first_free_slot: free_slots.start,
+                        num_free_slots: free_slots.len(),
+                    }
+                    .write(frame);
+                    Ok(())
+                })
+                .object(frame_id)??;
+            }
+            None => {
+                self.hand_off_untypeds(handoff, |_, _| {})?;
+            }
+        }
+
+        if let Some(slot) = handoff.irq_control_slot {
+            self.handoff_slot_relative_cptr(handoff, slot)?
+                .move_(&BootInfo::init_thread_cnode().relative(BootInfo::irq_control()))
+                .invocation("cnode_move")?;
+        }
+        if let Some(slot) = handoff.asid_control_slot {
+            self.handoff_slot_relative_cptr(handoff, slot)?
+                .move_(&BootInfo::init_thread_cnode().relative(BootInfo::asid_control()))
+                .invocation("cnode_move")?;
+        }
+        // The initializer keeps its own CNode cap, which it still needs to start threads, and
+        // allocates no more slots from it.
+        if let Some(slot) = handoff.init_cnode_slot {
+            self.handoff_slot_relative_cptr(handoff, slot)?
+                .copy(&init_thread_cnode_relative_cptr(), CapRights::all())
+                .invocation("cnode_copy")?;
+        }
+        Ok(())
+    }
+
+    // Untypeds from which nothing was allocated are moved whole. The free memory above the
+    // watermark of the others is carved into the largest possible child untypeds. Dummies which
+    // are still held are moved too, since nothing was allocated from them. Returns the number of
+    // untypeds handed off.
+    fn hand_off_untypeds(
+        &self,
+        handoff: &ResourceHandoff,
+        mut describe: impl FnMut(usize, HandoffUntypedDesc),
+    ) -> Result<usize> {
+        let cnode = BootInfo::init_thread_cnode()
+            .relative(self.orig_local_cptr::<cap_type::CNode>(handoff.cnode));
+        let mut slots = handoff.untyped_slots.clone();
+        let mut n = 0;
+        let mut hand_off = |paddr: usize, size_bits: usize, is_device: bool| {
+            let slot = slots
+                .next()
+                .ok_or(CapDLInitializerErrorCause::HandoffOutOfSlots)?;
+            trace!(
+                "Handing off untyped: paddr=0x{:x}, size_bits={}, slot={}",
+                paddr,
+                size_bits,
+                slot
+            );
+            describe(
+                n,
+                HandoffUntypedDesc {
+                    paddr: paddr.try_into()?,
+                    size_bits: size_bits.try_into()?,
+                    is_device,
+                },
+            );
+            n += 1;
+            Ok::<_, CapDLInitializerError>(slot)
+        };
+        for (i_ut, ut) in self.bootinfo.untyped_list().iter().enumerate() {
+            let ut_paddr_end = ut.paddr() + (1 << ut.size_bits());
+            let watermark = self.untyped_watermarks[i_ut];
+            if watermark == ut.paddr() {
+                let slot = hand_off(ut.paddr(), ut.size_bits(), ut.is_device())?;
+                self.handoff_slot_relative_cptr(handoff, slot)?
+                    .move_(&cslot_relative_cptr(self.bootinfo.untyped().start + i_ut))
+                    .invocation("cnode_move")?;
+                continue;
+            }
+            let mut cur_paddr = watermark;
+            while cur_paddr < ut_paddr_end {
+                let size_bits = usize::try_from(cur_paddr.trailing_zeros())
+                    .unwrap()
+                    .min((ut_paddr_end - cur_paddr).ilog2().try_into().unwrap());
+                if size_bits >= MIN_UNTYPED_SIZE_BITS {
+                    let slot = hand_off(cur_paddr, size_bits, ut.is_device())?;
+                    self.ut_local_cptr(i_ut)
+                        .untyped_retype(&ObjectBlueprint::Untyped { size_bits }, &cnode, slot, 1)
+                        .invocation("untyped_retype")?;
+                }
+                cur_paddr += 1 << size_bits;
+            }
+        }
+        for dummy in self.held_dummies.iter().flatten() {
+            let slot = hand_off(dummy.paddr, dummy.size_bits, dummy.is_device)?;
+            self.handoff_slot_relative_cptr(handoff, slot)?
+                .move_(&cslot_relative_cptr(dummy.slot))
+                .invocation("cnode_move")?;
+        }
+        Ok(n)
+    }
+
+    fn handoff_slot_relative_cptr(
+        &self,
+        handoff: &ResourceHandoff,
+        slot: CapSlot,
+    ) -> Result<AbsoluteCPtr> {
+        let size_bits = self
+            .spec()
+            .lookup_object::<&object::CNode>(handoff.cnode)?
+            .size_bits;
+        Ok(self
+            .orig_local_cptr::<cap_type::CNode>(handoff.cnode)
+            .relative_bits_with_depth(slot.try_into()?, size_bits))
+    }
+
     fn start_threads(&self) -> Result<()> {
         debug!("Starting threads");
         for (obj_id, obj) in self.spec().filter_objects::<&object::TCB>() {
             let tcb = self.orig_local_cptr::<cap_type::TCB>(obj_id);
             if obj.extra.resume {
-                tcb.tcb_resume()?;
+                tcb.tcb_resume().invocation("tcb_resume").object(obj_id)?;
             }
         }
         Ok(())
@@ -748,7 +1083,9 @@ impl<'a, N: ObjectName, D: Content, M: GetEmbeddedFrame, B: BorrowMut<[PerObject
     fn copy<U: CapType>(&mut self, cap: LocalCPtr<U>) -> Result<LocalCPtr<U>> {
         let slot = self.cslot_alloc_or_panic();
         let src = BootInfo::init_thread_cnode().relative(cap);
-        cslot_relative_cptr(slot).copy(&src, CapRights::all())?;
+        cslot_relative_cptr(slot)
+            .copy(&src, CapRights::all())
+            .invocation("cnode_copy")?;
         Ok(cslot_local_cptr(slot))
     }
 
@@ -785,6 +1122,14 @@ impl<'a, N: ObjectName, D: Content, M: GetEmbeddedFrame, B: BorrowMut<[PerObject
     }
 }
 
+/// Copies as much of `src[offset..]` as fits into `dst`.
+fn copy_at_offset(dst: &mut [u8], src: &[u8], offset: usize) {
+    let n = dst.len().min(src.len().saturating_sub(offset));
+    if n > 0 {
+        dst[..n].copy_from_slice(&src[offset..][..n]);
+    }
+}
+
 fn cslot_local_cptr<T: CapType>(slot: InitCSpaceSlot) -> LocalCPtr<T> {
     BootInfo::init_cspace_local_cptr(slot)
 }
@@ -138,6 +138,29 @@ impl<'a> Embedding<'a> {
         expr_struct.to_token_stream()
     }
 
+    fn embed_irq_object(
+        &self,
+        obj: &(impl fmt::Debug + HasCapTable),
+        extra: &impl fmt::Debug,
+    ) -> TokenStream {
+        let mut expr_struct = syn::parse2::<syn::ExprStruct>(to_tokens_via_debug(obj)).unwrap();
+        self.patch_field(
+            &mut expr_struct,
+            "slots",
+            syn::parse2::<syn::Expr>(self.embed_cap_table(obj.slots())).unwrap(),
+        );
+        self.patch_field(
+            &mut expr_struct,
+            "extra",
+            syn::parse2::<syn::Expr>({
+                let extra = to_tokens_via_debug(extra);
+                quote!(Indirect::from_borrowed(&#extra))
+            })
+            .unwrap(),
+        );
+        expr_struct.to_token_stream()
+    }
+
     fn embed_object(&self, obj: &Object<Ident, Ident>) -> TokenStream {
         match obj {
             Object::CNode(obj) => {
@@ -205,23 +228,7 @@ impl<'a> Embedding<'a> {
                 quote!(Object::PageTable(object::#toks))
             }
             Object::ArmIRQ(obj) => {
-                let mut expr_struct =
-                    syn::parse2::<syn::ExprStruct>(to_tokens_via_debug(obj)).unwrap();
-                self.patch_field(
-                    &mut expr_struct,
-                    "slots",
-                    syn::parse2::<syn::Expr>(self.embed_cap_table(obj.slots())).unwrap(),
-                );
-                self.patch_field(
-                    &mut expr_struct,
-                    "extra",
-                    syn::parse2::<syn::Expr>({
-                        let extra = to_tokens_via_debug(&obj.extra);
-                        quote!(Indirect::from_borrowed(&#extra))
-                    })
-                    .unwrap(),
-                );
-                let toks = expr_struct.to_token_stream();
+                let toks = self.embed_irq_object(obj, &obj.extra);
                 quote! {
                     {
                         use object::{ArmIRQ, ArmIRQExtraInfo};
@@ -229,6 +236,33 @@ impl<'a> Embedding<'a> {
                     }
                 }
             }
+            Object::IRQIOAPIC(obj) => {
+                let toks = self.embed_irq_object(obj, &obj.extra);
+                quote! {
+                    {
+                        use object::{IRQIOAPIC, IRQIOAPICExtraInfo};
+                        Object::IRQIOAPIC(#toks)
+                    }
+                }
+            }
+            Object::IRQMSI(obj) => {
+                let toks = self.embed_irq_object(obj, &obj.extra);
+                quote! {
+                    {
+                        use object::{IRQMSI, IRQMSIExtraInfo};
+                        Object::IRQMSI(#toks)
+                    }
+                }
+            }
+            Object::RiscvIRQ(obj) => {
+                let toks = self.embed_irq_object(obj, &obj.extra);
+                quote! {
+                    {
+                        use object::{RiscvIRQ, RiscvIRQExtraInfo};
+                        Object::RiscvIRQ(#toks)
+                    }
+                }
+            }
             obj => {
                 let obj = to_tokens_via_debug(obj);
                 quote! {
@@ -370,6 +404,13 @@ impl<'a> Embedding<'a> {
         let root_objects = to_tokens_via_debug(&spec.root_objects);
         let untyped_covers = to_tokens_via_debug(&spec.untyped_covers);
         let asid_slots = to_tokens_via_debug(&spec.asid_slots);
+        let resource_handoff = to_tokens_via_debug(&spec.resource_handoff);
+
+        let digest = self
+            .fill_map()
+            .spec_digest(self.spec(), |fill| fill)
+            .0
+            .into_iter();
 
         let toks = quote! {
             #[allow(unused_imports)]
@@ -387,8 +428,11 @@ impl<'a> Embedding<'a> {
                     root_objects: #root_objects,
                     untyped_covers: Indirect::from_borrowed(#untyped_covers.as_slice()),
                     asid_slots: Indirect::from_borrowed(#asid_slots.as_slice()),
+                    resource_handoff: #resource_handoff,
                 }
             };
+
+            pub const SPEC_DIGEST: #types_mod::SpecDigest = #types_mod::SpecDigest([#(#digest,)*]);
         };
 
         (toks, files_for_inclusion.into_iter().collect())
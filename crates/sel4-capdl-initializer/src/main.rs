@@ -17,8 +17,8 @@ use core::slice;
 use sel4::BootInfo;
 use sel4_capdl_initializer_core::{Initializer, InitializerBuffers, PerObjectBuffer};
 use sel4_capdl_initializer_types::{
-    IndirectDeflatedBytesContent, IndirectEmbeddedFrame, IndirectObjectName, SpecWithIndirection,
-    SpecWithSources,
+    IndirectEmbeddedFrame, IndirectMaybeDeflatedBytesContent, IndirectObjectName, SpecDigest,
+    SpecWithIndirection, SpecWithSources,
 };
 use sel4_dlmalloc::StaticHeapBounds;
 use sel4_logging::{LevelFilter, Logger, LoggerBuilder};
@@ -38,7 +38,7 @@ static LOGGER: Logger = LoggerBuilder::const_default()
 #[root_task(stack_size = 0x10000)]
 fn main(bootinfo: &BootInfo) -> ! {
     LOGGER.set().unwrap();
-    let spec_with_sources = get_spec_with_sources();
+    let (spec_with_sources, spec_digest) = get_spec_with_sources();
     let mut buffers = InitializerBuffers::new(vec![
         PerObjectBuffer::const_default();
         spec_with_sources.spec.objects.len()
@@ -47,6 +47,7 @@ fn main(bootinfo: &BootInfo) -> ! {
         bootinfo,
         user_image_bounds(),
         &spec_with_sources,
+        &spec_digest,
         &mut buffers,
     )
 }
@@ -75,25 +76,30 @@ static mut sel4_capdl_initializer_image_start: *mut u8 = ptr::null_mut();
 #[link_section = ".data"]
 static mut sel4_capdl_initializer_image_end: *mut u8 = ptr::null_mut();
 
-fn get_spec_with_sources<'a>() -> SpecWithSources<
-    'a,
-    Option<IndirectObjectName>,
-    IndirectDeflatedBytesContent,
-    IndirectEmbeddedFrame,
-> {
+fn get_spec_with_sources<'a>() -> (
+    SpecWithSources<
+        'a,
+        Option<IndirectObjectName>,
+        IndirectMaybeDeflatedBytesContent,
+        IndirectEmbeddedFrame,
+    >,
+    SpecDigest,
+) {
     let blob = unsafe {
         slice::from_raw_parts(
             sel4_capdl_initializer_serialized_spec_start,
             sel4_capdl_initializer_serialized_spec_size,
         )
     };
-    let (spec, source) = postcard::take_from_bytes::<SpecWithIndirection>(blob).unwrap();
-    SpecWithSources {
+    let ((spec, spec_digest), source) =
+        postcard::take_from_bytes::<(SpecWithIndirection, SpecDigest)>(blob).unwrap();
+    let spec_with_sources = SpecWithSources {
         spec,
         object_name_source: source,
         content_source: source,
         embedded_frame_source: source,
-    }
+    };
+    (spec_with_sources, spec_digest)
 }
 
 fn user_image_bounds() -> Range<usize> {
@@ -11,8 +11,10 @@ mk {
   dependencies = {
     inherit (versions) cfg-if log;
     miniz_oxide = { version = "0.6.2"; default-features = false; optional = true; };
+    object = { version = versions.object; default-features = false; features = [ "read" ]; optional = true; };
     serde = serdeWith [ "derive" "alloc" ] // { optional = true; };
     serde_json = { version = versions.serde_json; optional = true; };
+    sha2 = { version = "0.10.8"; default-features = false; optional = true; };
     inherit (localCrates)
       sel4-capdl-initializer-types-derive
     ;
@@ -21,8 +23,10 @@ mk {
   features = {
     std = [ "alloc" "serde_json" ];
     alloc = [ "miniz_oxide?/with-alloc" ];
+    builder = [ "alloc" "dep:object" ];
     serde = [ "alloc" "dep:serde" ];
     deflate = [ "dep:miniz_oxide" ];
+    digest = [ "dep:sha2" ];
     borrowed-indirect = [];
   };
 }
@@ -0,0 +1,246 @@
+//
+// Copyright 2023, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use object::{elf, BinaryFormat, Object, ObjectSegment, SegmentFlags};
+
+use crate::{FileContent, FillEntry, FillEntryContent, Rights, Word};
+
+use super::{SpecBuilder, VSpace};
+
+const PAGE_SIZE: usize = 1 << 12;
+
+#[derive(Debug)]
+pub enum LoadElfError {
+    Parse(object::read::Error),
+    NotElf,
+    /// A page of a loadable segment is already mapped in the VSpace.
+    AlreadyMapped {
+        vaddr: usize,
+    },
+}
+
+impl From<object::read::Error> for LoadElfError {
+    fn from(err: object::read::Error) -> Self {
+        Self::Parse(err)
+    }
+}
+
+impl fmt::Display for LoadElfError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Parse(err) => write!(f, "failed to parse ELF: {err}"),
+            Self::NotElf => write!(f, "not an ELF file"),
+            Self::AlreadyMapped { vaddr } => write!(f, "page {vaddr:#x} is already mapped"),
+        }
+    }
+}
+
+struct Page {
+    rights: Rights,
+    fill: Vec<FillEntry<FileContent>>,
+}
+
+impl SpecBuilder {
+    /// Maps the loadable segments of `elf` into `vspace` using 4K frames named after `prefix`,
+    /// and returns its entry point.
+    ///
+    /// Frames are filled from `file`, which is the name under which `elf` will be available to
+    /// `sel4-capdl-initializer-add-spec`. The rights of each frame are the union of those of
+    /// the segments which it contains.
+    pub fn load_elf(
+        &mut self,
+        vspace: &mut VSpace,
+        prefix: &str,
+        elf: &[u8],
+        file: impl Into<String>,
+    ) -> Result<Word, LoadElfError> {
+        let file = file.into();
+        let obj = object::File::parse(elf)?;
+        if obj.format() != BinaryFormat::Elf {
+            return Err(LoadElfError::NotElf);
+        }
+
+        let mut pages: BTreeMap<usize, Page> = BTreeMap::new();
+        for segment in obj.segments() {
+            let vaddr = to_usize(segment.address());
+            let mem_end = vaddr + to_usize(segment.size());
+            let (file_offset, file_size) = segment.file_range();
+            let (file_offset, file_end) = (to_usize(file_offset), vaddr + to_usize(file_size));
+            let p_flags = match segment.flags() {
+                SegmentFlags::Elf { p_flags } => p_flags,
+                _ => unreachable!(),
+            };
+            for page_vaddr in (vaddr & !(PAGE_SIZE - 1)..mem_end).step_by(PAGE_SIZE) {
+                let page = pages.entry(page_vaddr).or_insert_with(|| Page {
+                    rights: Rights::none(),
+                    fill: Vec::new(),
+                });
+                page.rights.read |= p_flags & elf::PF_R != 0;
+                page.rights.write |= p_flags & elf::PF_W != 0;
+                let start = page_vaddr.max(vaddr);
+                let end = (page_vaddr + PAGE_SIZE).min(file_end);
+                if start < end {
+                    page.fill.push(FillEntry {
+                        range: start - page_vaddr..end - page_vaddr,
+                        content: FillEntryContent::Data(FileContent {
+                            file: file.clone(),
+                            file_offset: file_offset + (start - vaddr),
+                        }),
+                    });
+                }
+            }
+        }
+
+        if let Some(vaddr) = pages
+            .keys()
+            .find(|vaddr| vspace.frame_at(**vaddr).is_some())
+        {
+            return Err(LoadElfError::AlreadyMapped { vaddr: *vaddr });
+        }
+
+        for (vaddr, page) in pages {
+            let frame = self.frame(
+                format!("{prefix}_frame_{vaddr:#x}"),
+                PAGE_SIZE.trailing_zeros().try_into().unwrap(),
+                None,
+                page.fill,
+            );
+            self.map(vspace, vaddr, frame, page.rights, true);
+        }
+
+        Ok(obj.entry())
+    }
+}
+
+fn to_usize(x: u64) -> usize {
+    x.try_into().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+
+    use crate::builder::VSpaceLayout;
+    use crate::{Cap, FrameInit, Object, ObjectId};
+
+    // (p_flags, p_offset, p_vaddr, p_filesz, p_memsz)
+    type Segment = (u32, u64, u64, u64, u64);
+
+    fn elf(entry: u64, segments: &[Segment], len: usize) -> Vec<u8> {
+        let mut elf = vec![0; len];
+        elf[..8].copy_from_slice(&[0x7f, b'E', b'L', b'F', 2, 1, 1, 0]);
+        let mut header = vec![];
+        header.extend(2u16.to_le_bytes()); // e_type
+        header.extend(0xb7u16.to_le_bytes()); // e_machine
+        header.extend(1u32.to_le_bytes()); // e_version
+        header.extend(entry.to_le_bytes());
+        header.extend(64u64.to_le_bytes()); // e_phoff
+        header.extend(0u64.to_le_bytes()); // e_shoff
+        header.extend(0u32.to_le_bytes()); // e_flags
+        header.extend(64u16.to_le_bytes()); // e_ehsize
+        header.extend(56u16.to_le_bytes()); // e_phentsize
+        header.extend(u16::try_from(segments.len()).unwrap().to_le_bytes());
+        header.extend([0; 6]); // e_shentsize, e_shnum, e_shstrndx
+        for (p_flags, p_offset, p_vaddr, p_filesz, p_memsz) in segments {
+            header.extend(elf::PT_LOAD.to_le_bytes());
+            header.extend(p_flags.to_le_bytes());
+            header.extend(p_offset.to_le_bytes());
+            header.extend(p_vaddr.to_le_bytes());
+            header.extend(p_vaddr.to_le_bytes());
+            header.extend(p_filesz.to_le_bytes());
+            header.extend(p_memsz.to_le_bytes());
+            header.extend(0x1000u64.to_le_bytes());
+        }
+        elf[16..16 + header.len()].copy_from_slice(&header);
+        elf
+    }
+
+    fn fill(builder: &SpecBuilder, frame: ObjectId) -> Vec<FillEntry<FileContent>> {
+        let Object::Frame(obj) = &builder.object(frame).object else {
+            panic!()
+        };
+        let FrameInit::Fill(fill) = &obj.init else {
+            panic!()
+        };
+        fill.entries.to_vec()
+    }
+
+    // The rights with which `frame` is mapped.
+    fn rights(builder: &SpecBuilder, frame: ObjectId) -> Rights {
+        (0..builder.num_objects())
+            .flat_map(|table| (0..512).filter_map(move |slot| builder.slot(table, slot)))
+            .find_map(|cap| match cap {
+                Cap::Frame(cap) if cap.object == frame => Some(cap.rights),
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    fn data(range: core::ops::Range<usize>, file_offset: usize) -> FillEntry<FileContent> {
+        FillEntry {
+            range,
+            content: FillEntryContent::Data(FileContent {
+                file: "a.elf".into(),
+                file_offset,
+            }),
+        }
+    }
+
+    #[test]
+    fn load() {
+        let text = (elf::PF_R | elf::PF_X, 0x1000, 0x40_0000, 0x10, 0x10);
+        let data_bss = (elf::PF_R | elf::PF_W, 0x1010, 0x40_0800, 0x8, 0x1000);
+        let rodata = (elf::PF_R, 0x1000, 0x40_2000, 0x4, 0x4);
+        let elf = elf(0x40_0004, &[text, data_bss, rodata], 0x1018);
+
+        let mut builder = SpecBuilder::new();
+        let mut vspace = builder.vspace("vs", VSpaceLayout::AARCH64);
+        let entry = builder.load_elf(&mut vspace, "a", &elf, "a.elf").unwrap();
+        assert_eq!(entry, 0x40_0004);
+
+        let [first, second, third] =
+            [0x40_0000, 0x40_1000, 0x40_2000].map(|vaddr| vspace.frame_at(vaddr).unwrap().id());
+        assert_eq!(vspace.frame_at(0x40_3000), None);
+        assert_eq!(builder.object(first).name, "a_frame_0x400000");
+
+        // Pages shared by segments combine their contents and rights, and pages of `.bss` are
+        // left empty.
+        assert_eq!(
+            fill(&builder, first),
+            [data(0..0x10, 0x1000), data(0x800..0x808, 0x1010)]
+        );
+        assert_eq!(rights(&builder, first), Rights::read_write());
+        assert_eq!(fill(&builder, second), []);
+        assert_eq!(rights(&builder, second), Rights::read_write());
+        assert_eq!(fill(&builder, third), [data(0..0x4, 0x1000)]);
+        assert_eq!(rights(&builder, third), Rights::read_only());
+    }
+
+    #[test]
+    fn load_errors() {
+        let segment = (elf::PF_R, 0, 0x1000, 0x10, 0x10);
+        let elf = elf(0, &[segment], 0x1000);
+        let mut builder = SpecBuilder::new();
+        let mut vspace = builder.vspace("vs", VSpaceLayout::AARCH64);
+        builder.load_elf(&mut vspace, "a", &elf, "a.elf").unwrap();
+        assert!(matches!(
+            builder.load_elf(&mut vspace, "b", &elf, "b.elf"),
+            Err(LoadElfError::AlreadyMapped { vaddr: 0x1000 })
+        ));
+        assert!(matches!(
+            builder.load_elf(&mut vspace, "c", b"garbage", "c.elf"),
+            Err(LoadElfError::Parse(_))
+        ));
+    }
+}
@@ -0,0 +1,252 @@
+//
+// Copyright 2023, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use alloc::collections::BTreeSet;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::Reverse;
+use core::fmt;
+
+use crate::{
+    object, Cap, CapTableEntry, IRQEntry, Indirect, Object, ObjectId, ResourceHandoff, Spec,
+    UntypedCover,
+};
+
+use super::{BuilderObject, BuilderSpec, ObjectSizes, SpecBuilder};
+
+/// An error from [`SpecBuilder::build`]. Object IDs are those of the builder's handles.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum BuildError {
+    MissingObjectSize {
+        object: ObjectId,
+        key: &'static str,
+    },
+    /// The object is covered by an untyped, but is not allocated from untyped memory.
+    NotAllocatable {
+        object: ObjectId,
+    },
+    CoverCycle {
+        object: ObjectId,
+    },
+}
+
+impl BuildError {
+    pub fn object(&self) -> ObjectId {
+        match self {
+            Self::MissingObjectSize { object, .. }
+            | Self::NotAllocatable { object }
+            | Self::CoverCycle { object } => *object,
+        }
+    }
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::MissingObjectSize { object, key } => {
+                write!(f, "no size for object {object}: object sizes have no {key:?}")
+            }
+            Self::NotAllocatable { object } => write!(
+                f,
+                "object {object} is covered by an untyped, but cannot be allocated from untyped memory"
+            ),
+            Self::CoverCycle { object } => {
+                write!(f, "object {object} is part of a cycle of untyped covers")
+            }
+        }
+    }
+}
+
+// The initializer expects root objects with paddrs first, in order of paddr, then the remaining
+// root objects in decreasing order of size, then objects which are not allocated from untyped
+// memory. Each cover's children must be contiguous, and the cover of an untyped must follow the
+// cover which contains it.
+pub(crate) fn build(
+    mut builder: SpecBuilder,
+    object_sizes: &ObjectSizes,
+) -> Result<BuilderSpec, BuildError> {
+    mark_vspace_roots(&mut builder);
+
+    let mut with_paddr = vec![];
+    let mut sized = vec![];
+    let mut unsized_ = vec![];
+    for (id, named_obj) in builder.objects.iter().enumerate() {
+        if builder.parents[id].is_some() {
+            continue;
+        }
+        let object = &named_obj.object;
+        match (
+            object.paddr(),
+            physical_size_bits(id, object, object_sizes)?,
+        ) {
+            (Some(paddr), _) => with_paddr.push((paddr, id)),
+            (None, Some(size_bits)) => sized.push((size_bits, id)),
+            (None, None) => unsized_.push(id),
+        }
+    }
+    with_paddr.sort_by_key(|(paddr, _)| *paddr);
+    sized.sort_by_key(|(size_bits, _)| Reverse(*size_bits));
+
+    let mut order = vec![];
+    order.extend(with_paddr.into_iter().map(|(_, id)| id));
+    order.extend(sized.into_iter().map(|(_, id)| id));
+    order.extend(unsized_);
+    let num_root_objects = order.len();
+
+    let mut covers = vec![];
+    let mut i = 0;
+    while i < order.len() {
+        let parent = order[i];
+        let children = &builder.children[parent];
+        if !children.is_empty() {
+            for child in children {
+                if physical_size_bits(*child, &builder.objects[*child].object, object_sizes)?
+                    .is_none()
+                {
+                    return Err(BuildError::NotAllocatable { object: *child });
+                }
+            }
+            covers.push(UntypedCover {
+                parent,
+                children: order.len()..order.len() + children.len(),
+            });
+            order.extend(children);
+        }
+        i += 1;
+    }
+
+    if order.len() != builder.objects.len() {
+        let placed = order.iter().copied().collect::<BTreeSet<_>>();
+        let object = (0..builder.objects.len())
+            .find(|id| !placed.contains(id))
+            .unwrap();
+        return Err(BuildError::CoverCycle { object });
+    }
+
+    let mut new_ids = vec![0; order.len()];
+    for (new_id, old_id) in order.iter().enumerate() {
+        new_ids[*old_id] = new_id;
+    }
+
+    let mut asid_pools = builder
+        .objects
+        .iter()
+        .enumerate()
+        .filter_map(|(id, named_obj)| match &named_obj.object {
+            Object::ASIDPool(obj) => Some((obj.high, new_ids[id])),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    asid_pools.sort();
+
+    let mut objects = builder.objects.into_iter().map(Some).collect::<Vec<_>>();
+    let objects = order
+        .iter()
+        .map(|old_id| {
+            let mut named_obj = objects[*old_id].take().unwrap();
+            let entries = builder.slots[*old_id]
+                .iter()
+                .map(|(slot, cap)| (*slot, remap_cap(cap.clone(), &new_ids)))
+                .collect::<Vec<_>>();
+            set_slots(&mut named_obj.object, entries);
+            named_obj
+        })
+        .collect::<Vec<_>>();
+
+    Ok(Spec {
+        objects: Indirect::from_owned(objects.into_boxed_slice()),
+        irqs: builder
+            .irqs
+            .iter()
+            .map(|entry| IRQEntry {
+                irq: entry.irq,
+                handler: new_ids[entry.handler],
+            })
+            .collect(),
+        asid_slots: asid_pools.into_iter().map(|(_high, id)| id).collect(),
+        root_objects: 0..num_root_objects,
+        untyped_covers: covers
+            .into_iter()
+            .map(|cover| UntypedCover {
+                parent: new_ids[cover.parent],
+                children: cover.children,
+            })
+            .collect(),
+        resource_handoff: builder.resource_handoff.map(|handoff| ResourceHandoff {
+            cnode: new_ids[handoff.cnode],
+            info_frame: handoff.info_frame.map(|frame| new_ids[frame]),
+            ..handoff
+        }),
+    })
+}
+
+fn mark_vspace_roots(builder: &mut SpecBuilder) {
+    let mut roots = BTreeSet::new();
+    for (id, named_obj) in builder.objects.iter().enumerate() {
+        if let Object::TCB(_) = named_obj.object {
+            if let Some(Cap::PageTable(cap)) = builder.slots[id].get(&object::TCB::SLOT_VSPACE) {
+                roots.insert(cap.object);
+            }
+        }
+    }
+    for id in roots {
+        if let Object::PageTable(obj) = &mut builder.objects[id].object {
+            obj.is_root = true;
+        }
+    }
+}
+
+fn physical_size_bits(
+    id: ObjectId,
+    object: &BuilderObject,
+    object_sizes: &ObjectSizes,
+) -> Result<Option<usize>, BuildError> {
+    object_sizes
+        .physical_size_bits(object)
+        .map_err(|key| BuildError::MissingObjectSize { object: id, key })
+}
+
+fn set_slots(object: &mut BuilderObject, entries: Vec<CapTableEntry>) {
+    let slots = match object {
+        Object::CNode(obj) => &mut obj.slots,
+        Object::TCB(obj) => &mut obj.slots,
+        Object::IRQ(obj) => &mut obj.slots,
+        Object::ArmIRQ(obj) => &mut obj.slots,
+        Object::IRQIOAPIC(obj) => &mut obj.slots,
+        Object::IRQMSI(obj) => &mut obj.slots,
+        Object::RiscvIRQ(obj) => &mut obj.slots,
+        Object::PageTable(obj) => &mut obj.slots,
+        _ => {
+            assert!(entries.is_empty());
+            return;
+        }
+    };
+    *slots = Indirect::from_owned(entries.into_boxed_slice());
+}
+
+fn remap_cap(mut cap: Cap, new_ids: &[ObjectId]) -> Cap {
+    let object = match &mut cap {
+        Cap::Untyped(cap) => &mut cap.object,
+        Cap::Endpoint(cap) => &mut cap.object,
+        Cap::Notification(cap) => &mut cap.object,
+        Cap::CNode(cap) => &mut cap.object,
+        Cap::TCB(cap) => &mut cap.object,
+        Cap::IRQHandler(cap) => &mut cap.object,
+        Cap::VCPU(cap) => &mut cap.object,
+        Cap::Frame(cap) => &mut cap.object,
+        Cap::PageTable(cap) => &mut cap.object,
+        Cap::ASIDPool(cap) => &mut cap.object,
+        Cap::ArmIRQHandler(cap) => &mut cap.object,
+        Cap::SchedContext(cap) => &mut cap.object,
+        Cap::Reply(cap) => &mut cap.object,
+        Cap::IRQIOAPICHandler(cap) => &mut cap.object,
+        Cap::IRQMSIHandler(cap) => &mut cap.object,
+        Cap::RiscvIRQHandler(cap) => &mut cap.object,
+        Cap::IOPorts(cap) => &mut cap.object,
+    };
+    *object = new_ids[*object];
+    cap
+}
@@ -0,0 +1,807 @@
+//
+// Copyright 2023, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+//! Programmatic construction of specs, for example from build scripts.
+//!
+//! Objects are added to a [`SpecBuilder`], which hands out typed [`ObjectHandle`]s for them.
+//! [`SpecBuilder::build`] then assigns final [`ObjectId`]s, ordering objects the way the
+//! initializer expects, and produces a spec which can be serialized to JSON for
+//! `sel4-capdl-initializer-add-spec`:
+//!
+//! ```ignore
+//! let mut builder = SpecBuilder::new();
+//! let mut vspace = builder.vspace("vspace", VSpaceLayout::AARCH64);
+//! let entry = builder.load_elf(&mut vspace, "component", &elf, "component.elf")?;
+//! let tcb = builder.thread(&mut vspace, "component", &ThreadConfig::new(entry, 0x1_0000_0000, 0x1_0000_1000));
+//! let cnode = builder.cnode("cnode", 10);
+//! builder.set_slot(tcb, object::TCB::SLOT_CSPACE, cnode.cap(0, 64 - 10));
+//! let ep = builder.endpoint("ep");
+//! let ep_slot = builder.cnode_alloc(cnode, ep.cap(0, Rights::all()));
+//! let spec = builder.build(&object_sizes)?;
+//! serde_json::to_writer(file, &spec)?;
+//! ```
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::marker::PhantomData;
+use core::ops::Range;
+
+use crate::{
+    cap, object, Badge, Cap, CapSlot, CapTableEntry, FileContent, Fill, FillEntry, FrameInit,
+    IRQEntry, Indirect, NamedObject, Object, ObjectId, ResourceHandoff, Rights, Spec, Word,
+};
+
+mod elf;
+mod layout;
+mod object_sizes;
+mod vspace;
+
+pub use elf::LoadElfError;
+pub use layout::BuildError;
+pub use object_sizes::ObjectSizes;
+pub use vspace::{ThreadConfig, VSpace, VSpaceLayout};
+
+pub type BuilderSpec = Spec<'static, String, FileContent, ()>;
+
+type BuilderObject = Object<'static, FileContent, ()>;
+
+/// Marker types for [`ObjectHandle`].
+pub mod kind {
+    pub enum Untyped {}
+    pub enum Endpoint {}
+    pub enum Notification {}
+    pub enum CNode {}
+    pub enum TCB {}
+    pub enum IRQ {}
+    pub enum VCPU {}
+    pub enum Frame {}
+    pub enum PageTable {}
+    pub enum ASIDPool {}
+    pub enum ArmIRQ {}
+    pub enum SchedContext {}
+    pub enum Reply {}
+    pub enum IRQIOAPIC {}
+    pub enum IRQMSI {}
+    pub enum RiscvIRQ {}
+    pub enum IOPorts {}
+}
+
+/// Implemented by the kinds of objects which hold caps.
+pub trait HasSlots {}
+
+impl HasSlots for kind::CNode {}
+impl HasSlots for kind::TCB {}
+impl HasSlots for kind::IRQ {}
+impl HasSlots for kind::ArmIRQ {}
+impl HasSlots for kind::IRQIOAPIC {}
+impl HasSlots for kind::IRQMSI {}
+impl HasSlots for kind::RiscvIRQ {}
+impl HasSlots for kind::PageTable {}
+
+/// A reference to an object in a [`SpecBuilder`].
+///
+/// The wrapped ID is only meaningful to the builder which created the handle. Objects are given
+/// their final IDs by [`SpecBuilder::build`].
+pub struct ObjectHandle<K> {
+    id: ObjectId,
+    phantom: PhantomData<K>,
+}
+
+impl<K> ObjectHandle<K> {
+    fn new(id: ObjectId) -> Self {
+        Self {
+            id,
+            phantom: PhantomData,
+        }
+    }
+
+    pub fn id(&self) -> ObjectId {
+        self.id
+    }
+}
+
+impl<K> Copy for ObjectHandle<K> {}
+
+impl<K> Clone for ObjectHandle<K> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<K> PartialEq for ObjectHandle<K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl<K> Eq for ObjectHandle<K> {}
+
+impl<K> fmt::Debug for ObjectHandle<K> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("ObjectHandle").field(&self.id).finish()
+    }
+}
+
+impl<K> From<ObjectHandle<K>> for ObjectId {
+    fn from(handle: ObjectHandle<K>) -> Self {
+        handle.id
+    }
+}
+
+macro_rules! simple_cap {
+    ($kind:ident, $cap:ident, $variant:ident) => {
+        impl ObjectHandle<kind::$kind> {
+            pub fn cap(self) -> Cap {
+                Cap::$variant(cap::$cap { object: self.id })
+            }
+        }
+    };
+}
+
+simple_cap!(Untyped, Untyped, Untyped);
+simple_cap!(TCB, TCB, TCB);
+simple_cap!(IRQ, IRQHandler, IRQHandler);
+simple_cap!(VCPU, VCPU, VCPU);
+simple_cap!(PageTable, PageTable, PageTable);
+simple_cap!(ASIDPool, ASIDPool, ASIDPool);
+simple_cap!(ArmIRQ, ArmIRQHandler, ArmIRQHandler);
+simple_cap!(SchedContext, SchedContext, SchedContext);
+simple_cap!(Reply, Reply, Reply);
+simple_cap!(IRQIOAPIC, IRQIOAPICHandler, IRQIOAPICHandler);
+simple_cap!(IRQMSI, IRQMSIHandler, IRQMSIHandler);
+simple_cap!(RiscvIRQ, RiscvIRQHandler, RiscvIRQHandler);
+simple_cap!(IOPorts, IOPorts, IOPorts);
+
+impl ObjectHandle<kind::Endpoint> {
+    pub fn cap(self, badge: Badge, rights: Rights) -> Cap {
+        Cap::Endpoint(cap::Endpoint {
+            object: self.id,
+            badge,
+            rights,
+        })
+    }
+}
+
+impl ObjectHandle<kind::Notification> {
+    pub fn cap(self, badge: Badge, rights: Rights) -> Cap {
+        Cap::Notification(cap::Notification {
+            object: self.id,
+            badge,
+            rights,
+        })
+    }
+}
+
+impl ObjectHandle<kind::CNode> {
+    pub fn cap(self, guard: Word, guard_size: Word) -> Cap {
+        Cap::CNode(cap::CNode {
+            object: self.id,
+            guard,
+            guard_size,
+        })
+    }
+}
+
+impl ObjectHandle<kind::Frame> {
+    pub fn cap(self, rights: Rights, cached: bool) -> Cap {
+        Cap::Frame(cap::Frame {
+            object: self.id,
+            rights,
+            cached,
+        })
+    }
+}
+
+/// Builds a [`Spec`] incrementally.
+///
+/// Caps passed to the builder refer to objects by the IDs of their handles.
+#[derive(Debug, Clone, Default)]
+pub struct SpecBuilder {
+    objects: Vec<NamedObject<'static, String, FileContent, ()>>,
+    slots: Vec<BTreeMap<CapSlot, Cap>>,
+    parents: Vec<Option<ObjectId>>,
+    children: Vec<Vec<ObjectId>>,
+    irqs: Vec<IRQEntry>,
+    resource_handoff: Option<ResourceHandoff>,
+}
+
+impl SpecBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn num_objects(&self) -> usize {
+        self.objects.len()
+    }
+
+    /// Adds an arbitrary object. Its cap table, if it has one, must be empty, and is instead
+    /// populated using [`set_slot`](Self::set_slot) and friends.
+    pub fn add_object(&mut self, name: impl Into<String>, object: BuilderObject) -> ObjectId {
+        let id = self.objects.len();
+        self.objects.push(NamedObject {
+            name: name.into(),
+            object,
+        });
+        self.slots.push(BTreeMap::new());
+        self.parents.push(None);
+        self.children.push(Vec::new());
+        id
+    }
+
+    fn add<K>(&mut self, name: impl Into<String>, object: BuilderObject) -> ObjectHandle<K> {
+        ObjectHandle::new(self.add_object(name, object))
+    }
+
+    pub fn untyped(
+        &mut self,
+        name: impl Into<String>,
+        size_bits: usize,
+        paddr: Option<usize>,
+    ) -> ObjectHandle<kind::Untyped> {
+        self.add(name, Object::Untyped(object::Untyped { size_bits, paddr }))
+    }
+
+    pub fn endpoint(&mut self, name: impl Into<String>) -> ObjectHandle<kind::Endpoint> {
+        self.add(name, Object::Endpoint)
+    }
+
+    pub fn notification(&mut self, name: impl Into<String>) -> ObjectHandle<kind::Notification> {
+        self.add(name, Object::Notification)
+    }
+
+    pub fn cnode(
+        &mut self,
+        name: impl Into<String>,
+        size_bits: usize,
+    ) -> ObjectHandle<kind::CNode> {
+        self.add(
+            name,
+            Object::CNode(object::CNode {
+                size_bits,
+                slots: no_slots(),
+            }),
+        )
+    }
+
+    pub fn tcb(
+        &mut self,
+        name: impl Into<String>,
+        extra: object::TCBExtraInfo<'static>,
+    ) -> ObjectHandle<kind::TCB> {
+        self.add(
+            name,
+            Object::TCB(object::TCB {
+                slots: no_slots(),
+                extra: Indirect::from_owned(alloc::boxed::Box::new(extra)),
+            }),
+        )
+    }
+
+    /// Adds an IRQ object and maps `irq` to it.
+    pub fn irq(&mut self, name: impl Into<String>, irq: Word) -> ObjectHandle<kind::IRQ> {
+        let handle = self.add(name, Object::IRQ(object::IRQ { slots: no_slots() }));
+        self.add_irq(irq, handle.id());
+        handle
+    }
+
+    /// Adds an ARM IRQ object and maps `irq` to it.
+    pub fn arm_irq(
+        &mut self,
+        name: impl Into<String>,
+        irq: Word,
+        extra: object::ArmIRQExtraInfo,
+    ) -> ObjectHandle<kind::ArmIRQ> {
+        let handle = self.add(
+            name,
+            Object::ArmIRQ(object::ArmIRQ {
+                slots: no_slots(),
+                extra: Indirect::from_owned(alloc::boxed::Box::new(extra)),
+            }),
+        );
+        self.add_irq(irq, handle.id());
+        handle
+    }
+
+    /// Adds an x86 IOAPIC IRQ object and maps `vector` to it.
+    pub fn irq_ioapic(
+        &mut self,
+        name: impl Into<String>,
+        vector: Word,
+        extra: object::IRQIOAPICExtraInfo,
+    ) -> ObjectHandle<kind::IRQIOAPIC> {
+        let handle = self.add(
+            name,
+            Object::IRQIOAPIC(object::IRQIOAPIC {
+                slots: no_slots(),
+                extra: Indirect::from_owned(alloc::boxed::Box::new(extra)),
+            }),
+        );
+        self.add_irq(vector, handle.id());
+        handle
+    }
+
+    /// Adds an x86 MSI IRQ object and maps `vector` to it.
+    pub fn irq_msi(
+        &mut self,
+        name: impl Into<String>,
+        vector: Word,
+        extra: object::IRQMSIExtraInfo,
+    ) -> ObjectHandle<kind::IRQMSI> {
+        let handle = self.add(
+            name,
+            Object::IRQMSI(object::IRQMSI {
+                slots: no_slots(),
+                extra: Indirect::from_owned(alloc::boxed::Box::new(extra)),
+            }),
+        );
+        self.add_irq(vector, handle.id());
+        handle
+    }
+
+    /// Adds a RISC-V IRQ object and maps `irq` to it.
+    pub fn riscv_irq(
+        &mut self,
+        name: impl Into<String>,
+        irq: Word,
+        extra: object::RiscvIRQExtraInfo,
+    ) -> ObjectHandle<kind::RiscvIRQ> {
+        let handle = self.add(
+            name,
+            Object::RiscvIRQ(object::RiscvIRQ {
+                slots: no_slots(),
+                extra: Indirect::from_owned(alloc::boxed::Box::new(extra)),
+            }),
+        );
+        self.add_irq(irq, handle.id());
+        handle
+    }
+
+    /// Maps `irq` to `handler`, which must be an IRQ object.
+    pub fn add_irq(&mut self, irq: Word, handler: ObjectId) {
+        assert!(self.objects[handler].object.is_irq());
+        assert!(self.irqs.iter().all(|entry| entry.irq != irq));
+        self.irqs.push(IRQEntry { irq, handler });
+    }
+
+    pub fn vcpu(&mut self, name: impl Into<String>) -> ObjectHandle<kind::VCPU> {
+        self.add(name, Object::VCPU)
+    }
+
+    pub fn frame(
+        &mut self,
+        name: impl Into<String>,
+        size_bits: usize,
+        paddr: Option<usize>,
+        fill: Vec<FillEntry<FileContent>>,
+    ) -> ObjectHandle<kind::Frame> {
+        for entry in &fill {
+            assert!(entry.range.end <= 1 << size_bits);
+        }
+        self.add(
+            name,
+            Object::Frame(object::Frame {
+                size_bits,
+                paddr,
+                init: FrameInit::Fill(Fill {
+                    entries: Indirect::from_owned(fill.into_boxed_slice()),
+                }),
+            }),
+        )
+    }
+
+    /// Adds a page table. Page tables installed in the VSpace slots of TCBs are marked as roots
+    /// by [`build`](Self::build) regardless of `is_root`. `level` must be `Some` only on x86_64.
+    pub fn page_table(
+        &mut self,
+        name: impl Into<String>,
+        is_root: bool,
+        level: Option<u8>,
+    ) -> ObjectHandle<kind::PageTable> {
+        self.add(
+            name,
+            Object::PageTable(object::PageTable {
+                is_root,
+                level,
+                slots: no_slots(),
+            }),
+        )
+    }
+
+    /// ASID pools are made in order of `high`.
+    pub fn asid_pool(
+        &mut self,
+        name: impl Into<String>,
+        high: Word,
+    ) -> ObjectHandle<kind::ASIDPool> {
+        self.add(name, Object::ASIDPool(object::ASIDPool { high }))
+    }
+
+    pub fn sched_context(
+        &mut self,
+        name: impl Into<String>,
+        size_bits: usize,
+        extra: object::SchedContextExtraInfo,
+    ) -> ObjectHandle<kind::SchedContext> {
+        self.add(
+            name,
+            Object::SchedContext(object::SchedContext { size_bits, extra }),
+        )
+    }
+
+    pub fn reply(&mut self, name: impl Into<String>) -> ObjectHandle<kind::Reply> {
+        self.add(name, Object::Reply)
+    }
+
+    /// Adds an x86 I/O port range, from `first_port` to `last_port` inclusive.
+    pub fn io_ports(
+        &mut self,
+        name: impl Into<String>,
+        first_port: Word,
+        last_port: Word,
+    ) -> ObjectHandle<kind::IOPorts> {
+        assert!(first_port <= last_port);
+        self.add(
+            name,
+            Object::IOPorts(object::IOPorts {
+                first_port,
+                last_port,
+            }),
+        )
+    }
+
+    pub fn object(&self, id: ObjectId) -> &NamedObject<'static, String, FileContent, ()> {
+        &self.objects[id]
+    }
+
+    pub fn object_mut(
+        &mut self,
+        id: ObjectId,
+    ) -> &mut NamedObject<'static, String, FileContent, ()> {
+        &mut self.objects[id]
+    }
+
+    /// Places `cap` in slot `slot` of `holder`, returning the cap previously in that slot.
+    pub fn set_slot<K: HasSlots>(
+        &mut self,
+        holder: ObjectHandle<K>,
+        slot: CapSlot,
+        cap: Cap,
+    ) -> Option<Cap> {
+        self.set_slot_by_id(holder.id(), slot, cap)
+    }
+
+    /// Like [`set_slot`](Self::set_slot), but panics if `holder` does not hold caps.
+    pub fn set_slot_by_id(&mut self, holder: ObjectId, slot: CapSlot, cap: Cap) -> Option<Cap> {
+        match &self.objects[holder].object {
+            Object::CNode(obj) => assert!(slot < 1 << obj.size_bits),
+            Object::TCB(_) | Object::PageTable(_) => {}
+            obj if obj.is_irq() => {}
+            _ => panic!("{:?} does not hold caps", self.objects[holder].name),
+        }
+        assert!(cap.obj() < self.objects.len());
+        self.slots[holder].insert(slot, cap)
+    }
+
+    pub fn slot(&self, holder: ObjectId, slot: CapSlot) -> Option<&Cap> {
+        self.slots[holder].get(&slot)
+    }
+
+    /// Returns the filled slots of `holder` within `slots`, in order.
+    pub fn slots_in(
+        &self,
+        holder: ObjectId,
+        slots: Range<CapSlot>,
+    ) -> impl Iterator<Item = (CapSlot, &Cap)> {
+        // `BTreeMap::range` panics on ranges which end before they start.
+        self.slots[holder]
+            .range(slots.start..slots.end.max(slots.start))
+            .map(|(slot, cap)| (*slot, cap))
+    }
+
+    /// Places `cap` in the lowest free slot of `cnode` other than slot 0, which is left empty so
+    /// that a CPtr of 0 can be used as a null CPtr.
+    pub fn cnode_alloc(&mut self, cnode: ObjectHandle<kind::CNode>, cap: Cap) -> CapSlot {
+        let size_bits = match &self.objects[cnode.id()].object {
+            Object::CNode(obj) => obj.size_bits,
+            _ => unreachable!(),
+        };
+        let slot = (1..1 << size_bits)
+            .find(|slot| !self.slots[cnode.id()].contains_key(slot))
+            .unwrap_or_else(|| panic!("{:?} is full", self.objects[cnode.id()].name));
+        self.set_slot(cnode, slot, cap);
+        slot
+    }
+
+    /// Adds `child` to the cover of `parent`. Covered objects are allocated from their parent,
+    /// in the order in which they are added to its cover.
+    pub fn cover(&mut self, parent: ObjectHandle<kind::Untyped>, child: impl Into<ObjectId>) {
+        self.cover_by_id(parent.id(), child.into())
+    }
+
+    /// Like [`cover`](Self::cover), but panics if `parent` is not an untyped.
+    pub fn cover_by_id(&mut self, parent: ObjectId, child: ObjectId) {
+        assert!(matches!(self.objects[parent].object, Object::Untyped(_)));
+        assert!(self.parents[child].is_none());
+        self.parents[child] = Some(parent);
+        self.children[parent].push(child);
+    }
+
+    /// Hands off leftover resources to a component. Object IDs in `handoff` are those of handles.
+    pub fn set_resource_handoff(&mut self, handoff: ResourceHandoff) {
+        assert!(matches!(
+            self.objects[handoff.cnode].object,
+            Object::CNode(_)
+        ));
+        if let Some(frame) = handoff.info_frame {
+            assert!(matches!(self.objects[frame].object, Object::Frame(_)));
+        }
+        self.resource_handoff = Some(handoff);
+    }
+
+    /// Assigns final object IDs and produces the spec.
+    pub fn build(self, object_sizes: &ObjectSizes) -> Result<BuilderSpec, BuildError> {
+        layout::build(self, object_sizes)
+    }
+}
+
+fn no_slots() -> Indirect<'static, [CapTableEntry]> {
+    Indirect::from_owned(Vec::new().into_boxed_slice())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::UntypedCover;
+
+    fn object_sizes() -> ObjectSizes {
+        [
+            ("seL4_EndpointObject", 4),
+            ("seL4_TCBObject", 11),
+            ("seL4_Slot", 5),
+            ("seL4_ASID_Pool", 12),
+            ("seL4_PageTableObject", 12),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    fn names(spec: &BuilderSpec) -> Vec<&str> {
+        spec.objects.iter().map(|obj| obj.name.as_str()).collect()
+    }
+
+    fn id_of(spec: &BuilderSpec, name: &str) -> ObjectId {
+        spec.objects
+            .iter()
+            .position(|obj| obj.name == name)
+            .unwrap()
+    }
+
+    #[test]
+    fn layout() {
+        let mut builder = SpecBuilder::new();
+        let irq = builder.irq("irq", 33);
+        let ep = builder.endpoint("ep");
+        let high = builder.untyped("high", 12, Some(0x2000));
+        let cnode = builder.cnode("cnode", 2);
+        let low = builder.untyped("low", 12, Some(0x1000));
+        let ut = builder.untyped("ut", 20, None);
+        let inner = builder.untyped("inner", 16, None);
+        let covered = builder.endpoint("covered");
+        let pool_b = builder.asid_pool("pool_b", 2);
+        let pool_a = builder.asid_pool("pool_a", 1);
+        builder.cover(ut, inner);
+        builder.cover(inner, covered);
+        builder.set_slot(cnode, 1, ep.cap(7, Rights::all()));
+        builder.set_slot(cnode, 2, covered.cap(0, Rights::none()));
+        builder.set_resource_handoff(ResourceHandoff {
+            cnode: cnode.id(),
+            untyped_slots: 3..4,
+            info_frame: None,
+            irq_control_slot: None,
+            asid_control_slot: None,
+            init_cnode_slot: None,
+        });
+        let _ = (irq, low, high, pool_a, pool_b);
+
+        let spec = builder.build(&object_sizes()).unwrap();
+        assert_eq!(
+            names(&spec),
+            ["low", "high", "ut", "pool_b", "pool_a", "cnode", "ep", "irq", "inner", "covered"]
+        );
+        assert_eq!(spec.root_objects, 0..8);
+        assert_eq!(
+            &spec.untyped_covers[..],
+            [
+                UntypedCover {
+                    parent: id_of(&spec, "ut"),
+                    children: 8..9,
+                },
+                UntypedCover {
+                    parent: id_of(&spec, "inner"),
+                    children: 9..10,
+                },
+            ]
+        );
+        assert_eq!(&spec.asid_slots[..], [4, 3]);
+        assert_eq!(spec.irqs[0].handler, id_of(&spec, "irq"));
+        assert_eq!(spec.resource_handoff.as_ref().unwrap().cnode, 5);
+
+        let Object::CNode(obj) = &spec.objects[5].object else {
+            panic!()
+        };
+        let [(1, Cap::Endpoint(first)), (2, Cap::Endpoint(second))] = &obj.slots[..] else {
+            panic!()
+        };
+        assert_eq!(first.object, id_of(&spec, "ep"));
+        assert_eq!(first.badge, 7);
+        assert_eq!(second.object, id_of(&spec, "covered"));
+    }
+
+    #[test]
+    fn vspace_roots() {
+        let mut builder = SpecBuilder::new();
+        let root = builder.page_table("root", false, None);
+        let other = builder.page_table("other", false, None);
+        let tcb = builder.tcb(
+            "tcb",
+            object::TCBExtraInfo {
+                ipc_buffer_addr: 0,
+                affinity: 0,
+                prio: 0,
+                max_prio: 0,
+                domain: None,
+                resume: true,
+                ip: 0,
+                sp: 0,
+                spsr: 0,
+                gprs: Indirect::from_owned(Vec::new().into_boxed_slice()),
+                master_fault_ep: None,
+            },
+        );
+        builder.set_slot(tcb, object::TCB::SLOT_VSPACE, root.cap());
+        let _ = other;
+        let spec = builder.build(&object_sizes()).unwrap();
+        let is_root = |name| match &spec.objects[id_of(&spec, name)].object {
+            Object::PageTable(obj) => obj.is_root,
+            _ => panic!(),
+        };
+        assert!(is_root("root"));
+        assert!(!is_root("other"));
+    }
+
+    #[test]
+    fn x86_and_riscv_kinds() {
+        let mut builder = SpecBuilder::new();
+        let ioapic = builder.irq_ioapic(
+            "ioapic",
+            48,
+            object::IRQIOAPICExtraInfo {
+                ioapic: 0,
+                pin: 1,
+                level: 1,
+                polarity: 0,
+            },
+        );
+        let msi = builder.irq_msi(
+            "msi",
+            49,
+            object::IRQMSIExtraInfo {
+                handle: 0,
+                pci_bus: 1,
+                pci_dev: 2,
+                pci_func: 3,
+            },
+        );
+        let riscv = builder.riscv_irq("riscv", 50, object::RiscvIRQExtraInfo { trigger: 1 });
+        let ports = builder.io_ports("ports", 0x60, 0x64);
+        let cnode = builder.cnode("cnode", 2);
+        builder.set_slot(cnode, 0, ioapic.cap());
+        builder.set_slot(cnode, 1, msi.cap());
+        builder.set_slot(cnode, 2, riscv.cap());
+        builder.set_slot(cnode, 3, ports.cap());
+
+        let spec = builder.build(&object_sizes()).unwrap();
+        assert_eq!(spec.check(), []);
+        let irqs = spec
+            .irqs
+            .iter()
+            .map(|entry| (entry.irq, spec.objects[entry.handler].name.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(irqs, [(48, "ioapic"), (49, "msi"), (50, "riscv")]);
+        assert_eq!(
+            spec.objects[id_of(&spec, "ports")].object,
+            Object::IOPorts(object::IOPorts {
+                first_port: 0x60,
+                last_port: 0x64,
+            })
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "first_port <= last_port")]
+    fn io_ports_reversed() {
+        SpecBuilder::new().io_ports("ports", 2, 1);
+    }
+
+    #[test]
+    fn build_errors() {
+        let mut builder = SpecBuilder::new();
+        let ut = builder.untyped("ut", 12, None);
+        let notification = builder.notification("notification");
+        builder.cover(ut, notification);
+        assert_eq!(
+            builder.build(&object_sizes()),
+            Err(BuildError::MissingObjectSize {
+                object: notification.id(),
+                key: "seL4_NotificationObject",
+            })
+        );
+
+        let mut builder = SpecBuilder::new();
+        let ut = builder.untyped("ut", 12, None);
+        let irq = builder.irq("irq", 1);
+        builder.cover(ut, irq);
+        assert_eq!(
+            builder.build(&object_sizes()),
+            Err(BuildError::NotAllocatable { object: irq.id() })
+        );
+
+        let mut builder = SpecBuilder::new();
+        let a = builder.untyped("a", 12, None);
+        let b = builder.untyped("b", 12, None);
+        builder.cover(a, b);
+        builder.cover(b, a);
+        assert_eq!(
+            builder.build(&object_sizes()),
+            Err(BuildError::CoverCycle { object: a.id() })
+        );
+    }
+
+    #[test]
+    fn cnode_alloc() {
+        let mut builder = SpecBuilder::new();
+        let cnode = builder.cnode("cnode", 2);
+        let ep = builder.endpoint("ep");
+        builder.set_slot(cnode, 2, ep.cap(0, Rights::all()));
+        assert_eq!(builder.cnode_alloc(cnode, ep.cap(1, Rights::all())), 1);
+        assert_eq!(builder.cnode_alloc(cnode, ep.cap(2, Rights::all())), 3);
+        assert!(builder.slot(cnode.id(), 0).is_none());
+        assert!(matches!(builder.slot(cnode.id(), 3), Some(Cap::Endpoint(cap)) if cap.badge == 2));
+    }
+
+    #[test]
+    #[should_panic(expected = "\"cnode\" is full")]
+    fn cnode_alloc_full() {
+        let mut builder = SpecBuilder::new();
+        let cnode = builder.cnode("cnode", 1);
+        let ep = builder.endpoint("ep");
+        builder.cnode_alloc(cnode, ep.cap(0, Rights::all()));
+        builder.cnode_alloc(cnode, ep.cap(0, Rights::all()));
+    }
+
+    #[test]
+    #[should_panic]
+    fn slot_out_of_bounds() {
+        let mut builder = SpecBuilder::new();
+        let cnode = builder.cnode("cnode", 2);
+        let ep = builder.endpoint("ep");
+        builder.set_slot(cnode, 4, ep.cap(0, Rights::all()));
+    }
+
+    #[test]
+    fn handles() {
+        let mut builder = SpecBuilder::new();
+        let a = builder.endpoint("a");
+        let b = builder.endpoint("b");
+        assert_eq!((a.id(), b.id()), (0, 1));
+        assert_eq!(ObjectId::from(b), 1);
+        assert_eq!(builder.num_objects(), 2);
+        assert_eq!(builder.object(b.id()).name, "b");
+    }
+}
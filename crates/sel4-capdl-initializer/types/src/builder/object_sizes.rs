@@ -0,0 +1,86 @@
+//
+// Copyright 2023, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+
+use crate::Object;
+
+/// The sizes of kernel objects, in bits, for the target kernel configuration, keyed by the names
+/// used in the `object_sizes.yaml` file generated by the seL4 build system (e.g.
+/// `seL4_TCBObject`).
+///
+/// These are needed to order objects the way the initializer expects.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct ObjectSizes {
+    sizes: BTreeMap<String, usize>,
+}
+
+impl ObjectSizes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, key: impl Into<String>, size_bits: usize) {
+        self.sizes.insert(key.into(), size_bits);
+    }
+
+    pub fn get(&self, key: &str) -> Option<usize> {
+        self.sizes.get(key).copied()
+    }
+
+    /// Returns the size of the memory from which `object` is allocated, or `None` for objects
+    /// which are not allocated from untyped memory. Fails with the missing key if the size
+    /// depends on an object size which is absent.
+    pub fn physical_size_bits<D, M>(
+        &self,
+        object: &Object<D, M>,
+    ) -> Result<Option<usize>, &'static str> {
+        let get = |key: &'static str| self.get(key).ok_or(key);
+        Ok(Some(match object {
+            Object::Untyped(obj) => obj.size_bits,
+            Object::Frame(obj) => obj.size_bits,
+            Object::SchedContext(obj) => obj.size_bits,
+            Object::CNode(obj) => obj.size_bits + get("seL4_Slot")?,
+            Object::IRQ(_)
+            | Object::ArmIRQ(_)
+            | Object::IRQIOAPIC(_)
+            | Object::IRQMSI(_)
+            | Object::RiscvIRQ(_)
+            | Object::IOPorts(_) => return Ok(None),
+            Object::Endpoint => get("seL4_EndpointObject")?,
+            Object::Notification => get("seL4_NotificationObject")?,
+            Object::TCB(_) => get("seL4_TCBObject")?,
+            Object::VCPU => get("seL4_VCPU")?,
+            Object::Reply => get("seL4_RTReplyObject")?,
+            Object::ASIDPool(_) => get("seL4_ASID_Pool")?,
+            Object::PageTable(obj) => match obj.level {
+                Some(0) => get("seL4_X64_PML4")?,
+                Some(1) => get("seL4_X64_PDPT")?,
+                Some(2) => get("seL4_PageDirectoryObject")?,
+                Some(_) => get("seL4_PageTableObject")?,
+                // On AArch64, root page tables are created as VSpace objects.
+                None if obj.is_root => ["seL4_VSpaceObject", "seL4_AARCH64_PGD"]
+                    .into_iter()
+                    .find_map(|key| self.get(key))
+                    .map(Ok)
+                    .unwrap_or_else(|| get("seL4_PageTableObject"))?,
+                None => get("seL4_PageTableObject")?,
+            },
+        }))
+    }
+}
+
+impl<K: Into<String>> FromIterator<(K, usize)> for ObjectSizes {
+    fn from_iter<T: IntoIterator<Item = (K, usize)>>(iter: T) -> Self {
+        Self {
+            sizes: iter
+                .into_iter()
+                .map(|(key, size_bits)| (key.into(), size_bits))
+                .collect(),
+        }
+    }
+}
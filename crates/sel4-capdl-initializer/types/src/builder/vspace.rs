@@ -0,0 +1,379 @@
+//
+// Copyright 2023, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::{object, CapSlot, Indirect, Object, ObjectId, Rights, Word};
+
+use super::{kind, ObjectHandle, SpecBuilder};
+
+const PAGE_BITS: usize = 12;
+const LEVEL_BITS: usize = 9;
+
+/// The shape of a virtual address space, which must match the initializer's `VSPACE_LEVELS`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct VSpaceLayout {
+    levels: usize,
+    explicit_levels: bool,
+}
+
+impl VSpaceLayout {
+    pub const AARCH64: Self = Self::new(4, false);
+    pub const RISCV64_SV39: Self = Self::new(3, false);
+    pub const X86_64: Self = Self::new(4, true);
+
+    /// `explicit_levels` is whether page tables carry their level, as they must on x86_64.
+    pub const fn new(levels: usize, explicit_levels: bool) -> Self {
+        Self {
+            levels,
+            explicit_levels,
+        }
+    }
+
+    pub const fn levels(&self) -> usize {
+        self.levels
+    }
+
+    // The size of the region mapped by an entry of a page table at `level`.
+    fn entry_bits(&self, level: usize) -> usize {
+        (self.levels - level - 1) * LEVEL_BITS + PAGE_BITS
+    }
+
+    fn level_tag(&self, level: usize) -> Option<u8> {
+        if self.explicit_levels {
+            Some(level.try_into().unwrap())
+        } else {
+            None
+        }
+    }
+}
+
+/// A virtual address space under construction, created with [`SpecBuilder::vspace`].
+#[derive(Debug, Clone)]
+pub struct VSpace {
+    name: String,
+    layout: VSpaceLayout,
+    root: ObjectHandle<kind::PageTable>,
+    // Keyed by level and the index of the region the page table maps.
+    page_tables: BTreeMap<(usize, usize), ObjectId>,
+    // Keyed by vaddr, with size in bits.
+    frames: BTreeMap<usize, (ObjectHandle<kind::Frame>, usize)>,
+}
+
+impl VSpace {
+    pub fn root(&self) -> ObjectHandle<kind::PageTable> {
+        self.root
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn layout(&self) -> VSpaceLayout {
+        self.layout
+    }
+
+    /// Returns the frame whose mapping contains `vaddr`, if any.
+    pub fn frame_at(&self, vaddr: usize) -> Option<ObjectHandle<kind::Frame>> {
+        self.frames
+            .range(..=vaddr)
+            .next_back()
+            .filter(|(start, (_, size_bits))| vaddr - *start < 1 << size_bits)
+            .map(|(_, (frame, _))| *frame)
+    }
+}
+
+/// The initial state of a thread created with [`SpecBuilder::thread`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ThreadConfig {
+    pub entry: Word,
+    /// The stack occupies `stack_size` bytes below this address.
+    pub stack_top: usize,
+    pub stack_size: usize,
+    pub ipc_buffer: usize,
+    pub prio: u8,
+    pub max_prio: u8,
+    pub affinity: Word,
+    pub domain: Option<u8>,
+    pub gprs: Vec<Word>,
+    pub resume: bool,
+}
+
+impl ThreadConfig {
+    pub fn new(entry: Word, stack_top: usize, ipc_buffer: usize) -> Self {
+        Self {
+            entry,
+            stack_top,
+            stack_size: 1 << PAGE_BITS,
+            ipc_buffer,
+            prio: 0,
+            max_prio: 0,
+            affinity: 0,
+            domain: None,
+            gprs: Vec::new(),
+            resume: true,
+        }
+    }
+}
+
+impl SpecBuilder {
+    /// Creates an empty address space, whose root page table is named `name`.
+    pub fn vspace(&mut self, name: impl Into<String>, layout: VSpaceLayout) -> VSpace {
+        let name = name.into();
+        let root = self.page_table(name.clone(), true, layout.level_tag(0));
+        VSpace {
+            name,
+            layout,
+            root,
+            page_tables: BTreeMap::new(),
+            frames: BTreeMap::new(),
+        }
+    }
+
+    /// Maps `frame` at `vaddr`, creating intermediate page tables as necessary.
+    ///
+    /// Panics if `vaddr` is not aligned to the size of the frame, if the frame's size is not a
+    /// size the layout can map, or if something is already mapped there.
+    pub fn map(
+        &mut self,
+        vspace: &mut VSpace,
+        vaddr: usize,
+        frame: ObjectHandle<kind::Frame>,
+        rights: Rights,
+        cached: bool,
+    ) {
+        let size_bits = match &self.object(frame.id()).object {
+            Object::Frame(obj) => obj.size_bits,
+            _ => unreachable!(),
+        };
+        let layout = vspace.layout;
+        let level = (0..layout.levels)
+            .find(|level| layout.entry_bits(*level) == size_bits)
+            .unwrap_or_else(|| panic!("frames of {size_bits} bits cannot be mapped"));
+        assert_eq!(vaddr % (1 << size_bits), 0);
+
+        let mut table = vspace.root.id();
+        for table_level in 0..level {
+            let child_level = table_level + 1;
+            let key = (child_level, vaddr >> layout.entry_bits(table_level));
+            table = match vspace.page_tables.get(&key) {
+                Some(child) => *child,
+                None => {
+                    let child = self.page_table(
+                        format!(
+                            "{}_pt_l{}_{:#x}",
+                            vspace.name,
+                            child_level,
+                            key.1 << layout.entry_bits(table_level)
+                        ),
+                        false,
+                        layout.level_tag(child_level),
+                    );
+                    let prev = self.set_slot_by_id(
+                        table,
+                        entry_index(&layout, table_level, vaddr),
+                        child.cap(),
+                    );
+                    assert!(prev.is_none(), "{vaddr:#x} is already mapped");
+                    vspace.page_tables.insert(key, child.id());
+                    child.id()
+                }
+            };
+        }
+        let prev = self.set_slot_by_id(
+            table,
+            entry_index(&layout, level, vaddr),
+            frame.cap(rights, cached),
+        );
+        assert!(prev.is_none(), "{vaddr:#x} is already mapped");
+        vspace.frames.insert(vaddr, (frame, size_bits));
+    }
+
+    /// Creates a TCB running in `vspace`, along with a stack and an IPC buffer mapped into it.
+    /// The stack and IPC buffer must not overlap anything already mapped.
+    ///
+    /// The TCB's CSpace is left for the caller to set.
+    pub fn thread(
+        &mut self,
+        vspace: &mut VSpace,
+        name: impl Into<String>,
+        config: &ThreadConfig,
+    ) -> ObjectHandle<kind::TCB> {
+        let name = name.into();
+        assert_eq!(config.stack_top % (1 << PAGE_BITS), 0);
+        assert_eq!(config.stack_size % (1 << PAGE_BITS), 0);
+        let stack_bottom = config.stack_top - config.stack_size;
+        for vaddr in (stack_bottom..config.stack_top).step_by(1 << PAGE_BITS) {
+            let frame = self.frame(
+                format!("{name}_stack_{vaddr:#x}"),
+                PAGE_BITS,
+                None,
+                Vec::new(),
+            );
+            self.map(vspace, vaddr, frame, Rights::read_write(), true);
+        }
+        let ipc_buffer = self.frame(format!("{name}_ipc_buffer"), PAGE_BITS, None, Vec::new());
+        self.map(
+            vspace,
+            config.ipc_buffer,
+            ipc_buffer,
+            Rights::read_write(),
+            true,
+        );
+        let tcb = self.tcb(
+            name,
+            object::TCBExtraInfo {
+                ipc_buffer_addr: config.ipc_buffer.try_into().unwrap(),
+                affinity: config.affinity,
+                prio: config.prio,
+                max_prio: config.max_prio,
+                domain: config.domain,
+                resume: config.resume,
+                ip: config.entry,
+                sp: config.stack_top.try_into().unwrap(),
+                spsr: 0,
+                gprs: Indirect::from_owned(config.gprs.clone().into_boxed_slice()),
+                master_fault_ep: None,
+            },
+        );
+        self.set_slot(tcb, object::TCB::SLOT_VSPACE, vspace.root.cap());
+        self.set_slot(
+            tcb,
+            object::TCB::SLOT_IPC_BUFFER,
+            ipc_buffer.cap(Rights::read_write(), true),
+        );
+        tcb
+    }
+}
+
+fn entry_index(layout: &VSpaceLayout, level: usize, vaddr: usize) -> CapSlot {
+    (vaddr >> layout.entry_bits(level)) % (1 << LEVEL_BITS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::Cap;
+
+    fn slots(builder: &SpecBuilder, table: ObjectId) -> Vec<(CapSlot, ObjectId)> {
+        (0..1 << LEVEL_BITS)
+            .filter_map(|slot| builder.slot(table, slot).map(|cap| (slot, cap.obj())))
+            .collect()
+    }
+
+    fn id_of(builder: &SpecBuilder, name: &str) -> ObjectId {
+        (0..builder.num_objects())
+            .find(|id| builder.object(*id).name == name)
+            .unwrap()
+    }
+
+    #[test]
+    fn map() {
+        let mut builder = SpecBuilder::new();
+        let mut vspace = builder.vspace("vs", VSpaceLayout::AARCH64);
+        let small = builder.frame("small", 12, None, Vec::new());
+        let large = builder.frame("large", 21, None, Vec::new());
+        builder.map(&mut vspace, 0x40_1000, small, Rights::read_write(), true);
+        builder.map(&mut vspace, 0x20_0000, large, Rights::none(), false);
+
+        let l1 = id_of(&builder, "vs_pt_l1_0x0");
+        let l2 = id_of(&builder, "vs_pt_l2_0x0");
+        let l3 = id_of(&builder, "vs_pt_l3_0x400000");
+        assert_eq!(builder.num_objects(), 6);
+        assert_eq!(slots(&builder, vspace.root().id()), [(0, l1)]);
+        assert_eq!(slots(&builder, l1), [(0, l2)]);
+        assert_eq!(slots(&builder, l2), [(1, large.id()), (2, l3)]);
+        assert_eq!(slots(&builder, l3), [(1, small.id())]);
+        assert!(matches!(
+            builder.slot(l3, 1),
+            Some(Cap::Frame(cap)) if cap.rights == Rights::read_write() && cap.cached
+        ));
+
+        assert_eq!(vspace.frame_at(0x40_1fff), Some(small));
+        assert_eq!(vspace.frame_at(0x40_2000), None);
+        assert_eq!(vspace.frame_at(0x3f_ffff), Some(large));
+        assert_eq!(vspace.frame_at(0x1f_ffff), None);
+    }
+
+    #[test]
+    fn x86_64_levels() {
+        let mut builder = SpecBuilder::new();
+        let mut vspace = builder.vspace("vs", VSpaceLayout::X86_64);
+        let frame = builder.frame("frame", 12, None, Vec::new());
+        builder.map(&mut vspace, 0x1000, frame, Rights::read_write(), true);
+        let levels = (0..builder.num_objects())
+            .filter_map(|id| match &builder.object(id).object {
+                Object::PageTable(obj) => Some(obj.level),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(levels, [Some(0), Some(1), Some(2), Some(3)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "0x1000 is already mapped")]
+    fn map_twice() {
+        let mut builder = SpecBuilder::new();
+        let mut vspace = builder.vspace("vs", VSpaceLayout::RISCV64_SV39);
+        let a = builder.frame("a", 12, None, Vec::new());
+        let b = builder.frame("b", 12, None, Vec::new());
+        builder.map(&mut vspace, 0x1000, a, Rights::read_write(), true);
+        builder.map(&mut vspace, 0x1000, b, Rights::read_write(), true);
+    }
+
+    #[test]
+    #[should_panic(expected = "frames of 16 bits cannot be mapped")]
+    fn unmappable_size() {
+        let mut builder = SpecBuilder::new();
+        let mut vspace = builder.vspace("vs", VSpaceLayout::AARCH64);
+        let frame = builder.frame("frame", 16, None, Vec::new());
+        builder.map(&mut vspace, 0x10000, frame, Rights::read_write(), true);
+    }
+
+    #[test]
+    fn thread() {
+        let mut builder = SpecBuilder::new();
+        let mut vspace = builder.vspace("vs", VSpaceLayout::AARCH64);
+        let mut config = ThreadConfig::new(0x40_0000, 0x80_0000, 0x90_0000);
+        config.stack_size = 0x2000;
+        config.prio = 100;
+        let tcb = builder.thread(&mut vspace, "t", &config);
+
+        let Object::TCB(obj) = &builder.object(tcb.id()).object else {
+            panic!()
+        };
+        assert_eq!(obj.extra.ip, 0x40_0000);
+        assert_eq!(obj.extra.sp, 0x80_0000);
+        assert_eq!(obj.extra.ipc_buffer_addr, 0x90_0000);
+        assert_eq!(obj.extra.prio, 100);
+
+        let stack = [0x7f_e000, 0x7f_f000].map(|vaddr| vspace.frame_at(vaddr).unwrap().id());
+        assert_eq!(builder.object(stack[0]).name, "t_stack_0x7fe000");
+        assert_eq!(builder.object(stack[1]).name, "t_stack_0x7ff000");
+        assert_eq!(vspace.frame_at(0x7f_d000), None);
+        assert_eq!(vspace.frame_at(0x80_0000), None);
+
+        let ipc_buffer = vspace.frame_at(0x90_0000).unwrap();
+        assert_eq!(builder.object(ipc_buffer.id()).name, "t_ipc_buffer");
+        assert_eq!(
+            builder
+                .slot(tcb.id(), object::TCB::SLOT_IPC_BUFFER)
+                .map(|cap| cap.obj()),
+            Some(ipc_buffer.id())
+        );
+        assert_eq!(
+            builder
+                .slot(tcb.id(), object::TCB::SLOT_VSPACE)
+                .map(|cap| cap.obj()),
+            Some(vspace.root().id())
+        );
+        assert!(builder.slot(tcb.id(), object::TCB::SLOT_CSPACE).is_none());
+    }
+}
@@ -54,6 +54,7 @@ impl<'a> object::TCB<'a> {
     pub const SLOT_CSPACE: CapSlot = 0;
     pub const SLOT_VSPACE: CapSlot = 1;
     pub const SLOT_IPC_BUFFER: CapSlot = 4;
+    pub const SLOT_FAULT_EP: CapSlot = 5;
     pub const SLOT_SC: CapSlot = 6;
     pub const SLOT_TEMP_FAULT_EP: CapSlot = 7;
     pub const SLOT_BOUND_NOTIFICATION: CapSlot = 8;
@@ -75,6 +76,12 @@ impl<'a> object::TCB<'a> {
         self.maybe_slot_as(Self::SLOT_SC)
     }
 
+    /// The fault endpoint (MCS only).
+    pub fn fault_ep(&self) -> Option<&cap::Endpoint> {
+        self.maybe_slot_as(Self::SLOT_FAULT_EP)
+    }
+
+    /// The timeout (temporal fault) endpoint (MCS only).
     pub fn temp_fault_ep(&self) -> Option<&cap::Endpoint> {
         self.maybe_slot_as(Self::SLOT_TEMP_FAULT_EP)
     }
@@ -104,6 +111,30 @@ impl<'a> object::ArmIRQ<'a> {
     }
 }
 
+impl<'a> object::IRQIOAPIC<'a> {
+    pub const SLOT_NOTIFICATION: CapSlot = 0;
+
+    pub fn notification(&self) -> Option<&cap::Notification> {
+        self.maybe_slot_as(Self::SLOT_NOTIFICATION)
+    }
+}
+
+impl<'a> object::IRQMSI<'a> {
+    pub const SLOT_NOTIFICATION: CapSlot = 0;
+
+    pub fn notification(&self) -> Option<&cap::Notification> {
+        self.maybe_slot_as(Self::SLOT_NOTIFICATION)
+    }
+}
+
+impl<'a> object::RiscvIRQ<'a> {
+    pub const SLOT_NOTIFICATION: CapSlot = 0;
+
+    pub fn notification(&self) -> Option<&cap::Notification> {
+        self.maybe_slot_as(Self::SLOT_NOTIFICATION)
+    }
+}
+
 // // //
 
 impl<'a> object::PageTable<'a> {
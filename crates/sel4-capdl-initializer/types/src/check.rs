@@ -0,0 +1,822 @@
+//
+// Copyright 2023, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use alloc::collections::BTreeSet;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::{
+    object, Cap, CapSlot, HandoffInfo, HasCapTable, Object, ObjectId, ResourceHandoff, Spec, Word,
+};
+
+// All supported architectures use 9-bit page table indices.
+const PAGE_TABLE_INDEX_BITS: usize = 9;
+
+const MAX_IO_PORT: Word = 0xffff;
+
+/// A problem with a spec which the initializer would otherwise only discover at boot time, if at
+/// all.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum SpecError {
+    DanglingCap {
+        holder: ObjectId,
+        slot: CapSlot,
+    },
+    CapTypeMismatch {
+        holder: ObjectId,
+        slot: CapSlot,
+    },
+    SlotOutOfBounds {
+        holder: ObjectId,
+        slot: CapSlot,
+    },
+    /// The holder is an object which has a fixed layout, and the cap does not belong in this slot.
+    UnexpectedCap {
+        holder: ObjectId,
+        slot: CapSlot,
+    },
+    MissingTCBSlot {
+        tcb: ObjectId,
+        slot: CapSlot,
+    },
+    /// A TCB's VSpace slot refers to a page table which is not marked as a root.
+    VSpaceNotRoot {
+        tcb: ObjectId,
+    },
+    /// The object's size in bits does not fit in a `usize`, or, given its paddr, the object
+    /// extends past the end of the address space.
+    InvalidSize {
+        object: ObjectId,
+    },
+    MisalignedPaddr {
+        object: ObjectId,
+    },
+    /// Root objects with paddrs must come first, in order of paddr.
+    MisorderedPaddr {
+        object: ObjectId,
+    },
+    OverlappingPaddr {
+        object: ObjectId,
+    },
+    InvalidCover {
+        parent: ObjectId,
+    },
+    /// The range of root objects extends past the end of the spec's objects.
+    InvalidRootObjects,
+    InvalidIRQHandler {
+        irq: Word,
+    },
+    DuplicateIRQ {
+        irq: Word,
+    },
+    InvalidASIDSlot {
+        object: ObjectId,
+    },
+    FillOutOfBounds {
+        frame: ObjectId,
+    },
+    /// An I/O port range is empty or extends past the last x86 I/O port.
+    InvalidIOPortRange {
+        object: ObjectId,
+    },
+    /// The resource handoff's CNode or info frame is not of the right type, its slots are out of
+    /// bounds, occupied, or overlapping, or its info frame is too small to describe an untyped for
+    /// each of its untyped slots.
+    InvalidResourceHandoff {
+        cnode: ObjectId,
+    },
+}
+
+impl SpecError {
+    /// Returns the object which the error concerns, if any.
+    pub fn object(&self) -> Option<ObjectId> {
+        match self {
+            Self::DanglingCap { holder, .. }
+            | Self::CapTypeMismatch { holder, .. }
+            | Self::SlotOutOfBounds { holder, .. }
+            | Self::UnexpectedCap { holder, .. } => Some(*holder),
+            Self::MissingTCBSlot { tcb, .. } | Self::VSpaceNotRoot { tcb } => Some(*tcb),
+            Self::InvalidSize { object }
+            | Self::MisalignedPaddr { object }
+            | Self::MisorderedPaddr { object }
+            | Self::OverlappingPaddr { object }
+            | Self::InvalidASIDSlot { object }
+            | Self::InvalidIOPortRange { object } => Some(*object),
+            Self::InvalidCover { parent } => Some(*parent),
+            Self::FillOutOfBounds { frame } => Some(*frame),
+            Self::InvalidResourceHandoff { cnode } => Some(*cnode),
+            Self::InvalidRootObjects
+            | Self::InvalidIRQHandler { .. }
+            | Self::DuplicateIRQ { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for SpecError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::DanglingCap { holder, slot } => write!(
+                f,
+                "cap in slot {slot} of object {holder} refers to a nonexistent object"
+            ),
+            Self::CapTypeMismatch { holder, slot } => write!(
+                f,
+                "cap in slot {slot} of object {holder} does not match the type of its object"
+            ),
+            Self::SlotOutOfBounds { holder, slot } => {
+                write!(f, "slot {slot} of object {holder} is out of bounds")
+            }
+            Self::UnexpectedCap { holder, slot } => write!(
+                f,
+                "cap in slot {slot} of object {holder} is of the wrong type for that slot"
+            ),
+            Self::MissingTCBSlot { tcb, slot } => {
+                write!(f, "TCB {tcb} is missing a cap in slot {slot}")
+            }
+            Self::VSpaceNotRoot { tcb } => write!(
+                f,
+                "VSpace of TCB {tcb} is a page table which is not marked as a root"
+            ),
+            Self::InvalidSize { object } => write!(f, "size of object {object} is invalid"),
+            Self::MisalignedPaddr { object } => {
+                write!(f, "paddr of object {object} is not aligned to its size")
+            }
+            Self::MisorderedPaddr { object } => write!(
+                f,
+                "root object {object} has a paddr, but does not follow root objects with lower paddrs"
+            ),
+            Self::OverlappingPaddr { object } => write!(
+                f,
+                "object {object} overlaps with the previous object with a paddr"
+            ),
+            Self::InvalidCover { parent } => write!(
+                f,
+                "cover of object {parent} is not a valid range of objects covered by an untyped"
+            ),
+            Self::InvalidRootObjects => {
+                write!(f, "root objects extend past the end of the spec's objects")
+            }
+            Self::InvalidIRQHandler { irq } => {
+                write!(f, "handler of IRQ {irq} is not an IRQ object")
+            }
+            Self::DuplicateIRQ { irq } => write!(f, "IRQ {irq} has more than one handler"),
+            Self::InvalidASIDSlot { object } => {
+                write!(f, "ASID slot refers to object {object}, which is not an ASID pool")
+            }
+            Self::FillOutOfBounds { frame } => {
+                write!(f, "fill of frame {frame} extends past its end")
+            }
+            Self::InvalidIOPortRange { object } => {
+                write!(f, "I/O port range of object {object} is invalid")
+            }
+            Self::InvalidResourceHandoff { cnode } => {
+                write!(f, "resource handoff to CNode {cnode} is invalid")
+            }
+        }
+    }
+}
+
+impl<'a, N, D, M> Spec<'a, N, D, M> {
+    /// Checks the spec for the problems described by [`SpecError`], returning all that are found.
+    ///
+    /// This only checks properties which can be checked without knowing the sizes of objects
+    /// for a particular kernel configuration.
+    pub fn check(&self) -> Vec<SpecError> {
+        let mut errs = vec![];
+        if self.root_objects.end > self.num_objects() {
+            errs.push(SpecError::InvalidRootObjects);
+        }
+        for (obj_id, obj) in self.objects().enumerate() {
+            self.check_object(obj_id, obj, &mut errs);
+        }
+        self.check_paddrs(&mut errs);
+        self.check_covers(&mut errs);
+        let mut irqs = BTreeSet::new();
+        for entry in self.irqs.iter() {
+            if !irqs.insert(entry.irq) {
+                errs.push(SpecError::DuplicateIRQ { irq: entry.irq });
+            }
+            if !matches!(
+                self.objects
+                    .get(entry.handler)
+                    .map(|named_obj| &named_obj.object),
+                Some(obj) if obj.is_irq()
+            ) {
+                errs.push(SpecError::InvalidIRQHandler { irq: entry.irq });
+            }
+        }
+        for obj_id in self.asid_slots.iter() {
+            if !matches!(
+                self.objects.get(*obj_id).map(|named_obj| &named_obj.object),
+                Some(Object::ASIDPool(_))
+            ) {
+                errs.push(SpecError::InvalidASIDSlot { object: *obj_id });
+            }
+        }
+        if let Some(handoff) = &self.resource_handoff {
+            if !self.is_valid_resource_handoff(handoff) {
+                errs.push(SpecError::InvalidResourceHandoff {
+                    cnode: handoff.cnode,
+                });
+            }
+        }
+        errs
+    }
+
+    fn check_object(&self, obj_id: ObjectId, obj: &Object<'a, D, M>, errs: &mut Vec<SpecError>) {
+        let (slots, expected): (_, fn(CapSlot, &Cap) -> Option<bool>) = match obj {
+            Object::CNode(cnode) => {
+                let Some(num_slots) = checked_pow2(cnode.size_bits) else {
+                    errs.push(SpecError::InvalidSize { object: obj_id });
+                    return;
+                };
+                for (slot, _) in cnode.slots() {
+                    if *slot >= num_slots {
+                        errs.push(SpecError::SlotOutOfBounds {
+                            holder: obj_id,
+                            slot: *slot,
+                        });
+                    }
+                }
+                (cnode.slots(), |_, _| Some(true))
+            }
+            Object::TCB(tcb) => {
+                for slot in [
+                    object::TCB::SLOT_CSPACE,
+                    object::TCB::SLOT_VSPACE,
+                    object::TCB::SLOT_IPC_BUFFER,
+                ] {
+                    if tcb.maybe_slot(slot).is_none() {
+                        errs.push(SpecError::MissingTCBSlot { tcb: obj_id, slot });
+                    }
+                }
+                if let Some(Cap::PageTable(cap)) = tcb.maybe_slot(object::TCB::SLOT_VSPACE) {
+                    if let Some(Object::PageTable(pt)) =
+                        self.objects.get(cap.object).map(|o| &o.object)
+                    {
+                        if !pt.is_root {
+                            errs.push(SpecError::VSpaceNotRoot { tcb: obj_id });
+                        }
+                    }
+                }
+                (tcb.slots(), expected_in_tcb)
+            }
+            Object::IRQ(irq) => (irq.slots(), expected_in_irq),
+            Object::ArmIRQ(irq) => (irq.slots(), expected_in_irq),
+            Object::IRQIOAPIC(irq) => (irq.slots(), expected_in_irq),
+            Object::IRQMSI(irq) => (irq.slots(), expected_in_irq),
+            Object::RiscvIRQ(irq) => (irq.slots(), expected_in_irq),
+            Object::PageTable(pt) => {
+                for (slot, _) in pt.slots() {
+                    if *slot >> PAGE_TABLE_INDEX_BITS != 0 {
+                        errs.push(SpecError::SlotOutOfBounds {
+                            holder: obj_id,
+                            slot: *slot,
+                        });
+                    }
+                }
+                (pt.slots(), |_, cap| {
+                    Some(matches!(cap, Cap::Frame(_) | Cap::PageTable(_)))
+                })
+            }
+            Object::Frame(frame) => {
+                let Some(frame_size) = checked_pow2(frame.size_bits) else {
+                    errs.push(SpecError::InvalidSize { object: obj_id });
+                    return;
+                };
+                if let Some(fill) = frame.init.as_fill() {
+                    if fill
+                        .entries
+                        .iter()
+                        .any(|entry| entry.range.end > frame_size)
+                    {
+                        errs.push(SpecError::FillOutOfBounds { frame: obj_id });
+                    }
+                }
+                return;
+            }
+            Object::Untyped(ut) => {
+                if checked_pow2(ut.size_bits).is_none() {
+                    errs.push(SpecError::InvalidSize { object: obj_id });
+                }
+                return;
+            }
+            Object::SchedContext(sc) => {
+                if checked_pow2(sc.size_bits).is_none() {
+                    errs.push(SpecError::InvalidSize { object: obj_id });
+                }
+                return;
+            }
+            Object::IOPorts(ports) => {
+                if ports.first_port > ports.last_port || ports.last_port > MAX_IO_PORT {
+                    errs.push(SpecError::InvalidIOPortRange { object: obj_id });
+                }
+                return;
+            }
+            _ => return,
+        };
+        for (slot, cap) in slots {
+            let holder = obj_id;
+            let slot = *slot;
+            match self.objects.get(cap.obj()) {
+                None => errs.push(SpecError::DanglingCap { holder, slot }),
+                Some(target) => {
+                    if !cap_matches_object(cap, &target.object) {
+                        errs.push(SpecError::CapTypeMismatch { holder, slot });
+                    }
+                }
+            }
+            if expected(slot, cap) == Some(false) {
+                errs.push(SpecError::UnexpectedCap { holder, slot });
+            }
+        }
+    }
+
+    fn check_paddrs(&self, errs: &mut Vec<SpecError>) {
+        let mut prev = None;
+        let mut seen_without_paddr = false;
+        for obj_id in self.root_objects.clone() {
+            // Reported by `check`.
+            let Some(named_obj) = self.objects.get(obj_id) else {
+                break;
+            };
+            let (paddr, size_bits) = match &named_obj.object {
+                Object::Untyped(ut) => (ut.paddr, ut.size_bits),
+                Object::Frame(frame) => (frame.paddr, frame.size_bits),
+                _ => (None, 0),
+            };
+            let paddr = match paddr {
+                Some(paddr) => paddr,
+                None => {
+                    seen_without_paddr = true;
+                    continue;
+                }
+            };
+            // Invalid sizes are reported by `check_object`.
+            let Some(size) = checked_pow2(size_bits) else {
+                continue;
+            };
+            if paddr & (size - 1) != 0 {
+                errs.push(SpecError::MisalignedPaddr { object: obj_id });
+            }
+            let Some(end) = paddr.checked_add(size) else {
+                errs.push(SpecError::InvalidSize { object: obj_id });
+                continue;
+            };
+            match prev {
+                _ if seen_without_paddr => {
+                    errs.push(SpecError::MisorderedPaddr { object: obj_id });
+                }
+                Some((prev_paddr, _)) if paddr < prev_paddr => {
+                    errs.push(SpecError::MisorderedPaddr { object: obj_id });
+                }
+                Some((_, prev_end)) if paddr < prev_end => {
+                    errs.push(SpecError::OverlappingPaddr { object: obj_id });
+                }
+                _ => {}
+            }
+            prev = Some((paddr, end));
+        }
+    }
+
+    fn check_covers(&self, errs: &mut Vec<SpecError>) {
+        for cover in self.untyped_covers.iter() {
+            let valid = matches!(
+                self.objects
+                    .get(cover.parent)
+                    .map(|named_obj| &named_obj.object),
+                Some(Object::Untyped(_))
+            ) && cover.children.end <= self.num_objects()
+                && cover.children.start >= self.root_objects.end
+                && !cover.children.contains(&cover.parent);
+            if !valid {
+                errs.push(SpecError::InvalidCover {
+                    parent: cover.parent,
+                });
+            }
+        }
+    }
+
+    fn is_valid_resource_handoff(&self, handoff: &ResourceHandoff) -> bool {
+        let cnode = match self
+            .objects
+            .get(handoff.cnode)
+            .map(|named_obj| &named_obj.object)
+        {
+            Some(Object::CNode(cnode)) => cnode,
+            _ => return false,
+        };
+        if let Some(frame) = handoff.info_frame {
+            match self.objects.get(frame).map(|named_obj| &named_obj.object) {
+                Some(Object::Frame(frame))
+                    if frame.init.is_fill()
+                        && handoff.untyped_slots.len()
+                            <= HandoffInfo::max_untypeds(frame.size_bits) => {}
+                _ => return false,
+            }
+        }
+        // Check the bounds of the slots before anything else, so that a large range of untyped
+        // slots is never iterated over.
+        let num_slots = match checked_pow2(cnode.size_bits) {
+            Some(num_slots) => num_slots,
+            None => return false,
+        };
+        let single_slots = [
+            handoff.irq_control_slot,
+            handoff.asid_control_slot,
+            handoff.init_cnode_slot,
+        ];
+        if handoff.untyped_slots.end > num_slots
+            || single_slots.iter().flatten().any(|slot| *slot >= num_slots)
+        {
+            return false;
+        }
+        let mut slots = BTreeSet::new();
+        for slot in single_slots.iter().flatten() {
+            if handoff.untyped_slots.contains(slot) || !slots.insert(*slot) {
+                return false;
+            }
+        }
+        for (slot, _) in cnode.slots() {
+            if handoff.untyped_slots.contains(slot) || slots.contains(slot) {
+                return false;
+            }
+        }
+        !handoff.untyped_slots.is_empty()
+    }
+}
+
+// `1 << bits`, or `None` if that does not fit in a `usize`.
+fn checked_pow2(bits: usize) -> Option<usize> {
+    u32::try_from(bits)
+        .ok()
+        .and_then(|bits| 1usize.checked_shl(bits))
+}
+
+fn expected_in_tcb(slot: CapSlot, cap: &Cap) -> Option<bool> {
+    Some(match slot {
+        object::TCB::SLOT_CSPACE => matches!(cap, Cap::CNode(_)),
+        object::TCB::SLOT_VSPACE => matches!(cap, Cap::PageTable(_)),
+        object::TCB::SLOT_IPC_BUFFER => matches!(cap, Cap::Frame(_)),
+        object::TCB::SLOT_FAULT_EP => matches!(cap, Cap::Endpoint(_)),
+        object::TCB::SLOT_SC => matches!(cap, Cap::SchedContext(_)),
+        object::TCB::SLOT_TEMP_FAULT_EP => matches!(cap, Cap::Endpoint(_)),
+        object::TCB::SLOT_BOUND_NOTIFICATION => matches!(cap, Cap::Notification(_)),
+        object::TCB::SLOT_VCPU => matches!(cap, Cap::VCPU(_)),
+        // Not used by the initializer.
+        _ => return None,
+    })
+}
+
+fn expected_in_irq(slot: CapSlot, cap: &Cap) -> Option<bool> {
+    Some(slot == object::IRQ::SLOT_NOTIFICATION && matches!(cap, Cap::Notification(_)))
+}
+
+fn cap_matches_object<D, M>(cap: &Cap, obj: &Object<D, M>) -> bool {
+    matches!(
+        (cap, obj),
+        (Cap::Untyped(_), Object::Untyped(_))
+            | (Cap::Endpoint(_), Object::Endpoint)
+            | (Cap::Notification(_), Object::Notification)
+            | (Cap::CNode(_), Object::CNode(_))
+            | (Cap::TCB(_), Object::TCB(_))
+            | (Cap::IRQHandler(_), Object::IRQ(_))
+            | (Cap::VCPU(_), Object::VCPU)
+            | (Cap::Frame(_), Object::Frame(_))
+            | (Cap::PageTable(_), Object::PageTable(_))
+            | (Cap::ASIDPool(_), Object::ASIDPool(_))
+            | (Cap::ArmIRQHandler(_), Object::ArmIRQ(_))
+            | (Cap::SchedContext(_), Object::SchedContext(_))
+            | (Cap::Reply(_), Object::Reply)
+            | (Cap::IRQIOAPICHandler(_), Object::IRQIOAPIC(_))
+            | (Cap::IRQMSIHandler(_), Object::IRQMSI(_))
+            | (Cap::RiscvIRQHandler(_), Object::RiscvIRQ(_))
+            | (Cap::IOPorts(_), Object::IOPorts(_))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+
+    use super::*;
+
+    use crate::{cap, Fill, FillEntry, FillEntryContent, FrameInit, IRQEntry, Indirect};
+    use crate::{CapTableEntry, NamedObject, Rights, UntypedCover};
+
+    type TestObject = Object<'static, (), ()>;
+    type TestSpec = Spec<'static, (), (), ()>;
+
+    fn spec(objects: Vec<TestObject>) -> TestSpec {
+        let num_objects = objects.len();
+        Spec {
+            objects: objects
+                .into_iter()
+                .map(|object| NamedObject { name: (), object })
+                .collect(),
+            irqs: [].into_iter().collect(),
+            asid_slots: [].into_iter().collect(),
+            root_objects: 0..num_objects,
+            untyped_covers: [].into_iter().collect(),
+            resource_handoff: None,
+        }
+    }
+
+    fn slots(entries: Vec<CapTableEntry>) -> Indirect<'static, [CapTableEntry]> {
+        Indirect::from_owned(entries.into_boxed_slice())
+    }
+
+    fn cnode(size_bits: usize, entries: Vec<CapTableEntry>) -> TestObject {
+        Object::CNode(object::CNode {
+            size_bits,
+            slots: slots(entries),
+        })
+    }
+
+    fn frame(size_bits: usize, paddr: Option<usize>, fill_end: Option<usize>) -> TestObject {
+        let entries = fill_end
+            .map(|end| FillEntry {
+                range: 0..end,
+                content: FillEntryContent::Data(()),
+            })
+            .into_iter()
+            .collect();
+        Object::Frame(object::Frame {
+            size_bits,
+            paddr,
+            init: FrameInit::Fill(Fill { entries }),
+        })
+    }
+
+    fn untyped(size_bits: usize, paddr: Option<usize>) -> TestObject {
+        Object::Untyped(object::Untyped { size_bits, paddr })
+    }
+
+    fn tcb(entries: Vec<CapTableEntry>) -> TestObject {
+        Object::TCB(object::TCB {
+            slots: slots(entries),
+            extra: Indirect::from_owned(Box::new(object::TCBExtraInfo {
+                ipc_buffer_addr: 0,
+                affinity: 0,
+                prio: 0,
+                max_prio: 0,
+                domain: None,
+                resume: true,
+                ip: 0,
+                sp: 0,
+                spsr: 0,
+                gprs: Indirect::from_owned(Vec::new().into_boxed_slice()),
+                master_fault_ep: None,
+            })),
+        })
+    }
+
+    fn ep_cap(object: ObjectId) -> Cap {
+        Cap::Endpoint(cap::Endpoint {
+            object,
+            badge: 0,
+            rights: Rights::all(),
+        })
+    }
+
+    #[test]
+    fn valid() {
+        let spec = spec(vec![
+            untyped(12, Some(0x1000)),
+            frame(12, Some(0x2000), Some(0x1000)),
+            cnode(2, vec![(3, ep_cap(3))]),
+            Object::Endpoint,
+        ]);
+        assert_eq!(spec.check(), []);
+    }
+
+    #[test]
+    fn caps() {
+        let spec = spec(vec![
+            cnode(2, vec![(0, ep_cap(1)), (1, ep_cap(2)), (4, ep_cap(1))]),
+            Object::Endpoint,
+            tcb(vec![(object::TCB::SLOT_CSPACE, ep_cap(1))]),
+            cnode(usize::BITS as usize, vec![]),
+        ]);
+        assert_eq!(
+            spec.check(),
+            [
+                SpecError::SlotOutOfBounds { holder: 0, slot: 4 },
+                SpecError::CapTypeMismatch { holder: 0, slot: 1 },
+                SpecError::MissingTCBSlot {
+                    tcb: 2,
+                    slot: object::TCB::SLOT_VSPACE
+                },
+                SpecError::MissingTCBSlot {
+                    tcb: 2,
+                    slot: object::TCB::SLOT_IPC_BUFFER
+                },
+                SpecError::UnexpectedCap {
+                    holder: 2,
+                    slot: object::TCB::SLOT_CSPACE
+                },
+                SpecError::InvalidSize { object: 3 },
+            ]
+        );
+    }
+
+    #[test]
+    fn dangling_cap() {
+        let spec = spec(vec![cnode(2, vec![(0, ep_cap(5))])]);
+        assert_eq!(
+            spec.check(),
+            [SpecError::DanglingCap { holder: 0, slot: 0 }]
+        );
+    }
+
+    #[test]
+    fn sizes() {
+        let spec = spec(vec![
+            frame(12, None, Some(0x1001)),
+            frame(usize::BITS as usize, None, None),
+            untyped(usize::BITS as usize + 1, None),
+        ]);
+        assert_eq!(
+            spec.check(),
+            [
+                SpecError::FillOutOfBounds { frame: 0 },
+                SpecError::InvalidSize { object: 1 },
+                SpecError::InvalidSize { object: 2 },
+            ]
+        );
+    }
+
+    #[test]
+    fn paddrs() {
+        let spec = spec(vec![
+            untyped(12, Some(0x2000)),
+            untyped(12, Some(0x1000)),
+            frame(12, Some(0x1800), None),
+            untyped(12, Some(usize::MAX & !0xfff)),
+            untyped(12, None),
+            untyped(12, Some(0x10_0000)),
+        ]);
+        assert_eq!(
+            spec.check(),
+            [
+                SpecError::MisorderedPaddr { object: 1 },
+                SpecError::MisalignedPaddr { object: 2 },
+                SpecError::OverlappingPaddr { object: 2 },
+                SpecError::InvalidSize { object: 3 },
+                SpecError::MisorderedPaddr { object: 5 },
+            ]
+        );
+    }
+
+    #[test]
+    fn tables() {
+        let mut spec = spec(vec![untyped(12, None), Object::Endpoint, Object::Endpoint]);
+        spec.root_objects = 0..4;
+        spec.irqs = [
+            IRQEntry { irq: 1, handler: 1 },
+            IRQEntry { irq: 1, handler: 7 },
+        ]
+        .into_iter()
+        .collect();
+        spec.asid_slots = [2].into_iter().collect();
+        spec.untyped_covers = [
+            UntypedCover {
+                parent: 0,
+                children: 3..4,
+            },
+            UntypedCover {
+                parent: 1,
+                children: 2..3,
+            },
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            spec.check(),
+            [
+                SpecError::InvalidRootObjects,
+                SpecError::InvalidCover { parent: 0 },
+                SpecError::InvalidCover { parent: 1 },
+                SpecError::InvalidIRQHandler { irq: 1 },
+                SpecError::DuplicateIRQ { irq: 1 },
+                SpecError::InvalidIRQHandler { irq: 1 },
+                SpecError::InvalidASIDSlot { object: 2 },
+            ]
+        );
+    }
+
+    #[test]
+    fn x86_and_riscv_kinds() {
+        let no_slots = || slots(vec![]);
+        let io_ports = |first_port, last_port| {
+            Object::IOPorts(object::IOPorts {
+                first_port,
+                last_port,
+            })
+        };
+        let mut spec = spec(vec![
+            Object::IRQIOAPIC(object::IRQIOAPIC {
+                slots: no_slots(),
+                extra: Indirect::from_owned(Box::new(object::IRQIOAPICExtraInfo {
+                    ioapic: 0,
+                    pin: 1,
+                    level: 1,
+                    polarity: 0,
+                })),
+            }),
+            Object::IRQMSI(object::IRQMSI {
+                slots: no_slots(),
+                extra: Indirect::from_owned(Box::new(object::IRQMSIExtraInfo {
+                    handle: 0,
+                    pci_bus: 1,
+                    pci_dev: 2,
+                    pci_func: 3,
+                })),
+            }),
+            Object::RiscvIRQ(object::RiscvIRQ {
+                slots: no_slots(),
+                extra: Indirect::from_owned(Box::new(object::RiscvIRQExtraInfo { trigger: 1 })),
+            }),
+            io_ports(0x60, 0x64),
+            io_ports(2, 1),
+            io_ports(0, MAX_IO_PORT + 1),
+            cnode(
+                3,
+                vec![
+                    (
+                        0,
+                        Cap::IRQIOAPICHandler(cap::IRQIOAPICHandler { object: 0 }),
+                    ),
+                    (1, Cap::IRQMSIHandler(cap::IRQMSIHandler { object: 1 })),
+                    (2, Cap::RiscvIRQHandler(cap::RiscvIRQHandler { object: 2 })),
+                    (3, Cap::IOPorts(cap::IOPorts { object: 3 })),
+                    (4, Cap::IRQMSIHandler(cap::IRQMSIHandler { object: 0 })),
+                    (5, Cap::IOPorts(cap::IOPorts { object: 2 })),
+                ],
+            ),
+        ]);
+        spec.irqs = [
+            IRQEntry { irq: 1, handler: 0 },
+            IRQEntry { irq: 2, handler: 1 },
+            IRQEntry { irq: 3, handler: 2 },
+            IRQEntry { irq: 4, handler: 3 },
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            spec.check(),
+            [
+                SpecError::InvalidIOPortRange { object: 4 },
+                SpecError::InvalidIOPortRange { object: 5 },
+                SpecError::CapTypeMismatch { holder: 6, slot: 4 },
+                SpecError::CapTypeMismatch { holder: 6, slot: 5 },
+                SpecError::InvalidIRQHandler { irq: 4 },
+            ]
+        );
+    }
+
+    #[test]
+    fn resource_handoff() {
+        let handoff = |untyped_slots, info_frame, init_cnode_slot| ResourceHandoff {
+            cnode: 0,
+            untyped_slots,
+            info_frame,
+            irq_control_slot: Some(1),
+            asid_control_slot: None,
+            init_cnode_slot,
+        };
+        let is_valid = |handoff| {
+            let mut spec = spec(vec![
+                cnode(4, vec![(2, ep_cap(2))]),
+                frame(6, None, None),
+                Object::Endpoint,
+            ]);
+            spec.resource_handoff = Some(handoff);
+            spec.check().is_empty()
+        };
+        assert!(is_valid(handoff(4..6, Some(1), Some(3))));
+        assert!(is_valid(handoff(3..16, None, None)));
+        // Slots out of bounds, including a range too large to iterate over
+        assert!(!is_valid(handoff(4..17, None, None)));
+        assert!(!is_valid(handoff(4..usize::MAX, None, None)));
+        assert!(!is_valid(handoff(4..6, None, Some(16))));
+        // Slots which overlap each other or a cap
+        assert!(!is_valid(handoff(1..6, None, None)));
+        assert!(!is_valid(handoff(2..6, None, None)));
+        assert!(!is_valid(handoff(4..6, None, Some(1))));
+        assert!(!is_valid(handoff(4..6, None, Some(5))));
+        // Too many untypeds to describe in the info frame, or no slots for them
+        assert!(!is_valid(handoff(4..7, Some(1), None)));
+        assert!(!is_valid(handoff(4..4, None, None)));
+        // The wrong types of objects
+        assert!(!is_valid(ResourceHandoff {
+            cnode: 2,
+            ..handoff(4..6, None, None)
+        }));
+        assert!(!is_valid(handoff(4..6, Some(2), None)));
+    }
+}
@@ -0,0 +1,605 @@
+//
+// Copyright 2023, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use core::fmt;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    object, Cap, FillEntry, FillEntryContentBootInfoId, FrameInit, IRQEntry, Object, ObjectId,
+    Rights, Spec, UntypedCover,
+};
+
+const DOMAIN: &[u8] = b"sel4-capdl-initializer spec digest v1\0";
+
+/// A SHA-256 digest identifying the system configuration described by a spec.
+///
+/// The digest covers every object, cap, and frame image in the spec, but not object names, and not
+/// how the spec and its frame contents happen to be stored (indirection, compression, or embedding
+/// of frames). Bytes of frames that are filled from bootinfo are hashed as zeros.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SpecDigest(pub [u8; 32]);
+
+impl SpecDigest {
+    pub const fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+impl fmt::Display for SpecDigest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for b in self.0.iter() {
+            write!(f, "{b:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a, N, D, M> Spec<'a, N, D, M> {
+    /// Computes the spec's [`SpecDigest`].
+    ///
+    /// `frame_image` is called, in order of object ID, for each frame whose initial contents are
+    /// not all zeros according to the spec (that is, which is embedded or has a non-empty fill),
+    /// and must pass the frame's entire initial contents to the given [`FrameImageHasher`].
+    pub fn digest<E>(
+        &self,
+        mut frame_image: impl FnMut(
+            ObjectId,
+            &object::Frame<'a, D, M>,
+            &mut FrameImageHasher<D>,
+        ) -> Result<(), E>,
+    ) -> Result<SpecDigest, E> {
+        let mut enc = Encoder(Sha256::new());
+        enc.bytes(DOMAIN);
+        enc.usize(self.objects.len());
+        for (obj_id, named_obj) in self.objects.iter().enumerate() {
+            let obj = &named_obj.object;
+            enc.object(obj);
+            if let Object::Frame(frame) = obj {
+                let fill_entries = match &frame.init {
+                    FrameInit::Fill(fill) if fill.is_empty() => continue,
+                    FrameInit::Fill(fill) => &fill.entries[..],
+                    FrameInit::Embedded(_) => &[],
+                };
+                let mut hasher = FrameImageHasher {
+                    enc: &mut enc,
+                    fill_entries,
+                    pos: 0,
+                };
+                frame_image(obj_id, frame, &mut hasher)?;
+                assert_eq!(hasher.pos, 1 << frame.size_bits);
+            }
+        }
+        enc.usize(self.irqs.len());
+        for IRQEntry { irq, handler } in self.irqs.iter() {
+            enc.u64(*irq);
+            enc.usize(*handler);
+        }
+        enc.usize(self.asid_slots.len());
+        for pool in self.asid_slots.iter() {
+            enc.usize(*pool);
+        }
+        enc.usize(self.root_objects.start);
+        enc.usize(self.root_objects.end);
+        enc.usize(self.untyped_covers.len());
+        for UntypedCover { parent, children } in self.untyped_covers.iter() {
+            enc.usize(*parent);
+            enc.usize(children.start);
+            enc.usize(children.end);
+        }
+        enc.bool(self.resource_handoff.is_some());
+        if let Some(handoff) = &self.resource_handoff {
+            enc.usize(handoff.cnode);
+            enc.usize(handoff.untyped_slots.start);
+            enc.usize(handoff.untyped_slots.end);
+            enc.option_usize(handoff.info_frame);
+            enc.option_usize(handoff.irq_control_slot);
+            enc.option_usize(handoff.asid_control_slot);
+            enc.option_usize(handoff.init_cnode_slot);
+        }
+        Ok(SpecDigest(enc.0.finalize().into()))
+    }
+}
+
+/// Receives the initial contents of a frame during [`Spec::digest`].
+pub struct FrameImageHasher<'b, D> {
+    enc: &'b mut Encoder,
+    fill_entries: &'b [FillEntry<D>],
+    pos: usize,
+}
+
+impl<'b, D> FrameImageHasher<'b, D> {
+    /// Hashes the next `bytes` of the frame's contents.
+    pub fn update(&mut self, bytes: &[u8]) {
+        if !self
+            .fill_entries
+            .iter()
+            .any(|entry| entry.content.is_bootinfo())
+        {
+            self.enc.bytes(bytes);
+            self.pos += bytes.len();
+            return;
+        }
+        let mut buf = [0; 64];
+        for chunk in bytes.chunks(buf.len()) {
+            let buf = &mut buf[..chunk.len()];
+            buf.copy_from_slice(chunk);
+            for entry in self.fill_entries.iter() {
+                if entry.content.is_bootinfo() {
+                    let start = entry.range.start.clamp(self.pos, self.pos + chunk.len());
+                    let end = entry.range.end.clamp(self.pos, self.pos + chunk.len());
+                    buf[start - self.pos..end - self.pos].fill(0);
+                }
+            }
+            self.enc.bytes(buf);
+            self.pos += chunk.len();
+        }
+    }
+}
+
+struct Encoder(Sha256);
+
+impl Encoder {
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.0.update(bytes)
+    }
+
+    fn u8(&mut self, v: u8) {
+        self.bytes(&[v])
+    }
+
+    fn u64(&mut self, v: u64) {
+        self.bytes(&v.to_le_bytes())
+    }
+
+    fn usize(&mut self, v: usize) {
+        self.u64(v.try_into().unwrap())
+    }
+
+    fn bool(&mut self, v: bool) {
+        self.u8(v.into())
+    }
+
+    fn option_usize(&mut self, v: Option<usize>) {
+        self.bool(v.is_some());
+        self.usize(v.unwrap_or(0));
+    }
+
+    fn rights(&mut self, rights: &Rights) {
+        self.bool(rights.read);
+        self.bool(rights.write);
+        self.bool(rights.grant);
+        self.bool(rights.grant_reply);
+    }
+
+    fn slots(&mut self, slots: &[(usize, Cap)]) {
+        self.usize(slots.len());
+        for (slot, cap) in slots.iter() {
+            self.usize(*slot);
+            self.cap(cap);
+        }
+    }
+
+    fn object<D, M>(&mut self, obj: &Object<D, M>) {
+        match obj {
+            Object::Untyped(obj) => {
+                self.u8(0);
+                self.usize(obj.size_bits);
+                self.option_usize(obj.paddr);
+            }
+            Object::Endpoint => self.u8(1),
+            Object::Notification => self.u8(2),
+            Object::CNode(obj) => {
+                self.u8(3);
+                self.usize(obj.size_bits);
+                self.slots(&obj.slots);
+            }
+            Object::TCB(obj) => {
+                self.u8(4);
+                self.slots(&obj.slots);
+                let extra = &obj.extra;
+                self.u64(extra.ipc_buffer_addr);
+                self.u64(extra.affinity);
+                self.u8(extra.prio);
+                self.u8(extra.max_prio);
+                self.bool(extra.domain.is_some());
+                self.u8(extra.domain.unwrap_or(0));
+                self.bool(extra.resume);
+                self.u64(extra.ip);
+                self.u64(extra.sp);
+                self.u64(extra.spsr);
+                self.usize(extra.gprs.len());
+                for gpr in extra.gprs.iter() {
+                    self.u64(*gpr);
+                }
+                self.bool(extra.master_fault_ep.is_some());
+                self.u64(extra.master_fault_ep.unwrap_or(0));
+            }
+            Object::IRQ(obj) => {
+                self.u8(5);
+                self.slots(&obj.slots);
+            }
+            Object::VCPU => self.u8(6),
+            Object::Frame(obj) => {
+                self.u8(7);
+                self.usize(obj.size_bits);
+                self.option_usize(obj.paddr);
+                // Data entries are covered by the frame image, which follows.
+                match &obj.init {
+                    FrameInit::Fill(fill) if fill.is_empty() => self.bool(false),
+                    FrameInit::Fill(fill) => {
+                        self.bool(true);
+                        let bootinfo_entries = fill
+                            .entries
+                            .iter()
+                            .filter_map(|entry| Some((&entry.range, entry.content.as_bootinfo()?)));
+                        self.usize(bootinfo_entries.clone().count());
+                        for (range, content) in bootinfo_entries {
+                            self.usize(range.start);
+                            self.usize(range.end);
+                            self.u8(match content.id {
+                                FillEntryContentBootInfoId::Fdt => 0,
+                                FillEntryContentBootInfoId::SpecDigest => 1,
+                            });
+                            self.usize(content.offset);
+                        }
+                    }
+                    FrameInit::Embedded(_) => {
+                        self.bool(true);
+                        self.usize(0);
+                    }
+                }
+            }
+            Object::PageTable(obj) => {
+                self.u8(8);
+                self.bool(obj.is_root);
+                self.option_usize(obj.level.map(usize::from));
+                self.slots(&obj.slots);
+            }
+            Object::ASIDPool(obj) => {
+                self.u8(9);
+                self.u64(obj.high);
+            }
+            Object::ArmIRQ(obj) => {
+                self.u8(10);
+                self.slots(&obj.slots);
+                self.u64(obj.extra.trigger);
+                self.u64(obj.extra.target);
+            }
+            Object::SchedContext(obj) => {
+                self.u8(11);
+                self.usize(obj.size_bits);
+                self.u64(obj.extra.period);
+                self.u64(obj.extra.budget);
+                self.u64(obj.extra.badge);
+            }
+            Object::Reply => self.u8(12),
+            Object::IRQIOAPIC(obj) => {
+                self.u8(13);
+                self.slots(&obj.slots);
+                self.u64(obj.extra.ioapic);
+                self.u64(obj.extra.pin);
+                self.u64(obj.extra.level);
+                self.u64(obj.extra.polarity);
+            }
+            Object::IRQMSI(obj) => {
+                self.u8(14);
+                self.slots(&obj.slots);
+                self.u64(obj.extra.handle);
+                self.u64(obj.extra.pci_bus);
+                self.u64(obj.extra.pci_dev);
+                self.u64(obj.extra.pci_func);
+            }
+            Object::RiscvIRQ(obj) => {
+                self.u8(15);
+                self.slots(&obj.slots);
+                self.u64(obj.extra.trigger);
+            }
+            Object::IOPorts(obj) => {
+                self.u8(16);
+                self.u64(obj.first_port);
+                self.u64(obj.last_port);
+            }
+        }
+    }
+
+    fn cap(&mut self, cap: &Cap) {
+        match cap {
+            Cap::Untyped(cap) => {
+                self.u8(0);
+                self.usize(cap.object);
+            }
+            Cap::Endpoint(cap) => {
+                self.u8(1);
+                self.usize(cap.object);
+                self.u64(cap.badge);
+                self.rights(&cap.rights);
+            }
+            Cap::Notification(cap) => {
+                self.u8(2);
+                self.usize(cap.object);
+                self.u64(cap.badge);
+                self.rights(&cap.rights);
+            }
+            Cap::CNode(cap) => {
+                self.u8(3);
+                self.usize(cap.object);
+                self.u64(cap.guard);
+                self.u64(cap.guard_size);
+            }
+            Cap::TCB(cap) => {
+                self.u8(4);
+                self.usize(cap.object);
+            }
+            Cap::IRQHandler(cap) => {
+                self.u8(5);
+                self.usize(cap.object);
+            }
+            Cap::VCPU(cap) => {
+                self.u8(6);
+                self.usize(cap.object);
+            }
+            Cap::Frame(cap) => {
+                self.u8(7);
+                self.usize(cap.object);
+                self.rights(&cap.rights);
+                self.bool(cap.cached);
+            }
+            Cap::PageTable(cap) => {
+                self.u8(8);
+                self.usize(cap.object);
+            }
+            Cap::ASIDPool(cap) => {
+                self.u8(9);
+                self.usize(cap.object);
+            }
+            Cap::ArmIRQHandler(cap) => {
+                self.u8(10);
+                self.usize(cap.object);
+            }
+            Cap::SchedContext(cap) => {
+                self.u8(11);
+                self.usize(cap.object);
+            }
+            Cap::Reply(cap) => {
+                self.u8(12);
+                self.usize(cap.object);
+            }
+            Cap::IRQIOAPICHandler(cap) => {
+                self.u8(13);
+                self.usize(cap.object);
+            }
+            Cap::IRQMSIHandler(cap) => {
+                self.u8(14);
+                self.usize(cap.object);
+            }
+            Cap::RiscvIRQHandler(cap) => {
+                self.u8(15);
+                self.usize(cap.object);
+            }
+            Cap::IOPorts(cap) => {
+                self.u8(16);
+                self.usize(cap.object);
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use alloc::boxed::Box;
+    use alloc::string::ToString;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    use super::*;
+
+    use crate::{cap, Fill, FillEntryContent, FillEntryContentBootInfo, Indirect, NamedObject};
+
+    type TestSpec = Spec<'static, &'static str, (), ()>;
+
+    const FRAME_SIZE: usize = 1 << 12;
+
+    fn spec(objects: Vec<(&'static str, Object<'static, (), ()>)>) -> TestSpec {
+        let num_objects = objects.len();
+        Spec {
+            objects: objects
+                .into_iter()
+                .map(|(name, object)| NamedObject { name, object })
+                .collect(),
+            irqs: [].into_iter().collect(),
+            asid_slots: [].into_iter().collect(),
+            root_objects: 0..num_objects,
+            untyped_covers: [].into_iter().collect(),
+            resource_handoff: None,
+        }
+    }
+
+    fn cnode(badge: u64) -> Object<'static, (), ()> {
+        let cap = Cap::Endpoint(cap::Endpoint {
+            object: 1,
+            badge,
+            rights: Rights::all(),
+        });
+        Object::CNode(object::CNode {
+            size_bits: 2,
+            slots: Indirect::from_owned(Box::new([(1, cap)])),
+        })
+    }
+
+    fn frame(entries: Vec<FillEntry<()>>) -> Object<'static, (), ()> {
+        Object::Frame(object::Frame {
+            size_bits: 12,
+            paddr: None,
+            init: FrameInit::Fill(Fill {
+                entries: Indirect::from_owned(entries.into_boxed_slice()),
+            }),
+        })
+    }
+
+    fn data(range: core::ops::Range<usize>) -> FillEntry<()> {
+        FillEntry {
+            range,
+            content: FillEntryContent::Data(()),
+        }
+    }
+
+    fn bootinfo(range: core::ops::Range<usize>) -> FillEntry<()> {
+        FillEntry {
+            range,
+            content: FillEntryContent::BootInfo(FillEntryContentBootInfo {
+                id: FillEntryContentBootInfoId::Fdt,
+                offset: 0,
+            }),
+        }
+    }
+
+    // Hashes `image` in chunks of `chunk_size` bytes.
+    fn digest(spec: &TestSpec, image: &[u8], chunk_size: usize) -> SpecDigest {
+        spec.digest(|_, _, hasher| {
+            for chunk in image.chunks(chunk_size) {
+                hasher.update(chunk);
+            }
+            Ok::<_, ()>(())
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn covers_objects_and_caps_but_not_names() {
+        let a = spec(vec![("cnode", cnode(1)), ("ep", Object::Endpoint)]);
+        let renamed = spec(vec![("other", cnode(1)), ("names", Object::Endpoint)]);
+        let rebadged = spec(vec![("cnode", cnode(2)), ("ep", Object::Endpoint)]);
+        let retyped = spec(vec![("cnode", cnode(1)), ("ep", Object::Notification)]);
+        assert_eq!(digest(&a, &[], 1), digest(&renamed, &[], 1));
+        assert_ne!(digest(&a, &[], 1), digest(&rebadged, &[], 1));
+        assert_ne!(digest(&a, &[], 1), digest(&retyped, &[], 1));
+    }
+
+    #[test]
+    fn x86_and_riscv_kinds() {
+        let ioapic = |pin| {
+            Object::IRQIOAPIC(object::IRQIOAPIC {
+                slots: Indirect::from_owned(Box::new([])),
+                extra: Indirect::from_owned(Box::new(object::IRQIOAPICExtraInfo {
+                    ioapic: 0,
+                    pin,
+                    level: 0,
+                    polarity: 0,
+                })),
+            })
+        };
+        let msi = |pci_func| {
+            Object::IRQMSI(object::IRQMSI {
+                slots: Indirect::from_owned(Box::new([])),
+                extra: Indirect::from_owned(Box::new(object::IRQMSIExtraInfo {
+                    handle: 0,
+                    pci_bus: 0,
+                    pci_dev: 0,
+                    pci_func,
+                })),
+            })
+        };
+        let riscv = |trigger| {
+            Object::RiscvIRQ(object::RiscvIRQ {
+                slots: Indirect::from_owned(Box::new([])),
+                extra: Indirect::from_owned(Box::new(object::RiscvIRQExtraInfo { trigger })),
+            })
+        };
+        let io_ports = |last_port| {
+            Object::IOPorts(object::IOPorts {
+                first_port: 0,
+                last_port,
+            })
+        };
+        let holding = |cap| {
+            Object::CNode(object::CNode {
+                size_bits: 2,
+                slots: Indirect::from_owned(Box::new([(0, cap)])),
+            })
+        };
+        let digests = [
+            ioapic(0),
+            ioapic(1),
+            msi(0),
+            msi(1),
+            riscv(0),
+            riscv(1),
+            io_ports(0),
+            io_ports(1),
+            holding(Cap::IRQIOAPICHandler(cap::IRQIOAPICHandler { object: 0 })),
+            holding(Cap::IRQMSIHandler(cap::IRQMSIHandler { object: 0 })),
+            holding(Cap::RiscvIRQHandler(cap::RiscvIRQHandler { object: 0 })),
+            holding(Cap::IOPorts(cap::IOPorts { object: 0 })),
+        ]
+        .map(|object| digest(&spec(vec![("object", object)]), &[], 1));
+        for (i, a) in digests.iter().enumerate() {
+            for b in &digests[i + 1..] {
+                assert_ne!(a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn frame_images() {
+        let spec = spec(vec![
+            ("empty", frame(vec![])),
+            ("data", frame(vec![data(0..4)])),
+        ]);
+        let mut called = vec![];
+        spec.digest(|obj_id, _, hasher| {
+            called.push(obj_id);
+            hasher.update(&[0; FRAME_SIZE]);
+            Ok::<_, ()>(())
+        })
+        .unwrap();
+        assert_eq!(called, [1]);
+
+        let mut image = vec![0; FRAME_SIZE];
+        image[..4].copy_from_slice(b"data");
+        // How the image is split up does not matter, but its contents do.
+        assert_eq!(digest(&spec, &image, FRAME_SIZE), digest(&spec, &image, 3));
+        assert_ne!(
+            digest(&spec, &image, FRAME_SIZE),
+            digest(&spec, &[0; FRAME_SIZE], FRAME_SIZE)
+        );
+
+        assert_eq!(spec.digest(|_, _, _| Err("failed")), Err("failed"),);
+    }
+
+    #[test]
+    fn bootinfo_is_hashed_as_zeros() {
+        let spec = spec(vec![("frame", frame(vec![data(0..4), bootinfo(100..200)]))]);
+        let mut image = vec![0; FRAME_SIZE];
+        image[..4].copy_from_slice(b"data");
+        let mut with_bootinfo = image.clone();
+        with_bootinfo[100..200].fill(0xff);
+        assert_eq!(digest(&spec, &image, 64), digest(&spec, &with_bootinfo, 7));
+        with_bootinfo[200] = 1;
+        assert_ne!(digest(&spec, &image, 64), digest(&spec, &with_bootinfo, 7));
+    }
+
+    #[test]
+    #[should_panic]
+    fn short_frame_image() {
+        let spec = spec(vec![("data", frame(vec![data(0..4)]))]);
+        digest(&spec, &[0; 4], 4);
+    }
+
+    #[test]
+    fn display() {
+        let mut bytes = [0; 32];
+        bytes[0] = 0xab;
+        bytes[31] = 0x01;
+        let s = SpecDigest(bytes).to_string();
+        assert_eq!(s.len(), 64);
+        assert!(s.starts_with("ab00"));
+        assert!(s.ends_with("0001"));
+    }
+}
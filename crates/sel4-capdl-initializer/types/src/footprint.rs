@@ -36,6 +36,9 @@ impl Footprint for IndirectEmbeddedFrame {}
 #[cfg(feature = "deflate")]
 impl Footprint for IndirectDeflatedBytesContent {}
 
+#[cfg(feature = "deflate")]
+impl Footprint for IndirectMaybeDeflatedBytesContent {}
+
 impl<'a, T: Sized + Footprint> Footprint for Indirect<'a, T> {
     fn external_footprint(&self) -> usize {
         self.inner().total_footprint()
@@ -80,6 +83,9 @@ impl<'a, D: Footprint, M: Footprint> Footprint for Object<'a, D, M> {
             Self::Frame(obj) => obj.init.external_footprint(),
             Self::PageTable(obj) => obj.slots.external_footprint(),
             Self::ArmIRQ(obj) => obj.slots.external_footprint(),
+            Self::IRQIOAPIC(obj) => obj.slots.external_footprint(),
+            Self::IRQMSI(obj) => obj.slots.external_footprint(),
+            Self::RiscvIRQ(obj) => obj.slots.external_footprint(),
             _ => 0,
         }
     }
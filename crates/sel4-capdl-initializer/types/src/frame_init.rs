@@ -226,6 +226,8 @@ pub struct FillEntryContentBootInfo {
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum FillEntryContentBootInfoId {
     Fdt,
+    /// The [`SpecDigest`](crate::SpecDigest) of the spec, as measured by the initializer.
+    SpecDigest,
 }
 
 // // //
@@ -386,3 +388,24 @@ impl Content for IndirectDeflatedBytesContent {
         .self_contained_copy_out(dst)
     }
 }
+
+/// Content which is stored either as is or deflated, whichever is smaller.
+#[cfg(feature = "deflate")]
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum IndirectMaybeDeflatedBytesContent {
+    Bytes(IndirectBytesContent),
+    Deflated(IndirectDeflatedBytesContent),
+}
+
+#[cfg(feature = "deflate")]
+impl Content for IndirectMaybeDeflatedBytesContent {
+    type Source = [u8];
+
+    fn copy_out(&self, source: &Self::Source, dst: &mut [u8]) {
+        match self {
+            Self::Bytes(content) => content.copy_out(source, dst),
+            Self::Deflated(content) => content.copy_out(source, dst),
+        }
+    }
+}
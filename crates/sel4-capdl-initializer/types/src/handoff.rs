@@ -0,0 +1,146 @@
+//
+// Copyright 2023, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use crate::{CapSlot, Word};
+
+/// The header of the info frame of a [`ResourceHandoff`](crate::ResourceHandoff).
+///
+/// The header is followed by one [`HandoffUntypedDesc`] for each untyped, in slot order. All
+/// fields are little-endian, regardless of architecture.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct HandoffInfo {
+    pub num_untypeds: usize,
+    pub first_untyped_slot: CapSlot,
+    /// The free slots of the initializer's CNode, if it is handed off, and zero otherwise.
+    pub first_free_slot: CapSlot,
+    pub num_free_slots: usize,
+}
+
+impl HandoffInfo {
+    pub const SIZE: usize = 32;
+
+    /// The number of untypeds whose descriptions fit in a frame of the given size.
+    pub const fn max_untypeds(frame_size_bits: usize) -> usize {
+        let frame_size = if frame_size_bits < usize::BITS as usize {
+            1 << frame_size_bits
+        } else {
+            usize::MAX
+        };
+        frame_size.saturating_sub(Self::SIZE) / HandoffUntypedDesc::SIZE
+    }
+
+    pub fn write(&self, frame: &mut [u8]) {
+        write_word(&mut frame[0..], self.num_untypeds as Word);
+        write_word(&mut frame[8..], self.first_untyped_slot as Word);
+        write_word(&mut frame[16..], self.first_free_slot as Word);
+        write_word(&mut frame[24..], self.num_free_slots as Word);
+    }
+
+    pub fn read(frame: &[u8]) -> Self {
+        Self {
+            num_untypeds: read_word(&frame[0..]) as usize,
+            first_untyped_slot: read_word(&frame[8..]) as CapSlot,
+            first_free_slot: read_word(&frame[16..]) as CapSlot,
+            num_free_slots: read_word(&frame[24..]) as usize,
+        }
+    }
+}
+
+/// Describes the `i`th untyped handed off, like `seL4_UntypedDesc`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct HandoffUntypedDesc {
+    pub paddr: Word,
+    pub size_bits: u8,
+    pub is_device: bool,
+}
+
+impl HandoffUntypedDesc {
+    pub const SIZE: usize = 16;
+
+    fn offset(i: usize) -> usize {
+        HandoffInfo::SIZE + i * Self::SIZE
+    }
+
+    pub fn write(&self, frame: &mut [u8], i: usize) {
+        let buf = &mut frame[Self::offset(i)..][..Self::SIZE];
+        write_word(buf, self.paddr);
+        buf[8] = self.size_bits;
+        buf[9] = self.is_device.into();
+        buf[10..].fill(0);
+    }
+
+    pub fn read(frame: &[u8], i: usize) -> Self {
+        let buf = &frame[Self::offset(i)..][..Self::SIZE];
+        Self {
+            paddr: read_word(buf),
+            size_bits: buf[8],
+            is_device: buf[9] != 0,
+        }
+    }
+}
+
+fn write_word(buf: &mut [u8], word: Word) {
+    buf[..8].copy_from_slice(&word.to_le_bytes());
+}
+
+fn read_word(buf: &[u8]) -> Word {
+    Word::from_le_bytes(buf[..8].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn max_untypeds() {
+        assert_eq!(HandoffInfo::max_untypeds(12), (4096 - 32) / 16);
+        assert_eq!(HandoffInfo::max_untypeds(6), 2);
+        assert_eq!(HandoffInfo::max_untypeds(5), 0);
+        assert_eq!(HandoffInfo::max_untypeds(0), 0);
+        assert_eq!(
+            HandoffInfo::max_untypeds(usize::BITS as usize),
+            usize::MAX / 16 - 2
+        );
+        assert_eq!(HandoffInfo::max_untypeds(usize::MAX), usize::MAX / 16 - 2);
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut frame = [0xff; 1 << 7];
+        let info = HandoffInfo {
+            num_untypeds: 2,
+            first_untyped_slot: 16,
+            first_free_slot: 0x1234,
+            num_free_slots: 0x100,
+        };
+        let descs = [
+            HandoffUntypedDesc {
+                paddr: 0x8000_0000,
+                size_bits: 20,
+                is_device: false,
+            },
+            HandoffUntypedDesc {
+                paddr: 0xffff_0000_0000,
+                size_bits: 12,
+                is_device: true,
+            },
+        ];
+        info.write(&mut frame);
+        for (i, desc) in descs.iter().enumerate() {
+            desc.write(&mut frame, i);
+        }
+        assert_eq!(HandoffInfo::read(&frame), info);
+        for (i, desc) in descs.iter().enumerate() {
+            assert_eq!(HandoffUntypedDesc::read(&frame, i), *desc);
+        }
+        // The layout is fixed, and the padding of descriptions is zeroed.
+        assert_eq!(frame[..8], 2u64.to_le_bytes());
+        assert_eq!(frame[32..40], 0x8000_0000u64.to_le_bytes());
+        assert_eq!(frame[40..48], [20, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(frame[56..64], [12, 1, 0, 0, 0, 0, 0, 0]);
+        assert!(frame[64..].iter().all(|b| *b == 0xff));
+    }
+}
@@ -15,14 +15,24 @@ use serde::{Deserialize, Serialize};
 mod cap_table;
 mod footprint;
 mod frame_init;
+mod handoff;
 mod indirect;
 mod inspect;
 mod object_name;
 mod spec;
 
+#[cfg(feature = "alloc")]
+mod check;
+
 #[cfg(feature = "alloc")]
 mod traverse;
 
+#[cfg(feature = "builder")]
+pub mod builder;
+
+#[cfg(feature = "digest")]
+mod digest;
+
 #[cfg(feature = "std")]
 mod when_std;
 
@@ -37,20 +47,30 @@ pub use frame_init::{
     IndirectBytesContent, IndirectEmbeddedFrame, NeverEmbedded, SelfContainedContent,
     SelfContainedGetEmbeddedFrame,
 };
+pub use handoff::{HandoffInfo, HandoffUntypedDesc};
 pub use indirect::Indirect;
 pub use object_name::{
     IndirectObjectName, ObjectName, ObjectNamesLevel, SelfContainedObjectName, Unnamed,
 };
 pub use spec::{
     cap, object, ASIDSlotEntry, Badge, CPtr, Cap, CapSlot, CapTableEntry, IRQEntry, NamedObject,
-    Object, ObjectId, Rights, Spec, TryFromCapError, TryFromObjectError, UntypedCover, Word,
+    Object, ObjectId, ResourceHandoff, Rights, Spec, TryFromCapError, TryFromObjectError,
+    UntypedCover, Word,
 };
 
+#[cfg(feature = "alloc")]
+pub use check::SpecError;
+
 #[cfg(feature = "alloc")]
 pub use frame_init::{FileContent, FileContentRange};
 
+#[cfg(feature = "digest")]
+pub use digest::{FrameImageHasher, SpecDigest};
+
 #[cfg(feature = "deflate")]
-pub use frame_init::{DeflatedBytesContent, IndirectDeflatedBytesContent};
+pub use frame_init::{
+    DeflatedBytesContent, IndirectDeflatedBytesContent, IndirectMaybeDeflatedBytesContent,
+};
 
 #[cfg(feature = "std")]
 pub use when_std::{FillMap, FillMapBuilder, InputSpec};
@@ -70,7 +90,7 @@ pub struct SpecWithSources<'a, N: ObjectName, D: Content, M: GetEmbeddedFrame> {
 
 #[cfg(feature = "deflate")]
 pub type SpecWithIndirection<'a> =
-    Spec<'a, Option<IndirectObjectName>, IndirectDeflatedBytesContent, IndirectEmbeddedFrame>;
+    Spec<'a, Option<IndirectObjectName>, IndirectMaybeDeflatedBytesContent, IndirectEmbeddedFrame>;
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
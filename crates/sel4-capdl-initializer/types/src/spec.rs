@@ -37,6 +37,7 @@ pub struct Spec<'a, N, D, M> {
     pub asid_slots: Indirect<'a, [ASIDSlotEntry]>,
     pub root_objects: Range<ObjectId>,
     pub untyped_covers: Indirect<'a, [UntypedCover]>,
+    pub resource_handoff: Option<ResourceHandoff>,
 }
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
@@ -55,6 +56,24 @@ pub struct UntypedCover {
     pub children: Range<ObjectId>,
 }
 
+/// Resources which the initializer hands off to a component once it has initialized every object,
+/// just before starting threads.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ResourceHandoff {
+    pub cnode: ObjectId,
+    /// Slots of `cnode` which receive untyped caps covering whatever memory is left over, in order.
+    pub untyped_slots: Range<CapSlot>,
+    /// A frame into which the initializer writes a description of those untypeds (see
+    /// [`HandoffInfo`](crate::HandoffInfo)).
+    pub info_frame: Option<ObjectId>,
+    pub irq_control_slot: Option<CapSlot>,
+    pub asid_control_slot: Option<CapSlot>,
+    /// A slot of `cnode` which receives a copy of the cap to the initializer's own CNode, whose
+    /// free slots are then described in the info frame.
+    pub init_cnode_slot: Option<CapSlot>,
+}
+
 #[derive(Debug, Clone, Eq, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct NamedObject<'a, N, D, M> {
@@ -78,6 +97,10 @@ pub enum Object<'a, D, M> {
     ArmIRQ(object::ArmIRQ<'a>),
     SchedContext(object::SchedContext),
     Reply,
+    IRQIOAPIC(object::IRQIOAPIC<'a>),
+    IRQMSI(object::IRQMSI<'a>),
+    RiscvIRQ(object::RiscvIRQ<'a>),
+    IOPorts(object::IOPorts),
 }
 
 impl<'a, D, M> Object<'a, D, M> {
@@ -88,6 +111,18 @@ impl<'a, D, M> Object<'a, D, M> {
             _ => None,
         }
     }
+
+    /// Whether this object stands for an IRQ, and thus can be mapped in [`Spec::irqs`].
+    pub fn is_irq(&self) -> bool {
+        matches!(
+            self,
+            Object::IRQ(_)
+                | Object::ArmIRQ(_)
+                | Object::IRQIOAPIC(_)
+                | Object::IRQMSI(_)
+                | Object::RiscvIRQ(_)
+        )
+    }
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -106,6 +141,10 @@ pub enum Cap {
     ArmIRQHandler(cap::ArmIRQHandler),
     SchedContext(cap::SchedContext),
     Reply(cap::Reply),
+    IRQIOAPICHandler(cap::IRQIOAPICHandler),
+    IRQMSIHandler(cap::IRQMSIHandler),
+    RiscvIRQHandler(cap::RiscvIRQHandler),
+    IOPorts(cap::IOPorts),
 }
 
 impl Cap {
@@ -124,6 +163,10 @@ impl Cap {
             Cap::ArmIRQHandler(cap) => cap.object,
             Cap::SchedContext(cap) => cap.object,
             Cap::Reply(cap) => cap.object,
+            Cap::IRQIOAPICHandler(cap) => cap.object,
+            Cap::IRQMSIHandler(cap) => cap.object,
+            Cap::RiscvIRQHandler(cap) => cap.object,
+            Cap::IOPorts(cap) => cap.object,
         }
     }
 }
@@ -138,6 +181,41 @@ pub struct Rights {
     pub grant_reply: bool,
 }
 
+impl Rights {
+    pub const fn none() -> Self {
+        Self {
+            read: false,
+            write: false,
+            grant: false,
+            grant_reply: false,
+        }
+    }
+
+    pub const fn read_only() -> Self {
+        Self {
+            read: true,
+            ..Self::none()
+        }
+    }
+
+    pub const fn read_write() -> Self {
+        Self {
+            read: true,
+            write: true,
+            ..Self::none()
+        }
+    }
+
+    pub const fn all() -> Self {
+        Self {
+            read: true,
+            write: true,
+            grant: true,
+            grant_reply: true,
+        }
+    }
+}
+
 pub mod object {
     use super::*;
 
@@ -170,6 +248,8 @@ pub mod object {
         pub affinity: Word,
         pub prio: u8,
         pub max_prio: u8,
+        /// The scheduling domain, if other than the default.
+        pub domain: Option<u8>,
         pub resume: bool,
 
         pub ip: Word,
@@ -177,6 +257,8 @@ pub mod object {
         pub spsr: Word,
         pub gprs: Indirect<'a, [Word]>,
 
+        /// The fault endpoint, on non-MCS kernels, as a CPtr in the TCB's own CSpace. On MCS
+        /// kernels, the cap in [`TCB::SLOT_FAULT_EP`] is used instead.
         pub master_fault_ep: Option<CPtr>,
     }
 
@@ -236,6 +318,59 @@ pub mod object {
         pub budget: u64,
         pub badge: Badge,
     }
+
+    #[derive(Debug, Clone, Eq, PartialEq, IsObject, IsObjectWithCapTable)]
+    #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+    pub struct IRQIOAPIC<'a> {
+        pub slots: Indirect<'a, [CapTableEntry]>,
+        pub extra: Indirect<'a, IRQIOAPICExtraInfo>,
+    }
+
+    #[derive(Debug, Clone, Eq, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+    pub struct IRQIOAPICExtraInfo {
+        pub ioapic: Word,
+        pub pin: Word,
+        pub level: Word,
+        pub polarity: Word,
+    }
+
+    #[derive(Debug, Clone, Eq, PartialEq, IsObject, IsObjectWithCapTable)]
+    #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+    pub struct IRQMSI<'a> {
+        pub slots: Indirect<'a, [CapTableEntry]>,
+        pub extra: Indirect<'a, IRQMSIExtraInfo>,
+    }
+
+    #[derive(Debug, Clone, Eq, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+    pub struct IRQMSIExtraInfo {
+        pub handle: Word,
+        pub pci_bus: Word,
+        pub pci_dev: Word,
+        pub pci_func: Word,
+    }
+
+    #[derive(Debug, Clone, Eq, PartialEq, IsObject, IsObjectWithCapTable)]
+    #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+    pub struct RiscvIRQ<'a> {
+        pub slots: Indirect<'a, [CapTableEntry]>,
+        pub extra: Indirect<'a, RiscvIRQExtraInfo>,
+    }
+
+    #[derive(Debug, Clone, Eq, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+    pub struct RiscvIRQExtraInfo {
+        pub trigger: Word,
+    }
+
+    /// A range of x86 I/O ports. Both ends are inclusive.
+    #[derive(Debug, Clone, Eq, PartialEq, IsObject)]
+    #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+    pub struct IOPorts {
+        pub first_port: Word,
+        pub last_port: Word,
+    }
 }
 
 pub mod cap {
@@ -254,8 +389,6 @@ pub mod cap {
         // TODO
         //   parse-capDL uses badge=0 to mean no badge. Is that good
         //   enough, or do we ever need to actually use the badge value '0'?
-        // TODO
-        //   Is it correct that these are ignored in the case of TCB::SLOT_TEMP_FAULT_EP?
         pub badge: Badge,
         pub rights: Rights,
     }
@@ -331,6 +464,30 @@ pub mod cap {
     pub struct Reply {
         pub object: ObjectId,
     }
+
+    #[derive(Debug, Clone, Eq, PartialEq, IsCap)]
+    #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+    pub struct IRQIOAPICHandler {
+        pub object: ObjectId,
+    }
+
+    #[derive(Debug, Clone, Eq, PartialEq, IsCap)]
+    #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+    pub struct IRQMSIHandler {
+        pub object: ObjectId,
+    }
+
+    #[derive(Debug, Clone, Eq, PartialEq, IsCap)]
+    #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+    pub struct RiscvIRQHandler {
+        pub object: ObjectId,
+    }
+
+    #[derive(Debug, Clone, Eq, PartialEq, IsCap)]
+    #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+    pub struct IOPorts {
+        pub object: ObjectId,
+    }
 }
 
 // // //
@@ -42,6 +42,10 @@ impl<'a, N, D, M> Spec<'a, N, D, M> {
                             Object::ArmIRQ(obj) => Object::ArmIRQ(obj.clone()),
                             Object::SchedContext(obj) => Object::SchedContext(obj.clone()),
                             Object::Reply => Object::Reply,
+                            Object::IRQIOAPIC(obj) => Object::IRQIOAPIC(obj.clone()),
+                            Object::IRQMSI(obj) => Object::IRQMSI(obj.clone()),
+                            Object::RiscvIRQ(obj) => Object::RiscvIRQ(obj.clone()),
+                            Object::IOPorts(obj) => Object::IOPorts(obj.clone()),
                         },
                     })
                 })
@@ -50,6 +54,7 @@ impl<'a, N, D, M> Spec<'a, N, D, M> {
             asid_slots: self.asid_slots.clone(),
             root_objects: self.root_objects.clone(),
             untyped_covers: self.untyped_covers.clone(),
+            resource_handoff: self.resource_handoff.clone(),
         })
     }
 }
@@ -109,10 +109,12 @@ impl From<&Rights> for sel4::CapRights {
     }
 }
 
-impl From<&FillEntryContentBootInfoId> for sel4::BootInfoExtraId {
-    fn from(id: &FillEntryContentBootInfoId) -> Self {
-        match id {
-            FillEntryContentBootInfoId::Fdt => sel4::BootInfoExtraId::Fdt,
+impl FillEntryContentBootInfoId {
+    /// The corresponding bootinfo extra, if this content is provided by the kernel.
+    pub fn bootinfo_extra_id(&self) -> Option<sel4::BootInfoExtraId> {
+        match self {
+            FillEntryContentBootInfoId::Fdt => Some(sel4::BootInfoExtraId::Fdt),
+            FillEntryContentBootInfoId::SpecDigest => None,
         }
     }
 }
@@ -5,6 +5,8 @@
 //
 
 use std::collections::BTreeMap;
+#[cfg(feature = "digest")]
+use std::convert::Infallible;
 use std::fs::File;
 use std::io;
 use std::ops::Deref;
@@ -13,13 +15,21 @@ use std::path::{Path, PathBuf};
 
 use crate::{FileContent, FileContentRange, Fill, NeverEmbedded, Spec};
 
+#[cfg(feature = "digest")]
+use crate::{FillEntryContent, FrameInit};
+
 pub type InputSpec = Spec<'static, String, FileContentRange, NeverEmbedded>;
 
 impl InputSpec {
     pub fn parse(s: &str) -> Self {
-        serde_json::from_str::<Spec<String, FileContent, ()>>(s)
-            .unwrap()
-            .traverse_embedded_frames::<NeverEmbedded>(|_| panic!())
+        Self::from_file_content_spec(
+            &serde_json::from_str::<Spec<String, FileContent, ()>>(s).unwrap(),
+        )
+    }
+
+    /// For specs which come from somewhere other than JSON, such as `.cdl` files.
+    pub fn from_file_content_spec(spec: &Spec<'static, String, FileContent, ()>) -> Self {
+        spec.traverse_embedded_frames::<NeverEmbedded>(|_| panic!())
             .traverse_data_with_context(|length, data| data.with_length(length))
     }
 
@@ -47,6 +57,31 @@ impl FillMap {
         }
         frame
     }
+
+    /// Computes the [`SpecDigest`](crate::SpecDigest) of a spec whose frame contents are in this
+    /// map. `embedded_fill` gives the original fill of each embedded frame.
+    #[cfg(feature = "digest")]
+    pub fn spec_digest<N, M>(
+        &self,
+        spec: &Spec<'_, N, FileContentRange, M>,
+        embedded_fill: impl Fn(&M) -> &Fill<'_, FileContentRange>,
+    ) -> crate::SpecDigest {
+        let result: Result<_, Infallible> = spec.digest(|_obj_id, frame, hasher| {
+            let fill = match &frame.init {
+                FrameInit::Fill(fill) => fill,
+                FrameInit::Embedded(embedded) => embedded_fill(embedded),
+            };
+            let mut image = vec![0; 1 << frame.size_bits];
+            for entry in fill.entries.iter() {
+                if let FillEntryContent::Data(key) = &entry.content {
+                    image[entry.range.clone()].copy_from_slice(self.get(key));
+                }
+            }
+            hasher.update(&image);
+            Ok(())
+        });
+        result.unwrap()
+    }
 }
 
 pub struct FillMapBuilder {
@@ -0,0 +1,23 @@
+#
+# Copyright 2023, Colias Group, LLC
+#
+# SPDX-License-Identifier: BSD-2-Clause
+#
+
+{ mk, localCrates, versions }:
+
+mk {
+  package.name = "sel4-capdl-initializer-validate";
+  dependencies = {
+    inherit (versions)
+      anyhow
+      serde_json
+      clap
+    ;
+    serde = { version = versions.serde; features = [ "derive" ]; };
+    inherit (localCrates)
+      sel4-capdl-initializer-cdl
+    ;
+    sel4-capdl-initializer-types = localCrates.sel4-capdl-initializer-types // { features = [ "std" "serde" "builder" ]; };
+  };
+}
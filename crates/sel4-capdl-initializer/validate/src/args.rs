@@ -0,0 +1,54 @@
+//
+// Copyright 2023, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use anyhow::Result;
+use clap::{Arg, ArgAction, Command};
+
+#[derive(Debug)]
+pub struct Args {
+    pub spec_path: String,
+    pub object_sizes_path: Option<String>,
+    pub untyped_list_path: Option<String>,
+    pub verbose: bool,
+}
+
+impl Args {
+    pub fn parse() -> Result<Self> {
+        let matches = Command::new("")
+            .arg(
+                Arg::new("spec")
+                    .short('f')
+                    .value_name("SPEC_FILE")
+                    .required(true),
+            )
+            .arg(
+                Arg::new("object_sizes")
+                    .long("object-sizes")
+                    .value_name("OBJECT_SIZES_FILE"),
+            )
+            .arg(
+                Arg::new("untyped_list")
+                    .long("untyped-list")
+                    .value_name("UNTYPED_LIST_FILE")
+                    .requires("object_sizes"),
+            )
+            .arg(Arg::new("verbose").short('v').action(ArgAction::SetTrue))
+            .get_matches();
+
+        let spec_path = matches.get_one::<String>("spec").unwrap().to_owned();
+        let object_sizes_path = matches.get_one::<String>("object_sizes").cloned();
+        let untyped_list_path = matches.get_one::<String>("untyped_list").cloned();
+
+        let verbose = *matches.get_one::<bool>("verbose").unwrap();
+
+        Ok(Self {
+            spec_path,
+            object_sizes_path,
+            untyped_list_path,
+            verbose,
+        })
+    }
+}
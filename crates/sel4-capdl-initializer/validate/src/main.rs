@@ -0,0 +1,122 @@
+//
+// Copyright 2023, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use std::fs;
+use std::process::ExitCode;
+
+use anyhow::{anyhow, Result};
+
+use sel4_capdl_initializer_cdl::{read_input_spec, read_object_sizes};
+use sel4_capdl_initializer_types::{InputSpec, ObjectId};
+
+mod args;
+mod simulate;
+
+use args::Args;
+use simulate::{simulate, SimulateError, Untyped};
+
+fn main() -> Result<ExitCode> {
+    let args = Args::parse()?;
+
+    if args.verbose {
+        eprintln!("{:#?}", args);
+    }
+
+    let object_sizes = args
+        .object_sizes_path
+        .as_ref()
+        .map(read_object_sizes)
+        .transpose()?;
+
+    let spec = read_input_spec(&args.spec_path, object_sizes.as_ref())?;
+
+    let errs = spec.check();
+    for err in errs.iter() {
+        match err.object() {
+            Some(obj_id) => eprintln!("error: {err} ({})", describe(&spec, obj_id)),
+            None => eprintln!("error: {err}"),
+        }
+    }
+    if !errs.is_empty() {
+        return Ok(ExitCode::FAILURE);
+    }
+
+    let untyped_list_path = match &args.untyped_list_path {
+        Some(path) => path,
+        None => return Ok(ExitCode::SUCCESS),
+    };
+    let untypeds: Vec<Untyped> = serde_json::from_str(&fs::read_to_string(untyped_list_path)?)?;
+    let report =
+        simulate(&spec, &untypeds, object_sizes.as_ref().unwrap()).map_err(|err| match err {
+            SimulateError::MissingObjectSize { object, key } => anyhow!(
+                "the size of {} is unknown, because the object sizes have no entry for {key:?}",
+                describe(&spec, object)
+            ),
+            SimulateError::ObjectTooLarge { object } => {
+                anyhow!("{} is too large", describe(&spec, object))
+            }
+            SimulateError::InvalidUntyped { .. } => anyhow!("{err}"),
+        })?;
+
+    println!("untyped usage:");
+    for (ut, usage) in untypeds.iter().zip(report.usage.iter()) {
+        println!(
+            "    {:#014x} (size_bits = {:2}{}): used {:#x}, padding {:#x}, free {:#x} in {} regions (largest block: {})",
+            ut.paddr,
+            ut.size_bits,
+            if ut.is_device { ", device" } else { "" },
+            usage.used,
+            usage.padding,
+            usage.free_bytes(),
+            usage.free.len(),
+            match usage.largest_free_block_bits() {
+                Some(bits) => format!("{bits} bits"),
+                None => "none".to_owned(),
+            },
+        );
+    }
+    println!("CSlots needed: {}", report.cslots);
+    if let Some(handoff) = &report.handoff {
+        println!(
+            "untypeds handed off: {} (of {} slots)",
+            handoff.untypeds, handoff.slots
+        );
+    }
+
+    for obj_id in report.unallocated.iter() {
+        eprintln!("error: no room for {}", describe(&spec, *obj_id));
+    }
+    for obj_id in report.unreachable.iter() {
+        eprintln!(
+            "error: {} is not contained in any untyped",
+            describe(&spec, *obj_id)
+        );
+    }
+    for obj_id in report.overfull_covers.iter() {
+        eprintln!(
+            "error: objects covered by {} do not fit in it",
+            describe(&spec, *obj_id)
+        );
+    }
+    if let Some(handoff) = &report.handoff {
+        if handoff.untypeds > handoff.slots {
+            eprintln!("error: too few slots for the untypeds handed off");
+        }
+    }
+
+    Ok(if report.fits() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+fn describe(spec: &InputSpec, obj_id: ObjectId) -> String {
+    match spec.objects.get(obj_id) {
+        Some(named_obj) => format!("object {obj_id} {:?}", named_obj.name),
+        None => format!("object {obj_id}"),
+    }
+}
@@ -0,0 +1,574 @@
+//
+// Copyright 2023, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+// Simulates the allocation algorithm of `sel4_capdl_initializer_core::Initializer::create_objects`,
+// which must be kept in sync with this module.
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::ops::Range;
+
+use serde::Deserialize;
+
+use sel4_capdl_initializer_types::builder::ObjectSizes;
+use sel4_capdl_initializer_types::{
+    object, FrameInit, Object, ObjectId, PageTableEntry, Rights, Spec,
+};
+
+const WORD_SIZE: usize = usize::BITS as usize;
+
+// Slots in which the initializer holds dummy untypeds while allocating objects with paddrs.
+const NUM_HOLD_SLOTS: usize = 2;
+
+// `seL4_MinUntypedBits`. Smaller free blocks are not handed off.
+const MIN_UNTYPED_SIZE_BITS: usize = 4;
+
+/// An entry of `BootInfo::untyped_list`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Untyped {
+    pub paddr: usize,
+    pub size_bits: usize,
+    pub is_device: bool,
+}
+
+impl Untyped {
+    fn end(&self) -> Option<usize> {
+        u32::try_from(self.size_bits)
+            .ok()
+            .and_then(|bits| 1usize.checked_shl(bits))
+            .and_then(|size| self.paddr.checked_add(size))
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum SimulateError {
+    MissingObjectSize {
+        object: ObjectId,
+        key: &'static str,
+    },
+    /// The object is too large to be allocated from any untyped.
+    ObjectTooLarge {
+        object: ObjectId,
+    },
+    /// The entry of the untyped list with this index extends past the end of the address space.
+    InvalidUntyped {
+        index: usize,
+    },
+}
+
+impl fmt::Display for SimulateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::MissingObjectSize { object, key } => write!(
+                f,
+                "the size of object {object} is unknown, because the object sizes have no entry for {key:?}"
+            ),
+            Self::ObjectTooLarge { object } => write!(f, "object {object} is too large"),
+            Self::InvalidUntyped { index } => write!(f, "untyped {index} is invalid"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    /// Indexed like the untyped list.
+    pub usage: Vec<UntypedUsage>,
+    /// Root objects without paddrs for which there was no room.
+    pub unallocated: Vec<ObjectId>,
+    /// Objects with paddrs which do not lie within a single untyped.
+    pub unreachable: Vec<ObjectId>,
+    /// Untyped objects whose covers do not fit in them.
+    pub overfull_covers: Vec<ObjectId>,
+    pub cslots: usize,
+    /// Present if the spec has a resource handoff.
+    pub handoff: Option<HandoffUsage>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct HandoffUsage {
+    /// Untypeds which would be handed off.
+    pub untypeds: usize,
+    /// Slots available for them.
+    pub slots: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct UntypedUsage {
+    /// Bytes taken by objects from the spec.
+    pub used: usize,
+    /// Bytes taken by dummy objects allocated to reach objects with paddrs.
+    pub padding: usize,
+    /// Regions left unallocated.
+    pub free: Vec<Range<usize>>,
+}
+
+impl Report {
+    pub fn fits(&self) -> bool {
+        self.unallocated.is_empty()
+            && self.unreachable.is_empty()
+            && self.overfull_covers.is_empty()
+            && self
+                .handoff
+                .iter()
+                .all(|handoff| handoff.untypeds <= handoff.slots)
+    }
+}
+
+impl UntypedUsage {
+    pub fn free_bytes(&self) -> usize {
+        self.free
+            .iter()
+            .map(|region| region.end - region.start)
+            .sum()
+    }
+
+    /// Returns the size of the largest object which could still be allocated.
+    pub fn largest_free_block_bits(&self) -> Option<usize> {
+        self.free
+            .iter()
+            .flat_map(|region| blocks(region.clone()))
+            .max()
+    }
+
+    /// Returns the number of untypeds the initializer would carve from the free regions when
+    /// handing them off.
+    pub fn num_handoff_untypeds(&self) -> usize {
+        self.free
+            .iter()
+            .flat_map(|region| blocks(region.clone()))
+            .filter(|bits| *bits >= MIN_UNTYPED_SIZE_BITS)
+            .count()
+    }
+}
+
+pub fn simulate<N, D, M>(
+    spec: &Spec<N, D, M>,
+    untypeds: &[Untyped],
+    object_sizes: &ObjectSizes,
+) -> Result<Report, SimulateError> {
+    let physical_size_bits =
+        |obj_id: ObjectId| match object_sizes.physical_size_bits(spec.object(obj_id)) {
+            Ok(Some(size_bits)) if size_bits >= WORD_SIZE => {
+                Err(SimulateError::ObjectTooLarge { object: obj_id })
+            }
+            Ok(size_bits) => Ok(size_bits),
+            Err(key) => Err(SimulateError::MissingObjectSize {
+                object: obj_id,
+                key,
+            }),
+        };
+    let is_embedded = |obj_id: ObjectId| {
+        matches!(
+            spec.object(obj_id),
+            Object::Frame(object::Frame {
+                init: FrameInit::Embedded(_),
+                ..
+            })
+        )
+    };
+
+    if let Some(index) = untypeds.iter().position(|ut| ut.end().is_none()) {
+        return Err(SimulateError::InvalidUntyped { index });
+    }
+
+    let mut report = Report {
+        usage: vec![UntypedUsage::default(); untypeds.len()],
+        ..Default::default()
+    };
+
+    let mut uts_by_paddr = (0..untypeds.len()).collect::<Vec<_>>();
+    uts_by_paddr.sort_by_key(|i| untypeds[*i].paddr);
+
+    let num_objs_with_paddr = spec
+        .root_objects()
+        .partition_point(|named_obj| named_obj.object.paddr().is_some());
+
+    let mut by_size_start = [0; WORD_SIZE];
+    let mut by_size_end = [0; WORD_SIZE];
+    {
+        for obj_id in num_objs_with_paddr..spec.root_objects.end {
+            if let Some(size_bits) = physical_size_bits(obj_id)? {
+                by_size_end[size_bits] += 1;
+            }
+        }
+        let mut acc = num_objs_with_paddr;
+        for (bits, n) in by_size_end.iter_mut().enumerate().rev() {
+            by_size_start[bits] = acc;
+            acc += *n;
+            *n = acc;
+        }
+    }
+
+    let mut num_dummies = 0;
+    let mut next_obj_with_paddr = 0;
+    for i_ut in uts_by_paddr.iter() {
+        let ut = &untypeds[*i_ut];
+        let usage = &mut report.usage[*i_ut];
+        let ut_paddr_end = ut.end().unwrap();
+        let mut cur_paddr = ut.paddr;
+        loop {
+            // Objects with paddrs which fall below the current untyped were not reached by any
+            // previous one.
+            while next_obj_with_paddr < num_objs_with_paddr
+                && spec.object(next_obj_with_paddr).paddr().unwrap() < cur_paddr
+            {
+                report.unreachable.push(next_obj_with_paddr);
+                next_obj_with_paddr += 1;
+            }
+            let target = if next_obj_with_paddr < num_objs_with_paddr {
+                ut_paddr_end.min(spec.object(next_obj_with_paddr).paddr().unwrap())
+            } else {
+                ut_paddr_end
+            };
+            let target_is_obj_with_paddr = target < ut_paddr_end;
+            while cur_paddr < target {
+                let max_size_bits = usize::try_from(cur_paddr.trailing_zeros())
+                    .unwrap()
+                    .min((target - cur_paddr).trailing_zeros().try_into().unwrap());
+                let mut created = false;
+                if !ut.is_device {
+                    for size_bits in (0..=max_size_bits.min(WORD_SIZE - 1)).rev() {
+                        let obj_id = &mut by_size_start[size_bits];
+                        while *obj_id < by_size_end[size_bits] && is_embedded(*obj_id) {
+                            *obj_id += 1;
+                        }
+                        if *obj_id < by_size_end[size_bits] {
+                            cur_paddr += 1 << size_bits;
+                            usage.used += 1 << size_bits;
+                            *obj_id += 1;
+                            created = true;
+                            break;
+                        }
+                    }
+                }
+                if !created {
+                    if target_is_obj_with_paddr {
+                        cur_paddr += 1 << max_size_bits;
+                        usage.padding += 1 << max_size_bits;
+                        num_dummies += 1;
+                    } else {
+                        usage.free.push(cur_paddr..target);
+                        cur_paddr = target;
+                    }
+                }
+            }
+            if target_is_obj_with_paddr {
+                let obj_id = next_obj_with_paddr;
+                let size = 1 << physical_size_bits(obj_id)?.unwrap();
+                match cur_paddr.checked_add(size) {
+                    Some(end) if end <= ut_paddr_end => {
+                        cur_paddr = end;
+                        usage.used += size;
+                    }
+                    _ => report.unreachable.push(obj_id),
+                }
+                next_obj_with_paddr += 1;
+            } else {
+                break;
+            }
+        }
+    }
+    report
+        .unreachable
+        .extend(next_obj_with_paddr..num_objs_with_paddr);
+
+    for bits in 0..WORD_SIZE {
+        report.unallocated.extend(
+            (by_size_start[bits]..by_size_end[bits]).filter(|obj_id| !is_embedded(*obj_id)),
+        );
+    }
+    report.unallocated.sort();
+
+    for cover in spec.untyped_covers.iter() {
+        let parent_size = match spec.object(cover.parent) {
+            Object::Untyped(obj) => 1usize << obj.size_bits,
+            _ => panic!(),
+        };
+        let mut watermark = Some(0usize);
+        for child in cover.children.clone() {
+            let size = 1 << physical_size_bits(child)?.unwrap();
+            watermark = watermark
+                .and_then(|watermark| watermark.checked_next_multiple_of(size))
+                .and_then(|start| start.checked_add(size));
+        }
+        if !matches!(watermark, Some(watermark) if watermark <= parent_size) {
+            report.overfull_covers.push(cover.parent);
+        }
+    }
+
+    report.cslots = count_cslots(spec, is_embedded);
+
+    // The dummies still held at the end are handed off too.
+    report.handoff = spec.resource_handoff.as_ref().map(|handoff| HandoffUsage {
+        untypeds: report
+            .usage
+            .iter()
+            .map(UntypedUsage::num_handoff_untypeds)
+            .sum::<usize>()
+            + num_dummies.min(NUM_HOLD_SLOTS),
+        slots: handoff.untyped_slots.len(),
+    });
+
+    Ok(report)
+}
+
+// The initializer never frees CSlots other than its hold slots, so the peak is the total.
+fn count_cslots<N, D, M>(spec: &Spec<N, D, M>, is_embedded: impl Fn(ObjectId) -> bool) -> usize {
+    let mut n = NUM_HOLD_SLOTS;
+
+    // Original caps for objects. IRQ handlers are counted below.
+    n += (0..spec.num_objects())
+        .filter(|obj_id| !is_embedded(*obj_id) && !spec.object(*obj_id).is_irq())
+        .count();
+    n += spec.asid_slots.len();
+    n += spec.irqs.len();
+
+    // Badged notification caps for IRQ handlers.
+    n += spec
+        .objects()
+        .filter_map(|obj| match obj {
+            Object::IRQ(obj) => obj.notification(),
+            Object::ArmIRQ(obj) => obj.notification(),
+            Object::IRQIOAPIC(obj) => obj.notification(),
+            Object::IRQMSI(obj) => obj.notification(),
+            Object::RiscvIRQ(obj) => obj.notification(),
+            _ => None,
+        })
+        .filter(|cap| cap.badge != 0)
+        .count();
+
+    // Copies of frame caps for mappings.
+    let mut visited = BTreeSet::new();
+    let mut stack = spec
+        .filter_objects_with::<&object::PageTable>(|obj| obj.is_root)
+        .map(|(obj_id, _)| obj_id)
+        .collect::<Vec<_>>();
+    while let Some(obj_id) = stack.pop() {
+        if !visited.insert(obj_id) {
+            continue;
+        }
+        let obj = spec.lookup_object::<&object::PageTable>(obj_id).unwrap();
+        for (_, entry) in obj.entries() {
+            match entry {
+                PageTableEntry::Frame(_) => n += 1,
+                PageTableEntry::PageTable(cap) => stack.push(cap.object),
+            }
+        }
+    }
+
+    // Minted fault and timeout endpoint caps, on MCS kernels.
+    n += spec
+        .filter_objects::<&object::TCB>()
+        .flat_map(|(_, obj)| [obj.fault_ep().or(obj.temp_fault_ep()), obj.temp_fault_ep()])
+        .flatten()
+        .filter(|cap| cap.badge != 0 && cap.rights != Rights::all())
+        .count();
+
+    n
+}
+
+// Decomposes a region into the naturally aligned blocks the initializer would allocate from it.
+fn blocks(region: Range<usize>) -> impl Iterator<Item = usize> {
+    let mut cur = region.start;
+    std::iter::from_fn(move || {
+        if cur >= region.end {
+            return None;
+        }
+        let bits = usize::try_from(cur.trailing_zeros())
+            .unwrap()
+            .min((region.end - cur).ilog2().try_into().unwrap());
+        cur += 1 << bits;
+        Some(bits)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use sel4_capdl_initializer_types::builder::{BuilderSpec, SpecBuilder};
+    use sel4_capdl_initializer_types::object::TCBExtraInfo;
+    use sel4_capdl_initializer_types::{Indirect, ResourceHandoff};
+
+    use super::*;
+
+    fn object_sizes() -> ObjectSizes {
+        [
+            ("seL4_EndpointObject", 4),
+            ("seL4_TCBObject", 11),
+            ("seL4_Slot", 5),
+        ]
+        .into_iter()
+        .collect()
+    }
+
+    fn ut(paddr: usize, size_bits: usize, is_device: bool) -> Untyped {
+        Untyped {
+            paddr,
+            size_bits,
+            is_device,
+        }
+    }
+
+    fn tcb_extra() -> TCBExtraInfo<'static> {
+        TCBExtraInfo {
+            ipc_buffer_addr: 0,
+            affinity: 0,
+            prio: 0,
+            max_prio: 0,
+            domain: None,
+            resume: true,
+            ip: 0,
+            sp: 0,
+            spsr: 0,
+            gprs: Indirect::from_owned(Vec::new().into_boxed_slice()),
+            master_fault_ep: None,
+        }
+    }
+
+    fn build(f: impl FnOnce(&mut SpecBuilder)) -> BuilderSpec {
+        let mut builder = SpecBuilder::new();
+        f(&mut builder);
+        builder.build(&object_sizes()).unwrap()
+    }
+
+    #[test]
+    fn by_size() {
+        let spec = build(|builder| {
+            builder.endpoint("ep");
+            builder.tcb("tcb", tcb_extra());
+        });
+        let report = simulate(&spec, &[ut(0, 16, false)], &object_sizes()).unwrap();
+        assert!(report.fits());
+        let usage = &report.usage[0];
+        assert_eq!(usage.used, 0x810);
+        assert_eq!(usage.padding, 0);
+        assert_eq!(usage.free, vec![0x810..0x1_0000]);
+        assert_eq!(usage.free_bytes(), 0xf7f0);
+        assert_eq!(usage.largest_free_block_bits(), Some(15));
+        assert_eq!(report.cslots, NUM_HOLD_SLOTS + 2);
+    }
+
+    #[test]
+    fn with_paddrs() {
+        let spec = build(|builder| {
+            builder.frame("frame", 12, Some(0x1_2000), Vec::new());
+            builder.frame("lost", 12, Some(0x10_0000), Vec::new());
+            builder.endpoint("ep");
+        });
+        let untypeds = [ut(0x1_0000, 16, true), ut(0x2_0000, 12, false)];
+        let report = simulate(&spec, &untypeds, &object_sizes()).unwrap();
+        assert!(!report.fits());
+        assert_eq!(report.unreachable, [1]);
+        assert!(report.unallocated.is_empty());
+
+        // Device memory is only used for objects with paddrs.
+        let device = &report.usage[0];
+        assert_eq!(device.padding, 0x2000);
+        assert_eq!(device.used, 0x1000);
+        assert_eq!(device.free, vec![0x1_3000..0x2_0000]);
+
+        let ram = &report.usage[1];
+        assert_eq!(ram.used, 0x10);
+        assert_eq!(ram.free, vec![0x2_0010..0x2_1000]);
+    }
+
+    #[test]
+    fn unallocated() {
+        let spec = build(|builder| {
+            builder.untyped("big", 20, None);
+            builder.endpoint("ep");
+        });
+        let report = simulate(&spec, &[ut(0, 16, false)], &object_sizes()).unwrap();
+        assert_eq!(report.unallocated, [0]);
+        assert!(!report.fits());
+    }
+
+    #[test]
+    fn overfull_covers() {
+        let spec = build(|builder| {
+            let ut = builder.untyped("ut", 11, None);
+            let tcb = builder.tcb("tcb", tcb_extra());
+            let ep = builder.endpoint("ep");
+            builder.cover(ut, ep);
+            builder.cover(ut, tcb);
+        });
+        let report = simulate(&spec, &[ut(0, 16, false)], &object_sizes()).unwrap();
+        assert_eq!(report.overfull_covers, [0]);
+    }
+
+    #[test]
+    fn errors() {
+        let spec = build(|builder| {
+            builder.endpoint("ep");
+        });
+        assert_eq!(
+            simulate(
+                &spec,
+                &[ut(0, 16, false), ut(0, 64, false)],
+                &object_sizes()
+            )
+            .unwrap_err(),
+            SimulateError::InvalidUntyped { index: 1 }
+        );
+        assert_eq!(
+            simulate(&spec, &[ut(usize::MAX & !0xf, 12, false)], &object_sizes()).unwrap_err(),
+            SimulateError::InvalidUntyped { index: 0 }
+        );
+
+        let spec = build(|builder| {
+            builder.cnode("cnode", WORD_SIZE - 2);
+        });
+        assert_eq!(
+            simulate(&spec, &[], &object_sizes()).unwrap_err(),
+            SimulateError::ObjectTooLarge { object: 0 }
+        );
+        assert_eq!(
+            simulate(&spec, &[], &ObjectSizes::new()).unwrap_err(),
+            SimulateError::MissingObjectSize {
+                object: 0,
+                key: "seL4_Slot"
+            }
+        );
+    }
+
+    #[test]
+    fn handoff() {
+        let handoff = |untyped_slots| {
+            build(|builder| {
+                let cnode = builder.cnode("cnode", 3);
+                builder.frame("frame", 12, Some(0x1_3000), Vec::new());
+                builder.set_resource_handoff(ResourceHandoff {
+                    cnode: cnode.id(),
+                    untyped_slots,
+                    info_frame: None,
+                    irq_control_slot: None,
+                    asid_control_slot: None,
+                    init_cnode_slot: None,
+                });
+            })
+        };
+        // Three dummies are allocated below the frame, of which the last two are still held.
+        let untypeds = [ut(0x1_0000, 16, true), ut(0x2_0000, 12, false)];
+
+        let report = simulate(&handoff(0..8), &untypeds, &object_sizes()).unwrap();
+        assert_eq!(report.usage[0].padding, 0x3000);
+        assert_eq!(report.usage[0].free, vec![0x1_4000..0x2_0000]);
+        assert_eq!(report.usage[1].free, vec![0x2_0100..0x2_1000]);
+        let usage = report.handoff.as_ref().unwrap();
+        assert_eq!(usage.untypeds, 2 + 4 + 2);
+        assert_eq!(usage.slots, 8);
+        assert!(report.fits());
+
+        let report = simulate(&handoff(0..7), &untypeds, &object_sizes()).unwrap();
+        assert!(!report.fits());
+    }
+
+    #[test]
+    fn blocks_are_naturally_aligned() {
+        assert_eq!(blocks(0x10..0x40).collect::<Vec<_>>(), [4, 5]);
+        assert_eq!(blocks(0x8..0x38).collect::<Vec<_>>(), [3, 4, 4, 3]);
+        assert_eq!(blocks(0x0..0x0).count(), 0);
+    }
+}
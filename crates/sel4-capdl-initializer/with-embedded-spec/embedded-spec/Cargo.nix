@@ -9,7 +9,7 @@
 mk {
   package.name = "sel4-capdl-initializer-with-embedded-spec-embedded-spec";
   dependencies = {
-    sel4-capdl-initializer-types = localCrates.sel4-capdl-initializer-types // { features = [ "borrowed-indirect" ]; };
+    sel4-capdl-initializer-types = localCrates.sel4-capdl-initializer-types // { features = [ "borrowed-indirect" "digest" ]; };
   };
   build-dependencies = {
     inherit (versions) serde_json;
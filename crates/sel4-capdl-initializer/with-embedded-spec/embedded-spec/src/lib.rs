@@ -11,4 +11,4 @@ mod gen {
     include!(concat!(env!("OUT_DIR"), "/spec.rs"));
 }
 
-pub use gen::SPEC;
+pub use gen::{SPEC, SPEC_DIGEST};
@@ -18,6 +18,14 @@ pub fn run(tell_cargo: bool) {
         footprint.tell_cargo();
     }
 
+    let errs = embedding.spec.check();
+    if !errs.is_empty() {
+        for err in errs.iter() {
+            eprintln!("error: {err}");
+        }
+        panic!("invalid spec");
+    }
+
     let embedded = &sel4_capdl_initializer_with_embedded_spec_embedded_spec::SPEC;
     let adapted_embedded: SpecCommon = embedded
         .traverse_names_with_context(|named_obj| {
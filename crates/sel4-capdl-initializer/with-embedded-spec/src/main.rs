@@ -12,7 +12,7 @@ use core::ops::Range;
 use sel4::BootInfo;
 use sel4_capdl_initializer_core::{Initializer, InitializerBuffers, PerObjectBuffer};
 use sel4_capdl_initializer_types::SpecWithSources;
-use sel4_capdl_initializer_with_embedded_spec_embedded_spec::SPEC;
+use sel4_capdl_initializer_with_embedded_spec_embedded_spec::{SPEC, SPEC_DIGEST};
 use sel4_logging::{LevelFilter, Logger, LoggerBuilder};
 
 const LOG_LEVEL: LevelFilter =
@@ -39,9 +39,13 @@ fn main(bootinfo: &BootInfo) -> ! {
         content_source: &trivial_source,
         embedded_frame_source: &trivial_source,
     };
-    Initializer::initialize(bootinfo, user_image_bounds(), &spec_with_sources, unsafe {
-        &mut BUFFERS
-    })
+    Initializer::initialize(
+        bootinfo,
+        user_image_bounds(),
+        &spec_with_sources,
+        &SPEC_DIGEST,
+        unsafe { &mut BUFFERS },
+    )
 }
 
 extern "C" {
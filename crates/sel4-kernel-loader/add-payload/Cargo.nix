@@ -18,6 +18,7 @@ mk {
       num
       clap
     ;
+    miniz_oxide = "0.6.2";
     object = { version = versions.object; features = [ "all" ]; };
     postcard = postcardWith [ "alloc" ];
     serde = serdeWith [ "alloc" "derive" ];
@@ -15,7 +15,10 @@ pub struct Args {
     pub platform_info_path: String,
     pub loader_path: String,
     pub app_path: String,
+    pub initrd_path: Option<String>,
+    pub bootargs: Option<String>,
     pub out_file_path: String,
+    pub deflate: bool,
     pub verbose: bool,
 }
 
@@ -59,12 +62,29 @@ impl Args {
                     .required(true),
             )
             .arg(Arg::new("app").long("app").value_name("APP").required(true))
+            .arg(
+                Arg::new("initrd")
+                    .long("initrd")
+                    .value_name("INITRD")
+                    .required(false),
+            )
+            .arg(
+                Arg::new("bootargs")
+                    .long("bootargs")
+                    .value_name("BOOTARGS")
+                    .required(false),
+            )
             .arg(
                 Arg::new("out_file")
                     .short('o')
                     .value_name("OUT_FILE")
                     .required(true),
             )
+            .arg(
+                Arg::new("deflate")
+                    .long("deflate")
+                    .action(ArgAction::SetTrue),
+            )
             .arg(Arg::new("verbose").short('v').action(ArgAction::SetTrue))
             .get_matches();
 
@@ -99,8 +119,14 @@ impl Args {
 
         let app_path = matches.get_one::<String>("app").unwrap().to_owned();
 
+        let initrd_path = matches.get_one::<String>("initrd").map(ToOwned::to_owned);
+
+        let bootargs = matches.get_one::<String>("bootargs").map(ToOwned::to_owned);
+
         let out_file_path = matches.get_one::<String>("out_file").unwrap().to_owned();
 
+        let deflate = *matches.get_one::<bool>("deflate").unwrap();
+
         let verbose = *matches.get_one::<bool>("verbose").unwrap();
 
         Ok(Self {
@@ -110,7 +136,10 @@ impl Args {
             platform_info_path,
             loader_path,
             app_path,
+            initrd_path,
+            bootargs,
             out_file_path,
+            deflate,
             verbose,
         })
     }
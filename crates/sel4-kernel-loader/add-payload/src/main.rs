@@ -56,7 +56,10 @@ where
         &args.kernel_path,
         &args.app_path,
         &args.dtb_path,
+        args.initrd_path.as_ref(),
+        args.bootargs.as_deref(),
         &args.platform_info_path,
+        args.deflate,
     );
 
     let loader_with_payload_bytes = render_elf::render_elf::<T>(&loader_bytes, &serialized_payload);
@@ -8,7 +8,7 @@ use std::fs::{self, File};
 use std::ops::Range;
 use std::path::Path;
 
-use heapless::Vec as HeaplessVec;
+use heapless::{String as HeaplessString, Vec as HeaplessVec};
 use num::{traits::WrappingSub, CheckedAdd, CheckedSub, Integer, NumCast, One, PrimInt};
 use object::{
     elf::PT_LOAD,
@@ -34,7 +34,10 @@ pub fn serialize_payload<T>(
     kernel_path: impl AsRef<Path>,
     app_path: impl AsRef<Path>,
     dtb_path: impl AsRef<Path>,
+    initrd_path: Option<impl AsRef<Path>>,
+    bootargs: Option<&str>,
     platform_info_path: impl AsRef<Path>,
+    deflate: bool,
 ) -> Vec<u8>
 where
     T: FileHeader<Endian = Endianness>,
@@ -43,7 +46,7 @@ where
     let platform_info: PlatformInfoForBuildSystem =
         serde_yaml::from_reader(fs::File::open(&platform_info_path).unwrap()).unwrap();
 
-    let mut builder = Builder::<T>::new();
+    let mut builder = Builder::<T>::new(deflate);
 
     let kernel_image = with_elf(&kernel_path, |elf| {
         builder.add_image(elf, elf_phys_to_vaddr_offset(elf))
@@ -71,11 +74,29 @@ where
             .next_multiple_of(&(T::Word::one() << PAGE_SIZE_BITS));
     let fdt_phys_addr_range = builder.add_region(fdt_paddr, fdt_content);
 
+    let initrd_phys_addr_range = initrd_path.map(|initrd_path| {
+        let initrd_content = fs::read(initrd_path).unwrap();
+        let initrd_paddr = fdt_phys_addr_range.start
+            - <T::Word as NumCast>::from(initrd_content.len())
+                .unwrap()
+                .next_multiple_of(&(T::Word::one() << PAGE_SIZE_BITS));
+        builder.add_region(initrd_paddr, initrd_content)
+    });
+
+    let bootargs = bootargs.map(|bootargs| {
+        let mut s = HeaplessString::new();
+        s.push_str(bootargs)
+            .unwrap_or_else(|_| panic!("bootargs longer than {MAX_BOOTARGS_LEN} bytes"));
+        s
+    });
+
     let payload = Payload {
         info: PayloadInfo {
             kernel_image,
             user_image,
             fdt_phys_addr_range: Some(fdt_phys_addr_range),
+            initrd_phys_addr_range,
+            bootargs,
         },
         data: builder.regions,
     };
@@ -90,6 +111,7 @@ where
 struct Builder<T: FileHeader> {
     regions: HeaplessVec<Region<T::Word, IndirectRegionContent<T::Word>>, DEFAULT_MAX_NUM_REGIONS>,
     actual_content: Vec<u8>,
+    deflate: bool,
 }
 
 impl<T> Builder<T>
@@ -97,10 +119,11 @@ where
     T: FileHeader<Endian = Endianness>,
     T::Word: PrimInt + WrappingSub + Integer,
 {
-    fn new() -> Self {
+    fn new(deflate: bool) -> Self {
         Self {
             regions: HeaplessVec::new(),
             actual_content: vec![],
+            deflate,
         }
     }
 
@@ -141,20 +164,34 @@ where
     fn add_region(&mut self, phys_addr_start: T::Word, content: Vec<u8>) -> Range<T::Word> {
         let phys_addr_range =
             phys_addr_start..(phys_addr_start + NumCast::from(content.len()).unwrap());
+        let deflated = self
+            .deflate
+            .then(|| miniz_oxide::deflate::compress_to_vec(&content, 10));
+        let (stored, compression) = match deflated {
+            // Only deflate regions for which that saves space.
+            Some(deflated) if deflated.len() < content.len() => (
+                deflated,
+                Some(Compression::Deflate {
+                    decompressed_len: NumCast::from(content.len()).unwrap(),
+                }),
+            ),
+            _ => (content, None),
+        };
         self.regions
             .push(Region {
                 phys_addr_range: phys_addr_range.clone(),
                 content: Some(IndirectRegionContent {
                     content_range: {
                         let start = self.actual_content.len();
-                        let end = start + content.len();
+                        let end = start + stored.len();
                         NumCast::from(start).unwrap()..NumCast::from(end).unwrap()
                     },
+                    compression,
                 }),
             })
             .ok()
             .unwrap();
-        self.actual_content.extend(content);
+        self.actual_content.extend(stored);
         phys_addr_range
     }
 
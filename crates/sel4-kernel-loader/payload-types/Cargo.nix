@@ -11,7 +11,11 @@ mk {
   dependencies = {
     serde = serdeWith [ "derive" ] // { optional = true; };
     heapless = { version = versions.heapless; features = [ "serde" ]; };
+    miniz_oxide = { version = "0.6.2"; default-features = false; };
     num-traits = { version = versions.num-traits; default-features = false; };
     inherit (localCrates) sel4-platform-info-types;
   };
+  dev-dependencies = {
+    miniz_oxide = "0.6.2";
+  };
 }
@@ -0,0 +1,934 @@
+//
+// Copyright 2023, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+// Just enough of the flattened device tree format to patch a few nodes while copying a blob to a
+// new location, without allocating.
+
+use core::ops::Range;
+
+const FDT_MAGIC: u32 = 0xd00d_feed;
+
+const FDT_BEGIN_NODE: u32 = 0x1;
+const FDT_END_NODE: u32 = 0x2;
+const FDT_PROP: u32 = 0x3;
+const FDT_NOP: u32 = 0x4;
+const FDT_END: u32 = 0x9;
+
+pub const HEADER_SIZE: usize = 40;
+
+const VERSION: u32 = 17;
+const LAST_COMP_VERSION: u32 = 16;
+
+const DEFAULT_ADDRESS_CELLS: u32 = 2;
+const DEFAULT_SIZE_CELLS: u32 = 1;
+
+const CHOSEN: &str = "chosen";
+const RESERVED_MEMORY: &str = "reserved-memory";
+
+// Property names which the patched blob may need in its strings block.
+const BOOTARGS: usize = 0;
+const INITRD_START: usize = 1;
+const INITRD_END: usize = 2;
+const ADDRESS_CELLS: usize = 3;
+const SIZE_CELLS: usize = 4;
+const RANGES: usize = 5;
+const REG: usize = 6;
+const NO_MAP: usize = 7;
+
+const PROP_NAMES: [&str; 8] = [
+    "bootargs",
+    "linux,initrd-start",
+    "linux,initrd-end",
+    "#address-cells",
+    "#size-cells",
+    "ranges",
+    "reg",
+    "no-map",
+];
+
+/// Changes to make to a blob while copying it.
+pub struct Patch<'a> {
+    /// Replaces `/chosen/bootargs`.
+    pub bootargs: Option<&'a str>,
+    /// Replaces `/chosen/linux,initrd-start` and `/chosen/linux,initrd-end`.
+    pub initrd: Option<Range<usize>>,
+    /// Nodes to add to `/reserved-memory`, by name, with `no-map`.
+    pub reserved_memory: &'a [(&'a str, Range<usize>)],
+}
+
+#[derive(Clone, Copy)]
+pub struct Fdt<'a> {
+    blob: &'a [u8],
+}
+
+impl<'a> Fdt<'a> {
+    /// Returns `None` if `blob` does not begin with a supported flattened device tree.
+    pub fn new(blob: &'a [u8]) -> Option<Self> {
+        let fdt = Self {
+            blob: blob.get(..Self::size_from_header(blob.get(..HEADER_SIZE)?)?)?,
+        };
+        if fdt.field(20)? < VERSION || fdt.field(24)? > VERSION {
+            return None;
+        }
+        fdt.mem_rsvmap()?;
+        fdt.dt_struct()?;
+        fdt.dt_strings()?;
+        Some(fdt)
+    }
+
+    /// Returns the size of the blob which begins with `header`, if `header` is long enough and
+    /// begins with the magic number.
+    pub fn size_from_header(header: &[u8]) -> Option<usize> {
+        if read_u32(header, 0)? != FDT_MAGIC {
+            return None;
+        }
+        Some(read_u32(header, 4)? as usize)
+    }
+
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.blob
+    }
+
+    /// The size of the blob [`write_patched`](Self::write_patched) would write, or `None` if this
+    /// blob is malformed or the patch cannot be expressed in it.
+    pub fn patched_size(&self, patch: &Patch) -> Option<usize> {
+        let mut writer = Writer { buf: None, pos: 0 };
+        self.rewrite(patch, &mut writer)?;
+        Some(writer.pos)
+    }
+
+    /// Writes a copy of this blob with `patch` applied to `dst`, which must be at least
+    /// [`patched_size`](Self::patched_size) bytes long.
+    pub fn write_patched(&self, patch: &Patch, dst: &mut [u8]) -> Option<usize> {
+        let mut writer = Writer {
+            buf: Some(dst),
+            pos: 0,
+        };
+        self.rewrite(patch, &mut writer)?;
+        Some(writer.pos)
+    }
+
+    fn field(&self, offset: usize) -> Option<u32> {
+        read_u32(self.blob, offset)
+    }
+
+    fn block(&self, offset_field: usize, size_field: usize) -> Option<&'a [u8]> {
+        let offset = self.field(offset_field)? as usize;
+        let size = self.field(size_field)? as usize;
+        self.blob.get(offset..offset.checked_add(size)?)
+    }
+
+    fn dt_struct(&self) -> Option<&'a [u8]> {
+        self.block(8, 36)
+    }
+
+    fn dt_strings(&self) -> Option<&'a [u8]> {
+        self.block(12, 32)
+    }
+
+    fn mem_rsvmap(&self) -> Option<&'a [u8]> {
+        let start = self.field(16)? as usize;
+        let mut end = start;
+        loop {
+            let entry = self.blob.get(end..end.checked_add(16)?)?;
+            end += 16;
+            if entry.iter().all(|b| *b == 0) {
+                return self.blob.get(start..end);
+            }
+        }
+    }
+
+    fn string(&self, offset: usize) -> Option<&'a [u8]> {
+        let tail = self.dt_strings()?.get(offset..)?;
+        Some(&tail[..tail.iter().position(|b| *b == 0)?])
+    }
+
+    fn rewrite(&self, patch: &Patch, w: &mut Writer) -> Option<()> {
+        let dt_struct = self.dt_struct()?;
+        let dt_strings = self.dt_strings()?;
+
+        // Reuse existing strings where possible, and append the rest.
+        let mut name_offsets = [0; PROP_NAMES.len()];
+        let mut name_is_new = [false; PROP_NAMES.len()];
+        let mut new_strings_size = 0;
+        for (i, name) in PROP_NAMES.iter().enumerate() {
+            name_offsets[i] = match find_string(dt_strings, name) {
+                Some(offset) => offset,
+                None => {
+                    name_is_new[i] = true;
+                    let offset = dt_strings.len() + new_strings_size;
+                    new_strings_size += name.len() + 1;
+                    offset
+                }
+            };
+        }
+        let names = Names {
+            offsets: name_offsets,
+        };
+
+        w.zeros(HEADER_SIZE);
+
+        let off_mem_rsvmap = w.pos;
+        w.bytes(self.mem_rsvmap()?);
+
+        let off_dt_struct = w.pos;
+        let mut emitter = Emitter {
+            w,
+            names: &names,
+            patch,
+        };
+        let mut depth = 0;
+        let mut current = Node::Other;
+        let mut seen_chosen = false;
+        // Properties must precede subnodes, so new properties of /chosen are emitted before its first
+        // subnode, if it has any.
+        let mut chosen_props_pending = false;
+        let mut seen_reserved_memory = false;
+        let mut root_cells = (DEFAULT_ADDRESS_CELLS, DEFAULT_SIZE_CELLS);
+        // #address-cells and #size-cells are not inherited.
+        let mut reserved_memory_cells = (DEFAULT_ADDRESS_CELLS, DEFAULT_SIZE_CELLS);
+        let mut pos = 0;
+        loop {
+            let token = read_u32(dt_struct, pos)?;
+            let token_start = pos;
+            pos += 4;
+            match token {
+                FDT_BEGIN_NODE => {
+                    let name_len = dt_struct.get(pos..)?.iter().position(|b| *b == 0)?;
+                    let name = &dt_struct[pos..pos + name_len];
+                    pos = align_up(pos + name_len + 1, 4)?;
+                    if depth == 2 && chosen_props_pending {
+                        emitter.chosen_props(root_cells.0)?;
+                        chosen_props_pending = false;
+                    }
+                    depth += 1;
+                    if depth == 2 {
+                        current = if name == CHOSEN.as_bytes() {
+                            seen_chosen = true;
+                            chosen_props_pending = true;
+                            Node::Chosen
+                        } else if name == RESERVED_MEMORY.as_bytes() {
+                            seen_reserved_memory = true;
+                            Node::ReservedMemory
+                        } else {
+                            Node::Other
+                        };
+                    }
+                    emitter.w.bytes(dt_struct.get(token_start..pos)?);
+                }
+                FDT_END_NODE => {
+                    match (depth, current) {
+                        (0, _) => return None,
+                        (1, _) => {
+                            if !seen_chosen {
+                                emitter.chosen_node(root_cells.0)?;
+                            }
+                            if !seen_reserved_memory {
+                                emitter.reserved_memory_node(root_cells)?;
+                            }
+                        }
+                        (2, Node::Chosen) if chosen_props_pending => {
+                            emitter.chosen_props(root_cells.0)?;
+                            chosen_props_pending = false;
+                        }
+                        (2, Node::ReservedMemory) => {
+                            emitter.reserved_memory_children(reserved_memory_cells)?;
+                        }
+                        _ => {}
+                    }
+                    depth -= 1;
+                    emitter.w.u32(FDT_END_NODE);
+                }
+                FDT_PROP => {
+                    let len = read_u32(dt_struct, pos)? as usize;
+                    let name = self.string(read_u32(dt_struct, pos + 4)? as usize)?;
+                    let value_end = (pos + 8).checked_add(len)?;
+                    let value = dt_struct.get(pos + 8..value_end)?;
+                    pos = align_up(value_end, 4)?;
+                    let is_name = |i: usize| name == PROP_NAMES[i].as_bytes();
+                    let cells = || read_u32(value, 0).filter(|_| len == 4);
+                    match (depth, current) {
+                        (1, _) => {
+                            if is_name(ADDRESS_CELLS) {
+                                root_cells.0 = cells()?;
+                            } else if is_name(SIZE_CELLS) {
+                                root_cells.1 = cells()?;
+                            }
+                        }
+                        (2, Node::ReservedMemory) => {
+                            if is_name(ADDRESS_CELLS) {
+                                reserved_memory_cells.0 = cells()?;
+                            } else if is_name(SIZE_CELLS) {
+                                reserved_memory_cells.1 = cells()?;
+                            }
+                        }
+                        (2, Node::Chosen) => {
+                            let replaced = (patch.bootargs.is_some() && is_name(BOOTARGS))
+                                || (patch.initrd.is_some()
+                                    && (is_name(INITRD_START) || is_name(INITRD_END)));
+                            if replaced {
+                                continue;
+                            }
+                        }
+                        _ => {}
+                    }
+                    emitter.w.bytes(dt_struct.get(token_start..pos)?);
+                }
+                FDT_NOP => {}
+                FDT_END => {
+                    if depth != 0 {
+                        return None;
+                    }
+                    emitter.w.u32(FDT_END);
+                    break;
+                }
+                _ => return None,
+            }
+        }
+        let w = emitter.w;
+        let size_dt_struct = w.pos - off_dt_struct;
+
+        let off_dt_strings = w.pos;
+        w.bytes(dt_strings);
+        for (name, is_new) in PROP_NAMES.iter().zip(name_is_new) {
+            if is_new {
+                w.bytes(name.as_bytes());
+                w.zeros(1);
+            }
+        }
+        let size_dt_strings = w.pos - off_dt_strings;
+
+        let total_size = w.pos;
+        let header: [(usize, usize); 10] = [
+            (0, FDT_MAGIC as usize),
+            (4, total_size),
+            (8, off_dt_struct),
+            (12, off_dt_strings),
+            (16, off_mem_rsvmap),
+            (20, VERSION as usize),
+            (24, LAST_COMP_VERSION as usize),
+            (28, self.field(28)? as usize),
+            (32, size_dt_strings),
+            (36, size_dt_struct),
+        ];
+        for (offset, value) in header {
+            w.set_u32(offset, value.try_into().ok()?);
+        }
+        Some(())
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Node {
+    Chosen,
+    ReservedMemory,
+    Other,
+}
+
+struct Names {
+    offsets: [usize; PROP_NAMES.len()],
+}
+
+struct Emitter<'a, 'b, 'c> {
+    w: &'a mut Writer<'b>,
+    names: &'a Names,
+    patch: &'a Patch<'c>,
+}
+
+impl<'a, 'b, 'c> Emitter<'a, 'b, 'c> {
+    fn chosen_node(&mut self, address_cells: u32) -> Option<()> {
+        if self.patch.bootargs.is_some() || self.patch.initrd.is_some() {
+            self.begin_node(&[CHOSEN.as_bytes()]);
+            self.chosen_props(address_cells)?;
+            self.w.u32(FDT_END_NODE);
+        }
+        Some(())
+    }
+
+    fn chosen_props(&mut self, address_cells: u32) -> Option<()> {
+        if let Some(bootargs) = self.patch.bootargs {
+            self.prop(BOOTARGS, bootargs.len() + 1, |w| {
+                w.bytes(bootargs.as_bytes());
+                w.zeros(1);
+                Some(())
+            })?;
+        }
+        if let Some(initrd) = &self.patch.initrd {
+            for (name, addr) in [(INITRD_START, initrd.start), (INITRD_END, initrd.end)] {
+                self.prop(name, cells_len(address_cells), |w| {
+                    w.cells(addr, address_cells)
+                })?;
+            }
+        }
+        Some(())
+    }
+
+    fn reserved_memory_node(&mut self, cells: (u32, u32)) -> Option<()> {
+        if !self.patch.reserved_memory.is_empty() {
+            self.begin_node(&[RESERVED_MEMORY.as_bytes()]);
+            self.prop(ADDRESS_CELLS, 4, |w| {
+                w.u32(cells.0);
+                Some(())
+            })?;
+            self.prop(SIZE_CELLS, 4, |w| {
+                w.u32(cells.1);
+                Some(())
+            })?;
+            self.prop(RANGES, 0, |_| Some(()))?;
+            self.reserved_memory_children(cells)?;
+            self.w.u32(FDT_END_NODE);
+        }
+        Some(())
+    }
+
+    fn reserved_memory_children(&mut self, cells: (u32, u32)) -> Option<()> {
+        for (name, range) in self.patch.reserved_memory {
+            let mut unit_address = [0; 2 * core::mem::size_of::<usize>()];
+            self.begin_node(&[
+                name.as_bytes(),
+                b"@",
+                format_hex(range.start, &mut unit_address),
+            ]);
+            self.prop(REG, cells_len(cells.0) + cells_len(cells.1), |w| {
+                w.cells(range.start, cells.0)?;
+                w.cells(range.len(), cells.1)
+            })?;
+            self.prop(NO_MAP, 0, |_| Some(()))?;
+            self.w.u32(FDT_END_NODE);
+        }
+        Some(())
+    }
+
+    fn begin_node(&mut self, name_parts: &[&[u8]]) {
+        self.w.u32(FDT_BEGIN_NODE);
+        for part in name_parts {
+            self.w.bytes(part);
+        }
+        self.w.zeros(1);
+        self.w.align(4);
+    }
+
+    fn prop(
+        &mut self,
+        name: usize,
+        len: usize,
+        f: impl FnOnce(&mut Writer) -> Option<()>,
+    ) -> Option<()> {
+        self.w.u32(FDT_PROP);
+        self.w.u32(len.try_into().ok()?);
+        self.w.u32(self.names.offsets[name].try_into().ok()?);
+        f(self.w)?;
+        self.w.align(4);
+        Some(())
+    }
+}
+
+struct Writer<'a> {
+    buf: Option<&'a mut [u8]>,
+    pos: usize,
+}
+
+impl<'a> Writer<'a> {
+    fn bytes(&mut self, bytes: &[u8]) {
+        if let Some(buf) = &mut self.buf {
+            buf[self.pos..][..bytes.len()].copy_from_slice(bytes);
+        }
+        self.pos += bytes.len();
+    }
+
+    fn zeros(&mut self, n: usize) {
+        if let Some(buf) = &mut self.buf {
+            buf[self.pos..][..n].fill(0);
+        }
+        self.pos += n;
+    }
+
+    fn align(&mut self, align: usize) {
+        self.zeros(self.pos.next_multiple_of(align) - self.pos);
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.bytes(&value.to_be_bytes());
+    }
+
+    fn set_u32(&mut self, offset: usize, value: u32) {
+        if let Some(buf) = &mut self.buf {
+            buf[offset..][..4].copy_from_slice(&value.to_be_bytes());
+        }
+    }
+
+    fn cells(&mut self, value: usize, num_cells: u32) -> Option<()> {
+        let value = u64::try_from(value).ok()?;
+        match num_cells {
+            1 => self.u32(value.try_into().ok()?),
+            2..=4 => {
+                self.zeros(cells_len(num_cells - 2));
+                self.bytes(&value.to_be_bytes());
+            }
+            _ => return None,
+        }
+        Some(())
+    }
+}
+
+fn cells_len(num_cells: u32) -> usize {
+    num_cells as usize * 4
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(
+        bytes
+            .get(offset..offset.checked_add(4)?)?
+            .try_into()
+            .unwrap(),
+    ))
+}
+
+fn find_string(strings: &[u8], name: &str) -> Option<usize> {
+    let needle = name.as_bytes();
+    strings
+        .windows(needle.len() + 1)
+        .position(|window| &window[..needle.len()] == needle && window[needle.len()] == 0)
+}
+
+fn format_hex(value: usize, buf: &mut [u8]) -> &[u8] {
+    let num_digits = (usize::BITS - value.leading_zeros()).div_ceil(4).max(1) as usize;
+    for (i, digit) in buf[..num_digits].iter_mut().rev().enumerate() {
+        *digit = b"0123456789abcdef"[(value >> (4 * i)) & 0xf];
+    }
+    &buf[..num_digits]
+}
+
+fn align_up(x: usize, align: usize) -> Option<usize> {
+    x.checked_next_multiple_of(align)
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::string::String;
+    use std::vec;
+    use std::vec::Vec;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct Builder {
+        dt_struct: Vec<u8>,
+        dt_strings: Vec<u8>,
+    }
+
+    impl Builder {
+        fn begin_node(&mut self, name: &str) -> &mut Self {
+            self.u32(FDT_BEGIN_NODE);
+            self.dt_struct.extend(name.as_bytes());
+            self.dt_struct.push(0);
+            self.align();
+            self
+        }
+
+        fn end_node(&mut self) -> &mut Self {
+            self.u32(FDT_END_NODE);
+            self
+        }
+
+        fn prop(&mut self, name: &str, value: &[u8]) -> &mut Self {
+            let offset = find_string(&self.dt_strings, name).unwrap_or_else(|| {
+                let offset = self.dt_strings.len();
+                self.dt_strings.extend(name.as_bytes());
+                self.dt_strings.push(0);
+                offset
+            });
+            self.u32(FDT_PROP);
+            self.u32(value.len().try_into().unwrap());
+            self.u32(offset.try_into().unwrap());
+            self.dt_struct.extend(value);
+            self.align();
+            self
+        }
+
+        fn u32(&mut self, value: u32) -> &mut Self {
+            self.dt_struct.extend(value.to_be_bytes());
+            self
+        }
+
+        fn align(&mut self) {
+            self.dt_struct
+                .resize(self.dt_struct.len().next_multiple_of(4), 0);
+        }
+
+        fn build(&mut self) -> Vec<u8> {
+            self.u32(FDT_END);
+            let off_mem_rsvmap = HEADER_SIZE;
+            let off_dt_struct = off_mem_rsvmap + 16;
+            let off_dt_strings = off_dt_struct + self.dt_struct.len();
+            let total_size = off_dt_strings + self.dt_strings.len();
+            let mut blob = Vec::new();
+            for field in [
+                FDT_MAGIC as usize,
+                total_size,
+                off_dt_struct,
+                off_dt_strings,
+                off_mem_rsvmap,
+                VERSION as usize,
+                LAST_COMP_VERSION as usize,
+                0,
+                self.dt_strings.len(),
+                self.dt_struct.len(),
+            ] {
+                blob.extend(u32::try_from(field).unwrap().to_be_bytes());
+            }
+            blob.extend([0; 16]);
+            blob.extend(&self.dt_struct);
+            blob.extend(&self.dt_strings);
+            blob
+        }
+    }
+
+    // A node, by path, or a property of one, by path and name.
+    type Entry = (String, Option<(String, Vec<u8>)>);
+
+    fn node(path: &str) -> Entry {
+        (path.into(), None)
+    }
+
+    fn prop(path: &str, name: &str, value: &[u8]) -> Entry {
+        (path.into(), Some((name.into(), value.to_vec())))
+    }
+
+    fn cells(values: &[u32]) -> Vec<u8> {
+        values
+            .iter()
+            .flat_map(|value| value.to_be_bytes())
+            .collect()
+    }
+
+    fn walk(blob: &[u8]) -> Vec<Entry> {
+        let fdt = Fdt::new(blob).unwrap();
+        let dt_struct = fdt.dt_struct().unwrap();
+        let mut path = Vec::<String>::new();
+        let path_string = |path: &[String]| match path {
+            [_] => String::from("/"),
+            _ => path.join("/"),
+        };
+        let mut entries = Vec::new();
+        let mut pos = 0;
+        loop {
+            let token = read_u32(dt_struct, pos).unwrap();
+            pos += 4;
+            match token {
+                FDT_BEGIN_NODE => {
+                    let len = dt_struct[pos..].iter().position(|b| *b == 0).unwrap();
+                    path.push(String::from_utf8(dt_struct[pos..pos + len].to_vec()).unwrap());
+                    pos = align_up(pos + len + 1, 4).unwrap();
+                    entries.push((path_string(&path), None));
+                }
+                FDT_END_NODE => {
+                    path.pop().unwrap();
+                }
+                FDT_PROP => {
+                    let len = read_u32(dt_struct, pos).unwrap() as usize;
+                    let name = fdt
+                        .string(read_u32(dt_struct, pos + 4).unwrap() as usize)
+                        .unwrap();
+                    let value = dt_struct[pos + 8..pos + 8 + len].to_vec();
+                    pos = align_up(pos + 8 + len, 4).unwrap();
+                    entries.push((
+                        path_string(&path),
+                        Some((String::from_utf8(name.to_vec()).unwrap(), value)),
+                    ));
+                }
+                FDT_NOP => {}
+                FDT_END => {
+                    assert!(path.is_empty());
+                    return entries;
+                }
+                _ => panic!(),
+            }
+        }
+    }
+
+    fn patch(blob: &[u8], patch: &Patch) -> Vec<u8> {
+        let fdt = Fdt::new(blob).unwrap();
+        let size = fdt.patched_size(patch).unwrap();
+        let mut patched = vec![0xaa; size];
+        assert_eq!(fdt.write_patched(patch, &mut patched), Some(size));
+        patched
+    }
+
+    const NO_PATCH: Patch = Patch {
+        bootargs: None,
+        initrd: None,
+        reserved_memory: &[],
+    };
+
+    #[test]
+    fn chosen_without_subnodes() {
+        let blob = Builder::default()
+            .begin_node("")
+            .prop("#address-cells", &cells(&[1]))
+            .prop("#size-cells", &cells(&[1]))
+            .begin_node("chosen")
+            .prop("bootargs", b"old\0")
+            .prop("stdout-path", b"serial0\0")
+            .end_node()
+            .begin_node("memory@0")
+            .prop("reg", &cells(&[0, 0x1_0000]))
+            .end_node()
+            .end_node()
+            .build();
+        let patched = patch(
+            &blob,
+            &Patch {
+                bootargs: Some("console=ttyAMA0"),
+                initrd: Some(0x1000..0x2000),
+                reserved_memory: &[("sel4-kernel", 0x8000..0x9000)],
+            },
+        );
+        assert_eq!(
+            walk(&patched),
+            [
+                node("/"),
+                prop("/", "#address-cells", &cells(&[1])),
+                prop("/", "#size-cells", &cells(&[1])),
+                node("/chosen"),
+                prop("/chosen", "stdout-path", b"serial0\0"),
+                prop("/chosen", "bootargs", b"console=ttyAMA0\0"),
+                prop("/chosen", "linux,initrd-start", &cells(&[0x1000])),
+                prop("/chosen", "linux,initrd-end", &cells(&[0x2000])),
+                node("/memory@0"),
+                prop("/memory@0", "reg", &cells(&[0, 0x1_0000])),
+                node("/reserved-memory"),
+                prop("/reserved-memory", "#address-cells", &cells(&[1])),
+                prop("/reserved-memory", "#size-cells", &cells(&[1])),
+                prop("/reserved-memory", "ranges", b""),
+                node("/reserved-memory/sel4-kernel@8000"),
+                prop(
+                    "/reserved-memory/sel4-kernel@8000",
+                    "reg",
+                    &cells(&[0x8000, 0x1000])
+                ),
+                prop("/reserved-memory/sel4-kernel@8000", "no-map", b""),
+            ]
+        );
+    }
+
+    #[test]
+    fn chosen_with_subnodes() {
+        let blob = Builder::default()
+            .begin_node("")
+            .begin_node("chosen")
+            .prop("bootargs", b"old\0")
+            .prop("linux,initrd-start", &cells(&[0, 0x10]))
+            .begin_node("framebuffer@0")
+            .prop("status", b"okay\0")
+            .end_node()
+            .end_node()
+            .end_node()
+            .build();
+        let patched = patch(
+            &blob,
+            &Patch {
+                bootargs: Some("new"),
+                ..NO_PATCH
+            },
+        );
+        // Properties precede subnodes, and those which are not patched are kept.
+        assert_eq!(
+            walk(&patched),
+            [
+                node("/"),
+                node("/chosen"),
+                prop("/chosen", "linux,initrd-start", &cells(&[0, 0x10])),
+                prop("/chosen", "bootargs", b"new\0"),
+                node("/chosen/framebuffer@0"),
+                prop("/chosen/framebuffer@0", "status", b"okay\0"),
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_chosen() {
+        let blob = Builder::default().begin_node("").end_node().build();
+        let patched = patch(
+            &blob,
+            &Patch {
+                initrd: Some(0x1000..0x2000),
+                ..NO_PATCH
+            },
+        );
+        assert_eq!(
+            walk(&patched),
+            [
+                node("/"),
+                node("/chosen"),
+                prop("/chosen", "linux,initrd-start", &cells(&[0, 0x1000])),
+                prop("/chosen", "linux,initrd-end", &cells(&[0, 0x2000])),
+            ]
+        );
+        assert_eq!(walk(&patch(&blob, &NO_PATCH)), [node("/")]);
+    }
+
+    #[test]
+    fn existing_reserved_memory() {
+        let reserved_memory = |cells_props: &[(&str, u32)]| {
+            let mut builder = Builder::default();
+            builder
+                .begin_node("")
+                .prop("#address-cells", &cells(&[2]))
+                .prop("#size-cells", &cells(&[2]))
+                .begin_node("reserved-memory");
+            for (name, value) in cells_props {
+                builder.prop(name, &cells(&[*value]));
+            }
+            builder
+                .prop("ranges", b"")
+                .begin_node("firmware@0")
+                .end_node()
+                .end_node()
+                .end_node();
+            let patched = patch(
+                &builder.build(),
+                &Patch {
+                    reserved_memory: &[("sel4-kernel", 0x8000..0x9000)],
+                    ..NO_PATCH
+                },
+            );
+            let entries = walk(&patched);
+            assert_eq!(
+                entries
+                    .iter()
+                    .filter(|(path, _)| path == "/reserved-memory")
+                    .count(),
+                2 + cells_props.len()
+            );
+            assert_eq!(
+                entries[entries.len() - 4..],
+                [
+                    node("/reserved-memory/firmware@0"),
+                    node("/reserved-memory/sel4-kernel@8000"),
+                    entries[entries.len() - 2].clone(),
+                    prop("/reserved-memory/sel4-kernel@8000", "no-map", b""),
+                ]
+            );
+            entries[entries.len() - 2].1.clone().unwrap()
+        };
+
+        // The cells of the node itself are used, which default to (2, 1) rather than those of the
+        // root.
+        assert_eq!(
+            reserved_memory(&[]),
+            ("reg".into(), cells(&[0, 0x8000, 0x1000]))
+        );
+        assert_eq!(
+            reserved_memory(&[("#address-cells", 1), ("#size-cells", 1)]),
+            ("reg".into(), cells(&[0x8000, 0x1000]))
+        );
+    }
+
+    #[test]
+    fn strings_are_reused() {
+        let mut builder = Builder::default();
+        builder.begin_node("").begin_node("names");
+        for name in PROP_NAMES {
+            builder.prop(name, b"");
+        }
+        let blob = builder.end_node().end_node().build();
+        let strings = Fdt::new(&blob).unwrap().dt_strings().unwrap();
+
+        assert_eq!(patch(&blob, &NO_PATCH), blob);
+        let patched = patch(
+            &blob,
+            &Patch {
+                bootargs: Some("new"),
+                initrd: Some(0x1000..0x2000),
+                reserved_memory: &[("sel4-kernel", 0x8000..0x9000)],
+            },
+        );
+        assert_eq!(Fdt::new(&patched).unwrap().dt_strings().unwrap(), strings);
+
+        let blob = Builder::default()
+            .begin_node("")
+            .prop("compatible", b"x\0")
+            .prop("reg", b"")
+            .end_node()
+            .build();
+        let patched = patch(&blob, &NO_PATCH);
+        let mut expected = b"compatible\0reg\0".to_vec();
+        for name in PROP_NAMES.iter().filter(|name| **name != "reg") {
+            expected.extend(name.as_bytes());
+            expected.push(0);
+        }
+        assert_eq!(Fdt::new(&patched).unwrap().dt_strings().unwrap(), expected);
+    }
+
+    #[test]
+    fn malformed() {
+        let blob = Builder::default()
+            .begin_node("")
+            .prop("compatible", b"x\0")
+            .end_node()
+            .build();
+        let with_field = |offset: usize, value: u32| {
+            let mut blob = blob.clone();
+            blob[offset..][..4].copy_from_slice(&value.to_be_bytes());
+            blob
+        };
+        assert!(Fdt::new(&blob).is_some());
+        assert!(Fdt::new(&[]).is_none());
+        assert!(Fdt::new(&blob[..HEADER_SIZE]).is_none());
+        assert!(Fdt::new(&blob[..blob.len() - 1]).is_none());
+        assert!(Fdt::new(&[0; 64]).is_none());
+        assert!(Fdt::new(&with_field(20, 16)).is_none());
+        assert!(Fdt::new(&with_field(24, 18)).is_none());
+        assert!(Fdt::new(&with_field(12, u32::MAX)).is_none());
+        assert!(Fdt::new(&with_field(36, u32::MAX)).is_none());
+
+        let unpatchable = |blob: Vec<u8>| Fdt::new(&blob).unwrap().patched_size(&NO_PATCH);
+        // An unknown token.
+        assert_eq!(unpatchable(with_field(HEADER_SIZE + 16, 7)), None);
+        // Unbalanced nodes.
+        assert_eq!(unpatchable(Builder::default().begin_node("").build()), None);
+        assert_eq!(
+            unpatchable(
+                Builder::default()
+                    .begin_node("")
+                    .end_node()
+                    .end_node()
+                    .build()
+            ),
+            None
+        );
+        // A property which extends past the structure block.
+        assert_eq!(
+            unpatchable(
+                Builder::default()
+                    .begin_node("")
+                    .prop("compatible", b"x\0")
+                    .u32(FDT_PROP)
+                    .u32(0xffff_fff0)
+                    .u32(0)
+                    .end_node()
+                    .build()
+            ),
+            None
+        );
+        // A property name outside of the strings block.
+        assert_eq!(
+            unpatchable(
+                Builder::default()
+                    .begin_node("")
+                    .u32(FDT_PROP)
+                    .u32(0)
+                    .u32(0x100)
+                    .end_node()
+                    .build()
+            ),
+            None
+        );
+    }
+}
@@ -8,11 +8,12 @@
 #![deny(unsafe_op_in_unsafe_fn)]
 #![allow(clippy::useless_conversion)]
 
+use core::iter;
 use core::ops::Range;
 use core::ptr;
 use core::slice;
 
-use heapless::Vec;
+use heapless::{String, Vec};
 use num_traits::{PrimInt, WrappingAdd};
 
 #[cfg(feature = "serde")]
@@ -20,8 +21,12 @@ use serde::{Deserialize, Serialize};
 
 use sel4_platform_info_types::PlatformInfo;
 
+pub mod fdt;
+
 pub const DEFAULT_MAX_NUM_REGIONS: usize = 16;
 
+pub const MAX_BOOTARGS_LEN: usize = 256;
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Payload<T, U = IndirectRegionContent<T>, const N: usize = DEFAULT_MAX_NUM_REGIONS> {
@@ -35,6 +40,8 @@ pub struct PayloadInfo<T> {
     pub kernel_image: ImageInfo<T>,
     pub user_image: ImageInfo<T>,
     pub fdt_phys_addr_range: Option<Range<T>>,
+    pub initrd_phys_addr_range: Option<Range<T>>,
+    pub bootargs: Option<String<MAX_BOOTARGS_LEN>>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -75,8 +82,15 @@ impl<T: Clone, U> Region<T, U> {
 pub trait RegionContent {
     type Source: ?Sized;
 
+    /// The length of the content once copied out.
     fn len(&self) -> usize;
 
+    /// The number of bytes the content occupies in its source, which is less than its length if it
+    /// is compressed.
+    fn stored_len(&self) -> usize {
+        self.len()
+    }
+
     fn copy_out(&self, source: &Self::Source, dst: &mut [u8]);
 }
 
@@ -84,6 +98,14 @@ pub trait RegionContent {
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct IndirectRegionContent<T> {
     pub content_range: Range<T>,
+    pub compression: Option<Compression<T>>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Compression<T> {
+    /// Raw deflate, without a zlib header.
+    Deflate { decompressed_len: T },
 }
 
 impl<T: PrimInt> IndirectRegionContent<T> {
@@ -96,11 +118,31 @@ impl<T: PrimInt> RegionContent for IndirectRegionContent<T> {
     type Source = [u8];
 
     fn len(&self) -> usize {
+        match &self.compression {
+            None => self.stored_len(),
+            Some(Compression::Deflate { decompressed_len }) => decompressed_len.to_usize().unwrap(),
+        }
+    }
+
+    fn stored_len(&self) -> usize {
         self.to_usize_range().len()
     }
 
     fn copy_out(&self, source: &Self::Source, dst: &mut [u8]) {
-        dst.copy_from_slice(&source[self.to_usize_range()])
+        let stored = &source[self.to_usize_range()];
+        match &self.compression {
+            None => dst.copy_from_slice(stored),
+            Some(Compression::Deflate { .. }) => {
+                let n = miniz_oxide::inflate::decompress_slice_iter_to_slice(
+                    dst,
+                    iter::once(stored),
+                    false, // zlib_header
+                    true,  // ignore_adler32
+                )
+                .unwrap();
+                assert_eq!(n, dst.len())
+            }
+        }
     }
 }
 
@@ -149,7 +191,7 @@ impl<U: RegionContent, const N: usize> Payload<usize, U, N> {
     }
 }
 
-impl<U, const N: usize> Payload<usize, U, N> {
+impl<U: RegionContent, const N: usize> Payload<usize, U, N> {
     pub fn sanity_check<T: PrimInt>(
         &self,
         platform_info: &PlatformInfo<T>,
@@ -160,8 +202,54 @@ impl<U, const N: usize> Payload<usize, U, N> {
         for region in self.data.iter() {
             assert!(any_range_contains(memory.iter(), &region.phys_addr_range));
             assert!(ranges_are_disjoint(&own_footprint, &region.phys_addr_range));
+            if let Some(content) = &region.content {
+                assert_eq!(
+                    content.len(),
+                    region.phys_addr_range.len(),
+                    "region content length does not match region size",
+                );
+            }
         }
     }
+
+    /// Finds the highest `size` bytes of platform memory, aligned to `align`, which overlap neither
+    /// the loader, nor any payload region, nor any of `also_avoid`.
+    pub fn find_free_space<T: PrimInt>(
+        &self,
+        platform_info: &PlatformInfo<T>,
+        own_footprint: Range<usize>,
+        also_avoid: &[Range<usize>],
+        size: usize,
+        align: usize,
+    ) -> Option<Range<usize>> {
+        let avoid = || {
+            [own_footprint.clone()]
+                .into_iter()
+                .chain(
+                    self.data
+                        .iter()
+                        .map(|region| region.phys_addr_range.clone()),
+                )
+                .chain(also_avoid.iter().cloned())
+        };
+        platform_info
+            .memory
+            .iter()
+            .map(|memory| memory.start.to_usize().unwrap()..memory.end.to_usize().unwrap())
+            .flat_map(|memory| {
+                [memory.end]
+                    .into_iter()
+                    .chain(avoid().map(|range| range.start))
+                    .filter_map(move |end| {
+                        let start = end.checked_sub(size)? / align * align;
+                        let candidate = start..start + size;
+                        (memory.start <= candidate.start && candidate.end <= memory.end)
+                            .then_some(candidate)
+                    })
+            })
+            .filter(|candidate| avoid().all(|range| ranges_are_disjoint(&range, candidate)))
+            .max_by_key(|candidate| candidate.start)
+    }
 }
 
 fn ranges_are_disjoint(this: &Range<usize>, that: &Range<usize>) -> bool {
@@ -178,3 +266,85 @@ fn any_range_contains<'a, T: PrimInt + 'a>(
 ) -> bool {
     these.any(|this| range_contains(this, that))
 }
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::vec;
+    use std::vec::Vec;
+
+    use super::*;
+
+    fn deflated(data: &[u8]) -> (Vec<u8>, IndirectRegionContent<usize>) {
+        let compressed = miniz_oxide::deflate::compress_to_vec(data, 10);
+        let mut source = vec![0xaa; 3];
+        source.extend(&compressed);
+        source.extend([0xaa; 3]);
+        let content = IndirectRegionContent {
+            content_range: 3..3 + compressed.len(),
+            compression: Some(Compression::Deflate {
+                decompressed_len: data.len(),
+            }),
+        };
+        (source, content)
+    }
+
+    fn image_info(phys_addr_range: Range<usize>) -> ImageInfo<usize> {
+        ImageInfo {
+            phys_addr_range,
+            phys_to_virt_offset: 0,
+            virt_entry: 0,
+        }
+    }
+
+    #[test]
+    fn deflate_round_trip() {
+        let data = (0..0x1000).map(|i| (i % 7) as u8).collect::<Vec<_>>();
+        let (source, content) = deflated(&data);
+        assert_eq!(content.len(), data.len());
+        assert_eq!(content.stored_len(), content.content_range.len());
+        assert!(content.stored_len() < data.len());
+        let mut dst = vec![0; content.len()];
+        content.copy_out(&source, &mut dst);
+        assert_eq!(dst, data);
+
+        let stored = IndirectRegionContent {
+            content_range: 1..4,
+            compression: None,
+        };
+        assert_eq!((stored.len(), stored.stored_len()), (3, 3));
+        let mut dst = [0; 3];
+        stored.copy_out(&[0, 1, 2, 3, 4], &mut dst);
+        assert_eq!(dst, [1, 2, 3]);
+    }
+
+    #[test]
+    #[should_panic(expected = "region content length does not match region size")]
+    fn sanity_check_rejects_wrong_decompressed_len() {
+        let data = [0x5a; 0x1000];
+        let (_, mut content) = deflated(&data);
+        content.compression = Some(Compression::Deflate {
+            decompressed_len: data.len() - 1,
+        });
+        let payload: Payload<usize> = Payload {
+            info: PayloadInfo {
+                kernel_image: image_info(0x1000..0x2000),
+                user_image: image_info(0x2000..0x2000),
+                fdt_phys_addr_range: None,
+                initrd_phys_addr_range: None,
+                bootargs: None,
+            },
+            data: heapless::Vec::from_slice(&[Region {
+                phys_addr_range: 0x1000..0x2000,
+                content: Some(content),
+            }])
+            .unwrap(),
+        };
+        let platform_info = PlatformInfo {
+            memory: &[0x1000..0x2000, 0x8000..0x9000],
+            devices: &[],
+        };
+        payload.sanity_check(&platform_info, 0x8000..0x9000);
+    }
+}
@@ -22,8 +22,8 @@ extern "C" {
 }
 
 #[no_mangle]
-extern "C" fn arch_main() -> ! {
-    main(())
+extern "C" fn arch_main(bootloader_fdt_paddr: usize) -> ! {
+    main((), bootloader_fdt_paddr)
 }
 
 #[no_mangle]
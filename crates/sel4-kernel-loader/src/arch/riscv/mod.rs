@@ -21,8 +21,8 @@ pub(crate) struct PerCoreImpl {
 }
 
 #[no_mangle]
-extern "C" fn arch_main(hart_id: usize) -> ! {
-    main(PerCoreImpl { hart_id })
+extern "C" fn arch_main(hart_id: usize, bootloader_fdt_paddr: usize) -> ! {
+    main(PerCoreImpl { hart_id }, bootloader_fdt_paddr)
 }
 
 #[no_mangle]
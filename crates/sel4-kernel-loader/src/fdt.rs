@@ -0,0 +1,37 @@
+//
+// Copyright 2023, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use core::ops::Range;
+use core::slice;
+
+use sel4_kernel_loader_payload_types::fdt::{Fdt, HEADER_SIZE};
+use sel4_platform_info::PLATFORM_INFO;
+
+/// Finds the blob the bootloader passed at `paddr`, if there is one and it lies within platform
+/// memory.
+pub(crate) fn from_bootloader(paddr: usize) -> Option<Fdt<'static>> {
+    let in_memory = |range: Range<usize>| {
+        PLATFORM_INFO.memory.iter().any(|memory| {
+            let start = usize::try_from(memory.start).unwrap_or(usize::MAX);
+            let end = usize::try_from(memory.end).unwrap_or(usize::MAX);
+            start <= range.start && range.end <= end
+        })
+    };
+    if paddr == 0 || !in_memory(paddr..paddr.checked_add(HEADER_SIZE)?) {
+        return None;
+    }
+    let header = unsafe { slice::from_raw_parts(paddr as *const u8, HEADER_SIZE) };
+    let size = Fdt::size_from_header(header)?;
+    if !in_memory(paddr..paddr.checked_add(size)?) {
+        return None;
+    }
+    Fdt::new(unsafe { slice::from_raw_parts(paddr as *const u8, size) })
+}
+
+pub(crate) fn phys_addr_range(fdt: &Fdt) -> Range<usize> {
+    let start = fdt.as_bytes().as_ptr() as usize;
+    start..start + fdt.as_bytes().len()
+}
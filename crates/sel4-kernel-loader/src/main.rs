@@ -10,14 +10,21 @@
 #![allow(unreachable_code)]
 #![allow(clippy::reversed_empty_ranges)]
 
+use core::ops::Range;
+use core::slice;
+
 use spin::RwLock;
 
-use sel4_kernel_loader_payload_types::PayloadInfo;
+use sel4_kernel_loader_payload_types::{
+    fdt::{Fdt, Patch},
+    Payload, PayloadInfo, RegionContent,
+};
 use sel4_platform_info::PLATFORM_INFO;
 
 mod arch;
 mod barrier;
 mod drivers;
+mod fdt;
 mod fmt;
 mod logging;
 mod plat;
@@ -32,6 +39,8 @@ use crate::{
 
 const MAX_NUM_NODES: usize = sel4_config::sel4_cfg_usize!(MAX_NUM_NODES);
 
+const FDT_ALIGN: usize = 4096;
+
 static SECONDARY_CORE_INIT_INFO: RwLock<Option<SecondaryCoreInitInfo>> = RwLock::new(None);
 
 struct SecondaryCoreInitInfo {
@@ -40,7 +49,7 @@ struct SecondaryCoreInitInfo {
     barrier: Barrier,
 }
 
-fn main(per_core: <ArchImpl as Arch>::PerCore) -> ! {
+fn main(per_core: <ArchImpl as Arch>::PerCore, bootloader_fdt_paddr: usize) -> ! {
     ArchImpl::init();
     PlatImpl::init();
 
@@ -48,7 +57,7 @@ fn main(per_core: <ArchImpl as Arch>::PerCore) -> ! {
 
     log::info!("Starting loader");
 
-    let (payload, region_content_source) = this_image::get_payload();
+    let (mut payload, region_content_source) = this_image::get_payload();
 
     let own_footprint = this_image::get_user_image_bounds();
 
@@ -57,20 +66,70 @@ fn main(per_core: <ArchImpl as Arch>::PerCore) -> ! {
     log::debug!("Payload info: {:#x?}", payload.info);
     log::debug!("Payload regions:");
     for region in payload.data.iter() {
+        // The number of bytes stored in the image, which is less than the length of the region if
+        // its content is compressed, and absent if it is zeroed.
         log::debug!(
             "    0x{:x?} {:?}",
             region.phys_addr_range,
-            region.content.is_some()
+            region.content.as_ref().map(RegionContent::stored_len)
         );
     }
 
     payload.sanity_check(&PLATFORM_INFO, own_footprint.clone());
 
+    let bootargs = payload.info.bootargs.clone();
+    let reserved_memory = [
+        (
+            "sel4-kernel",
+            payload.info.kernel_image.phys_addr_range.clone(),
+        ),
+        (
+            "sel4-user-image",
+            payload.info.user_image.phys_addr_range.clone(),
+        ),
+    ];
+    let fdt_patch = Patch {
+        bootargs: bootargs.as_deref(),
+        initrd: payload.info.initrd_phys_addr_range.clone(),
+        reserved_memory: &reserved_memory,
+    };
+
+    // The bootloader's device tree may lie where the payload is about to go, so it must be
+    // relocated first.
+    let mut fdt_phys_addr_range = None;
+    if let Some(fdt) = fdt::from_bootloader(bootloader_fdt_paddr) {
+        log::info!(
+            "Using device tree from bootloader at {:#x}",
+            bootloader_fdt_paddr
+        );
+        fdt_phys_addr_range = relocate_fdt(&fdt, &fdt_patch, &payload, own_footprint.clone());
+        if fdt_phys_addr_range.is_none() {
+            log::warn!("Failed to patch device tree from bootloader");
+        }
+    }
+
     log::debug!("Copying payload data");
     unsafe {
         payload.copy_data_out(region_content_source);
     }
 
+    if fdt_phys_addr_range.is_none() {
+        if let Some(embedded) = payload.info.fdt_phys_addr_range.clone() {
+            log::info!("Using embedded device tree");
+            let blob =
+                unsafe { slice::from_raw_parts(embedded.start as *const u8, embedded.len()) };
+            fdt_phys_addr_range = Fdt::new(blob)
+                .and_then(|fdt| relocate_fdt(&fdt, &fdt_patch, &payload, own_footprint.clone()));
+            if fdt_phys_addr_range.is_none() {
+                log::warn!("Failed to patch embedded device tree, passing it on as is");
+                fdt_phys_addr_range = Some(embedded);
+            }
+        }
+    }
+
+    log::debug!("Device tree: {:#x?}", fdt_phys_addr_range);
+    payload.info.fdt_phys_addr_range = fdt_phys_addr_range;
+
     for core_id in 1..MAX_NUM_NODES {
         let sp = this_image::stacks::get_secondary_stack_bottom(core_id);
         {
@@ -94,6 +153,25 @@ fn main(per_core: <ArchImpl as Arch>::PerCore) -> ! {
     common_epilogue(0, &payload.info, per_core)
 }
 
+fn relocate_fdt(
+    fdt: &Fdt,
+    patch: &Patch,
+    payload: &Payload<usize>,
+    own_footprint: Range<usize>,
+) -> Option<Range<usize>> {
+    let size = fdt.patched_size(patch)?;
+    let dst_range = payload.find_free_space(
+        &PLATFORM_INFO,
+        own_footprint,
+        &[fdt::phys_addr_range(fdt)],
+        size,
+        FDT_ALIGN,
+    )?;
+    let dst = unsafe { slice::from_raw_parts_mut(dst_range.start as *mut u8, dst_range.len()) };
+    fdt.write_patched(patch, dst)?;
+    Some(dst_range)
+}
+
 fn secondary_main(per_core: <ArchImpl as Arch>::PerCore) -> ! {
     let core_id;
     let payload_info;
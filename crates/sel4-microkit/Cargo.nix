@@ -10,15 +10,29 @@ mk {
   package.name = "sel4-microkit";
   dependencies = {
     inherit (versions) cfg-if;
+    inherit (localCrates)
+      sel4-immutable-cell
+      sel4-microkit-macros
+      sel4-externally-shared
+    ;
+    sel4-async-single-threaded-executor = localCrates.sel4-async-single-threaded-executor // { optional = true; };
+    futures = {
+      version = versions.futures;
+      default-features = false;
+      features = [
+        "alloc"
+      ];
+      optional = true;
+    };
+  };
+  target."cfg(target_env = \"sel4\")".dependencies = {
+    inherit (versions) log;
     inherit (localCrates)
       sel4-panicking
       sel4-panicking-env
       sel4-immediate-sync-once-cell
-      sel4-immutable-cell
       sel4-dlmalloc
       sel4-sync
-      sel4-microkit-macros
-      sel4-externally-shared
     ;
     sel4-runtime-common = localCrates.sel4-runtime-common // { features = [ "tls" "unwinding" "start" ]; };
     sel4 = localCrates.sel4 // { features = [ "single-threaded" ]; };
@@ -30,6 +44,7 @@ mk {
     full = [
       "default"
       "alloc"
+      "async"
     ];
     unwinding = [
       "sel4-panicking/unwinding"
@@ -37,5 +52,10 @@ mk {
     alloc = [
       "sel4-panicking/alloc"
     ];
+    async = [
+      "alloc"
+      "dep:futures"
+      "dep:sel4-async-single-threaded-executor"
+    ];
   };
 }
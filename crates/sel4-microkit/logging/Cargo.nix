@@ -0,0 +1,22 @@
+#
+# Copyright 2023, Colias Group, LLC
+#
+# SPDX-License-Identifier: BSD-2-Clause
+#
+
+{ mk, localCrates, versions, zerocopyWith }:
+
+mk {
+  package.name = "sel4-microkit-logging";
+  dependencies = {
+    inherit (versions) log;
+    zerocopy = zerocopyWith [ "derive" ];
+    sel4-microkit = localCrates.sel4-microkit // { default-features = false; };
+    inherit (localCrates)
+      sel4-immediate-sync-once-cell
+      sel4-logging
+      sel4-shared-ring-buffer
+    ;
+    sel4-externally-shared = localCrates.sel4-externally-shared // { features = [ "unstable" ]; };
+  };
+}
@@ -0,0 +1,306 @@
+//
+// Copyright 2023, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+#![no_std]
+
+//! A [`log`] backend for Microkit protection domains which writes records to a ring buffer in a
+//! shared memory region instead of to the kernel's debug console, so that it works with release
+//! kernels too.
+//!
+//! Each protection domain which logs has its own ring, shared with a dedicated logger protection
+//! domain. Each such protection domain installs a [`RingLogger`], with its own level filter, which
+//! prefixes records with [`pd_name`]. The logger protection domain drains the rings with
+//! [`LogRingReader`]s, for example into a UART driver.
+//!
+//! The memory region backing a ring must be at least `size_of::<RawLogRing>()` bytes, and must be
+//! mapped into both protection domains. The Microkit zero-initializes memory regions, so neither
+//! end needs to initialize it.
+//!
+//! ```ignore
+//! static LOGGER: RingLogger = RingLoggerBuilder::const_default()
+//!     .level_filter(LevelFilter::Info)
+//!     .notify(LOGGER_CHANNEL)
+//!     .build();
+//!
+//! #[protection_domain]
+//! fn init() -> impl Handler {
+//!     LOGGER
+//!         .set(unsafe { memory_region!(log_ring_vaddr, n = LOG_RING_SIZE).as_externally_shared_typed() })
+//!         .unwrap();
+//!     // ...
+//! }
+//! ```
+
+use core::cell::RefCell;
+use core::fmt::{self, Write as _};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use log::{Log, Metadata, Record, SetLoggerError};
+use zerocopy::{AsBytes, FromBytes, FromZeroes};
+
+use sel4_externally_shared::ExternallySharedRef;
+use sel4_immediate_sync_once_cell::ImmediateSyncOnceCell;
+use sel4_logging::{FmtRecordFn, FMT_RECORD_DEFAULT};
+use sel4_microkit::{pd_name, Channel};
+use sel4_shared_ring_buffer::{
+    roles::{Read, Write},
+    InitializationStrategy, PeerMisbehaviorError, RawRingBuffer, RingBuffer,
+};
+
+pub use sel4_logging::LevelFilter;
+
+pub const LOG_CHUNK_SIZE: usize = 63;
+
+/// A fragment of a log record, as stored in a [`RawLogRing`].
+#[repr(C)]
+#[derive(Copy, Clone, Debug, AsBytes, FromBytes, FromZeroes)]
+pub struct LogChunk {
+    len: u8,
+    bytes: [u8; LOG_CHUNK_SIZE],
+}
+
+impl LogChunk {
+    const fn empty() -> Self {
+        Self {
+            len: 0,
+            bytes: [0; LOG_CHUNK_SIZE],
+        }
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes[..usize::from(self.len).min(LOG_CHUNK_SIZE)]
+    }
+}
+
+/// The layout of the shared memory region backing a log ring.
+pub type RawLogRing = RawRingBuffer<LogChunk>;
+
+/// A [`log::Log`] implementation which writes records to a log ring.
+pub struct RingLogger {
+    level_filter: LevelFilter,
+    filter: fn(&Metadata) -> bool,
+    fmt: FmtRecordFn,
+    notify: Option<Channel>,
+    ring: ImmediateSyncOnceCell<RefCell<RingBuffer<'static, Write, LogChunk>>>,
+    dropped_records: AtomicUsize,
+}
+
+// Protection domains are single-threaded.
+unsafe impl Send for RingLogger {}
+
+impl RingLogger {
+    pub const fn const_default() -> Self {
+        Self {
+            level_filter: LevelFilter::Warn,
+            filter: |_| true,
+            fmt: FMT_RECORD_DEFAULT,
+            notify: None,
+            ring: ImmediateSyncOnceCell::new(),
+            dropped_records: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn level_filter(&self) -> LevelFilter {
+        self.level_filter
+    }
+
+    /// Installs `self` as the logger, writing to `ring`.
+    pub fn set(
+        &'static self,
+        ring: ExternallySharedRef<'static, RawLogRing>,
+    ) -> Result<(), SetLoggerError> {
+        log::set_logger(self)?;
+        log::set_max_level(self.level_filter());
+        // Cannot fail, because log::set_logger succeeds at most once.
+        let _ = self.ring.set(RefCell::new(RingBuffer::new(
+            ring,
+            InitializationStrategy::ReadState,
+        )));
+        Ok(())
+    }
+
+    /// Returns the number of records which were dropped, either because the ring was full or
+    /// because they were logged while another record was being written.
+    pub fn dropped_records(&self) -> usize {
+        self.dropped_records.load(Ordering::Relaxed)
+    }
+
+    fn write_record(
+        &self,
+        ring: &mut RingBuffer<'static, Write, LogChunk>,
+        record: &Record,
+    ) -> Result<bool, PeerMisbehaviorError> {
+        let wrapped = DisplayWrapper {
+            fmt: self.fmt,
+            record,
+        };
+        let mut counter = CountingWriter(0);
+        write_prefixed(&mut counter, &wrapped);
+        let num_chunks = counter.0.div_ceil(LOG_CHUNK_SIZE);
+        if ring.num_empty_slots()? < num_chunks {
+            return Ok(false);
+        }
+        let mut writer = ChunkWriter {
+            ring,
+            chunk: LogChunk::empty(),
+            remaining_chunks: num_chunks,
+        };
+        write_prefixed(&mut writer, &wrapped);
+        writer.finish();
+        Ok(true)
+    }
+}
+
+impl Log for RingLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level_filter && (self.filter)(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let Some(ring) = self.ring.get() else {
+            return;
+        };
+        let written = match ring.try_borrow_mut() {
+            Ok(mut ring) => matches!(self.write_record(&mut ring, record), Ok(true)),
+            Err(_) => false,
+        };
+        if written {
+            if let Some(channel) = self.notify {
+                channel.notify();
+            }
+        } else {
+            self.dropped_records.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+pub struct RingLoggerBuilder(RingLogger);
+
+impl RingLoggerBuilder {
+    pub const fn const_default() -> Self {
+        Self(RingLogger::const_default())
+    }
+
+    pub const fn build(self) -> RingLogger {
+        self.0
+    }
+
+    pub const fn level_filter(mut self, level_filter: LevelFilter) -> Self {
+        self.0.level_filter = level_filter;
+        self
+    }
+
+    pub const fn filter(mut self, filter: fn(&Metadata) -> bool) -> Self {
+        self.0.filter = filter;
+        self
+    }
+
+    pub const fn fmt(mut self, fmt: FmtRecordFn) -> Self {
+        self.0.fmt = fmt;
+        self
+    }
+
+    /// Notify `channel`, which leads to the logger protection domain, after each record.
+    pub const fn notify(mut self, channel: Channel) -> Self {
+        self.0.notify = Some(channel);
+        self
+    }
+}
+
+/// The logger protection domain's end of a log ring.
+pub struct LogRingReader<'a> {
+    ring: RingBuffer<'a, Read, LogChunk>,
+}
+
+impl<'a> LogRingReader<'a> {
+    pub fn new(ring: ExternallySharedRef<'a, RawLogRing>) -> Self {
+        Self {
+            ring: RingBuffer::new(ring, InitializationStrategy::ReadState),
+        }
+    }
+
+    /// Passes the contents of the ring to `f`, in order, a chunk at a time.
+    ///
+    /// Records are committed to the ring whole, and each ends with a newline, so output from
+    /// several rings can be interleaved between calls without splitting records.
+    pub fn drain(&mut self, mut f: impl FnMut(&[u8])) -> Result<(), PeerMisbehaviorError> {
+        while let Some(chunk) = self.ring.dequeue()? {
+            f(chunk.bytes());
+        }
+        Ok(())
+    }
+}
+
+//
+
+fn write_prefixed(w: &mut impl fmt::Write, record: &DisplayWrapper) {
+    // Neither writer fails.
+    let _ = writeln!(w, "[{}] {}", pd_name(), record);
+}
+
+struct DisplayWrapper<'a> {
+    fmt: FmtRecordFn,
+    record: &'a Record<'a>,
+}
+
+impl<'a> fmt::Display for DisplayWrapper<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        (self.fmt)(self.record, f)
+    }
+}
+
+struct CountingWriter(usize);
+
+impl fmt::Write for CountingWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0 += s.len();
+        Ok(())
+    }
+}
+
+struct ChunkWriter<'a, 'b> {
+    ring: &'a mut RingBuffer<'b, Write, LogChunk>,
+    chunk: LogChunk,
+    // Guards against records whose formatting is not deterministic.
+    remaining_chunks: usize,
+}
+
+impl<'a, 'b> ChunkWriter<'a, 'b> {
+    fn flush_chunk(&mut self) {
+        if self.chunk.len > 0 && self.remaining_chunks > 0 {
+            self.ring.force_enqueue(self.chunk, false);
+            self.remaining_chunks -= 1;
+        }
+        self.chunk = LogChunk::empty();
+    }
+
+    fn finish(mut self) {
+        self.flush_chunk();
+        self.ring.commit();
+    }
+}
+
+impl<'a, 'b> fmt::Write for ChunkWriter<'a, 'b> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut bytes = s.as_bytes();
+        while !bytes.is_empty() {
+            let len = usize::from(self.chunk.len);
+            let n = bytes.len().min(LOG_CHUNK_SIZE - len);
+            self.chunk.bytes[len..][..n].copy_from_slice(&bytes[..n]);
+            self.chunk.len += u8::try_from(n).unwrap();
+            bytes = &bytes[n..];
+            if usize::from(self.chunk.len) == LOG_CHUNK_SIZE {
+                self.flush_chunk();
+            }
+        }
+        Ok(())
+    }
+}
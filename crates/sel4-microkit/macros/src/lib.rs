@@ -9,7 +9,8 @@ use proc_macro2::TokenStream as TokenStream2;
 use quote::quote;
 use syn::parse_macro_input;
 
-/// Declares the initialization function, stack size, and, optionally, heap and heap size.
+/// Declares the initialization function, stack size, and, optionally, heap and heap size and
+/// error policy.
 ///
 /// The syntax is:
 ///
@@ -24,6 +25,11 @@ use syn::parse_macro_input;
 ///   - `stack_size`: Sets the stack size. Defaults to `0x4000`.
 ///   - `heap_size`: Declares a `#[global_allocator]` implemented using Dlmalloc and a
 ///     statically-allocated heap. Optional.
+///   - `error_policy`: Either an `ErrorPolicy`, which determines what the main loop does when any
+///     of the handler's entrypoints returns an error, or an `ErrorPolicies`, which does so per
+///     entrypoint. Defaults to `ErrorPolicy::Escalate`, which aborts the protection domain.
+///
+/// Keys must appear in the order above.
 ///
 /// The function to which the attribute is applied will be used to initialize the protection domain.
 /// It must satisfy `FnOnce() -> T where T: Handler`.
@@ -4,21 +4,26 @@
 # SPDX-License-Identifier: BSD-2-Clause
 #
 
-{ mk, localCrates, serdeWith }:
+{ mk, localCrates, versions, serdeWith, postcardWith }:
 
 mk {
   package.name = "sel4-microkit-message";
   dependencies = {
+    inherit (versions) zerocopy;
     serde = serdeWith [] // {
       optional = true;
     };
+    postcard = postcardWith [] // {
+      optional = true;
+    };
     inherit (localCrates)
       sel4-microkit
+      sel4-microkit-message-macros
       sel4-microkit-message-types
     ;
   };
   features = {
     default = [ "postcard" ];
-    postcard = [ "dep:serde" "sel4-microkit-message-types/postcard" ];
+    postcard = [ "dep:postcard" "dep:serde" "sel4-microkit-message-types/postcard" ];
   };
 }
@@ -0,0 +1,269 @@
+//
+// Copyright 2023, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use proc_macro::TokenStream;
+use proc_macro2::{Literal, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::parse::{Parse, ParseStream};
+use syn::spanned::Spanned;
+use syn::{parse_macro_input, Error, FnArg, Ident, ItemTrait, Pat, ReturnType, Token, TraitItem};
+
+/// Declares a protection domain's service as a trait, and generates a client and a dispatcher for
+/// it.
+///
+/// The syntax is:
+///
+/// ```rust,ignore
+/// #[service(encoding = $encoding:ident)]
+/// pub trait Timer {
+///     fn now(&mut self) -> Microseconds;
+///
+///     fn set_timeout(&mut self, relative: Microseconds);
+/// }
+/// ```
+///
+/// Where `$encoding` is either `zerocopy`, in which case the arguments and return values of all
+/// methods must implement `zerocopy::AsBytes` and `zerocopy::FromBytes`, or `postcard`, in which
+/// case they must implement `serde::Serialize` and `serde::de::DeserializeOwned`. Methods must
+/// take `&self` or `&mut self`, and may not be generic.
+///
+/// In addition to the trait itself, the macro generates:
+///
+/// - A `TimerClient` struct, constructed from the `sel4_microkit::Channel` to the server, with a
+///   method for each method of the trait which makes a protected procedure call to the server.
+/// - A `dispatch_timer` function, for use from the server's `Handler::protected`, which decodes a
+///   request, calls the corresponding method of a `T: Timer`, and encodes the reply.
+///
+/// Both return `sel4_microkit_message::rpc::RpcErrorFor<E>` on failure, where `E` is the encoding.
+/// A server which cannot dispatch a request would typically reply with
+/// `MessageInfo::send_unspecified_error()`, which its client then observes as
+/// `RpcError::Unspecified`.
+#[proc_macro_attribute]
+pub fn service(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(attr as ServiceArgs);
+    let item = parse_macro_input!(item as ItemTrait);
+    expand_service(&args, &item)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+struct ServiceArgs {
+    encoding: Ident,
+}
+
+impl Parse for ServiceArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let key: Ident = input.parse()?;
+        if key != "encoding" {
+            return Err(Error::new(key.span(), "expected `encoding`"));
+        }
+        input.parse::<Token![=]>()?;
+        let encoding = input.parse()?;
+        input.parse::<Option<Token![,]>>()?;
+        Ok(Self { encoding })
+    }
+}
+
+struct Method<'a> {
+    ident: &'a Ident,
+    label: Literal,
+    arg_idents: Vec<&'a Ident>,
+    arg_tys: Vec<&'a syn::Type>,
+    ret_ty: TokenStream2,
+    doc_attrs: Vec<&'a syn::Attribute>,
+}
+
+fn expand_service(args: &ServiceArgs, item: &ItemTrait) -> syn::Result<TokenStream2> {
+    let encoding = match args.encoding.to_string().as_str() {
+        "zerocopy" => quote!(::sel4_microkit_message::rpc::Zerocopy),
+        "postcard" => quote!(::sel4_microkit_message::rpc::Postcard),
+        _ => {
+            return Err(Error::new(
+                args.encoding.span(),
+                "expected `zerocopy` or `postcard`",
+            ))
+        }
+    };
+
+    let methods = item
+        .items
+        .iter()
+        .enumerate()
+        .map(|(i, trait_item)| parse_method(i, trait_item))
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    let vis = &item.vis;
+    let trait_ident = &item.ident;
+    let client_ident = format_ident!("{}Client", trait_ident);
+    let dispatch_ident = format_ident!("dispatch_{}", to_snake_case(&trait_ident.to_string()));
+    let client_doc = format!("Client for [`{trait_ident}`].");
+    let dispatch_doc =
+        format!("Dispatches a request to a method of [`{trait_ident}`], returning the reply.");
+
+    let client_methods = methods.iter().map(|method| {
+        let Method {
+            ident,
+            label,
+            arg_idents,
+            arg_tys,
+            ret_ty,
+            doc_attrs,
+        } = method;
+        quote! {
+            #(#doc_attrs)*
+            pub fn #ident(
+                &self,
+                #(#arg_idents: #arg_tys,)*
+            ) -> Result<#ret_ty, ::sel4_microkit_message::rpc::RpcErrorFor<#encoding>> {
+                ::sel4_microkit_message::rpc::call::<#encoding, _>(
+                    self.channel,
+                    #label,
+                    |__encoder| {
+                        #(__encoder.put(&#arg_idents)?;)*
+                        Ok(())
+                    },
+                )
+            }
+        }
+    });
+
+    let dispatch_arms = methods.iter().map(|method| {
+        let Method {
+            ident,
+            label,
+            arg_idents,
+            arg_tys,
+            ..
+        } = method;
+        quote! {
+            #label => {
+                let (#(#arg_idents,)*) = ::sel4_microkit_message::rpc::recv_request::<#encoding, _>(
+                    __msg_info,
+                    |__decoder| Ok((#(__decoder.take::<#arg_tys>()?,)*)),
+                )?;
+                let __ret = __server.#ident(#(#arg_idents),*);
+                ::sel4_microkit_message::rpc::send_reply::<#encoding, _>(&__ret)
+            }
+        }
+    });
+
+    Ok(quote! {
+        #item
+
+        #[doc = #client_doc]
+        #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+        #vis struct #client_ident {
+            channel: ::sel4_microkit::Channel,
+        }
+
+        impl #client_ident {
+            pub const fn new(channel: ::sel4_microkit::Channel) -> Self {
+                Self { channel }
+            }
+
+            pub fn channel(&self) -> ::sel4_microkit::Channel {
+                self.channel
+            }
+
+            #(#client_methods)*
+        }
+
+        #[doc = #dispatch_doc]
+        #vis fn #dispatch_ident<T: #trait_ident + ?Sized>(
+            __server: &mut T,
+            __msg_info: &::sel4_microkit::MessageInfo,
+        ) -> Result<::sel4_microkit::MessageInfo, ::sel4_microkit_message::rpc::RpcErrorFor<#encoding>> {
+            match __msg_info.label() {
+                #(#dispatch_arms)*
+                __label => Err(::sel4_microkit_message::rpc::RpcError::UnexpectedLabel(__label)),
+            }
+        }
+    })
+}
+
+fn parse_method(i: usize, trait_item: &TraitItem) -> syn::Result<Method<'_>> {
+    let method = match trait_item {
+        TraitItem::Method(method) => method,
+        _ => {
+            return Err(Error::new(
+                trait_item.span(),
+                "service traits may only contain methods",
+            ))
+        }
+    };
+    let sig = &method.sig;
+    if !sig.generics.params.is_empty() || sig.asyncness.is_some() || sig.variadic.is_some() {
+        return Err(Error::new(
+            sig.span(),
+            "service methods may not be generic, async, or variadic",
+        ));
+    }
+
+    let mut inputs = sig.inputs.iter();
+    match inputs.next() {
+        Some(FnArg::Receiver(receiver)) if receiver.reference.is_some() => {}
+        _ => {
+            return Err(Error::new(
+                sig.span(),
+                "service methods must take `&self` or `&mut self`",
+            ))
+        }
+    }
+
+    let mut arg_idents = vec![];
+    let mut arg_tys = vec![];
+    for input in inputs {
+        let pat_type = match input {
+            FnArg::Typed(pat_type) => pat_type,
+            FnArg::Receiver(_) => unreachable!(),
+        };
+        match &*pat_type.pat {
+            Pat::Ident(pat_ident) if pat_ident.subpat.is_none() => {
+                arg_idents.push(&pat_ident.ident);
+            }
+            pat => {
+                return Err(Error::new(
+                    pat.span(),
+                    "service method arguments must be identifiers",
+                ))
+            }
+        }
+        arg_tys.push(&*pat_type.ty);
+    }
+
+    let ret_ty = match &sig.output {
+        ReturnType::Default => quote!(()),
+        ReturnType::Type(_, ty) => quote!(#ty),
+    };
+
+    Ok(Method {
+        ident: &sig.ident,
+        label: Literal::usize_unsuffixed(i),
+        arg_idents,
+        arg_tys,
+        ret_ty,
+        doc_attrs: method
+            .attrs
+            .iter()
+            .filter(|attr| attr.path.is_ident("doc"))
+            .collect(),
+    })
+}
+
+fn to_snake_case(s: &str) -> String {
+    let mut snake = String::new();
+    for (i, c) in s.chars().enumerate() {
+        if c.is_ascii_uppercase() {
+            if i != 0 {
+                snake.push('_');
+            }
+            snake.push(c.to_ascii_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
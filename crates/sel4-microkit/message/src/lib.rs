@@ -22,8 +22,15 @@ use sel4_microkit_message_types::{
 #[cfg(feature = "postcard")]
 use sel4_microkit_message_types::MessageValueUsingPostcard;
 
+pub use sel4_microkit_message_macros::service;
 pub use sel4_microkit_message_types as types;
 
+pub mod rpc;
+
+// Lets the paths generated by `service` resolve within this crate's tests.
+#[cfg(test)]
+extern crate self as sel4_microkit_message;
+
 pub const UNSPECIFIED_ERROR_LABEL: MessageLabel = (1 << MessageInfo::label_width()) - 1;
 
 pub trait MessageInfoExt: Sized {
@@ -0,0 +1,386 @@
+//
+// Copyright 2023, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+//! Runtime support for services declared with [`service`](crate::service).
+//!
+//! A request's label identifies the method being called, and its arguments are encoded one after
+//! the other in the IPC buffer. A successful reply has label 0 and contains the encoded return
+//! value.
+
+use core::fmt;
+use core::marker::PhantomData;
+use core::mem;
+
+#[cfg(feature = "postcard")]
+use serde::{de::DeserializeOwned, Serialize};
+
+use zerocopy::{AsBytes, FromBytes, Unalign};
+
+use sel4_microkit::{with_msg_bytes, with_msg_bytes_mut, Channel, MessageInfo};
+
+use sel4_microkit_message_types::MessageLabel;
+
+use crate::{bytes_to_mrs, mrs_to_bytes, UNSPECIFIED_ERROR_LABEL};
+
+/// An encoding for the arguments and return values of service methods.
+pub trait Encoding {
+    type Error: fmt::Debug + fmt::Display;
+}
+
+/// Types which can be encoded using `E`.
+pub trait Encode<E: Encoding> {
+    /// Writes `self` to the start of `buf`, returning the number of bytes written.
+    fn encode(&self, buf: &mut [u8]) -> Result<usize, E::Error>;
+}
+
+/// Types which can be decoded using `E`.
+pub trait Decode<E: Encoding>: Sized {
+    /// Reads a value from the start of `buf`, returning it along with the number of bytes read.
+    fn decode(buf: &[u8]) -> Result<(Self, usize), E::Error>;
+}
+
+/// Encodes values as their in-memory representations, using `zerocopy`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Zerocopy {}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ZerocopyError {
+    ValueTooLarge,
+    MessageTooShort,
+}
+
+impl fmt::Display for ZerocopyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::ValueTooLarge => write!(f, "value too large for IPC buffer"),
+            Self::MessageTooShort => write!(f, "message too short"),
+        }
+    }
+}
+
+impl Encoding for Zerocopy {
+    type Error = ZerocopyError;
+}
+
+impl<T: AsBytes> Encode<Zerocopy> for T {
+    fn encode(&self, buf: &mut [u8]) -> Result<usize, ZerocopyError> {
+        self.write_to_prefix(buf)
+            .ok_or(ZerocopyError::ValueTooLarge)?;
+        Ok(mem::size_of_val(self))
+    }
+}
+
+impl<T: FromBytes + Copy> Decode<Zerocopy> for T {
+    fn decode(buf: &[u8]) -> Result<(Self, usize), ZerocopyError> {
+        Unalign::<T>::read_from_prefix(buf)
+            .ok_or(ZerocopyError::MessageTooShort)
+            .map(|unalign| (unalign.get(), mem::size_of::<T>()))
+    }
+}
+
+/// Encodes values using `postcard`.
+#[cfg(feature = "postcard")]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Postcard {}
+
+#[cfg(feature = "postcard")]
+impl Encoding for Postcard {
+    type Error = postcard::Error;
+}
+
+#[cfg(feature = "postcard")]
+impl<T: Serialize> Encode<Postcard> for T {
+    fn encode(&self, buf: &mut [u8]) -> Result<usize, postcard::Error> {
+        postcard::to_slice(self, buf).map(|used| used.len())
+    }
+}
+
+#[cfg(feature = "postcard")]
+impl<T: DeserializeOwned> Decode<Postcard> for T {
+    fn decode(buf: &[u8]) -> Result<(Self, usize), postcard::Error> {
+        postcard::take_from_bytes(buf).map(|(val, rest)| (val, buf.len() - rest.len()))
+    }
+}
+
+/// Encodes a sequence of values into a buffer.
+pub struct Encoder<'a, E> {
+    buf: &'a mut [u8],
+    pos: usize,
+    _phantom: PhantomData<E>,
+}
+
+impl<'a, E: Encoding> Encoder<'a, E> {
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Self {
+            buf,
+            pos: 0,
+            _phantom: PhantomData,
+        }
+    }
+
+    pub fn put<T: Encode<E>>(&mut self, val: &T) -> Result<(), E::Error> {
+        self.pos += val.encode(&mut self.buf[self.pos..])?;
+        Ok(())
+    }
+
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+}
+
+/// Decodes a sequence of values from a buffer.
+pub struct Decoder<'a, E> {
+    buf: &'a [u8],
+    _phantom: PhantomData<E>,
+}
+
+impl<'a, E: Encoding> Decoder<'a, E> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self {
+            buf,
+            _phantom: PhantomData,
+        }
+    }
+
+    pub fn take<T: Decode<E>>(&mut self) -> Result<T, E::Error> {
+        let (val, n) = T::decode(self.buf)?;
+        self.buf = &self.buf[n..];
+        Ok(val)
+    }
+}
+
+/// Error type for calls to and dispatch of service methods.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RpcError<E> {
+    /// Encoding a request or reply failed.
+    Encode(E),
+    /// Decoding a request or reply failed.
+    Decode(E),
+    /// The label of a request does not identify a method, or that of a reply does not indicate
+    /// success.
+    UnexpectedLabel(MessageLabel),
+    /// The server replied with
+    /// [`send_unspecified_error`](crate::MessageInfoExt::send_unspecified_error), for example
+    /// because it could not dispatch the request.
+    Unspecified,
+}
+
+pub type RpcErrorFor<E> = RpcError<<E as Encoding>::Error>;
+
+impl<E: fmt::Display> fmt::Display for RpcError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Encode(err) => write!(f, "encode error: {}", err),
+            Self::Decode(err) => write!(f, "decode error: {}", err),
+            Self::UnexpectedLabel(label) => write!(f, "unexpected label: {}", label),
+            Self::Unspecified => write!(f, "unspecified error"),
+        }
+    }
+}
+
+/// Calls method `label` of the service on the other end of `channel`, with arguments written by
+/// `f`.
+pub fn call<E: Encoding, R: Decode<E>>(
+    channel: Channel,
+    label: MessageLabel,
+    f: impl FnOnce(&mut Encoder<E>) -> Result<(), E::Error>,
+) -> Result<R, RpcErrorFor<E>> {
+    let num_bytes = with_msg_bytes_mut(|buf| {
+        let mut encoder = Encoder::new(buf);
+        f(&mut encoder)?;
+        Ok(encoder.position())
+    })
+    .map_err(RpcError::Encode)?;
+    let reply = channel.pp_call(MessageInfo::new(label, bytes_to_mrs(num_bytes)));
+    match reply.label() {
+        0 => {}
+        UNSPECIFIED_ERROR_LABEL => return Err(RpcError::Unspecified),
+        label => return Err(RpcError::UnexpectedLabel(label)),
+    }
+    decode(&reply, |decoder| decoder.take())
+}
+
+/// Decodes the arguments of a request using `f`.
+pub fn recv_request<E: Encoding, T>(
+    msg_info: &MessageInfo,
+    f: impl FnOnce(&mut Decoder<E>) -> Result<T, E::Error>,
+) -> Result<T, RpcErrorFor<E>> {
+    decode(msg_info, f)
+}
+
+/// Encodes the return value of a method as a reply.
+pub fn send_reply<E: Encoding, T: Encode<E>>(val: &T) -> Result<MessageInfo, RpcErrorFor<E>> {
+    let num_bytes = with_msg_bytes_mut(|buf| val.encode(buf)).map_err(RpcError::Encode)?;
+    Ok(MessageInfo::new(0, bytes_to_mrs(num_bytes)))
+}
+
+fn decode<E: Encoding, T>(
+    msg_info: &MessageInfo,
+    f: impl FnOnce(&mut Decoder<E>) -> Result<T, E::Error>,
+) -> Result<T, RpcErrorFor<E>> {
+    with_msg_bytes(|buf| f(&mut Decoder::new(&buf[..mrs_to_bytes(msg_info.count())])))
+        .map_err(RpcError::Decode)
+}
+
+#[cfg(test)]
+mod tests {
+    use core::convert::Infallible;
+
+    use sel4_microkit::simulator::{PdConfig, PdId, Simulator};
+    use sel4_microkit::Handler;
+
+    use super::*;
+    use crate::{service, MessageInfoExt};
+
+    #[service(encoding = zerocopy)]
+    trait Register {
+        fn set(&mut self, hi: u32, lo: u16);
+
+        fn get(&self) -> u64;
+    }
+
+    #[derive(Default)]
+    struct Server {
+        value: u64,
+    }
+
+    impl Register for Server {
+        fn set(&mut self, hi: u32, lo: u16) {
+            self.value = u64::from(hi) << 16 | u64::from(lo);
+        }
+
+        fn get(&self) -> u64 {
+            self.value
+        }
+    }
+
+    impl Handler for Server {
+        type Error = Infallible;
+
+        fn protected(
+            &mut self,
+            _channel: Channel,
+            msg_info: MessageInfo,
+        ) -> Result<MessageInfo, Self::Error> {
+            Ok(dispatch_register(self, &msg_info)
+                .unwrap_or_else(|_| MessageInfo::send_unspecified_error()))
+        }
+    }
+
+    struct Client;
+
+    impl Handler for Client {
+        type Error = Infallible;
+    }
+
+    const CHANNEL: Channel = Channel::new(0);
+
+    fn system() -> (Simulator, PdId, PdId) {
+        let mut sim = Simulator::new();
+        let server = sim.add_pd(
+            PdConfig::new("server").priority(200).passive(true),
+            Server::default,
+        );
+        let client = sim.add_pd(PdConfig::new("client").priority(100), || Client);
+        sim.add_channel(client, 0, server, 0);
+        sim.start().unwrap();
+        (sim, server, client)
+    }
+
+    #[test]
+    fn zerocopy_round_trip() {
+        let mut buf = [0; 16];
+        let mut encoder = Encoder::<Zerocopy>::new(&mut buf);
+        encoder.put(&1u8).unwrap();
+        encoder.put(&0x0203_0405u32).unwrap();
+        encoder.put(&[6u16, 7]).unwrap();
+        assert_eq!(encoder.put(&0u64), Err(ZerocopyError::ValueTooLarge));
+        let num_bytes = encoder.position();
+        assert_eq!(num_bytes, 9);
+        let mut decoder = Decoder::<Zerocopy>::new(&buf[..num_bytes]);
+        assert_eq!(decoder.take(), Ok(1u8));
+        assert_eq!(decoder.take(), Ok(0x0203_0405u32));
+        assert_eq!(decoder.take(), Ok([6u16, 7]));
+        assert_eq!(decoder.take::<u8>(), Err(ZerocopyError::MessageTooShort));
+    }
+
+    #[cfg(feature = "postcard")]
+    #[test]
+    fn postcard_round_trip() {
+        let mut buf = [0; 16];
+        let mut encoder = Encoder::<Postcard>::new(&mut buf);
+        encoder.put(&Some(300u32)).unwrap();
+        encoder.put(&(-1i16, true)).unwrap();
+        let num_bytes = encoder.position();
+        let mut decoder = Decoder::<Postcard>::new(&buf[..num_bytes]);
+        assert_eq!(decoder.take(), Ok(Some(300u32)));
+        assert_eq!(decoder.take(), Ok((-1i16, true)));
+        assert!(decoder.take::<u8>().is_err());
+    }
+
+    #[test]
+    fn client_round_trip() {
+        let (mut sim, _, client) = system();
+        let value = sim
+            .with_handler(client, |_: &mut Client| {
+                let register = RegisterClient::new(CHANNEL);
+                register.set(0x1234_5678, 0x9abc).unwrap();
+                register.get()
+            })
+            .unwrap();
+        assert_eq!(value, Ok(0x1234_5678_9abc));
+    }
+
+    #[test]
+    fn labels_follow_method_order() {
+        let (mut sim, server, _) = system();
+        sim.with_handler(server, |server: &mut Server| {
+            let num_bytes = with_msg_bytes_mut(|buf| {
+                let mut encoder = Encoder::<Zerocopy>::new(buf);
+                encoder.put(&1u32).unwrap();
+                encoder.put(&2u16).unwrap();
+                encoder.position()
+            });
+            let reply = dispatch_register(server, &MessageInfo::new(0, bytes_to_mrs(num_bytes)));
+            assert_eq!(reply.map(|reply| reply.label()), Ok(0));
+            assert_eq!(server.value, 0x1_0002);
+
+            let reply = dispatch_register(server, &MessageInfo::new(1, 0)).unwrap();
+            assert_eq!(
+                decode::<Zerocopy, _>(&reply, |decoder| decoder.take::<u64>()),
+                Ok(0x1_0002),
+            );
+
+            assert_eq!(
+                dispatch_register(server, &MessageInfo::new(2, 0)).map(|_| ()),
+                Err(RpcError::UnexpectedLabel(2)),
+            );
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn truncated_request() {
+        let (mut sim, server, _) = system();
+        let reply = sim
+            .with_handler(server, |server: &mut Server| {
+                dispatch_register(server, &MessageInfo::new(0, 0)).map(|_| ())
+            })
+            .unwrap();
+        assert_eq!(reply, Err(RpcError::Decode(ZerocopyError::MessageTooShort)));
+    }
+
+    #[test]
+    fn unspecified_error() {
+        let (mut sim, _, client) = system();
+        let value = sim
+            .with_handler(client, |_: &mut Client| {
+                call::<Zerocopy, u64>(CHANNEL, 2, |_| Ok(()))
+            })
+            .unwrap();
+        assert_eq!(value, Err(RpcError::Unspecified));
+    }
+}
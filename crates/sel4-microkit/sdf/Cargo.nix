@@ -0,0 +1,18 @@
+#
+# Copyright 2023, Colias Group, LLC
+#
+# SPDX-License-Identifier: BSD-2-Clause
+#
+
+{ mk, localCrates, versions }:
+
+mk {
+  package.name = "sel4-microkit-sdf";
+  dependencies = {
+    inherit (versions) proc-macro2 quote;
+    xmltree = { version = "0.10.3"; features = [ "attribute-order" ]; };
+    inherit (localCrates)
+      sel4-rustfmt-helper
+    ;
+  };
+}
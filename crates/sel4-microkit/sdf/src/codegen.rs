@@ -0,0 +1,300 @@
+//
+// Copyright 2023, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use std::collections::BTreeMap;
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::quote;
+
+use sel4_rustfmt_helper::Rustfmt;
+
+use crate::{ParseError, SystemDescription};
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum CodegenError {
+    UnknownProtectionDomain { pd: String },
+}
+
+impl fmt::Display for CodegenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnknownProtectionDomain { pd } => {
+                write!(f, "no protection domain named '{pd}'")
+            }
+        }
+    }
+}
+
+impl Error for CodegenError {}
+
+impl SystemDescription {
+    /// Generates Rust items describing the protection domain `pd` for use with `sel4-microkit`.
+    ///
+    /// The generated code contains two modules:
+    ///
+    /// - `channels`, with a `Channel` constant for each of the protection domain's channels,
+    ///   named after the protection domain at the other end, and for each of its IRQs, named
+    ///   `IRQ_<irq>`. Where a name would be ambiguous, including after characters which cannot
+    ///   appear in identifiers are replaced, the channel id is appended to it.
+    /// - `memory_regions`, with a `<SYMBOL>_SIZE` constant and an accessor function for each map
+    ///   with a `setvar_vaddr`, and an accessor function for each `<setvar>`. Accessors are named
+    ///   after the symbols they read.
+    ///
+    /// The symbols read by accessors are only present in the protection domain's binary if the
+    /// accessors are used.
+    pub fn generate_pd_module(&self, pd: &str) -> Result<TokenStream, CodegenError> {
+        let pd = self
+            .get_protection_domain(pd)
+            .ok_or_else(|| CodegenError::UnknownProtectionDomain { pd: pd.to_owned() })?;
+
+        let mut channels = BTreeMap::<String, Vec<usize>>::new();
+        for (end, other) in self.channels_of(&pd.name) {
+            channels
+                .entry(sanitize(&other.to_uppercase()))
+                .or_default()
+                .push(end.id);
+        }
+        for irq in &pd.irqs {
+            channels
+                .entry(format!("IRQ_{}", irq.irq))
+                .or_default()
+                .push(irq.id);
+        }
+        let channels = channels.into_iter().flat_map(|(name, ids)| {
+            let ambiguous = ids.len() > 1;
+            ids.into_iter().map(move |id| {
+                let ident = if ambiguous {
+                    format_ident_sanitized(&format!("{name}_{id}"))
+                } else {
+                    format_ident_sanitized(&name)
+                };
+                quote! {
+                    pub const #ident: Channel = Channel::new(#id);
+                }
+            })
+        });
+
+        let maps = pd.maps.iter().filter_map(|map| {
+            let symbol = map.setvar_vaddr.as_ref()?;
+            let mr = self.get_memory_region(&map.mr)?;
+            let size = usize::try_from(mr.size).unwrap();
+            let size_ident = format_ident_sanitized(&format!("{}_SIZE", symbol.to_uppercase()));
+            let fn_ident = format_ident_sanitized(symbol);
+            // Symbols are checked to be identifiers by `validate`.
+            let symbol_ident = Ident::new(symbol, Span::call_site());
+            let doc = format!(
+                "Returns the address at which memory region `{}` is mapped.",
+                mr.name
+            );
+            Some(quote! {
+                pub const #size_ident: usize = #size;
+
+                #[doc = #doc]
+                pub fn #fn_ident() -> core::ptr::NonNull<[u8]> {
+                    sel4_microkit::memory_region_symbol!(#symbol_ident: *mut [u8], n = #size_ident)
+                }
+            })
+        });
+
+        let setvars = pd.setvars.iter().map(|setvar| {
+            let fn_ident = format_ident_sanitized(&setvar.symbol);
+            let symbol_ident = Ident::new(&setvar.symbol, Span::call_site());
+            let doc = format!(
+                "Returns the physical address of memory region `{}`.",
+                setvar.region_paddr
+            );
+            quote! {
+                #[doc = #doc]
+                pub fn #fn_ident() -> usize {
+                    *sel4_microkit::var!(#symbol_ident: usize = 0)
+                }
+            }
+        });
+
+        Ok(quote! {
+            pub mod channels {
+                use sel4_microkit::Channel;
+
+                #(#channels)*
+            }
+
+            pub mod memory_regions {
+                #(#maps)*
+
+                #(#setvars)*
+            }
+        })
+    }
+}
+
+/// Generates `$OUT_DIR/sdf.rs` for the protection domain `pd` in the system description at
+/// `sdf_path`, for inclusion into that protection domain's crate.
+///
+/// Panics, failing the build, if the system description is invalid or does not contain `pd`.
+/// Intended to be called from a build script.
+pub fn generate_pd_module_for_build_script(sdf_path: impl AsRef<Path>, pd: &str) {
+    let sdf_path = sdf_path.as_ref();
+    let s = fs::read_to_string(sdf_path)
+        .unwrap_or_else(|err| panic!("failed to read {}: {err}", sdf_path.display()));
+    let sdf = SystemDescription::parse_unvalidated(&s)
+        .unwrap_or_else(|err| panic!("failed to parse {}: {err}", sdf_path.display()));
+    if let Err(errs) = sdf.validate() {
+        panic!("{}: {}", sdf_path.display(), ParseError::Invalid(errs));
+    }
+    let fragment = sdf
+        .generate_pd_module(pd)
+        .unwrap_or_else(|err| panic!("{}: {err}", sdf_path.display()));
+    let out_dir = env::var("OUT_DIR").unwrap();
+    let out_path = PathBuf::from(&out_dir).join("sdf.rs");
+    fs::write(&out_path, format!("{fragment}")).unwrap();
+    Rustfmt::detect().format(&out_path);
+    println!("cargo:rerun-if-changed={}", sdf_path.display());
+}
+
+fn format_ident_sanitized(s: &str) -> Ident {
+    Ident::new(&sanitize(s), Span::call_site())
+}
+
+fn sanitize(s: &str) -> String {
+    let mut sanitized = s
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>();
+    if sanitized.starts_with(|c: char| c.is_ascii_digit()) {
+        sanitized.insert(0, '_');
+    }
+    sanitized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SDF: &str = r#"
+        <system>
+            <memory_region name="buf" size="0x2000" />
+            <protection_domain name="client">
+                <program_image path="client.elf" />
+                <map mr="buf" vaddr="0x4000000" setvar_vaddr="buf_vaddr" />
+                <setvar symbol="buf_paddr" region_paddr="buf" />
+                <irq irq="33" id="5" />
+            </protection_domain>
+            <protection_domain name="net-server">
+                <program_image path="server.elf" />
+            </protection_domain>
+            <protection_domain name="timer">
+                <program_image path="timer.elf" />
+            </protection_domain>
+            <channel>
+                <end pd="client" id="0" />
+                <end pd="net-server" id="0" />
+            </channel>
+            <channel>
+                <end pd="client" id="1" />
+                <end pd="net-server" id="1" />
+            </channel>
+            <channel>
+                <end pd="timer" id="3" />
+                <end pd="client" id="2" />
+            </channel>
+        </system>
+    "#;
+
+    fn generate(pd: &str) -> Result<String, CodegenError> {
+        let sdf = SystemDescription::parse(SDF).unwrap();
+        sdf.generate_pd_module(pd).map(|tokens| tokens.to_string())
+    }
+
+    #[test]
+    fn channels() {
+        let client = generate("client").unwrap();
+        for expected in [
+            "pub const NET_SERVER_0 : Channel = Channel :: new (0usize) ;",
+            "pub const NET_SERVER_1 : Channel = Channel :: new (1usize) ;",
+            "pub const TIMER : Channel = Channel :: new (2usize) ;",
+            "pub const IRQ_33 : Channel = Channel :: new (5usize) ;",
+        ] {
+            assert!(client.contains(expected), "{expected} not in {client}");
+        }
+        let timer = generate("timer").unwrap();
+        assert!(timer.contains("pub const CLIENT : Channel = Channel :: new (3usize) ;"));
+    }
+
+    #[test]
+    fn channels_named_alike() {
+        let sdf = SystemDescription::parse(
+            r#"
+            <system>
+                <protection_domain name="client">
+                    <program_image path="client.elf" />
+                </protection_domain>
+                <protection_domain name="net-server">
+                    <program_image path="a.elf" />
+                </protection_domain>
+                <protection_domain name="net_server">
+                    <program_image path="b.elf" />
+                </protection_domain>
+                <channel>
+                    <end pd="client" id="0" />
+                    <end pd="net-server" id="0" />
+                </channel>
+                <channel>
+                    <end pd="client" id="1" />
+                    <end pd="net_server" id="0" />
+                </channel>
+            </system>
+        "#,
+        )
+        .unwrap();
+        let client = sdf.generate_pd_module("client").unwrap().to_string();
+        for expected in [
+            "pub const NET_SERVER_0 : Channel = Channel :: new (0usize) ;",
+            "pub const NET_SERVER_1 : Channel = Channel :: new (1usize) ;",
+        ] {
+            assert!(client.contains(expected), "{expected} not in {client}");
+        }
+    }
+
+    #[test]
+    fn memory_regions() {
+        let client = generate("client").unwrap();
+        for expected in [
+            "pub const BUF_VADDR_SIZE : usize = 8192usize ;",
+            "pub fn buf_vaddr () -> core :: ptr :: NonNull < [u8] >",
+            "memory_region_symbol ! (buf_vaddr : * mut [u8] , n = BUF_VADDR_SIZE)",
+            "pub fn buf_paddr () -> usize",
+            "var ! (buf_paddr : usize = 0)",
+        ] {
+            assert!(client.contains(expected), "{expected} not in {client}");
+        }
+        let timer = generate("timer").unwrap();
+        assert!(!timer.contains("pub fn"));
+    }
+
+    #[test]
+    fn unknown_protection_domain() {
+        assert_eq!(
+            generate("missing"),
+            Err(CodegenError::UnknownProtectionDomain {
+                pd: "missing".to_owned()
+            })
+        );
+    }
+
+    #[test]
+    fn sanitized_idents() {
+        assert_eq!(
+            format_ident_sanitized("net-server.0").to_string(),
+            "net_server_0"
+        );
+        assert_eq!(format_ident_sanitized("3d").to_string(), "_3d");
+    }
+}
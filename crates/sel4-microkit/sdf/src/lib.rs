@@ -0,0 +1,328 @@
+//
+// Copyright 2023, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+//! Host-side tools for seL4 Microkit system description files (SDFs).
+//!
+//! This crate can parse and validate the `.system` XML files consumed by the `microkit` tool,
+//! build them programmatically, and generate Rust constants for protection domains from them.
+//! The last of these is meant to be used from build scripts, so that the channel indices and
+//! memory region symbols used by a protection domain are checked against its system description
+//! at build time:
+//!
+//! ```rust,ignore
+//! // build.rs
+//! fn main() {
+//!     sel4_microkit_sdf::generate_pd_module_for_build_script("../../example.system", "client");
+//! }
+//!
+//! // src/main.rs
+//! mod sdf {
+//!     include!(concat!(env!("OUT_DIR"), "/sdf.rs"));
+//! }
+//!
+//! sdf::channels::SERVER.notify();
+//! ```
+
+use std::path::PathBuf;
+
+mod codegen;
+mod parse;
+mod render;
+mod validate;
+
+pub use codegen::{generate_pd_module_for_build_script, CodegenError};
+pub use parse::ParseError;
+pub use validate::ValidationError;
+
+/// The maximum number of channels (including IRQs) per protection domain supported by
+/// `sel4-microkit`, which reserves one more badge bit than the `microkit` tool.
+pub const MAX_CHANNELS: usize = 62;
+
+/// The maximum priority of a protection domain.
+pub const MAX_PRIORITY: u8 = 254;
+
+/// The maximum length, in bytes, of a protection domain's name.
+pub const MAX_PD_NAME_LEN: usize = 15;
+
+/// Page sizes which may be used for memory regions.
+pub const PAGE_SIZES: &[u64] = &[0x1000, 0x200_000];
+
+pub const DEFAULT_PAGE_SIZE: u64 = 0x1000;
+
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct SystemDescription {
+    pub memory_regions: Vec<MemoryRegion>,
+    pub protection_domains: Vec<ProtectionDomain>,
+    pub channels: Vec<Channel>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct MemoryRegion {
+    pub name: String,
+    pub size: u64,
+    pub page_size: Option<u64>,
+    pub phys_addr: Option<u64>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ProtectionDomain {
+    pub name: String,
+    pub priority: u8,
+    pub pp: bool,
+    pub passive: bool,
+    pub budget: Option<u64>,
+    pub period: Option<u64>,
+    pub program_image: PathBuf,
+    pub maps: Vec<Map>,
+    pub irqs: Vec<Irq>,
+    pub setvars: Vec<SetVar>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Map {
+    pub mr: String,
+    pub vaddr: u64,
+    pub perms: Perms,
+    pub cached: bool,
+    pub setvar_vaddr: Option<String>,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Perms {
+    pub read: bool,
+    pub write: bool,
+    pub execute: bool,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Irq {
+    pub irq: u64,
+    pub id: usize,
+    pub trigger: Option<IrqTrigger>,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum IrqTrigger {
+    Level,
+    Edge,
+}
+
+/// A `<setvar>` element, which patches a symbol with the physical address of a memory region.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SetVar {
+    pub symbol: String,
+    pub region_paddr: String,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Channel {
+    pub ends: [ChannelEnd; 2],
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ChannelEnd {
+    pub pd: String,
+    pub id: usize,
+}
+
+impl SystemDescription {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses and validates a system description.
+    pub fn parse(s: &str) -> Result<Self, ParseError> {
+        let this = Self::parse_unvalidated(s)?;
+        this.validate().map_err(ParseError::Invalid)?;
+        Ok(this)
+    }
+
+    pub fn memory_region(mut self, memory_region: MemoryRegion) -> Self {
+        self.memory_regions.push(memory_region);
+        self
+    }
+
+    pub fn protection_domain(mut self, protection_domain: ProtectionDomain) -> Self {
+        self.protection_domains.push(protection_domain);
+        self
+    }
+
+    pub fn channel(mut self, a: ChannelEnd, b: ChannelEnd) -> Self {
+        self.channels.push(Channel { ends: [a, b] });
+        self
+    }
+
+    pub fn get_memory_region(&self, name: &str) -> Option<&MemoryRegion> {
+        self.memory_regions.iter().find(|mr| mr.name == name)
+    }
+
+    pub fn get_protection_domain(&self, name: &str) -> Option<&ProtectionDomain> {
+        self.protection_domains.iter().find(|pd| pd.name == name)
+    }
+
+    /// Returns the ends of the channels which `pd` participates in, each paired with the name of
+    /// the protection domain at the other end.
+    pub fn channels_of<'a>(
+        &'a self,
+        pd: &'a str,
+    ) -> impl Iterator<Item = (&'a ChannelEnd, &'a str)> + 'a {
+        self.channels.iter().flat_map(move |channel| {
+            let [a, b] = &channel.ends;
+            [(a, b), (b, a)]
+                .into_iter()
+                .filter(move |(this, _)| this.pd == pd)
+                .map(|(this, other)| (this, other.pd.as_str()))
+        })
+    }
+}
+
+impl MemoryRegion {
+    pub fn new(name: impl Into<String>, size: u64) -> Self {
+        Self {
+            name: name.into(),
+            size,
+            page_size: None,
+            phys_addr: None,
+        }
+    }
+
+    pub fn page_size(mut self, page_size: u64) -> Self {
+        self.page_size = Some(page_size);
+        self
+    }
+
+    pub fn phys_addr(mut self, phys_addr: u64) -> Self {
+        self.phys_addr = Some(phys_addr);
+        self
+    }
+
+    pub fn effective_page_size(&self) -> u64 {
+        self.page_size.unwrap_or(DEFAULT_PAGE_SIZE)
+    }
+}
+
+impl ProtectionDomain {
+    pub fn new(name: impl Into<String>, program_image: impl Into<PathBuf>) -> Self {
+        Self {
+            name: name.into(),
+            priority: 0,
+            pp: false,
+            passive: false,
+            budget: None,
+            period: None,
+            program_image: program_image.into(),
+            maps: vec![],
+            irqs: vec![],
+            setvars: vec![],
+        }
+    }
+
+    pub fn priority(mut self, priority: u8) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn pp(mut self, pp: bool) -> Self {
+        self.pp = pp;
+        self
+    }
+
+    pub fn passive(mut self, passive: bool) -> Self {
+        self.passive = passive;
+        self
+    }
+
+    pub fn budget(mut self, budget: u64) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    pub fn period(mut self, period: u64) -> Self {
+        self.period = Some(period);
+        self
+    }
+
+    pub fn map(mut self, map: Map) -> Self {
+        self.maps.push(map);
+        self
+    }
+
+    pub fn irq(mut self, irq: Irq) -> Self {
+        self.irqs.push(irq);
+        self
+    }
+
+    pub fn setvar(mut self, symbol: impl Into<String>, region_paddr: impl Into<String>) -> Self {
+        self.setvars.push(SetVar {
+            symbol: symbol.into(),
+            region_paddr: region_paddr.into(),
+        });
+        self
+    }
+}
+
+impl Map {
+    /// Returns a read-write, cached mapping of `mr` at `vaddr`.
+    pub fn new(mr: impl Into<String>, vaddr: u64) -> Self {
+        Self {
+            mr: mr.into(),
+            vaddr,
+            perms: Perms::READ_WRITE,
+            cached: true,
+            setvar_vaddr: None,
+        }
+    }
+
+    pub fn perms(mut self, perms: Perms) -> Self {
+        self.perms = perms;
+        self
+    }
+
+    pub fn cached(mut self, cached: bool) -> Self {
+        self.cached = cached;
+        self
+    }
+
+    pub fn setvar_vaddr(mut self, symbol: impl Into<String>) -> Self {
+        self.setvar_vaddr = Some(symbol.into());
+        self
+    }
+}
+
+impl Perms {
+    pub const READ_ONLY: Self = Self {
+        read: true,
+        write: false,
+        execute: false,
+    };
+
+    pub const READ_WRITE: Self = Self {
+        read: true,
+        write: true,
+        execute: false,
+    };
+}
+
+impl Irq {
+    pub fn new(irq: u64, id: usize) -> Self {
+        Self {
+            irq,
+            id,
+            trigger: None,
+        }
+    }
+
+    pub fn trigger(mut self, trigger: IrqTrigger) -> Self {
+        self.trigger = Some(trigger);
+        self
+    }
+}
+
+impl ChannelEnd {
+    pub fn new(pd: impl Into<String>, id: usize) -> Self {
+        Self { pd: pd.into(), id }
+    }
+}
@@ -0,0 +1,448 @@
+//
+// Copyright 2023, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use std::error::Error;
+use std::fmt;
+use std::path::PathBuf;
+
+use xmltree::{Element, XMLNode};
+
+use crate::{
+    Channel, ChannelEnd, Irq, IrqTrigger, Map, MemoryRegion, Perms, ProtectionDomain, SetVar,
+    SystemDescription, ValidationError,
+};
+
+#[derive(Debug)]
+pub enum ParseError {
+    Xml(xmltree::ParseError),
+    UnexpectedElement {
+        parent: String,
+        element: String,
+    },
+    UnexpectedAttribute {
+        element: String,
+        attribute: String,
+    },
+    MissingAttribute {
+        element: String,
+        attribute: String,
+    },
+    InvalidAttribute {
+        element: String,
+        attribute: String,
+        value: String,
+    },
+    MissingProgramImage {
+        pd: String,
+    },
+    WrongNumberOfChannelEnds {
+        num_ends: usize,
+    },
+    Invalid(Vec<ValidationError>),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Xml(err) => write!(f, "malformed XML: {err}"),
+            Self::UnexpectedElement { parent, element } => {
+                write!(f, "unexpected element <{element}> in <{parent}>")
+            }
+            Self::UnexpectedAttribute { element, attribute } => {
+                write!(f, "unexpected attribute '{attribute}' on <{element}>")
+            }
+            Self::MissingAttribute { element, attribute } => {
+                write!(f, "missing attribute '{attribute}' on <{element}>")
+            }
+            Self::InvalidAttribute {
+                element,
+                attribute,
+                value,
+            } => write!(
+                f,
+                "invalid value '{value}' for attribute '{attribute}' on <{element}>"
+            ),
+            Self::MissingProgramImage { pd } => {
+                write!(f, "protection domain '{pd}' has no <program_image>")
+            }
+            Self::WrongNumberOfChannelEnds { num_ends } => {
+                write!(
+                    f,
+                    "<channel> must have exactly 2 <end> elements, not {num_ends}"
+                )
+            }
+            Self::Invalid(errs) => {
+                write!(f, "invalid system description:")?;
+                for err in errs {
+                    write!(f, "\n  {err}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+type Result<T> = std::result::Result<T, ParseError>;
+
+impl SystemDescription {
+    /// Parses a system description without validating it.
+    pub fn parse_unvalidated(s: &str) -> Result<Self> {
+        let root = Element::parse(s.as_bytes()).map_err(ParseError::Xml)?;
+        if root.name != "system" {
+            return Err(ParseError::UnexpectedElement {
+                parent: "".to_owned(),
+                element: root.name,
+            });
+        }
+        check_attrs(&root, &[])?;
+        let mut this = Self::new();
+        for child in children(&root) {
+            match child.name.as_str() {
+                "memory_region" => this.memory_regions.push(parse_memory_region(child)?),
+                "protection_domain" => this
+                    .protection_domains
+                    .push(parse_protection_domain(child)?),
+                "channel" => this.channels.push(parse_channel(child)?),
+                _ => return Err(unexpected_element(&root, child)),
+            }
+        }
+        Ok(this)
+    }
+}
+
+fn parse_memory_region(e: &Element) -> Result<MemoryRegion> {
+    check_attrs(e, &["name", "size", "page_size", "phys_addr"])?;
+    check_no_children(e)?;
+    Ok(MemoryRegion {
+        name: required(e, "name")?.to_owned(),
+        size: required_with(e, "size", parse_int)?,
+        page_size: optional_with(e, "page_size", parse_int)?,
+        phys_addr: optional_with(e, "phys_addr", parse_int)?,
+    })
+}
+
+fn parse_protection_domain(e: &Element) -> Result<ProtectionDomain> {
+    check_attrs(
+        e,
+        &["name", "priority", "pp", "passive", "budget", "period"],
+    )?;
+    let name = required(e, "name")?.to_owned();
+    let mut program_image: Option<PathBuf> = None;
+    let mut maps = vec![];
+    let mut irqs = vec![];
+    let mut setvars = vec![];
+    for child in children(e) {
+        check_no_children(child)?;
+        match child.name.as_str() {
+            "program_image" => {
+                check_attrs(child, &["path"])?;
+                if program_image.is_some() {
+                    return Err(unexpected_element(e, child));
+                }
+                program_image = Some(required(child, "path")?.into());
+            }
+            "map" => {
+                check_attrs(child, &["mr", "vaddr", "perms", "cached", "setvar_vaddr"])?;
+                maps.push(Map {
+                    mr: required(child, "mr")?.to_owned(),
+                    vaddr: required_with(child, "vaddr", parse_int)?,
+                    perms: optional_with(child, "perms", parse_perms)?.unwrap_or(Perms::READ_WRITE),
+                    cached: optional_with(child, "cached", parse_bool)?.unwrap_or(true),
+                    setvar_vaddr: optional(child, "setvar_vaddr").map(ToOwned::to_owned),
+                });
+            }
+            "irq" => {
+                check_attrs(child, &["irq", "id", "trigger"])?;
+                irqs.push(Irq {
+                    irq: required_with(child, "irq", parse_int)?,
+                    id: required_with(child, "id", parse_int)?,
+                    trigger: optional_with(child, "trigger", parse_trigger)?,
+                });
+            }
+            "setvar" => {
+                check_attrs(child, &["symbol", "region_paddr"])?;
+                setvars.push(SetVar {
+                    symbol: required(child, "symbol")?.to_owned(),
+                    region_paddr: required(child, "region_paddr")?.to_owned(),
+                });
+            }
+            _ => return Err(unexpected_element(e, child)),
+        }
+    }
+    Ok(ProtectionDomain {
+        program_image: program_image
+            .ok_or_else(|| ParseError::MissingProgramImage { pd: name.clone() })?,
+        name,
+        priority: optional_with(e, "priority", parse_int)?.unwrap_or(0),
+        pp: optional_with(e, "pp", parse_bool)?.unwrap_or(false),
+        passive: optional_with(e, "passive", parse_bool)?.unwrap_or(false),
+        budget: optional_with(e, "budget", parse_int)?,
+        period: optional_with(e, "period", parse_int)?,
+        maps,
+        irqs,
+        setvars,
+    })
+}
+
+fn parse_channel(e: &Element) -> Result<Channel> {
+    check_attrs(e, &[])?;
+    let mut ends = vec![];
+    for child in children(e) {
+        check_no_children(child)?;
+        match child.name.as_str() {
+            "end" => {
+                check_attrs(child, &["pd", "id"])?;
+                ends.push(ChannelEnd {
+                    pd: required(child, "pd")?.to_owned(),
+                    id: required_with(child, "id", parse_int)?,
+                });
+            }
+            _ => return Err(unexpected_element(e, child)),
+        }
+    }
+    let num_ends = ends.len();
+    Ok(Channel {
+        ends: ends
+            .try_into()
+            .map_err(|_| ParseError::WrongNumberOfChannelEnds { num_ends })?,
+    })
+}
+
+fn children(e: &Element) -> impl Iterator<Item = &Element> {
+    e.children.iter().filter_map(XMLNode::as_element)
+}
+
+fn unexpected_element(parent: &Element, child: &Element) -> ParseError {
+    ParseError::UnexpectedElement {
+        parent: parent.name.clone(),
+        element: child.name.clone(),
+    }
+}
+
+fn check_no_children(e: &Element) -> Result<()> {
+    match children(e).next() {
+        Some(child) => Err(unexpected_element(e, child)),
+        None => Ok(()),
+    }
+}
+
+fn check_attrs(e: &Element, allowed: &[&str]) -> Result<()> {
+    for attribute in e.attributes.keys() {
+        if !allowed.contains(&attribute.as_str()) {
+            return Err(ParseError::UnexpectedAttribute {
+                element: e.name.clone(),
+                attribute: attribute.clone(),
+            });
+        }
+    }
+    Ok(())
+}
+
+fn optional<'a>(e: &'a Element, attribute: &str) -> Option<&'a str> {
+    e.attributes.get(attribute).map(String::as_str)
+}
+
+fn required<'a>(e: &'a Element, attribute: &str) -> Result<&'a str> {
+    optional(e, attribute).ok_or_else(|| ParseError::MissingAttribute {
+        element: e.name.clone(),
+        attribute: attribute.to_owned(),
+    })
+}
+
+fn optional_with<T>(
+    e: &Element,
+    attribute: &str,
+    f: impl FnOnce(&str) -> Option<T>,
+) -> Result<Option<T>> {
+    optional(e, attribute)
+        .map(|value| {
+            f(value).ok_or_else(|| ParseError::InvalidAttribute {
+                element: e.name.clone(),
+                attribute: attribute.to_owned(),
+                value: value.to_owned(),
+            })
+        })
+        .transpose()
+}
+
+fn required_with<T>(e: &Element, attribute: &str, f: impl FnOnce(&str) -> Option<T>) -> Result<T> {
+    required(e, attribute)?;
+    Ok(optional_with(e, attribute, f)?.unwrap())
+}
+
+// Like the `microkit` tool, accept decimal or `0x`-prefixed hexadecimal, with `_` separators.
+fn parse_int<T: TryFrom<u64>>(s: &str) -> Option<T> {
+    let s = s.replace('_', "");
+    let n = match s.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+    n.ok().and_then(|n| n.try_into().ok())
+}
+
+fn parse_bool(s: &str) -> Option<bool> {
+    match s {
+        "true" => Some(true),
+        "false" => Some(false),
+        _ => None,
+    }
+}
+
+fn parse_perms(s: &str) -> Option<Perms> {
+    let mut perms = Perms {
+        read: false,
+        write: false,
+        execute: false,
+    };
+    for c in s.chars() {
+        let perm = match c {
+            'r' => &mut perms.read,
+            'w' => &mut perms.write,
+            'x' => &mut perms.execute,
+            _ => return None,
+        };
+        if *perm {
+            return None;
+        }
+        *perm = true;
+    }
+    Some(perms)
+}
+
+fn parse_trigger(s: &str) -> Option<IrqTrigger> {
+    match s {
+        "level" => Some(IrqTrigger::Level),
+        "edge" => Some(IrqTrigger::Edge),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SDF: &str = r#"
+        <system>
+            <memory_region name="buf" size="0x2_000" />
+            <memory_region name="uart" size="0x1000" phys_addr="0x9000000" />
+            <protection_domain name="client" priority="100" budget="100" period="200">
+                <program_image path="client.elf" />
+                <map mr="buf" vaddr="0x4000000" perms="r" setvar_vaddr="buf_vaddr" />
+                <setvar symbol="buf_paddr" region_paddr="buf" />
+            </protection_domain>
+            <protection_domain name="server" pp="true">
+                <program_image path="server.elf" />
+                <map mr="buf" vaddr="0x4000000" cached="false" />
+                <map mr="uart" vaddr="0x5000000" perms="rw" />
+                <irq irq="33" id="1" trigger="level" />
+            </protection_domain>
+            <channel>
+                <end pd="client" id="0" />
+                <end pd="server" id="0" />
+            </channel>
+        </system>
+    "#;
+
+    #[test]
+    fn parse() {
+        let sdf = SystemDescription::parse(SDF).unwrap();
+        let expected = SystemDescription::new()
+            .memory_region(MemoryRegion::new("buf", 0x2000))
+            .memory_region(MemoryRegion::new("uart", 0x1000).phys_addr(0x900_0000))
+            .protection_domain(
+                ProtectionDomain::new("client", "client.elf")
+                    .priority(100)
+                    .budget(100)
+                    .period(200)
+                    .map(
+                        Map::new("buf", 0x400_0000)
+                            .perms(Perms::READ_ONLY)
+                            .setvar_vaddr("buf_vaddr"),
+                    )
+                    .setvar("buf_paddr", "buf"),
+            )
+            .protection_domain(
+                ProtectionDomain::new("server", "server.elf")
+                    .pp(true)
+                    .map(Map::new("buf", 0x400_0000).cached(false))
+                    .map(Map::new("uart", 0x500_0000))
+                    .irq(Irq::new(33, 1).trigger(IrqTrigger::Level)),
+            )
+            .channel(ChannelEnd::new("client", 0), ChannelEnd::new("server", 0));
+        assert_eq!(sdf, expected);
+    }
+
+    #[test]
+    fn render_round_trip() {
+        let sdf = SystemDescription::parse(SDF).unwrap();
+        assert_eq!(SystemDescription::parse(&sdf.to_xml()).unwrap(), sdf);
+    }
+
+    fn parse_err(s: &str) -> ParseError {
+        SystemDescription::parse(s).unwrap_err()
+    }
+
+    #[test]
+    fn malformed() {
+        assert!(matches!(parse_err("<system>"), ParseError::Xml(_)));
+        assert!(matches!(
+            parse_err("<sys />"),
+            ParseError::UnexpectedElement { element, .. } if element == "sys"
+        ));
+        assert!(matches!(
+            parse_err(r#"<system><memory_region name="a" size="0x1000" cached="true" /></system>"#),
+            ParseError::UnexpectedAttribute { attribute, .. } if attribute == "cached"
+        ));
+        assert!(matches!(
+            parse_err(r#"<system><memory_region name="a" /></system>"#),
+            ParseError::MissingAttribute { attribute, .. } if attribute == "size"
+        ));
+        assert!(matches!(
+            parse_err(r#"<system><memory_region name="a" size="4k" /></system>"#),
+            ParseError::InvalidAttribute { value, .. } if value == "4k"
+        ));
+        assert!(matches!(
+            parse_err(r#"<system><protection_domain name="a" /></system>"#),
+            ParseError::MissingProgramImage { pd } if pd == "a"
+        ));
+        assert!(matches!(
+            parse_err(r#"<system><channel><end pd="a" id="0" /></channel></system>"#),
+            ParseError::WrongNumberOfChannelEnds { num_ends: 1 }
+        ));
+    }
+
+    #[test]
+    fn attribute_values() {
+        assert_eq!(parse_int::<u64>("0x1_000"), Some(0x1000));
+        assert_eq!(parse_int::<u64>("1_000"), Some(1000));
+        assert_eq!(parse_int::<u8>("256"), None);
+        assert_eq!(parse_perms("rwx").map(|perms| perms.execute), Some(true));
+        assert_eq!(parse_perms("rr"), None);
+        assert_eq!(parse_bool("1"), None);
+    }
+
+    #[test]
+    fn invalid() {
+        let s = r#"
+            <system>
+                <protection_domain name="a">
+                    <program_image path="a.elf" />
+                    <map mr="missing" vaddr="0x1000" />
+                </protection_domain>
+            </system>
+        "#;
+        assert!(SystemDescription::parse_unvalidated(s).is_ok());
+        assert!(matches!(
+            parse_err(s),
+            ParseError::Invalid(errs) if errs == [ValidationError::UnknownMemoryRegion {
+                pd: "a".to_owned(),
+                mr: "missing".to_owned(),
+            }]
+        ));
+    }
+}
@@ -0,0 +1,133 @@
+//
+// Copyright 2023, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use xmltree::{Element, EmitterConfig, XMLNode};
+
+use crate::{IrqTrigger, Perms, SystemDescription};
+
+impl SystemDescription {
+    /// Renders this system description in the format expected by the `microkit` tool.
+    pub fn to_xml(&self) -> String {
+        let mut root = Element::new("system");
+
+        for mr in &self.memory_regions {
+            let mut e = Element::new("memory_region");
+            attr(&mut e, "name", &mr.name);
+            attr(&mut e, "size", hex(mr.size));
+            if let Some(page_size) = mr.page_size {
+                attr(&mut e, "page_size", hex(page_size));
+            }
+            if let Some(phys_addr) = mr.phys_addr {
+                attr(&mut e, "phys_addr", hex(phys_addr));
+            }
+            child(&mut root, e);
+        }
+
+        for pd in &self.protection_domains {
+            let mut e = Element::new("protection_domain");
+            attr(&mut e, "name", &pd.name);
+            attr(&mut e, "priority", pd.priority.to_string());
+            if pd.pp {
+                attr(&mut e, "pp", "true");
+            }
+            if pd.passive {
+                attr(&mut e, "passive", "true");
+            }
+            if let Some(budget) = pd.budget {
+                attr(&mut e, "budget", budget.to_string());
+            }
+            if let Some(period) = pd.period {
+                attr(&mut e, "period", period.to_string());
+            }
+
+            let mut program_image = Element::new("program_image");
+            attr(
+                &mut program_image,
+                "path",
+                pd.program_image.display().to_string(),
+            );
+            child(&mut e, program_image);
+
+            for map in &pd.maps {
+                let mut map_e = Element::new("map");
+                attr(&mut map_e, "mr", &map.mr);
+                attr(&mut map_e, "vaddr", hex(map.vaddr));
+                attr(&mut map_e, "perms", render_perms(&map.perms));
+                attr(&mut map_e, "cached", map.cached.to_string());
+                if let Some(symbol) = &map.setvar_vaddr {
+                    attr(&mut map_e, "setvar_vaddr", symbol);
+                }
+                child(&mut e, map_e);
+            }
+
+            for setvar in &pd.setvars {
+                let mut setvar_e = Element::new("setvar");
+                attr(&mut setvar_e, "symbol", &setvar.symbol);
+                attr(&mut setvar_e, "region_paddr", &setvar.region_paddr);
+                child(&mut e, setvar_e);
+            }
+
+            for irq in &pd.irqs {
+                let mut irq_e = Element::new("irq");
+                attr(&mut irq_e, "irq", irq.irq.to_string());
+                attr(&mut irq_e, "id", irq.id.to_string());
+                if let Some(trigger) = irq.trigger {
+                    let trigger = match trigger {
+                        IrqTrigger::Level => "level",
+                        IrqTrigger::Edge => "edge",
+                    };
+                    attr(&mut irq_e, "trigger", trigger);
+                }
+                child(&mut e, irq_e);
+            }
+
+            child(&mut root, e);
+        }
+
+        for channel in &self.channels {
+            let mut e = Element::new("channel");
+            for end in &channel.ends {
+                let mut end_e = Element::new("end");
+                attr(&mut end_e, "pd", &end.pd);
+                attr(&mut end_e, "id", end.id.to_string());
+                child(&mut e, end_e);
+            }
+            child(&mut root, e);
+        }
+
+        let mut buf = vec![];
+        root.write_with_config(&mut buf, EmitterConfig::new().perform_indent(true))
+            .unwrap();
+        String::from_utf8(buf).unwrap()
+    }
+}
+
+fn attr(e: &mut Element, name: &str, value: impl AsRef<str>) {
+    e.attributes
+        .insert(name.to_owned(), value.as_ref().to_owned());
+}
+
+fn child(e: &mut Element, child: Element) {
+    e.children.push(XMLNode::Element(child));
+}
+
+fn hex(n: u64) -> String {
+    format!("{n:#x}")
+}
+
+fn render_perms(perms: &Perms) -> String {
+    let mut s = String::new();
+    if perms.read {
+        s.push('r');
+    }
+    if perms.write {
+        s.push('w');
+    }
+    if perms.execute {
+        s.push('x');
+    }
+    s
+}
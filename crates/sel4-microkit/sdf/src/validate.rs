@@ -0,0 +1,483 @@
+//
+// Copyright 2023, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::ops::Range;
+
+use crate::{SystemDescription, MAX_CHANNELS, MAX_PD_NAME_LEN, MAX_PRIORITY, PAGE_SIZES};
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ValidationError {
+    DuplicateMemoryRegion {
+        mr: String,
+    },
+    InvalidPageSize {
+        mr: String,
+        page_size: u64,
+    },
+    MisalignedSize {
+        mr: String,
+    },
+    MisalignedPhysAddr {
+        mr: String,
+    },
+    DuplicateProtectionDomain {
+        pd: String,
+    },
+    NameTooLong {
+        pd: String,
+    },
+    InvalidPriority {
+        pd: String,
+        priority: u8,
+    },
+    BudgetExceedsPeriod {
+        pd: String,
+    },
+    UnknownMemoryRegion {
+        pd: String,
+        mr: String,
+    },
+    MisalignedMap {
+        pd: String,
+        mr: String,
+    },
+    OverlappingMaps {
+        pd: String,
+        mr_a: String,
+        mr_b: String,
+    },
+    DuplicateSymbol {
+        pd: String,
+        symbol: String,
+    },
+    /// Accessors named after symbols are generated for them, so symbols must be Rust identifiers.
+    InvalidSymbol {
+        pd: String,
+        symbol: String,
+    },
+    DuplicateIrq {
+        irq: u64,
+    },
+    UnknownProtectionDomain {
+        pd: String,
+    },
+    SelfChannel {
+        pd: String,
+    },
+    InvalidChannelId {
+        pd: String,
+        id: usize,
+    },
+    DuplicateChannelId {
+        pd: String,
+        id: usize,
+    },
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::DuplicateMemoryRegion { mr } => write!(f, "duplicate memory region '{mr}'"),
+            Self::InvalidPageSize { mr, page_size } => write!(
+                f,
+                "memory region '{mr}' has invalid page size {page_size:#x}"
+            ),
+            Self::MisalignedSize { mr } => write!(
+                f,
+                "size of memory region '{mr}' is not a multiple of its page size"
+            ),
+            Self::MisalignedPhysAddr { mr } => write!(
+                f,
+                "physical address of memory region '{mr}' is not aligned to its page size"
+            ),
+            Self::DuplicateProtectionDomain { pd } => {
+                write!(f, "duplicate protection domain '{pd}'")
+            }
+            Self::NameTooLong { pd } => write!(
+                f,
+                "name of protection domain '{pd}' is longer than {MAX_PD_NAME_LEN} bytes"
+            ),
+            Self::InvalidPriority { pd, priority } => write!(
+                f,
+                "protection domain '{pd}' has priority {priority}, which exceeds {MAX_PRIORITY}"
+            ),
+            Self::BudgetExceedsPeriod { pd } => write!(
+                f,
+                "budget of protection domain '{pd}' exceeds its period"
+            ),
+            Self::UnknownMemoryRegion { pd, mr } => write!(
+                f,
+                "protection domain '{pd}' refers to unknown memory region '{mr}'"
+            ),
+            Self::MisalignedMap { pd, mr } => write!(
+                f,
+                "protection domain '{pd}' maps memory region '{mr}' at a vaddr which is not aligned to its page size"
+            ),
+            Self::OverlappingMaps { pd, mr_a, mr_b } => write!(
+                f,
+                "protection domain '{pd}' has overlapping maps of memory regions '{mr_a}' and '{mr_b}'"
+            ),
+            Self::DuplicateSymbol { pd, symbol } => write!(
+                f,
+                "protection domain '{pd}' sets symbol '{symbol}' more than once"
+            ),
+            Self::InvalidSymbol { pd, symbol } => write!(
+                f,
+                "protection domain '{pd}' sets symbol '{symbol}', which is not a Rust identifier"
+            ),
+            Self::DuplicateIrq { irq } => write!(f, "IRQ {irq} is claimed more than once"),
+            Self::UnknownProtectionDomain { pd } => {
+                write!(f, "channel refers to unknown protection domain '{pd}'")
+            }
+            Self::SelfChannel { pd } => {
+                write!(f, "channel connects protection domain '{pd}' to itself")
+            }
+            Self::InvalidChannelId { pd, id } => write!(
+                f,
+                "protection domain '{pd}' uses channel id {id}, which is not less than {MAX_CHANNELS}"
+            ),
+            Self::DuplicateChannelId { pd, id } => write!(
+                f,
+                "protection domain '{pd}' uses channel id {id} more than once"
+            ),
+        }
+    }
+}
+
+impl SystemDescription {
+    /// Checks the constraints that the `microkit` tool imposes on system descriptions.
+    ///
+    /// All violations are reported, rather than just the first.
+    pub fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        let mut errs = vec![];
+
+        let mut mr_names = BTreeSet::new();
+        for mr in &self.memory_regions {
+            if !mr_names.insert(mr.name.as_str()) {
+                errs.push(ValidationError::DuplicateMemoryRegion {
+                    mr: mr.name.clone(),
+                });
+            }
+            let page_size = mr.effective_page_size();
+            if !PAGE_SIZES.contains(&page_size) {
+                errs.push(ValidationError::InvalidPageSize {
+                    mr: mr.name.clone(),
+                    page_size,
+                });
+                continue;
+            }
+            if mr.size == 0 || mr.size % page_size != 0 {
+                errs.push(ValidationError::MisalignedSize {
+                    mr: mr.name.clone(),
+                });
+            }
+            if mr.phys_addr.is_some_and(|paddr| paddr % page_size != 0) {
+                errs.push(ValidationError::MisalignedPhysAddr {
+                    mr: mr.name.clone(),
+                });
+            }
+        }
+
+        let mut pd_names = BTreeSet::new();
+        let mut irqs = BTreeSet::new();
+        let mut channel_ids = BTreeMap::new();
+
+        for pd in &self.protection_domains {
+            let name = || pd.name.clone();
+            if !pd_names.insert(pd.name.as_str()) {
+                errs.push(ValidationError::DuplicateProtectionDomain { pd: name() });
+            }
+            if pd.name.len() > MAX_PD_NAME_LEN {
+                errs.push(ValidationError::NameTooLong { pd: name() });
+            }
+            if pd.priority > MAX_PRIORITY {
+                errs.push(ValidationError::InvalidPriority {
+                    pd: name(),
+                    priority: pd.priority,
+                });
+            }
+            if let (Some(budget), Some(period)) = (pd.budget, pd.period) {
+                if budget > period {
+                    errs.push(ValidationError::BudgetExceedsPeriod { pd: name() });
+                }
+            }
+
+            let mut symbols = BTreeSet::new();
+            let mut mapped: Vec<(Range<u64>, &str)> = vec![];
+            for map in &pd.maps {
+                if let Some(symbol) = &map.setvar_vaddr {
+                    check_symbol(&mut errs, &mut symbols, &pd.name, symbol);
+                }
+                let Some(mr) = self.get_memory_region(&map.mr) else {
+                    errs.push(ValidationError::UnknownMemoryRegion {
+                        pd: name(),
+                        mr: map.mr.clone(),
+                    });
+                    continue;
+                };
+                if map.vaddr % mr.effective_page_size() != 0 {
+                    errs.push(ValidationError::MisalignedMap {
+                        pd: name(),
+                        mr: mr.name.clone(),
+                    });
+                }
+                let range = map.vaddr..map.vaddr.saturating_add(mr.size);
+                for (other_range, other_mr) in &mapped {
+                    if range.start < other_range.end && other_range.start < range.end {
+                        errs.push(ValidationError::OverlappingMaps {
+                            pd: name(),
+                            mr_a: other_mr.to_string(),
+                            mr_b: mr.name.clone(),
+                        });
+                    }
+                }
+                mapped.push((range, &mr.name));
+            }
+
+            for setvar in &pd.setvars {
+                check_symbol(&mut errs, &mut symbols, &pd.name, &setvar.symbol);
+                if self.get_memory_region(&setvar.region_paddr).is_none() {
+                    errs.push(ValidationError::UnknownMemoryRegion {
+                        pd: name(),
+                        mr: setvar.region_paddr.clone(),
+                    });
+                }
+            }
+
+            for irq in &pd.irqs {
+                if !irqs.insert(irq.irq) {
+                    errs.push(ValidationError::DuplicateIrq { irq: irq.irq });
+                }
+                check_channel_id(&mut errs, &mut channel_ids, &pd.name, irq.id);
+            }
+        }
+
+        for channel in &self.channels {
+            let [a, b] = &channel.ends;
+            if a.pd == b.pd {
+                errs.push(ValidationError::SelfChannel { pd: a.pd.clone() });
+            }
+            for end in &channel.ends {
+                if self.get_protection_domain(&end.pd).is_none() {
+                    errs.push(ValidationError::UnknownProtectionDomain { pd: end.pd.clone() });
+                }
+                check_channel_id(&mut errs, &mut channel_ids, &end.pd, end.id);
+            }
+        }
+
+        if errs.is_empty() {
+            Ok(())
+        } else {
+            Err(errs)
+        }
+    }
+}
+
+fn check_symbol<'a>(
+    errs: &mut Vec<ValidationError>,
+    symbols: &mut BTreeSet<&'a str>,
+    pd: &str,
+    symbol: &'a str,
+) {
+    if !is_identifier(symbol) {
+        errs.push(ValidationError::InvalidSymbol {
+            pd: pd.to_owned(),
+            symbol: symbol.to_owned(),
+        });
+    }
+    if !symbols.insert(symbol) {
+        errs.push(ValidationError::DuplicateSymbol {
+            pd: pd.to_owned(),
+            symbol: symbol.to_owned(),
+        });
+    }
+}
+
+// Keywords, including reserved ones, of all editions.
+const KEYWORDS: &[&str] = &[
+    "Self", "abstract", "as", "async", "await", "become", "box", "break", "const", "continue",
+    "crate", "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if",
+    "impl", "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub",
+    "ref", "return", "self", "static", "struct", "super", "trait", "true", "try", "type", "typeof",
+    "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && s != "_"
+        && !KEYWORDS.contains(&s)
+}
+
+fn check_channel_id<'a>(
+    errs: &mut Vec<ValidationError>,
+    channel_ids: &mut BTreeMap<&'a str, BTreeSet<usize>>,
+    pd: &'a str,
+    id: usize,
+) {
+    if id >= MAX_CHANNELS {
+        errs.push(ValidationError::InvalidChannelId {
+            pd: pd.to_owned(),
+            id,
+        });
+    }
+    if !channel_ids.entry(pd).or_default().insert(id) {
+        errs.push(ValidationError::DuplicateChannelId {
+            pd: pd.to_owned(),
+            id,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{ChannelEnd, Irq, Map, MemoryRegion, ProtectionDomain};
+
+    fn errs(sdf: &SystemDescription) -> Vec<ValidationError> {
+        sdf.validate().unwrap_err()
+    }
+
+    #[test]
+    fn valid() {
+        let sdf = SystemDescription::new()
+            .memory_region(MemoryRegion::new("a", 0x400_000).page_size(0x200_000))
+            .protection_domain(
+                ProtectionDomain::new("client", "client.elf")
+                    .map(Map::new("a", 0x200_000).setvar_vaddr("a_vaddr"))
+                    .irq(Irq::new(33, MAX_CHANNELS - 1)),
+            )
+            .protection_domain(ProtectionDomain::new("server", "server.elf").priority(254))
+            .channel(ChannelEnd::new("client", 0), ChannelEnd::new("server", 0));
+        assert_eq!(sdf.validate(), Ok(()));
+    }
+
+    #[test]
+    fn memory_regions() {
+        let sdf = SystemDescription::new()
+            .memory_region(MemoryRegion::new("a", 0x1000))
+            .memory_region(MemoryRegion::new("a", 0x1800))
+            .memory_region(MemoryRegion::new("b", 0x1000).page_size(0x2000))
+            .memory_region(MemoryRegion::new("c", 0x1000).phys_addr(0x800));
+        assert_eq!(
+            errs(&sdf),
+            [
+                ValidationError::DuplicateMemoryRegion { mr: "a".to_owned() },
+                ValidationError::MisalignedSize { mr: "a".to_owned() },
+                ValidationError::InvalidPageSize {
+                    mr: "b".to_owned(),
+                    page_size: 0x2000,
+                },
+                ValidationError::MisalignedPhysAddr { mr: "c".to_owned() },
+            ]
+        );
+    }
+
+    #[test]
+    fn protection_domains() {
+        let pd = || ProtectionDomain::new("a", "a.elf");
+        let sdf = SystemDescription::new()
+            .memory_region(MemoryRegion::new("mr", 0x2000))
+            .protection_domain(
+                pd().priority(255)
+                    .budget(2)
+                    .period(1)
+                    .map(Map::new("mr", 0x1000).setvar_vaddr("x"))
+                    .map(Map::new("mr", 0x2800))
+                    .map(Map::new("missing", 0x10_000))
+                    .setvar("x", "mr")
+                    .setvar("dma-vaddr", "mr")
+                    .setvar("type", "mr"),
+            )
+            .protection_domain(pd())
+            .protection_domain(ProtectionDomain::new("a_very_long_name", "b.elf"));
+        assert_eq!(
+            errs(&sdf),
+            [
+                ValidationError::InvalidPriority {
+                    pd: "a".to_owned(),
+                    priority: 255,
+                },
+                ValidationError::BudgetExceedsPeriod { pd: "a".to_owned() },
+                ValidationError::MisalignedMap {
+                    pd: "a".to_owned(),
+                    mr: "mr".to_owned(),
+                },
+                ValidationError::OverlappingMaps {
+                    pd: "a".to_owned(),
+                    mr_a: "mr".to_owned(),
+                    mr_b: "mr".to_owned(),
+                },
+                ValidationError::UnknownMemoryRegion {
+                    pd: "a".to_owned(),
+                    mr: "missing".to_owned(),
+                },
+                ValidationError::DuplicateSymbol {
+                    pd: "a".to_owned(),
+                    symbol: "x".to_owned(),
+                },
+                ValidationError::InvalidSymbol {
+                    pd: "a".to_owned(),
+                    symbol: "dma-vaddr".to_owned(),
+                },
+                ValidationError::InvalidSymbol {
+                    pd: "a".to_owned(),
+                    symbol: "type".to_owned(),
+                },
+                ValidationError::DuplicateProtectionDomain { pd: "a".to_owned() },
+                ValidationError::NameTooLong {
+                    pd: "a_very_long_name".to_owned(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn channels() {
+        let sdf = SystemDescription::new()
+            .protection_domain(
+                ProtectionDomain::new("a", "a.elf")
+                    .irq(Irq::new(1, 0))
+                    .irq(Irq::new(1, MAX_CHANNELS)),
+            )
+            .protection_domain(ProtectionDomain::new("b", "b.elf"))
+            .channel(ChannelEnd::new("a", 0), ChannelEnd::new("b", 0))
+            .channel(ChannelEnd::new("b", 1), ChannelEnd::new("b", 2))
+            .channel(ChannelEnd::new("b", 3), ChannelEnd::new("c", 0));
+        assert_eq!(
+            errs(&sdf),
+            [
+                ValidationError::DuplicateIrq { irq: 1 },
+                ValidationError::InvalidChannelId {
+                    pd: "a".to_owned(),
+                    id: MAX_CHANNELS,
+                },
+                ValidationError::DuplicateChannelId {
+                    pd: "a".to_owned(),
+                    id: 0,
+                },
+                ValidationError::SelfChannel { pd: "b".to_owned() },
+                ValidationError::UnknownProtectionDomain { pd: "c".to_owned() },
+            ]
+        );
+    }
+
+    #[test]
+    fn identifiers() {
+        for s in ["a", "_a", "buf_vaddr", "x2"] {
+            assert!(is_identifier(s), "{s}");
+        }
+        for s in ["", "_", "2x", "dma-vaddr", "a.b", "type", "Self", "r#type"] {
+            assert!(!is_identifier(s), "{s}");
+        }
+    }
+}
@@ -0,0 +1,398 @@
+//
+// Copyright 2023, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use alloc::rc::Rc;
+use core::array;
+use core::cell::{Cell, RefCell};
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+
+use futures::stream::Stream;
+use futures::task::{LocalSpawnExt, SpawnError};
+
+use sel4_async_single_threaded_executor::{LocalPool, LocalSpawner};
+
+use crate::cspace::{Channel, MAX_CHANNELS};
+use crate::handler::Handler;
+use crate::message::{FastMessageRegisters, MessageInfo};
+
+/// A [`Handler`] which drives a set of tasks on a single-threaded executor.
+///
+/// Each time the protection domain receives an event, the event is delivered to the tasks which
+/// are awaiting it, and the executor is run until it stalls. The protection domain then blocks in
+/// the main loop until the next event arrives.
+///
+/// Tasks interact with the outside world through an [`AsyncContext`].
+///
+/// The executor has no notion of time or of devices. Protection domains which must poll other
+/// state after the executor stalls (for example, timer queues or network interfaces) can provide a
+/// poll function using [`AsyncHandler::new_with_poll`]. It is called each time the executor
+/// stalls, and returns whether it made progress, in which case the executor is run again.
+pub struct AsyncHandler<P = fn() -> bool> {
+    local_pool: LocalPool,
+    shared: Rc<Shared>,
+    poll: P,
+}
+
+impl AsyncHandler {
+    pub fn new<T: Future<Output = ()> + 'static>(f: impl FnOnce(AsyncContext) -> T) -> Self {
+        Self::new_with_poll(|| false, f)
+    }
+}
+
+impl<P: FnMut() -> bool> AsyncHandler<P> {
+    /// Spawns the future returned by `f`, and runs the executor until it stalls for the first
+    /// time.
+    pub fn new_with_poll<T: Future<Output = ()> + 'static>(
+        poll: P,
+        f: impl FnOnce(AsyncContext) -> T,
+    ) -> Self {
+        let local_pool = LocalPool::new();
+        let shared = Rc::new(Shared::new());
+        let ctx = AsyncContext {
+            shared: shared.clone(),
+            spawner: local_pool.spawner(),
+        };
+        ctx.spawner.spawn_local(f(ctx.clone())).unwrap();
+        let mut this = Self {
+            local_pool,
+            shared,
+            poll,
+        };
+        this.react();
+        this
+    }
+
+    fn react(&mut self) {
+        loop {
+            let _ = self.local_pool.run_all_until_stalled();
+            if !(self.poll)() {
+                break;
+            }
+        }
+    }
+}
+
+impl<P: FnMut() -> bool> Handler for AsyncHandler<P> {
+    type Error = AsyncHandlerError;
+
+    fn notified(&mut self, channel: Channel) -> Result<(), Self::Error> {
+        self.shared.notify(channel);
+        self.react();
+        Ok(())
+    }
+
+    fn protected_with_mrs(
+        &mut self,
+        channel: Channel,
+        msg_info: MessageInfo,
+        msg: FastMessageRegisters,
+    ) -> Result<(MessageInfo, FastMessageRegisters), Self::Error> {
+        self.shared.deliver_call(channel, msg_info, msg);
+        self.react();
+        self.shared.take_reply(channel)
+    }
+}
+
+/// Error type for [`AsyncHandler`].
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum AsyncHandlerError {
+    /// The executor stalled before a task replied to a protected procedure call.
+    ///
+    /// The reply capability is overwritten each time this protection domain receives an event,
+    /// so a reply must be produced before the executor stalls.
+    UnansweredProtectedCall { channel: Channel },
+}
+
+impl fmt::Display for AsyncHandlerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnansweredProtectedCall { channel } => write!(
+                f,
+                "protected procedure call from channel {channel:?} was not answered before the executor stalled"
+            ),
+        }
+    }
+}
+
+/// A handle through which an [`AsyncHandler`]'s tasks await events and spawn other tasks.
+#[derive(Clone)]
+pub struct AsyncContext {
+    shared: Rc<Shared>,
+    spawner: LocalSpawner,
+}
+
+impl AsyncContext {
+    pub fn spawner(&self) -> LocalSpawner {
+        self.spawner.clone()
+    }
+
+    pub fn spawn(&self, fut: impl Future<Output = ()> + 'static) -> Result<(), SpawnError> {
+        self.spawner.spawn_local(fut)
+    }
+
+    /// Returns a stream which yields an item each time `channel` is notified.
+    ///
+    /// As with the underlying notification object, notifications which arrive before they are
+    /// awaited are coalesced into a single item. Only one task should await a given channel's
+    /// notifications at a time.
+    pub fn notifications(&self, channel: Channel) -> Notifications {
+        Notifications {
+            shared: self.shared.clone(),
+            channel,
+        }
+    }
+
+    /// Waits for the next notification from `channel`.
+    pub async fn notified(&self, channel: Channel) {
+        self.notifications(channel).next_notification().await
+    }
+
+    /// Returns a stream of the protected procedure calls received on `channel`.
+    ///
+    /// Only one task should await a given channel's protected procedure calls at a time. See
+    /// [`ProtectedCall`] for constraints on replying.
+    pub fn protected_calls(&self, channel: Channel) -> ProtectedCalls {
+        ProtectedCalls {
+            shared: self.shared.clone(),
+            channel,
+        }
+    }
+}
+
+/// Stream returned by [`AsyncContext::notifications`].
+pub struct Notifications {
+    shared: Rc<Shared>,
+    channel: Channel,
+}
+
+impl Notifications {
+    pub fn channel(&self) -> Channel {
+        self.channel
+    }
+
+    pub async fn next_notification(&mut self) {
+        futures::future::poll_fn(|cx| self.shared.poll_notification(self.channel, cx)).await
+    }
+}
+
+impl Stream for Notifications {
+    type Item = ();
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.shared.poll_notification(self.channel, cx).map(Some)
+    }
+}
+
+/// Stream returned by [`AsyncContext::protected_calls`].
+pub struct ProtectedCalls {
+    shared: Rc<Shared>,
+    channel: Channel,
+}
+
+impl ProtectedCalls {
+    pub fn channel(&self) -> Channel {
+        self.channel
+    }
+
+    pub async fn next_call(&mut self) -> ProtectedCall {
+        futures::future::poll_fn(|cx| self.shared.poll_call(self.channel, cx)).await
+    }
+}
+
+impl Stream for ProtectedCalls {
+    type Item = ProtectedCall;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.shared.poll_call(self.channel, cx).map(Some)
+    }
+}
+
+/// An incoming protected procedure call, awaiting a reply.
+///
+/// The main loop sends the reply once the executor stalls, so a task must call
+/// [`reply`](ProtectedCall::reply) without awaiting any other event in between. If no reply has
+/// been produced by then, the [`AsyncHandler`] fails with
+/// [`AsyncHandlerError::UnansweredProtectedCall`], and replies to the call made later are ignored.
+///
+/// Only the first [`NUM_FAST_MESSAGE_REGISTERS`](crate::NUM_FAST_MESSAGE_REGISTERS) message
+/// registers of the request and of the reply are carried by this type. Any others are in the IPC
+/// buffer, where they are only valid until this protection domain next performs IPC.
+pub struct ProtectedCall {
+    shared: Rc<Shared>,
+    channel: Channel,
+    generation: u64,
+    msg_info: MessageInfo,
+    msg: FastMessageRegisters,
+}
+
+impl ProtectedCall {
+    pub fn channel(&self) -> Channel {
+        self.channel
+    }
+
+    pub fn msg_info(&self) -> &MessageInfo {
+        &self.msg_info
+    }
+
+    pub fn msg(&self) -> &FastMessageRegisters {
+        &self.msg
+    }
+
+    pub fn reply(self, msg_info: MessageInfo, msg: FastMessageRegisters) {
+        if self.shared.current_call.get() == Some((self.channel, self.generation)) {
+            *self.shared.reply.borrow_mut() = Some((msg_info, msg));
+        }
+    }
+}
+
+struct Shared {
+    channels: RefCell<[ChannelState; MAX_CHANNELS]>,
+    // The call being handled, if any, identified by its channel and a count of the calls received
+    // before it, so that replies to earlier calls can be told apart.
+    current_call: Cell<Option<(Channel, u64)>>,
+    num_calls: Cell<u64>,
+    reply: RefCell<Option<(MessageInfo, FastMessageRegisters)>>,
+}
+
+#[derive(Default)]
+struct ChannelState {
+    notified: bool,
+    notification_waker: Option<Waker>,
+    incoming_call: Option<(u64, MessageInfo, FastMessageRegisters)>,
+    call_waker: Option<Waker>,
+}
+
+impl Shared {
+    fn new() -> Self {
+        Self {
+            channels: RefCell::new(array::from_fn(|_| ChannelState::default())),
+            current_call: Cell::new(None),
+            num_calls: Cell::new(0),
+            reply: RefCell::new(None),
+        }
+    }
+
+    fn notify(&self, channel: Channel) {
+        let waker = {
+            let mut channels = self.channels.borrow_mut();
+            let state = &mut channels[channel.index()];
+            state.notified = true;
+            state.notification_waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+
+    fn poll_notification(&self, channel: Channel, cx: &mut Context<'_>) -> Poll<()> {
+        let mut channels = self.channels.borrow_mut();
+        let state = &mut channels[channel.index()];
+        if state.notified {
+            state.notified = false;
+            Poll::Ready(())
+        } else {
+            state.notification_waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+
+    fn deliver_call(&self, channel: Channel, msg_info: MessageInfo, msg: FastMessageRegisters) {
+        let generation = self.num_calls.get();
+        self.num_calls.set(generation + 1);
+        self.current_call.set(Some((channel, generation)));
+        *self.reply.borrow_mut() = None;
+        let waker = {
+            let mut channels = self.channels.borrow_mut();
+            let state = &mut channels[channel.index()];
+            state.incoming_call = Some((generation, msg_info, msg));
+            state.call_waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+
+    fn poll_call(self: &Rc<Self>, channel: Channel, cx: &mut Context<'_>) -> Poll<ProtectedCall> {
+        let mut channels = self.channels.borrow_mut();
+        let state = &mut channels[channel.index()];
+        match state.incoming_call.take() {
+            Some((generation, msg_info, msg)) => Poll::Ready(ProtectedCall {
+                shared: self.clone(),
+                channel,
+                generation,
+                msg_info,
+                msg,
+            }),
+            None => {
+                state.call_waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+
+    fn take_reply(
+        &self,
+        channel: Channel,
+    ) -> Result<(MessageInfo, FastMessageRegisters), AsyncHandlerError> {
+        // Discard the call if no task picked it up.
+        self.channels.borrow_mut()[channel.index()].incoming_call = None;
+        self.current_call.set(None);
+        self.reply
+            .borrow_mut()
+            .take()
+            .ok_or(AsyncHandlerError::UnansweredProtectedCall { channel })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use super::*;
+    use crate::message::MessageLabel;
+
+    fn reply(call: ProtectedCall, label: MessageLabel) {
+        call.reply(MessageInfo::new(label, 0), Default::default())
+    }
+
+    fn call(handler: &mut AsyncHandler, channel: usize) -> Result<MessageLabel, AsyncHandlerError> {
+        handler
+            .protected_with_mrs(
+                Channel::new(channel),
+                MessageInfo::default(),
+                Default::default(),
+            )
+            .map(|(msg_info, _)| msg_info.label())
+    }
+
+    #[test]
+    fn overlapping_calls() {
+        let mut handler = AsyncHandler::new(|ctx| async move {
+            let mut first_calls = ctx.protected_calls(Channel::new(0));
+            let mut second_calls = ctx.protected_calls(Channel::new(1));
+            let mut stale = Vec::new();
+            // Keeps a call without replying, and then replies to it during a call on another
+            // channel.
+            stale.push(first_calls.next_call().await);
+            reply(second_calls.next_call().await, 2);
+            reply(stale.pop().unwrap(), 1);
+            // Likewise, during the next call on the same channel.
+            stale.push(first_calls.next_call().await);
+            let _unanswered = first_calls.next_call().await;
+            reply(stale.pop().unwrap(), 3);
+        });
+        let unanswered = Err(AsyncHandlerError::UnansweredProtectedCall {
+            channel: Channel::new(0),
+        });
+        assert_eq!(call(&mut handler, 0), unanswered);
+        assert_eq!(call(&mut handler, 1), Ok(2));
+        assert_eq!(call(&mut handler, 0), unanswered);
+        assert_eq!(call(&mut handler, 0), unanswered);
+    }
+}
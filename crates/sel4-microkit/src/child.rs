@@ -0,0 +1,136 @@
+//
+// Copyright 2023, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use sel4::sel4_cfg;
+
+use crate::cspace::{slot_to_local_cptr, Slot, BASE_IRQ_CAP};
+
+// For rustdoc.
+#[allow(unused_imports)]
+use crate::Handler;
+
+const BASE_TCB_CAP: Slot = BASE_IRQ_CAP + 64;
+const BASE_VM_TCB_CAP: Slot = BASE_TCB_CAP + 64;
+#[sel4_cfg(all(any(ARCH_AARCH32, ARCH_AARCH64), ARM_HYPERVISOR_SUPPORT))]
+const BASE_VCPU_CAP: Slot = BASE_VM_TCB_CAP + 64;
+
+const MAX_CHILDREN: usize = 64;
+
+/// A child of this protection domain, identified by the id given to it in the system description.
+///
+/// A child is either a protection domain or a virtual machine. Faults raised by children are
+/// delivered to [`Handler::fault`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Child {
+    index: usize,
+}
+
+impl Child {
+    pub const fn new(index: usize) -> Self {
+        assert!(index < MAX_CHILDREN);
+        Self { index }
+    }
+
+    pub const fn index(&self) -> usize {
+        self.index
+    }
+
+    fn tcb(&self) -> sel4::TCB {
+        slot_to_local_cptr(BASE_TCB_CAP + self.index)
+    }
+
+    fn vm_tcb(&self) -> sel4::TCB {
+        slot_to_local_cptr(BASE_VM_TCB_CAP + self.index)
+    }
+
+    /// Reads the first `count` registers of this child protection domain.
+    pub fn pd_read_registers(
+        &self,
+        suspend: bool,
+        count: usize,
+    ) -> Result<sel4::UserContext, sel4::Error> {
+        self.tcb()
+            .tcb_read_registers(suspend, count.try_into().unwrap())
+    }
+
+    /// Writes the first `count` registers of this child protection domain.
+    pub fn pd_write_registers(
+        &self,
+        resume: bool,
+        count: usize,
+        regs: &mut sel4::UserContext,
+    ) -> Result<(), sel4::Error> {
+        self.tcb()
+            .tcb_write_registers(resume, count.try_into().unwrap(), regs)
+    }
+
+    /// Restarts this child protection domain at `entry_point`.
+    ///
+    /// Corresponds to `microkit_pd_restart`.
+    pub fn pd_restart(&self, entry_point: usize) -> Result<(), sel4::Error> {
+        restart(self.tcb(), entry_point)
+    }
+
+    /// Stops this child protection domain.
+    ///
+    /// Corresponds to `microkit_pd_stop`.
+    pub fn pd_stop(&self) -> Result<(), sel4::Error> {
+        self.tcb().tcb_suspend()
+    }
+
+    /// Restarts the vCPU of this child virtual machine at `entry_point`.
+    ///
+    /// Corresponds to `microkit_vcpu_restart`.
+    pub fn vm_restart(&self, entry_point: usize) -> Result<(), sel4::Error> {
+        restart(self.vm_tcb(), entry_point)
+    }
+
+    /// Stops the vCPU of this child virtual machine.
+    ///
+    /// Corresponds to `microkit_vcpu_stop`.
+    pub fn vm_stop(&self) -> Result<(), sel4::Error> {
+        self.vm_tcb().tcb_suspend()
+    }
+}
+
+#[sel4_cfg(all(any(ARCH_AARCH32, ARCH_AARCH64), ARM_HYPERVISOR_SUPPORT))]
+impl Child {
+    fn vcpu(&self) -> sel4::VCPU {
+        slot_to_local_cptr(BASE_VCPU_CAP + self.index)
+    }
+
+    /// Corresponds to `microkit_vcpu_arm_inject_irq`.
+    pub fn vcpu_inject_irq(
+        &self,
+        irq: u16,
+        priority: u8,
+        group: u8,
+        index: u8,
+    ) -> Result<(), sel4::Error> {
+        self.vcpu().vcpu_inject_irq(irq, priority, group, index)
+    }
+
+    /// Corresponds to `microkit_vcpu_arm_ack_vppi`.
+    pub fn vcpu_ack_vppi(&self, irq: sel4::Word) -> Result<(), sel4::Error> {
+        self.vcpu().vcpu_ack_vppi(irq)
+    }
+
+    /// Corresponds to `microkit_vcpu_arm_read_reg`.
+    pub fn vcpu_read_reg(&self, reg: sel4::VCPUReg) -> Result<sel4::Word, sel4::Error> {
+        self.vcpu().vcpu_read_regs(reg)
+    }
+
+    /// Corresponds to `microkit_vcpu_arm_write_reg`.
+    pub fn vcpu_write_reg(&self, reg: sel4::VCPUReg, value: sel4::Word) -> Result<(), sel4::Error> {
+        self.vcpu().vcpu_write_regs(reg, value)
+    }
+}
+
+fn restart(tcb: sel4::TCB, entry_point: usize) -> Result<(), sel4::Error> {
+    let mut regs = sel4::UserContext::default();
+    *regs.pc_mut() = entry_point.try_into().unwrap();
+    tcb.tcb_write_registers(true, 1, &mut regs)
+}
@@ -5,8 +5,13 @@
 //
 
 use core::fmt;
+use core::mem;
 
-use crate::message::MessageInfo;
+use crate::message::{FastMessageRegisters, MessageInfo};
+use crate::FastMessages;
+
+#[cfg(target_env = "sel4")]
+use crate::env::{channel_has_irq, channel_has_notification, channel_has_pp_endpoint};
 
 // For rustdoc.
 #[allow(unused_imports)]
@@ -14,17 +19,26 @@ use crate::Handler;
 
 pub(crate) type Slot = usize;
 
+#[cfg(target_env = "sel4")]
 pub(crate) const INPUT_CAP: sel4::Endpoint = slot_to_local_cptr(1);
+#[cfg(target_env = "sel4")]
 pub(crate) const REPLY_CAP: sel4::Reply = slot_to_local_cptr(4);
+#[cfg(target_env = "sel4")]
 pub(crate) const MONITOR_EP_CAP: sel4::Endpoint = slot_to_local_cptr(5);
 
+#[cfg(target_env = "sel4")]
 const BASE_OUTPUT_NOTIFICATION_CAP: Slot = 10;
+#[cfg(target_env = "sel4")]
 const BASE_ENDPOINT_CAP: Slot = BASE_OUTPUT_NOTIFICATION_CAP + 64;
-const BASE_IRQ_CAP: Slot = BASE_ENDPOINT_CAP + 64;
+#[cfg(target_env = "sel4")]
+pub(crate) const BASE_IRQ_CAP: Slot = BASE_ENDPOINT_CAP + 64;
 
-const MAX_CHANNELS: Slot = 63;
+// One fewer than the `microkit` tool allows, because the top two bits of a badge are reserved for
+// distinguishing endpoint and fault events from notifications.
+pub(crate) const MAX_CHANNELS: Slot = 62;
 
-const fn slot_to_local_cptr<T: sel4::CapType>(slot: Slot) -> sel4::LocalCPtr<T> {
+#[cfg(target_env = "sel4")]
+pub(crate) const fn slot_to_local_cptr<T: sel4::CapType>(slot: Slot) -> sel4::LocalCPtr<T> {
     sel4::LocalCPtr::from_bits(slot as sel4::CPtrBits)
 }
 
@@ -37,9 +51,71 @@ pub struct Channel {
 impl Channel {
     pub const fn new(index: usize) -> Self {
         assert!(index < MAX_CHANNELS);
+        // The channel's badge bit must exist, which it may not on 32-bit targets, and must not be
+        // reserved.
+        #[cfg(target_env = "sel4")]
+        assert!(match (1 as sel4::Word).checked_shl(index as u32) {
+            Some(bit) => bit & crate::handler::RESERVED_BADGE_BITS == 0,
+            None => false,
+        });
         Self { index }
     }
 
+    pub(crate) const fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Prepare a [`DeferredAction`] for syscall coalescing using [`Handler::take_deferred_action`].
+    ///
+    /// Like [`notify`](Channel::notify), panics if the channel has no notification.
+    pub fn defer_notify(&self) -> DeferredAction {
+        if !self.has_notification() {
+            panic!("{}: {self:?}", ChannelError::NoNotification)
+        }
+        DeferredAction::new(*self, DeferredActionInterface::Notify)
+    }
+
+    /// Prepare a [`DeferredAction`] for syscall coalescing using [`Handler::take_deferred_action`].
+    ///
+    /// Panics if the channel has no IRQ. Unlike [`irq_ack`](Channel::irq_ack), this cannot
+    /// return an error, because a coalesced action is executed without checking its channel.
+    pub fn defer_irq_ack(&self) -> DeferredAction {
+        if !self.has_irq() {
+            panic!("{}: {self:?}", ChannelError::NoIrq)
+        }
+        DeferredAction::new(*self, DeferredActionInterface::IrqAck)
+    }
+
+    /// Like [`try_notify`](Channel::try_notify), but panics if the channel has no notification.
+    pub fn notify(&self) {
+        self.try_notify()
+            .unwrap_or_else(|err| panic!("{err}: {self:?}"))
+    }
+
+    /// Like [`try_pp_call`](Channel::try_pp_call), but panics if the channel has no endpoint.
+    pub fn pp_call(&self, msg_info: MessageInfo) -> MessageInfo {
+        self.try_pp_call(msg_info)
+            .unwrap_or_else(|err| panic!("{err}: {self:?}"))
+    }
+
+    /// Like [`try_pp_call_with_mrs`](Channel::try_pp_call_with_mrs), but panics if the channel has
+    /// no endpoint.
+    pub fn pp_call_with_mrs<T: FastMessages>(
+        &self,
+        msg_info: MessageInfo,
+        messages: T,
+    ) -> (MessageInfo, FastMessageRegisters) {
+        self.try_pp_call_with_mrs(msg_info, messages)
+            .unwrap_or_else(|err| panic!("{err}: {self:?}"))
+    }
+}
+
+// In the simulator, these methods are implemented by the simulated kernel instead.
+//
+// The `has_*` methods rely on bitmaps patched by the `microkit` tool, and return `true` for every
+// channel when the tool did not patch them. See `env.rs`.
+#[cfg(target_env = "sel4")]
+impl Channel {
     fn local_cptr<T: sel4::CapType>(&self, offset: Slot) -> sel4::LocalCPtr<T> {
         slot_to_local_cptr(offset + self.index)
     }
@@ -56,28 +132,82 @@ impl Channel {
         self.local_cptr::<sel4::cap_type::Endpoint>(BASE_ENDPOINT_CAP)
     }
 
-    pub fn notify(&self) {
-        self.notification().signal()
+    /// Returns whether this channel leads to a protection domain which it can notify.
+    pub fn has_notification(&self) -> bool {
+        channel_has_notification(self.index)
+    }
+
+    /// Returns whether this channel leads to a protection domain which it can call.
+    pub fn has_pp_endpoint(&self) -> bool {
+        channel_has_pp_endpoint(self.index)
+    }
+
+    /// Returns whether this channel delivers an IRQ.
+    pub fn has_irq(&self) -> bool {
+        channel_has_irq(self.index)
+    }
+
+    pub fn try_notify(&self) -> Result<(), ChannelError> {
+        if !self.has_notification() {
+            return Err(ChannelError::NoNotification);
+        }
+        self.notification().signal();
+        Ok(())
     }
 
     pub fn irq_ack(&self) -> Result<(), IrqAckError> {
+        if !self.has_irq() {
+            return Err(ChannelError::NoIrq.into());
+        }
         self.irq_handler()
             .irq_handler_ack()
             .map_err(IrqAckError::from_sel4_error)
     }
 
-    pub fn pp_call(&self, msg_info: MessageInfo) -> MessageInfo {
-        MessageInfo::from_sel4(self.endpoint().call(msg_info.into_sel4()))
+    pub fn try_pp_call(&self, msg_info: MessageInfo) -> Result<MessageInfo, ChannelError> {
+        if !self.has_pp_endpoint() {
+            return Err(ChannelError::NoPpEndpoint);
+        }
+        Ok(MessageInfo::from_sel4(
+            self.endpoint().call(msg_info.into_sel4()),
+        ))
     }
 
-    /// Prepare a [`DeferredAction`] for syscall coalescing using [`Handler::take_deferred_action`].
-    pub fn defer_notify(&self) -> DeferredAction {
-        DeferredAction::new(*self, DeferredActionInterface::Notify)
+    /// Like [`try_pp_call`](Channel::try_pp_call), but passes the first
+    /// [`NUM_FAST_MESSAGE_REGISTERS`](crate::NUM_FAST_MESSAGE_REGISTERS) message registers of both
+    /// the request and the reply in CPU registers rather than through the IPC buffer.
+    ///
+    /// Any message registers beyond those are still transferred through the IPC buffer.
+    pub fn try_pp_call_with_mrs<T: FastMessages>(
+        &self,
+        msg_info: MessageInfo,
+        messages: T,
+    ) -> Result<(MessageInfo, FastMessageRegisters), ChannelError> {
+        if !self.has_pp_endpoint() {
+            return Err(ChannelError::NoPpEndpoint);
+        }
+        let ret = self
+            .endpoint()
+            .call_with_mrs(msg_info.into_sel4(), messages);
+        Ok((MessageInfo::from_sel4(ret.info), ret.msg))
     }
+}
 
-    /// Prepare a [`DeferredAction`] for syscall coalescing using [`Handler::take_deferred_action`].
-    pub fn defer_irq_ack(&self) -> DeferredAction {
-        DeferredAction::new(*self, DeferredActionInterface::IrqAck)
+/// Error type returned by [`Channel`] methods when the channel lacks the required capability.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ChannelError {
+    NoNotification,
+    NoPpEndpoint,
+    NoIrq,
+}
+
+impl fmt::Display for ChannelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NoNotification => write!(f, "channel has no notification"),
+            Self::NoPpEndpoint => write!(f, "channel has no protected procedure call endpoint"),
+            Self::NoIrq => write!(f, "channel has no IRQ"),
+        }
     }
 }
 
@@ -118,6 +248,7 @@ impl DeferredAction {
         }
     }
 
+    #[cfg(target_env = "sel4")]
     pub(crate) fn prepare(&self) -> PreparedDeferredAction {
         match self.interface() {
             DeferredActionInterface::Notify => PreparedDeferredAction::new(
@@ -134,12 +265,14 @@ impl DeferredAction {
     }
 }
 
+#[cfg(target_env = "sel4")]
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub(crate) struct PreparedDeferredAction {
     cptr: sel4::Unspecified,
     msg_info: sel4::MessageInfo,
 }
 
+#[cfg(target_env = "sel4")]
 impl PreparedDeferredAction {
     pub(crate) fn new(cptr: sel4::Unspecified, msg_info: sel4::MessageInfo) -> Self {
         Self { cptr, msg_info }
@@ -176,24 +309,164 @@ impl DeferredActionSlot {
     }
 }
 
+/// A bounded queue of [`DeferredAction`]s, for use with [`Handler::take_deferred_actions`].
+///
+/// Identical actions, such as repeated notifications through the same channel, are coalesced.
+pub struct DeferredActionQueue<const N: usize> {
+    actions: [Option<DeferredAction>; N],
+    len: usize,
+}
+
+impl<const N: usize> DeferredActionQueue<N> {
+    pub const fn new() -> Self {
+        Self {
+            actions: [None; N],
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Queues `action`, unless an identical action is already queued.
+    ///
+    /// If the queue is full, the oldest action is executed immediately to make room.
+    pub fn defer(&mut self, action: DeferredAction) -> Result<(), IrqAckError> {
+        if self.actions[..self.len].contains(&Some(action)) {
+            return Ok(());
+        }
+        let mut ret = Ok(());
+        if self.len == N {
+            ret = self.actions[0].take().unwrap().execute_now();
+            self.actions.rotate_left(1);
+            self.len -= 1;
+        }
+        self.actions[self.len] = Some(action);
+        self.len += 1;
+        ret
+    }
+
+    /// Removes and returns the queued actions, oldest first.
+    pub fn drain(&mut self) -> impl Iterator<Item = DeferredAction> + '_ {
+        let len = mem::replace(&mut self.len, 0);
+        self.actions[..len]
+            .iter_mut()
+            .map(|action| action.take().unwrap())
+    }
+}
+
+impl<const N: usize> Default for DeferredActionQueue<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Error type returned by [`Channel::irq_ack`].
 #[derive(Debug, PartialEq, Eq)]
 pub struct IrqAckError {
-    sel4_error: sel4::Error,
+    inner: IrqAckErrorInner,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum IrqAckErrorInner {
+    Channel(ChannelError),
+    #[cfg(target_env = "sel4")]
+    Sel4(sel4::Error),
 }
 
 impl IrqAckError {
+    #[cfg(target_env = "sel4")]
     fn from_sel4_error(sel4_error: sel4::Error) -> Self {
-        Self { sel4_error }
+        Self {
+            inner: IrqAckErrorInner::Sel4(sel4_error),
+        }
+    }
+
+    /// Returns the [`ChannelError`] which caused this error, if any.
+    pub fn channel_error(&self) -> Option<ChannelError> {
+        match self.inner {
+            IrqAckErrorInner::Channel(err) => Some(err),
+            #[cfg(target_env = "sel4")]
+            IrqAckErrorInner::Sel4(_) => None,
+        }
     }
+}
 
-    fn as_sel4_error(&self) -> &sel4::Error {
-        &self.sel4_error
+impl From<ChannelError> for IrqAckError {
+    fn from(err: ChannelError) -> Self {
+        Self {
+            inner: IrqAckErrorInner::Channel(err),
+        }
     }
 }
 
 impl fmt::Display for IrqAckError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "irq ack error: {:?}", self.as_sel4_error())
+        match &self.inner {
+            IrqAckErrorInner::Channel(err) => write!(f, "irq ack error: {err}"),
+            #[cfg(target_env = "sel4")]
+            IrqAckErrorInner::Sel4(err) => write!(f, "irq ack error: {err:?}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::convert::Infallible;
+
+    use super::*;
+    use crate::simulator::{PdConfig, PdId, Simulator};
+
+    struct Pd;
+
+    impl Handler for Pd {
+        type Error = Infallible;
+    }
+
+    // Channel 0 leads to another protection domain, and channel 1 delivers an IRQ.
+    fn system() -> (Simulator, PdId) {
+        let mut sim = Simulator::new();
+        let a = sim.add_pd(PdConfig::new("a").priority(100), || Pd);
+        let b = sim.add_pd(PdConfig::new("b").priority(200), || Pd);
+        sim.add_channel(a, 0, b, 0);
+        sim.add_irq(a, 1, 0);
+        sim.start().unwrap();
+        (sim, a)
+    }
+
+    fn defer_on(channel: usize, interface: DeferredActionInterface) {
+        let (mut sim, a) = system();
+        sim.with_handler(a, |_: &mut Pd| {
+            let channel = Channel::new(channel);
+            let action = match interface {
+                DeferredActionInterface::Notify => channel.defer_notify(),
+                DeferredActionInterface::IrqAck => channel.defer_irq_ack(),
+            };
+            assert_eq!(action, DeferredAction::new(channel, interface));
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn defer_with_capabilities() {
+        defer_on(0, DeferredActionInterface::Notify);
+        defer_on(1, DeferredActionInterface::IrqAck);
+    }
+
+    #[test]
+    #[should_panic(expected = "channel has no notification")]
+    fn defer_notify_without_notification() {
+        defer_on(1, DeferredActionInterface::Notify);
+    }
+
+    #[test]
+    #[should_panic(expected = "channel has no IRQ")]
+    fn defer_irq_ack_without_irq() {
+        defer_on(0, DeferredActionInterface::IrqAck);
     }
 }
@@ -10,6 +10,7 @@ pub use sel4_panicking::catch_unwind;
 pub use sel4_panicking_env::abort;
 
 use crate::env::get_ipc_buffer;
+use crate::error_policy::ErrorPolicies;
 use crate::handler::{run_handler, Handler};
 use crate::panicking::init_panicking;
 
@@ -53,17 +54,30 @@ extern "C" {
 #[doc(hidden)]
 #[macro_export]
 macro_rules! declare_init {
-    ($init:expr) => {
+    (@error_policy) => {
+        $crate::ErrorPolicies::default()
+    };
+    (@error_policy $error_policy:expr) => {
+        $error_policy
+    };
+    ($init:expr $(, $error_policy:expr)?) => {
         #[no_mangle]
         fn __sel4_microkit__main() {
-            $crate::_private::run_main($init);
+            $crate::_private::run_main(
+                $init,
+                $crate::_private::declare_init!(@error_policy $($error_policy)?),
+            );
         }
     };
 }
 
 #[allow(clippy::missing_safety_doc)]
-pub fn run_main<T: Handler>(init: impl FnOnce() -> T + UnwindSafe) {
-    let result = catch_unwind(|| match run_handler(init()) {
+pub fn run_main<T: Handler>(
+    init: impl FnOnce() -> T + UnwindSafe,
+    error_policies: impl Into<ErrorPolicies>,
+) {
+    let error_policies = error_policies.into();
+    let result = catch_unwind(|| match run_handler(init(), &error_policies) {
         Ok(absurdity) => match absurdity {},
         Err(err) => err,
     });
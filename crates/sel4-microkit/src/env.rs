@@ -28,6 +28,46 @@ pub fn pd_is_passive() -> bool {
     *passive.get()
 }
 
+// Bitmaps of the channels with a notification, endpoint, or IRQ cap, patched by the `microkit` tool.
+//
+// They default to `!0`, which stands for "unknown" rather than "every cap is present": versions
+// of the tool which do not patch them leave every channel appearing to have every cap, so the
+// `has_*` checks pass and any missing cap is instead reported by the kernel when it is invoked.
+
+#[no_mangle]
+#[used(linker)]
+#[link_section = ".data"]
+static microkit_notifications: ImmutableCell<sel4::Word> = ImmutableCell::new(!0);
+
+#[no_mangle]
+#[used(linker)]
+#[link_section = ".data"]
+static microkit_pps: ImmutableCell<sel4::Word> = ImmutableCell::new(!0);
+
+#[no_mangle]
+#[used(linker)]
+#[link_section = ".data"]
+static microkit_irqs: ImmutableCell<sel4::Word> = ImmutableCell::new(!0);
+
+fn bitmap_has(bitmap: &ImmutableCell<sel4::Word>, index: usize) -> bool {
+    u32::try_from(index)
+        .ok()
+        .and_then(|shift| (1 as sel4::Word).checked_shl(shift))
+        .is_some_and(|bit| *bitmap.get() & bit != 0)
+}
+
+pub(crate) fn channel_has_notification(index: usize) -> bool {
+    bitmap_has(&microkit_notifications, index)
+}
+
+pub(crate) fn channel_has_pp_endpoint(index: usize) -> bool {
+    bitmap_has(&microkit_pps, index)
+}
+
+pub(crate) fn channel_has_irq(index: usize) -> bool {
+    bitmap_has(&microkit_irqs, index)
+}
+
 #[no_mangle]
 #[used(linker)]
 #[link_section = ".data"]
@@ -0,0 +1,103 @@
+//
+// Copyright 2023, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use core::fmt;
+
+use crate::message::MessageInfo;
+
+// For rustdoc.
+#[allow(unused_imports)]
+use crate::Handler;
+
+/// What the main loop does when one of a [`Handler`]'s entrypoints returns an error.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum ErrorPolicy {
+    /// Terminate the main loop and abort the protection domain. The resulting fault is delivered
+    /// to its parent, if it has one, or otherwise to the monitor. The default.
+    #[default]
+    Escalate,
+    /// Log the error using the `log` crate (for example, to a `sel4-logging` logger), and
+    /// continue as [`Continue`](ErrorPolicy::Continue) does.
+    LogAndContinue,
+    /// Continue the main loop. Protected procedure calls are replied to with a message whose label
+    /// is `sel4_microkit_message::UNSPECIFIED_ERROR_LABEL`, and faulting children are left
+    /// blocked.
+    Continue,
+}
+
+impl ErrorPolicy {
+    /// Returns `Err(err)` if `self` is [`Escalate`](ErrorPolicy::Escalate), and `Ok(())` otherwise.
+    pub(crate) fn handle<E: fmt::Display>(self, entrypoint: &str, err: E) -> Result<(), E> {
+        match self {
+            Self::Escalate => Err(err),
+            Self::LogAndContinue => {
+                log::error!("{entrypoint}() returned error: {err}");
+                Ok(())
+            }
+            Self::Continue => Ok(()),
+        }
+    }
+}
+
+/// Per-entrypoint [`ErrorPolicy`]s.
+///
+/// Pass to the [`protection_domain`](crate::protection_domain) macro with the `error_policy` key.
+/// An [`ErrorPolicy`] may be passed instead, in which case it applies to all entrypoints.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub struct ErrorPolicies {
+    notified: ErrorPolicy,
+    protected: ErrorPolicy,
+    fault: ErrorPolicy,
+}
+
+impl ErrorPolicies {
+    /// Every entrypoint uses `policy`.
+    pub const fn new(policy: ErrorPolicy) -> Self {
+        Self {
+            notified: policy,
+            protected: policy,
+            fault: policy,
+        }
+    }
+
+    /// Sets the policy for [`Handler::notified`].
+    pub const fn notified(mut self, policy: ErrorPolicy) -> Self {
+        self.notified = policy;
+        self
+    }
+
+    /// Sets the policy for [`Handler::protected`] and [`Handler::protected_with_mrs`].
+    pub const fn protected(mut self, policy: ErrorPolicy) -> Self {
+        self.protected = policy;
+        self
+    }
+
+    /// Sets the policy for [`Handler::fault`].
+    pub const fn fault(mut self, policy: ErrorPolicy) -> Self {
+        self.fault = policy;
+        self
+    }
+
+    pub(crate) fn handle_notified_error<E: fmt::Display>(&self, err: E) -> Result<(), E> {
+        self.notified.handle("notified", err)
+    }
+
+    pub(crate) fn handle_protected_error<E: fmt::Display>(&self, err: E) -> Result<MessageInfo, E> {
+        self.protected.handle("protected", err)?;
+        // Equal to sel4_microkit_message::UNSPECIFIED_ERROR_LABEL.
+        Ok(MessageInfo::new((1 << MessageInfo::label_width()) - 1, 0))
+    }
+
+    pub(crate) fn handle_fault_error<E: fmt::Display>(&self, err: E) -> Result<(), E> {
+        self.fault.handle("fault", err)
+    }
+}
+
+impl From<ErrorPolicy> for ErrorPolicies {
+    fn from(policy: ErrorPolicy) -> Self {
+        Self::new(policy)
+    }
+}
@@ -4,18 +4,36 @@
 // SPDX-License-Identifier: BSD-2-Clause
 //
 
+use core::array;
 use core::fmt;
 
 pub use core::convert::Infallible;
 
-use crate::cspace::{
-    Channel, DeferredAction, PreparedDeferredAction, INPUT_CAP, MONITOR_EP_CAP, REPLY_CAP,
+use crate::cspace::{Channel, DeferredAction};
+use crate::message::{
+    with_msg_regs, with_msg_regs_mut, FastMessageRegisters, MessageInfo, NUM_FAST_MESSAGE_REGISTERS,
 };
-use crate::message::MessageInfo;
-use crate::pd_is_passive;
 
+#[cfg(target_env = "sel4")]
+use crate::cspace::{PreparedDeferredAction, INPUT_CAP, MONITOR_EP_CAP, REPLY_CAP};
+#[cfg(target_env = "sel4")]
+use crate::{pd_is_passive, Child, ErrorPolicies};
+
+#[cfg(target_env = "sel4")]
 const EVENT_TYPE_MASK: sel4::Word = 1 << (sel4::WORD_SIZE - 1);
 
+#[cfg(target_env = "sel4")]
+const FAULT_MASK: sel4::Word = 1 << (sel4::WORD_SIZE - 2);
+
+#[cfg(target_env = "sel4")]
+pub(crate) const RESERVED_BADGE_BITS: sel4::Word = EVENT_TYPE_MASK | FAULT_MASK;
+
+#[cfg(target_env = "sel4")]
+const CHILD_MASK: sel4::Word = 0xff;
+
+#[cfg(target_env = "sel4")]
+const NO_MESSAGE_REGISTERS: [sel4::Word; 0] = [];
+
 /// Trait for the application-specific part of a protection domain's main loop.
 pub trait Handler {
     /// Error type returned by this protection domain's entrypoints.
@@ -39,6 +57,47 @@ pub trait Handler {
         panic!("unexpected protected procedure call from channel {channel:?} with msg_info={msg_info:?}")
     }
 
+    /// Like [`protected`](Handler::protected), but with the first [`NUM_FAST_MESSAGE_REGISTERS`]
+    /// message registers of both the request and the reply passed in CPU registers rather than
+    /// through the IPC buffer. The main loop always calls this method, so protection domains
+    /// serving small RPCs can override it to avoid touching the IPC buffer altogether.
+    ///
+    /// Entries of `msg` and of the returned registers beyond the respective message's count are
+    /// ignored. The default implementation spills `msg` into the IPC buffer, calls
+    /// [`protected`](Handler::protected), and loads the reply's registers back out.
+    fn protected_with_mrs(
+        &mut self,
+        channel: Channel,
+        msg_info: MessageInfo,
+        msg: FastMessageRegisters,
+    ) -> Result<(MessageInfo, FastMessageRegisters), Self::Error> {
+        with_msg_regs_mut(|regs| {
+            let n = msg_info.count().min(NUM_FAST_MESSAGE_REGISTERS);
+            regs[..n].copy_from_slice(&msg[..n]);
+        });
+        let reply_msg_info = self.protected(channel, msg_info)?;
+        let reply_msg = load_fast_msg_regs(&reply_msg_info);
+        Ok((reply_msg_info, reply_msg))
+    }
+
+    /// This method has the same meaning as its analog in `libmicrokit`, but receives the fault
+    /// already decoded.
+    ///
+    /// Returning `Some(reply_msg_info)` replies to the fault, which resumes `child` for fault
+    /// types where that is meaningful. The reply's message registers are taken from the IPC
+    /// buffer. Returning `None` leaves `child` blocked, for example so that it can be restarted
+    /// with [`Child::pd_restart`].
+    ///
+    /// The default implementation just panics.
+    #[cfg(target_env = "sel4")]
+    fn fault(
+        &mut self,
+        child: Child,
+        fault: sel4::Fault,
+    ) -> Result<Option<MessageInfo>, Self::Error> {
+        panic!("unexpected fault from child {child:?}: {fault:?}")
+    }
+
     /// An advanced feature for use by protection domains which seek to coalesce syscalls when
     /// possible.
     ///
@@ -47,15 +106,36 @@ pub trait Handler {
     fn take_deferred_action(&mut self) -> Option<DeferredAction> {
         None
     }
+
+    /// Like [`take_deferred_action`](Handler::take_deferred_action), but for protection domains
+    /// which defer several actions per event, for example using a
+    /// [`DeferredActionQueue`](crate::DeferredActionQueue).
+    ///
+    /// The main loop executes all but the last of the returned actions immediately, and fuses the
+    /// last with the next `seL4_Recv`. The default implementation just returns the result of
+    /// [`take_deferred_action`](Handler::take_deferred_action).
+    ///
+    /// Actions can only be deferred when there is no reply pending, because a reply is already
+    /// fused with the next `seL4_Recv`. If this method returns any actions after an event which
+    /// is answered with a reply, such as a protected procedure call, then the main loop panics
+    /// without executing any of them.
+    fn take_deferred_actions(&mut self) -> impl Iterator<Item = DeferredAction> + '_ {
+        self.take_deferred_action().into_iter()
+    }
 }
 
+#[cfg(target_env = "sel4")]
 pub(crate) enum Never {}
 
-pub(crate) fn run_handler<T: Handler>(mut handler: T) -> Result<Never, T::Error> {
-    let mut reply_tag: Option<MessageInfo> = None;
+#[cfg(target_env = "sel4")]
+pub(crate) fn run_handler<T: Handler>(
+    mut handler: T,
+    error_policies: &ErrorPolicies,
+) -> Result<Never, T::Error> {
+    let mut reply: Option<(MessageInfo, FastMessageRegisters)> = None;
 
+    // The monitor expects a message whose only register, MR0, is 0.
     let mut prepared_deferred_action: Option<PreparedDeferredAction> = if pd_is_passive() {
-        sel4::with_ipc_buffer_mut(|ipc_buffer| ipc_buffer.msg_regs_mut()[0] = 0);
         Some(PreparedDeferredAction::new(
             MONITOR_EP_CAP.cast(),
             sel4::MessageInfoBuilder::default().length(1).build(),
@@ -65,45 +145,99 @@ pub(crate) fn run_handler<T: Handler>(mut handler: T) -> Result<Never, T::Error>
     };
 
     loop {
-        let (tag, badge) = match (reply_tag.take(), prepared_deferred_action.take()) {
-            (Some(tag), None) => INPUT_CAP.reply_recv(tag.into_sel4(), REPLY_CAP),
-            (None, Some(action)) => action.cptr().nb_send_recv(
+        let sel4::RecvWithMRs {
+            info: tag,
+            badge,
+            msg,
+        } = match (reply.take(), prepared_deferred_action.take()) {
+            (Some((tag, msg)), None) => {
+                INPUT_CAP.reply_recv_with_mrs(tag.into_sel4(), msg, REPLY_CAP)
+            }
+            (None, Some(action)) => action.cptr().nb_send_recv_with_mrs(
                 action.msg_info(),
+                NO_MESSAGE_REGISTERS,
                 INPUT_CAP.cast::<sel4::cap_type::Unspecified>(),
                 REPLY_CAP,
             ),
-            (None, None) => INPUT_CAP.recv(REPLY_CAP),
+            (None, None) => INPUT_CAP.recv_with_mrs(REPLY_CAP),
             _ => unreachable!(),
         };
 
         let tag = MessageInfo::from_sel4(tag);
 
+        let is_fault = badge & FAULT_MASK != 0;
         let is_endpoint = badge & EVENT_TYPE_MASK != 0;
 
-        if is_endpoint {
+        if is_fault {
+            let child = Child::new((badge & CHILD_MASK).try_into().unwrap());
+            with_msg_regs_mut(|regs| {
+                let n = tag.count().min(NUM_FAST_MESSAGE_REGISTERS);
+                regs[..n].copy_from_slice(&msg[..n]);
+            });
+            let fault = sel4::with_ipc_buffer(|ipc_buffer| {
+                sel4::Fault::new(ipc_buffer, &tag.clone().into_sel4())
+            });
+            reply = match handler.fault(child, fault) {
+                Ok(reply_msg_info) => reply_msg_info.map(|reply_msg_info| {
+                    (reply_msg_info.clone(), load_fast_msg_regs(&reply_msg_info))
+                }),
+                Err(err) => {
+                    error_policies.handle_fault_error(err)?;
+                    None
+                }
+            };
+        } else if is_endpoint {
             let channel_index = badge & (sel4::Word::try_from(sel4::WORD_SIZE).unwrap() - 1);
-            reply_tag =
-                Some(handler.protected(Channel::new(channel_index.try_into().unwrap()), tag)?);
+            reply = Some(
+                match handler.protected_with_mrs(
+                    Channel::new(channel_index.try_into().unwrap()),
+                    tag,
+                    msg,
+                ) {
+                    Ok(reply) => reply,
+                    Err(err) => (
+                        error_policies.handle_protected_error(err)?,
+                        [0; NUM_FAST_MESSAGE_REGISTERS],
+                    ),
+                },
+            );
         } else {
             let mut badge_bits = badge;
             while badge_bits != 0 {
                 let i = badge_bits.trailing_zeros();
-                handler.notified(Channel::new(i.try_into().unwrap()))?;
+                if let Err(err) = handler.notified(Channel::new(i.try_into().unwrap())) {
+                    error_policies.handle_notified_error(err)?;
+                }
                 badge_bits &= !(1 << i);
             }
         };
 
-        prepared_deferred_action = handler
-            .take_deferred_action()
-            .as_ref()
-            .map(DeferredAction::prepare);
+        let mut deferred_actions = handler.take_deferred_actions();
 
-        if prepared_deferred_action.is_some() && is_endpoint {
-            panic!("handler yielded deferred action after call to 'protected()'");
+        if reply.is_some() {
+            // Checked before executing any, so that the batch is rejected as a whole.
+            if deferred_actions.next().is_some() {
+                panic!("handler yielded deferred action with a reply pending");
+            }
+            continue;
+        }
+
+        let mut last_deferred_action = None;
+        for action in deferred_actions {
+            if let Some(eager) = last_deferred_action.replace(action) {
+                // Like the fused action, errors are ignored.
+                let _ = eager.execute_now();
+            }
         }
+
+        prepared_deferred_action = last_deferred_action.as_ref().map(DeferredAction::prepare);
     }
 }
 
+fn load_fast_msg_regs(msg_info: &MessageInfo) -> FastMessageRegisters {
+    with_msg_regs(|regs| array::from_fn(|i| if i < msg_info.count() { regs[i] } else { 0 }))
+}
+
 /// A [`Handler`] implementation which does not override any of the default method implementations.
 pub struct NullHandler(());
 
@@ -31,66 +31,91 @@
 //! symbols.
 //!
 //! Use the [`protection_domain`] macro to declare the initialization function, stack size, and,
-//! optionally, heap and heap size.
+//! optionally, heap and heap size, and what to do when the handler returns an error.
+//!
+//! When built for a target other than seL4, this crate instead provides a [`simulator`], which
+//! runs several [`Handler`] implementations as simulated protection domains within a single
+//! process, for testing.
 
 #[cfg(feature = "alloc")]
 extern crate alloc;
 
+#[cfg(not(target_env = "sel4"))]
+extern crate std;
+
+#[cfg(target_env = "sel4")]
 use sel4_panicking_env::abort;
 
 pub use sel4_microkit_macros::protection_domain;
 
+#[cfg(feature = "async")]
+mod async_handler;
 mod cspace;
-mod entry;
-mod env;
 mod handler;
-mod heap;
 mod memory_region;
 mod message;
 
-pub mod panicking;
+cfg_if::cfg_if! {
+    if #[cfg(target_env = "sel4")] {
+        mod child;
+        mod entry;
+        mod env;
+        mod error_policy;
+        mod heap;
+
+        pub mod panicking;
+
+        pub use child::Child;
+        pub use env::{pd_is_passive, pd_name};
+        pub use error_policy::{ErrorPolicies, ErrorPolicy};
+        pub use sel4::{FastMessages, Fault};
+    } else {
+        pub mod simulator;
+
+        pub use simulator::{pd_is_passive, pd_name, FastMessages};
+    }
+}
 
+#[cfg(feature = "async")]
+pub use async_handler::{
+    AsyncContext, AsyncHandler, AsyncHandlerError, Notifications, ProtectedCall, ProtectedCalls,
+};
 pub use cspace::{
-    Channel, DeferredAction, DeferredActionInterface, DeferredActionSlot, IrqAckError,
+    Channel, ChannelError, DeferredAction, DeferredActionInterface, DeferredActionQueue,
+    DeferredActionSlot, IrqAckError,
 };
-pub use env::{pd_is_passive, pd_name};
 pub use handler::{Handler, Infallible, NullHandler};
-pub use memory_region::{cast_memory_region_checked, cast_memory_region_to_slice_checked};
+pub use memory_region::{
+    cast_memory_region_checked, cast_memory_region_to_slice_checked, MemoryRegion,
+};
 pub use message::{
     get_mr, set_mr, with_msg_bytes, with_msg_bytes_mut, with_msg_regs, with_msg_regs_mut,
-    MessageInfo, MessageLabel, MessageRegisterValue,
+    FastMessageRegisters, MessageInfo, MessageLabel, MessageRegisterValue,
+    NUM_FAST_MESSAGE_REGISTERS,
 };
 
-/// Declares the initialization function, stack size, and, optionally, heap and heap size.
+/// Declares the initialization function, stack size, and, optionally, heap and heap size and
+/// error policy.
 ///
 /// See the [`protection_domain`] attribute macro for more detail.
 #[macro_export]
 macro_rules! declare_protection_domain {
     {
-        init = $init:expr $(,)?
+        init = $init:expr
+        $(, stack_size = $stack_size:expr)?
+        $(, heap_size = $heap_size:expr)?
+        $(, error_policy = $error_policy:expr)?
+        $(,)?
     } => {
-        $crate::_private::declare_protection_domain! {
-            init = $init,
-            stack_size = $crate::_private::DEFAULT_STACK_SIZE,
-        }
+        $crate::_private::declare_init!($init $(, $error_policy)?);
+        $crate::_private::declare_protection_domain!(@stack $($stack_size)?);
+        $($crate::_private::declare_heap!($heap_size);)?
     };
-    {
-        init = $init:expr,
-        stack_size = $stack_size:expr $(,)?
-    } => {
-        $crate::_private::declare_init!($init);
-        $crate::_private::declare_stack!($stack_size);
+    (@stack) => {
+        $crate::_private::declare_stack!($crate::_private::DEFAULT_STACK_SIZE);
     };
-    {
-        init = $init:expr,
-        $(stack_size = $stack_size:expr,)?
-        heap_size = $heap_size:expr $(,)?
-    } => {
-        $crate::_private::declare_heap!($heap_size);
-        $crate::_private::declare_protection_domain! {
-            init = $init,
-            $(stack_size = $stack_size,)?
-        }
+    (@stack $stack_size:expr) => {
+        $crate::_private::declare_stack!($stack_size);
     };
 }
 
@@ -101,15 +126,20 @@ pub const DEFAULT_STACK_SIZE: usize = 0x10000;
 #[doc(hidden)]
 pub mod _private {
     pub use sel4_immutable_cell::ImmutableCell;
+
+    #[cfg(target_env = "sel4")]
     pub use sel4_runtime_common::declare_stack;
 
+    #[cfg(target_env = "sel4")]
     pub use crate::heap::_private as heap;
 
+    #[cfg(target_env = "sel4")]
     pub use crate::{
         declare_heap, declare_init, declare_protection_domain, entry::run_main, DEFAULT_STACK_SIZE,
     };
 }
 
+#[cfg(target_env = "sel4")]
 sel4::config::sel4_cfg_if! {
     if #[cfg(PRINTING)] {
         pub use sel4_panicking_env::{debug_print, debug_println};
@@ -127,3 +157,6 @@ sel4::config::sel4_cfg_if! {
         }
     }
 }
+
+#[cfg(not(target_env = "sel4"))]
+pub use std::{print as debug_print, println as debug_println};
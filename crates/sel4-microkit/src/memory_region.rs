@@ -7,8 +7,11 @@
 //! Utilities for declaring and using share memory regions.
 
 use core::mem;
+use core::ops::Range;
 use core::ptr::NonNull;
 
+use sel4_externally_shared::{access::ReadOnly, ExternallySharedRef};
+
 /// Declares a symbol via which the `microkit` tool can inject a memory region's address, and
 /// returns the memory region's address at runtime.
 ///
@@ -73,6 +76,182 @@ macro_rules! memory_region_symbol {
     }};
 }
 
+/// Declares symbols via which the `microkit` tool can inject a memory region's virtual and,
+/// optionally, physical addresses, and returns a [`MemoryRegion`] at runtime.
+///
+/// The symbols correspond to a `<map>`'s `setvar_vaddr` and a `<setvar>`'s `region_paddr` in the
+/// system description. `cached` should match the `<map>`'s `cached` attribute, and defaults to
+/// `true`.
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// let dma_region = memory_region!(
+///     virtio_net_driver_dma_vaddr,
+///     n = VIRTIO_NET_DRIVER_DMA_SIZE,
+///     paddr = virtio_net_driver_dma_paddr,
+///     cached = false,
+/// );
+/// ```
+#[macro_export]
+macro_rules! memory_region {
+    ($vaddr_symbol:ident, n = $n:expr $(,)?) => {
+        $crate::memory_region!(@build $vaddr_symbol, $n, None, true)
+    };
+    ($vaddr_symbol:ident, n = $n:expr, cached = $cached:expr $(,)?) => {
+        $crate::memory_region!(@build $vaddr_symbol, $n, None, $cached)
+    };
+    ($vaddr_symbol:ident, n = $n:expr, paddr = $paddr_symbol:ident $(,)?) => {
+        $crate::memory_region!(@build $vaddr_symbol, $n, Some($paddr_symbol), true)
+    };
+    ($vaddr_symbol:ident, n = $n:expr, paddr = $paddr_symbol:ident, cached = $cached:expr $(,)?) => {
+        $crate::memory_region!(@build $vaddr_symbol, $n, Some($paddr_symbol), $cached)
+    };
+    (@build $vaddr_symbol:ident, $n:expr, None, $cached:expr) => {
+        unsafe {
+            $crate::MemoryRegion::new(
+                $crate::memory_region_symbol!($vaddr_symbol: *mut [u8], n = $n),
+                None,
+                $cached,
+            )
+        }
+    };
+    (@build $vaddr_symbol:ident, $n:expr, Some($paddr_symbol:ident), $cached:expr) => {
+        unsafe {
+            $crate::MemoryRegion::new(
+                $crate::memory_region_symbol!($vaddr_symbol: *mut [u8], n = $n),
+                Some(*$crate::var!($paddr_symbol: usize = 0)),
+                $cached,
+            )
+        }
+    };
+}
+
+/// A memory region mapped into this protection domain, along with its physical address, if known,
+/// and whether it is mapped cached.
+///
+/// The physical address is required for handing out parts of the region to devices for DMA.
+/// Offsets into the region are what `sel4-bounce-buffer-allocator` allocates.
+#[derive(Debug, Copy, Clone)]
+pub struct MemoryRegion {
+    ptr: NonNull<[u8]>,
+    paddr: Option<usize>,
+    cached: bool,
+}
+
+impl MemoryRegion {
+    /// # Safety
+    ///
+    /// `ptr` must point to a memory region of its length which is mapped for the lifetime of the
+    /// program, and, if present, `paddr` must be its physical address.
+    pub const unsafe fn new(ptr: NonNull<[u8]>, paddr: Option<usize>, cached: bool) -> Self {
+        Self { ptr, paddr, cached }
+    }
+
+    pub fn as_ptr(&self) -> NonNull<[u8]> {
+        self.ptr
+    }
+
+    pub fn vaddr(&self) -> usize {
+        self.ptr.as_ptr().cast::<u8>() as usize
+    }
+
+    pub fn paddr(&self) -> Option<usize> {
+        self.paddr
+    }
+
+    pub fn size(&self) -> usize {
+        self.ptr.len()
+    }
+
+    pub fn is_cached(&self) -> bool {
+        self.cached
+    }
+
+    pub fn vaddr_range(&self) -> Range<usize> {
+        self.vaddr()..self.vaddr() + self.size()
+    }
+
+    pub fn paddr_range(&self) -> Option<Range<usize>> {
+        self.paddr.map(|paddr| paddr..paddr + self.size())
+    }
+
+    /// The largest alignment which is guaranteed in both virtual and physical address spaces for
+    /// buffers aligned to it within this region, suitable for
+    /// `sel4_bounce_buffer_allocator::BounceBufferAllocator::new`.
+    pub fn max_alignment(&self) -> usize {
+        let vaddr_bits = self.vaddr().trailing_zeros();
+        let bits = match self.paddr {
+            Some(paddr) => vaddr_bits.min(paddr.trailing_zeros()),
+            None => vaddr_bits,
+        };
+        1 << bits
+    }
+
+    pub fn offset_to_vaddr(&self, offset: usize) -> Option<usize> {
+        (offset < self.size()).then(|| self.vaddr() + offset)
+    }
+
+    pub fn vaddr_to_offset(&self, vaddr: usize) -> Option<usize> {
+        self.vaddr_range()
+            .contains(&vaddr)
+            .then(|| vaddr - self.vaddr())
+    }
+
+    pub fn offset_to_paddr(&self, offset: usize) -> Option<usize> {
+        let paddr = self.paddr?;
+        (offset < self.size()).then(|| paddr + offset)
+    }
+
+    pub fn paddr_to_offset(&self, paddr: usize) -> Option<usize> {
+        let range = self.paddr_range()?;
+        range.contains(&paddr).then(|| paddr - range.start)
+    }
+
+    pub fn vaddr_to_paddr(&self, vaddr: usize) -> Option<usize> {
+        self.offset_to_paddr(self.vaddr_to_offset(vaddr)?)
+    }
+
+    pub fn paddr_to_vaddr(&self, paddr: usize) -> Option<usize> {
+        self.offset_to_vaddr(self.paddr_to_offset(paddr)?)
+    }
+
+    /// # Safety
+    ///
+    /// The caller must ensure that no other views of this region are used in a conflicting way
+    /// within this protection domain.
+    pub unsafe fn as_externally_shared(&self) -> ExternallySharedRef<'static, [u8]> {
+        ExternallySharedRef::new(self.ptr)
+    }
+
+    /// # Safety
+    ///
+    /// See [`as_externally_shared`](Self::as_externally_shared).
+    pub unsafe fn as_externally_shared_read_only(
+        &self,
+    ) -> ExternallySharedRef<'static, [u8], ReadOnly> {
+        ExternallySharedRef::new(self.ptr)
+    }
+
+    /// Views the start of this region as a `T`, checking its size and alignment.
+    ///
+    /// # Safety
+    ///
+    /// See [`as_externally_shared`](Self::as_externally_shared).
+    pub unsafe fn as_externally_shared_typed<T>(&self) -> ExternallySharedRef<'static, T> {
+        ExternallySharedRef::new(cast_memory_region_checked(self.ptr))
+    }
+
+    /// Views this region as a `[T]`, checking its size and alignment.
+    ///
+    /// # Safety
+    ///
+    /// See [`as_externally_shared`](Self::as_externally_shared).
+    pub unsafe fn as_externally_shared_slice<T>(&self) -> ExternallySharedRef<'static, [T]> {
+        ExternallySharedRef::new(cast_memory_region_to_slice_checked(self.ptr))
+    }
+}
+
 pub fn cast_memory_region_checked<T: Sized>(bytes_ptr: NonNull<[u8]>) -> NonNull<T> {
     let ptr = bytes_ptr.cast::<T>();
     assert!(is_aligned(ptr.as_ptr()));
@@ -6,25 +6,50 @@
 
 //! Utilities for handling IPC messages for protected procedure calls.
 
-pub type MessageLabel = sel4::Word;
-pub type MessageRegisterValue = sel4::Word;
+cfg_if::cfg_if! {
+    if #[cfg(target_env = "sel4")] {
+        use sel4::{
+            with_ipc_buffer, with_ipc_buffer_mut, MessageInfo as RawMessageInfo, Word,
+            NUM_FAST_MESSAGE_REGISTERS as RAW_NUM_FAST_MESSAGE_REGISTERS,
+        };
+    } else {
+        use crate::simulator::{
+            with_ipc_buffer, with_ipc_buffer_mut, RawMessageInfo, Word,
+            NUM_FAST_MESSAGE_REGISTERS as RAW_NUM_FAST_MESSAGE_REGISTERS,
+        };
+    }
+}
+
+pub type MessageLabel = Word;
+pub type MessageRegisterValue = Word;
+
+/// Number of message registers which can be passed in CPU registers rather than through the IPC
+/// buffer.
+pub const NUM_FAST_MESSAGE_REGISTERS: usize = RAW_NUM_FAST_MESSAGE_REGISTERS;
+
+/// The contents of the message registers which are passed in CPU registers.
+pub type FastMessageRegisters = [MessageRegisterValue; NUM_FAST_MESSAGE_REGISTERS];
 
 #[derive(Debug, Clone)]
 pub struct MessageInfo {
-    inner: sel4::MessageInfo,
+    inner: RawMessageInfo,
 }
 
 impl MessageInfo {
+    #[cfg(target_env = "sel4")]
     pub(crate) fn from_sel4(inner: sel4::MessageInfo) -> Self {
         Self { inner }
     }
 
+    #[cfg(target_env = "sel4")]
     pub(crate) fn into_sel4(self) -> sel4::MessageInfo {
         self.inner
     }
 
     pub fn new(label: MessageLabel, count: usize) -> Self {
-        Self::from_sel4(sel4::MessageInfo::new(label, 0, 0, count))
+        Self {
+            inner: RawMessageInfo::new(label, 0, 0, count),
+        }
     }
 
     pub fn label(&self) -> MessageLabel {
@@ -32,7 +57,7 @@ impl MessageInfo {
     }
 
     pub const fn label_width() -> usize {
-        sel4::MessageInfo::label_width()
+        RawMessageInfo::label_width()
     }
 
     pub fn count(&self) -> usize {
@@ -47,19 +72,19 @@ impl Default for MessageInfo {
 }
 
 pub fn with_msg_regs<T>(f: impl FnOnce(&[MessageRegisterValue]) -> T) -> T {
-    sel4::with_ipc_buffer(|ipc_buffer| f(ipc_buffer.msg_regs()))
+    with_ipc_buffer(|ipc_buffer| f(ipc_buffer.msg_regs()))
 }
 
 pub fn with_msg_regs_mut<T>(f: impl FnOnce(&mut [MessageRegisterValue]) -> T) -> T {
-    sel4::with_ipc_buffer_mut(|ipc_buffer| f(ipc_buffer.msg_regs_mut()))
+    with_ipc_buffer_mut(|ipc_buffer| f(ipc_buffer.msg_regs_mut()))
 }
 
 pub fn with_msg_bytes<T>(f: impl FnOnce(&[u8]) -> T) -> T {
-    sel4::with_ipc_buffer(|ipc_buffer| f(ipc_buffer.msg_bytes()))
+    with_ipc_buffer(|ipc_buffer| f(ipc_buffer.msg_bytes()))
 }
 
 pub fn with_msg_bytes_mut<T>(f: impl FnOnce(&mut [u8]) -> T) -> T {
-    sel4::with_ipc_buffer_mut(|ipc_buffer| f(ipc_buffer.msg_bytes_mut()))
+    with_ipc_buffer_mut(|ipc_buffer| f(ipc_buffer.msg_bytes_mut()))
 }
 
 pub fn set_mr(i: usize, value: MessageRegisterValue) {
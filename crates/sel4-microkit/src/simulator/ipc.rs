@@ -0,0 +1,134 @@
+//
+// Copyright 2023, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+// Host stand-ins for the parts of the `sel4` crate's IPC interface used by this crate.
+
+use core::array;
+use core::mem;
+use core::slice;
+
+use super::kernel::with_current_pd;
+
+pub type Word = u64;
+
+pub const NUM_FAST_MESSAGE_REGISTERS: usize = 4;
+
+pub(crate) const NUM_MESSAGE_REGISTERS: usize = 120;
+
+// As on seL4, the length field can exceed `NUM_MESSAGE_REGISTERS`.
+const LENGTH_WIDTH: usize = 7;
+
+const WORD_SIZE: usize = mem::size_of::<Word>() * 8;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) struct RawMessageInfo {
+    label: Word,
+    length: usize,
+}
+
+impl RawMessageInfo {
+    pub(crate) fn new(
+        label: Word,
+        _caps_unwrapped: Word,
+        _extra_caps: Word,
+        length: usize,
+    ) -> Self {
+        assert!(label >> Self::label_width() == 0);
+        assert!(length >> LENGTH_WIDTH == 0);
+        Self { label, length }
+    }
+
+    pub(crate) fn label(&self) -> Word {
+        self.label
+    }
+
+    pub(crate) const fn label_width() -> usize {
+        WORD_SIZE - 12
+    }
+
+    pub(crate) fn length(&self) -> usize {
+        self.length
+    }
+}
+
+pub(crate) struct IPCBuffer {
+    msg: [Word; NUM_MESSAGE_REGISTERS],
+}
+
+impl IPCBuffer {
+    pub(crate) fn new() -> Self {
+        Self {
+            msg: [0; NUM_MESSAGE_REGISTERS],
+        }
+    }
+
+    pub(crate) fn msg_regs(&self) -> &[Word] {
+        &self.msg
+    }
+
+    pub(crate) fn msg_regs_mut(&mut self) -> &mut [Word] {
+        &mut self.msg
+    }
+
+    pub(crate) fn msg_bytes(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.msg.as_ptr().cast(), mem::size_of_val(&self.msg)) }
+    }
+
+    pub(crate) fn msg_bytes_mut(&mut self) -> &mut [u8] {
+        unsafe {
+            slice::from_raw_parts_mut(self.msg.as_mut_ptr().cast(), mem::size_of_val(&self.msg))
+        }
+    }
+}
+
+pub(crate) fn with_ipc_buffer<F, T>(f: F) -> T
+where
+    F: FnOnce(&IPCBuffer) -> T,
+{
+    with_current_pd(|pd| f(&pd.ipc_buffer.borrow()))
+}
+
+pub(crate) fn with_ipc_buffer_mut<F, T>(f: F) -> T
+where
+    F: FnOnce(&mut IPCBuffer) -> T,
+{
+    with_current_pd(|pd| f(&mut pd.ipc_buffer.borrow_mut()))
+}
+
+/// Mirrors `sel4::FastMessages`.
+pub trait FastMessages: fast_messages_sealing::FastMessagesSealed {
+    fn prepare_in(self) -> [Option<Word>; NUM_FAST_MESSAGE_REGISTERS];
+}
+
+impl<const N: usize> FastMessages for [Word; N]
+where
+    [Word; N]: fast_messages_sealing::FastMessagesSealed,
+{
+    fn prepare_in(self) -> [Option<Word>; NUM_FAST_MESSAGE_REGISTERS] {
+        array::from_fn(|i| if i < self.len() { Some(self[i]) } else { None })
+    }
+}
+
+impl FastMessages for &[Word] {
+    fn prepare_in(self) -> [Option<Word>; NUM_FAST_MESSAGE_REGISTERS] {
+        assert!(self.len() <= NUM_FAST_MESSAGE_REGISTERS);
+        array::from_fn(|i| if i < self.len() { Some(self[i]) } else { None })
+    }
+}
+
+mod fast_messages_sealing {
+    use super::Word;
+
+    pub trait FastMessagesSealed {}
+
+    impl FastMessagesSealed for [Word; 0] {}
+    impl FastMessagesSealed for [Word; 1] {}
+    impl FastMessagesSealed for [Word; 2] {}
+    impl FastMessagesSealed for [Word; 3] {}
+    impl FastMessagesSealed for [Word; 4] {}
+
+    impl FastMessagesSealed for &[Word] {}
+}
@@ -0,0 +1,559 @@
+//
+// Copyright 2023, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+use core::any::Any;
+use core::array;
+use core::cell::{Cell, RefCell};
+use core::cmp::Reverse;
+
+use std::boxed::Box;
+use std::collections::BTreeMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::rc::Rc;
+use std::string::{String, ToString};
+use std::vec::Vec;
+
+use crate::cspace::{Channel, ChannelError, DeferredAction, MAX_CHANNELS};
+use crate::handler::Handler;
+use crate::message::{FastMessageRegisters, MessageInfo};
+
+use super::ipc::{IPCBuffer, Word, NUM_FAST_MESSAGE_REGISTERS, NUM_MESSAGE_REGISTERS};
+use super::SimulationError;
+
+std::thread_local! {
+    static CURRENT_KERNEL: RefCell<Option<Rc<Kernel>>> = const { RefCell::new(None) };
+}
+
+/// Identifies a simulated protection domain.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct PdId(usize);
+
+pub(crate) struct Kernel {
+    pds: RefCell<Vec<Rc<Pd>>>,
+    irqs: RefCell<BTreeMap<u64, IrqState>>,
+    stack: RefCell<Vec<PdId>>,
+    next_seq: Cell<u64>,
+    faults: RefCell<Vec<SimulationError>>,
+}
+
+pub(crate) struct Pd {
+    pub(crate) name: &'static str,
+    pub(crate) priority: u8,
+    pub(crate) passive: bool,
+    pub(crate) ipc_buffer: RefCell<IPCBuffer>,
+    init: RefCell<Option<Init>>,
+    // Taken while the protection domain is running, and never returned if it faults.
+    handler: RefCell<Option<Box<dyn DynHandler>>>,
+    faulted: Cell<bool>,
+    ends: RefCell<BTreeMap<usize, End>>,
+    pending: Cell<Word>,
+    pending_since: Cell<u64>,
+}
+
+type Init = Box<dyn FnOnce() -> Box<dyn DynHandler>>;
+
+#[derive(Debug, Copy, Clone)]
+enum End {
+    Pd { pd: PdId, id: usize },
+    Irq { irq: u64 },
+}
+
+struct IrqState {
+    pd: PdId,
+    id: usize,
+    masked: bool,
+    latched: bool,
+}
+
+// Unwinds a protection domain which has called a faulted protection domain, and so will never
+// receive a reply. Raised with `resume_unwind`, so the panic hook is not invoked.
+struct BlockedForever;
+
+pub(crate) trait DynHandler {
+    fn notified(&mut self, channel: Channel) -> Result<(), String>;
+
+    fn protected_with_mrs(
+        &mut self,
+        channel: Channel,
+        msg_info: MessageInfo,
+        msg: FastMessageRegisters,
+    ) -> Result<(MessageInfo, FastMessageRegisters), String>;
+
+    fn take_deferred_actions(&mut self) -> Vec<DeferredAction>;
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: Handler + 'static> DynHandler for T {
+    fn notified(&mut self, channel: Channel) -> Result<(), String> {
+        Handler::notified(self, channel).map_err(|err| err.to_string())
+    }
+
+    fn protected_with_mrs(
+        &mut self,
+        channel: Channel,
+        msg_info: MessageInfo,
+        msg: FastMessageRegisters,
+    ) -> Result<(MessageInfo, FastMessageRegisters), String> {
+        Handler::protected_with_mrs(self, channel, msg_info, msg).map_err(|err| err.to_string())
+    }
+
+    fn take_deferred_actions(&mut self) -> Vec<DeferredAction> {
+        Handler::take_deferred_actions(self).collect()
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+pub(crate) fn with_kernel<T>(f: impl FnOnce(&Kernel) -> T) -> T {
+    let kernel = CURRENT_KERNEL
+        .with(|current| current.borrow().clone())
+        .unwrap_or_else(|| panic!("not running within a simulation"));
+    f(&kernel)
+}
+
+pub(crate) fn with_current_pd<T>(f: impl FnOnce(&Pd) -> T) -> T {
+    with_kernel(|kernel| {
+        let pd = kernel
+            .current()
+            .map(|id| kernel.pd(id))
+            .unwrap_or_else(|| panic!("not running within a simulated protection domain"));
+        f(&pd)
+    })
+}
+
+// Makes `kernel` the current kernel until dropped.
+pub(crate) struct Entered {
+    prev: Option<Rc<Kernel>>,
+}
+
+impl Entered {
+    pub(crate) fn new(kernel: &Rc<Kernel>) -> Self {
+        let prev = CURRENT_KERNEL.with(|current| current.replace(Some(kernel.clone())));
+        Self { prev }
+    }
+}
+
+impl Drop for Entered {
+    fn drop(&mut self) {
+        CURRENT_KERNEL.with(|current| *current.borrow_mut() = self.prev.take());
+    }
+}
+
+// Marks a protection domain as running until dropped, including when unwinding.
+struct Running<'a> {
+    kernel: &'a Kernel,
+}
+
+impl<'a> Running<'a> {
+    fn new(kernel: &'a Kernel, pd: PdId) -> Self {
+        kernel.stack.borrow_mut().push(pd);
+        Self { kernel }
+    }
+}
+
+impl<'a> Drop for Running<'a> {
+    fn drop(&mut self) {
+        self.kernel.stack.borrow_mut().pop();
+    }
+}
+
+impl Kernel {
+    pub(crate) fn new() -> Self {
+        Self {
+            pds: RefCell::new(Vec::new()),
+            irqs: RefCell::new(BTreeMap::new()),
+            stack: RefCell::new(Vec::new()),
+            next_seq: Cell::new(0),
+            faults: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub(crate) fn add_pd<T: Handler + 'static>(
+        &self,
+        name: &'static str,
+        priority: u8,
+        passive: bool,
+        init: impl FnOnce() -> T + 'static,
+    ) -> PdId {
+        let mut pds = self.pds.borrow_mut();
+        let id = PdId(pds.len());
+        pds.push(Rc::new(Pd {
+            name,
+            priority,
+            passive,
+            ipc_buffer: RefCell::new(IPCBuffer::new()),
+            init: RefCell::new(Some(Box::new(move || Box::new(init())))),
+            handler: RefCell::new(None),
+            faulted: Cell::new(false),
+            ends: RefCell::new(BTreeMap::new()),
+            pending: Cell::new(0),
+            pending_since: Cell::new(0),
+        }));
+        id
+    }
+
+    pub(crate) fn add_channel(&self, a: PdId, a_id: usize, b: PdId, b_id: usize) {
+        self.add_end(a, a_id, End::Pd { pd: b, id: b_id });
+        self.add_end(b, b_id, End::Pd { pd: a, id: a_id });
+    }
+
+    pub(crate) fn add_irq(&self, pd: PdId, id: usize, irq: u64) {
+        self.add_end(pd, id, End::Irq { irq });
+        let prev = self.irqs.borrow_mut().insert(
+            irq,
+            IrqState {
+                pd,
+                id,
+                masked: false,
+                latched: false,
+            },
+        );
+        assert!(prev.is_none(), "IRQ {irq} is claimed more than once");
+    }
+
+    fn add_end(&self, pd: PdId, id: usize, end: End) {
+        let pd = self.pd(pd);
+        assert!(
+            id < MAX_CHANNELS,
+            "protection domain '{}' uses channel id {id}, which is not less than {MAX_CHANNELS}",
+            pd.name
+        );
+        let prev = pd.ends.borrow_mut().insert(id, end);
+        assert!(
+            prev.is_none(),
+            "protection domain '{}' uses channel id {id} more than once",
+            pd.name
+        );
+    }
+
+    pub(crate) fn pd(&self, id: PdId) -> Rc<Pd> {
+        self.pds.borrow()[id.0].clone()
+    }
+
+    fn current(&self) -> Option<PdId> {
+        self.stack.borrow().last().copied()
+    }
+
+    fn current_priority(&self) -> Option<u8> {
+        self.current().map(|id| self.pd(id).priority)
+    }
+
+    fn end(&self, pd: PdId, channel: Channel) -> Option<End> {
+        self.pd(pd).ends.borrow().get(&channel.index()).copied()
+    }
+
+    fn connected_pd(&self, pd: PdId, channel: Channel) -> Option<(PdId, usize)> {
+        match self.end(pd, channel) {
+            Some(End::Pd { pd, id }) => Some((pd, id)),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn has_pd_end(&self, channel: Channel) -> bool {
+        self.connected_pd(self.current().unwrap(), channel)
+            .is_some()
+    }
+
+    pub(crate) fn has_irq_end(&self, channel: Channel) -> bool {
+        matches!(
+            self.end(self.current().unwrap(), channel),
+            Some(End::Irq { .. })
+        )
+    }
+
+    pub(crate) fn take_fault(&self) -> Result<(), SimulationError> {
+        let mut faults = self.faults.borrow_mut();
+        if faults.is_empty() {
+            Ok(())
+        } else {
+            let first = faults.remove(0);
+            faults.clear();
+            Err(first)
+        }
+    }
+
+    fn fault(&self, id: PdId, error: String) {
+        let pd = self.pd(id);
+        pd.faulted.set(true);
+        self.faults
+            .borrow_mut()
+            .push(SimulationError { pd: pd.name, error });
+    }
+
+    // Runs `f` on behalf of `id`. Returns `None`, and marks `id` as faulted, if `id` calls a
+    // faulted protection domain.
+    fn run_as<T>(&self, id: PdId, f: impl FnOnce() -> T) -> Option<T> {
+        let _running = Running::new(self, id);
+        match panic::catch_unwind(AssertUnwindSafe(f)) {
+            Ok(ret) => Some(ret),
+            Err(payload) if payload.is::<BlockedForever>() => {
+                self.fault(
+                    id,
+                    "blocked forever on a call to a faulted protection domain".to_string(),
+                );
+                None
+            }
+            Err(payload) => panic::resume_unwind(payload),
+        }
+    }
+
+    fn signal(&self, id: PdId, channel_id: usize) {
+        let pd = self.pd(id);
+        let pending = pd.pending.get();
+        if pending == 0 {
+            let seq = self.next_seq.get();
+            self.next_seq.set(seq + 1);
+            pd.pending_since.set(seq);
+        }
+        pd.pending.set(pending | (1 << channel_id));
+    }
+
+    // Runs protection domains with pending notifications, highest priority first and
+    // first-come-first-served within a priority, for as long as any have a priority greater than
+    // `threshold`.
+    pub(crate) fn schedule_above(&self, threshold: Option<u8>) {
+        loop {
+            let next = self
+                .pds
+                .borrow()
+                .iter()
+                .enumerate()
+                .filter(|(_, pd)| {
+                    pd.pending.get() != 0
+                        && !pd.passive
+                        && !pd.faulted.get()
+                        && pd.handler.borrow().is_some()
+                        && threshold.map_or(true, |threshold| pd.priority > threshold)
+                })
+                .max_by_key(|(_, pd)| (pd.priority, Reverse(pd.pending_since.get())))
+                .map(|(i, _)| PdId(i));
+            match next {
+                Some(id) => self.dispatch_notifications(id),
+                None => break,
+            }
+        }
+    }
+
+    fn preempt(&self) {
+        self.schedule_above(self.current_priority())
+    }
+
+    fn dispatch_notifications(&self, id: PdId) {
+        let pd = self.pd(id);
+        let mut handler = pd.handler.borrow_mut().take().unwrap();
+        let result = self.run_as(id, || {
+            deliver_notifications(&pd, &mut *handler)?;
+            // Like the real main loop, execute deferred actions just before waiting for the next
+            // event.
+            for action in handler.take_deferred_actions() {
+                let _ = action.execute_now();
+            }
+            Ok(())
+        });
+        match result {
+            Some(Ok(())) => *pd.handler.borrow_mut() = Some(handler),
+            Some(Err(err)) => self.fault(id, err),
+            None => {}
+        }
+    }
+
+    pub(crate) fn initialize(&self) {
+        let mut order = (0..self.pds.borrow().len()).map(PdId).collect::<Vec<_>>();
+        order.sort_by_key(|id| Reverse(self.pd(*id).priority));
+        for id in order {
+            let pd = self.pd(id);
+            self.schedule_above(Some(pd.priority));
+            let init = pd.init.borrow_mut().take().unwrap();
+            if let Some(handler) = self.run_as(id, init) {
+                *pd.handler.borrow_mut() = Some(handler);
+            }
+        }
+        self.schedule_above(None);
+    }
+
+    pub(crate) fn run_on_behalf_of<T: 'static, U>(
+        &self,
+        id: PdId,
+        f: impl FnOnce(&mut T) -> U,
+    ) -> Option<U> {
+        let pd = self.pd(id);
+        let mut handler =
+            pd.handler.borrow_mut().take().unwrap_or_else(|| {
+                panic!("protection domain '{}' is not waiting for events", pd.name)
+            });
+        let ret = self.run_as(id, || {
+            f(handler
+                .as_any_mut()
+                .downcast_mut::<T>()
+                .unwrap_or_else(|| panic!("wrong handler type for '{}'", pd.name)))
+        });
+        if ret.is_some() {
+            *pd.handler.borrow_mut() = Some(handler);
+        }
+        ret
+    }
+
+    pub(crate) fn notify_from_outside(&self, id: PdId, channel_id: usize) {
+        self.signal(id, channel_id);
+    }
+
+    pub(crate) fn inject_irq(&self, irq: u64) {
+        let target = {
+            let mut irqs = self.irqs.borrow_mut();
+            let state = irqs
+                .get_mut(&irq)
+                .unwrap_or_else(|| panic!("IRQ {irq} is not claimed"));
+            if state.masked {
+                state.latched = true;
+                None
+            } else {
+                state.masked = true;
+                Some((state.pd, state.id))
+            }
+        };
+        if let Some((pd, id)) = target {
+            self.signal(pd, id);
+        }
+    }
+
+    pub(crate) fn notify(&self, channel: Channel) -> Result<(), ChannelError> {
+        let current = self.current().unwrap();
+        let (pd, id) = self
+            .connected_pd(current, channel)
+            .ok_or(ChannelError::NoNotification)?;
+        self.signal(pd, id);
+        self.preempt();
+        Ok(())
+    }
+
+    pub(crate) fn irq_ack(&self, channel: Channel) -> Result<(), ChannelError> {
+        let current = self.current().unwrap();
+        let irq = match self.end(current, channel) {
+            Some(End::Irq { irq }) => irq,
+            _ => return Err(ChannelError::NoIrq),
+        };
+        let target = {
+            let mut irqs = self.irqs.borrow_mut();
+            let state = irqs.get_mut(&irq).unwrap();
+            if state.latched {
+                state.latched = false;
+                Some((state.pd, state.id))
+            } else {
+                state.masked = false;
+                None
+            }
+        };
+        if let Some((pd, id)) = target {
+            self.signal(pd, id);
+            self.preempt();
+        }
+        Ok(())
+    }
+
+    pub(crate) fn pp_call(
+        &self,
+        channel: Channel,
+        msg_info: MessageInfo,
+        fast_in: [Option<Word>; NUM_FAST_MESSAGE_REGISTERS],
+    ) -> Result<(MessageInfo, FastMessageRegisters), ChannelError> {
+        let caller_id = self.current().unwrap();
+        let caller = self.pd(caller_id);
+        let (callee_id, callee_channel_id) = self
+            .connected_pd(caller_id, channel)
+            .ok_or(ChannelError::NoPpEndpoint)?;
+        let callee = self.pd(callee_id);
+
+        assert!(
+            callee.priority > caller.priority,
+            "protection domain '{}' called '{}', which does not have a higher priority",
+            caller.name,
+            callee.name,
+        );
+
+        if callee.faulted.get() {
+            panic::resume_unwind(Box::new(BlockedForever));
+        }
+
+        let mut handler = callee.handler.borrow_mut().take().unwrap_or_else(|| {
+            panic!(
+                "protection domain '{}' called '{}', which is not waiting for calls",
+                caller.name, callee.name,
+            )
+        });
+
+        // Like seL4, transfer at most `NUM_MESSAGE_REGISTERS` message registers, and truncate the
+        // length accordingly.
+        let count = msg_info.count().min(NUM_MESSAGE_REGISTERS);
+        let msg_info = MessageInfo::new(msg_info.label(), count);
+        let msg: FastMessageRegisters = {
+            let caller_ipc_buffer = caller.ipc_buffer.borrow();
+            let mut callee_ipc_buffer = callee.ipc_buffer.borrow_mut();
+            let regs = &mut callee_ipc_buffer.msg_regs_mut()[..count];
+            regs.copy_from_slice(&caller_ipc_buffer.msg_regs()[..count]);
+            for (reg, fast) in regs.iter_mut().zip(fast_in) {
+                if let Some(fast) = fast {
+                    *reg = fast;
+                }
+            }
+            array::from_fn(|i| regs.get(i).copied().unwrap_or(0))
+        };
+
+        let result = self.run_as(callee_id, || {
+            // A passive protection domain only runs on its callers' scheduling contexts, so
+            // notifications which arrived in the meantime are delivered first.
+            if callee.passive {
+                deliver_notifications(&callee, &mut *handler)?;
+            }
+            let reply =
+                handler.protected_with_mrs(Channel::new(callee_channel_id), msg_info, msg)?;
+            if !handler.take_deferred_actions().is_empty() {
+                return Err("handler yielded deferred action with a reply pending".to_string());
+            }
+            Ok(reply)
+        });
+
+        let (reply_msg_info, reply_msg) = match result {
+            Some(Ok(reply)) => reply,
+            Some(Err(err)) => {
+                self.fault(callee_id, err);
+                panic::resume_unwind(Box::new(BlockedForever));
+            }
+            None => panic::resume_unwind(Box::new(BlockedForever)),
+        };
+
+        *callee.handler.borrow_mut() = Some(handler);
+
+        let reply_count = reply_msg_info.count().min(NUM_MESSAGE_REGISTERS);
+        let reply_msg_info = MessageInfo::new(reply_msg_info.label(), reply_count);
+        let reply_msg: FastMessageRegisters =
+            array::from_fn(|i| if i < reply_count { reply_msg[i] } else { 0 });
+        {
+            let callee_ipc_buffer = callee.ipc_buffer.borrow();
+            let mut caller_ipc_buffer = caller.ipc_buffer.borrow_mut();
+            let regs = &mut caller_ipc_buffer.msg_regs_mut()[..reply_count];
+            regs.copy_from_slice(&callee_ipc_buffer.msg_regs()[..reply_count]);
+            let n = reply_count.min(NUM_FAST_MESSAGE_REGISTERS);
+            regs[..n].copy_from_slice(&reply_msg[..n]);
+        }
+
+        self.preempt();
+
+        Ok((reply_msg_info, reply_msg))
+    }
+}
+
+fn deliver_notifications(pd: &Pd, handler: &mut dyn DynHandler) -> Result<(), String> {
+    let mut badge_bits = pd.pending.replace(0);
+    while badge_bits != 0 {
+        let i = badge_bits.trailing_zeros();
+        handler.notified(Channel::new(i.try_into().unwrap()))?;
+        badge_bits &= !(1 << i);
+    }
+    Ok(())
+}
@@ -0,0 +1,527 @@
+//
+// Copyright 2023, Colias Group, LLC
+//
+// SPDX-License-Identifier: BSD-2-Clause
+//
+
+//! A host-side simulator for testing protection domains without seL4.
+//!
+//! A [`Simulator`] runs several [`Handler`] implementations as simulated protection domains
+//! within the current thread, connected by simulated channels and IRQs. Events are delivered in
+//! the order that seL4 would deliver them under the Microkit's scheduling model:
+//!
+//! - Protection domains are initialized in descending order of priority.
+//! - When a notification arrives for a protection domain with a higher priority than the
+//!   currently running one, the recipient runs immediately. Otherwise, it runs once no
+//!   higher-priority work remains, with pending notifications served first-come-first-served
+//!   among protection domains of equal priority.
+//! - A protected procedure call runs the callee to completion on the caller's behalf. The callee
+//!   must have a higher priority than the caller.
+//! - Passive protection domains only run when called, at which point any notifications which
+//!   arrived in the meantime are delivered before the call.
+//! - An IRQ is masked from delivery until it is acknowledged, after which a latched occurrence is
+//!   delivered again.
+//!
+//! A handler error faults the protection domain that returned it, as does calling a faulted
+//! protection domain. Faults are reported as [`SimulationError`]s by the [`Simulator`] method
+//! during which they occur.
+//!
+//! The methods of [`Channel`], the message register accessors, [`pd_name`], and [`pd_is_passive`]
+//! act on the simulated system, and must only be called from within a simulated protection domain.
+//!
+//! ```ignore
+//! let mut sim = Simulator::new();
+//! let server = sim.add_pd(PdConfig::new("server").priority(200), Server::new);
+//! let client = sim.add_pd(PdConfig::new("client").priority(100), Client::new);
+//! sim.add_channel(client, 0, server, 1);
+//! sim.start()?;
+//! sim.with_handler(client, |client: &mut Client| client.do_call())?;
+//! ```
+
+use core::fmt;
+use core::ptr::NonNull;
+
+use std::alloc::{self, Layout};
+use std::error::Error;
+use std::rc::Rc;
+use std::string::String;
+use std::vec::Vec;
+
+use crate::cspace::{Channel, ChannelError, IrqAckError};
+use crate::handler::Handler;
+use crate::message::{FastMessageRegisters, MessageInfo};
+
+mod ipc;
+mod kernel;
+
+use kernel::{with_current_pd, with_kernel, Entered, Kernel};
+
+pub(crate) use ipc::{with_ipc_buffer, with_ipc_buffer_mut, RawMessageInfo};
+
+pub use ipc::{FastMessages, Word, NUM_FAST_MESSAGE_REGISTERS};
+pub use kernel::PdId;
+
+const PAGE_SIZE: usize = 4096;
+
+/// Configuration for a simulated protection domain.
+#[derive(Debug, Clone)]
+pub struct PdConfig {
+    name: &'static str,
+    priority: u8,
+    passive: bool,
+}
+
+impl PdConfig {
+    /// A protection domain named `name`, with priority 0, which is not passive.
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            priority: 0,
+            passive: false,
+        }
+    }
+
+    pub fn priority(mut self, priority: u8) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn passive(mut self, passive: bool) -> Self {
+        self.passive = passive;
+        self
+    }
+}
+
+/// A fault in a simulated protection domain.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SimulationError {
+    pub(crate) pd: &'static str,
+    pub(crate) error: String,
+}
+
+impl SimulationError {
+    /// The name of the protection domain which faulted.
+    pub fn pd(&self) -> &'static str {
+        self.pd
+    }
+
+    /// A description of the fault.
+    pub fn error(&self) -> &str {
+        &self.error
+    }
+}
+
+impl fmt::Display for SimulationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "protection domain '{}' faulted: {}", self.pd, self.error)
+    }
+}
+
+impl Error for SimulationError {}
+
+/// A simulated system of protection domains.
+pub struct Simulator {
+    // Dropped before `memory_regions`, so that handlers can refer to them until they are dropped.
+    kernel: Rc<Kernel>,
+    memory_regions: Vec<HostMemoryRegion>,
+    started: bool,
+}
+
+impl Simulator {
+    pub fn new() -> Self {
+        Self {
+            kernel: Rc::new(Kernel::new()),
+            memory_regions: Vec::new(),
+            started: false,
+        }
+    }
+
+    /// Adds a protection domain, which will be initialized by `init` during [`start`](Self::start).
+    pub fn add_pd<T: Handler + 'static>(
+        &mut self,
+        config: PdConfig,
+        init: impl FnOnce() -> T + 'static,
+    ) -> PdId {
+        self.assert_not_started();
+        self.kernel
+            .add_pd(config.name, config.priority, config.passive, init)
+    }
+
+    /// Connects channel `a_id` of `a` to channel `b_id` of `b`.
+    pub fn add_channel(&mut self, a: PdId, a_id: usize, b: PdId, b_id: usize) {
+        self.assert_not_started();
+        self.kernel.add_channel(a, a_id, b, b_id)
+    }
+
+    /// Delivers `irq` to `pd` through channel `id`. See [`inject_irq`](Self::inject_irq).
+    pub fn add_irq(&mut self, pd: PdId, id: usize, irq: u64) {
+        self.assert_not_started();
+        self.kernel.add_irq(pd, id, irq)
+    }
+
+    /// Allocates a zeroed, page-aligned region of host memory, which remains valid for as long as
+    /// the simulator.
+    ///
+    /// Pass the result to the `init` closures of the protection domains which share it, in place
+    /// of the `memory_region_symbol!` they would use on seL4.
+    pub fn add_memory_region(&mut self, size: usize) -> NonNull<[u8]> {
+        let region = HostMemoryRegion::new(size);
+        let ptr = region.as_ptr();
+        self.memory_regions.push(region);
+        ptr
+    }
+
+    /// Initializes the protection domains, and then runs them until none have work to do.
+    pub fn start(&mut self) -> Result<(), SimulationError> {
+        self.assert_not_started();
+        self.started = true;
+        self.enter(|kernel| kernel.initialize())
+    }
+
+    /// Signals channel `id` of `pd` from outside of the simulated system, and then runs the
+    /// protection domains until none have work to do.
+    pub fn notify(&mut self, pd: PdId, id: usize) -> Result<(), SimulationError> {
+        self.assert_started();
+        self.enter(|kernel| {
+            kernel.notify_from_outside(pd, id);
+            kernel.schedule_above(None);
+        })
+    }
+
+    /// Raises `irq`, and then runs the protection domains until none have work to do.
+    ///
+    /// If `irq` has been delivered but not yet acknowledged, it is delivered again once it is.
+    pub fn inject_irq(&mut self, irq: u64) -> Result<(), SimulationError> {
+        self.assert_started();
+        self.enter(|kernel| {
+            kernel.inject_irq(irq);
+            kernel.schedule_above(None);
+        })
+    }
+
+    /// Runs `f` with the handler of `pd` as though it were code running in `pd`, for example to
+    /// make protected procedure calls or send notifications, and then runs the protection domains
+    /// until none have work to do.
+    ///
+    /// Panics if the handler of `pd` is not of type `T`.
+    pub fn with_handler<T: Handler + 'static, U>(
+        &mut self,
+        pd: PdId,
+        f: impl FnOnce(&mut T) -> U,
+    ) -> Result<U, SimulationError> {
+        self.assert_started();
+        let mut ret = None;
+        self.enter(|kernel| {
+            ret = kernel.run_on_behalf_of(pd, f);
+            kernel.schedule_above(None);
+        })?;
+        Ok(ret.unwrap())
+    }
+
+    fn enter(&mut self, f: impl FnOnce(&Kernel)) -> Result<(), SimulationError> {
+        {
+            let _entered = Entered::new(&self.kernel);
+            f(&self.kernel);
+        }
+        self.kernel.take_fault()
+    }
+
+    fn assert_started(&self) {
+        assert!(self.started, "simulator has not been started");
+    }
+
+    fn assert_not_started(&self) {
+        assert!(!self.started, "simulator has already been started");
+    }
+}
+
+impl Default for Simulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct HostMemoryRegion {
+    ptr: NonNull<u8>,
+    layout: Layout,
+}
+
+impl HostMemoryRegion {
+    fn new(size: usize) -> Self {
+        let layout = Layout::from_size_align(size.max(1), PAGE_SIZE).unwrap();
+        let ptr = NonNull::new(unsafe { alloc::alloc_zeroed(layout) })
+            .unwrap_or_else(|| alloc::handle_alloc_error(layout));
+        Self { ptr, layout }
+    }
+
+    fn as_ptr(&self) -> NonNull<[u8]> {
+        NonNull::slice_from_raw_parts(self.ptr, self.layout.size())
+    }
+}
+
+impl Drop for HostMemoryRegion {
+    fn drop(&mut self) {
+        unsafe { alloc::dealloc(self.ptr.as_ptr(), self.layout) }
+    }
+}
+
+/// Returns the name of the current simulated protection domain.
+pub fn pd_name() -> &'static str {
+    with_current_pd(|pd| pd.name)
+}
+
+/// Returns whether the current simulated protection domain is passive.
+pub fn pd_is_passive() -> bool {
+    with_current_pd(|pd| pd.passive)
+}
+
+// A channel to a simulated protection domain has both a notification and an endpoint.
+impl Channel {
+    pub fn has_notification(&self) -> bool {
+        with_kernel(|kernel| kernel.has_pd_end(*self))
+    }
+
+    pub fn has_pp_endpoint(&self) -> bool {
+        with_kernel(|kernel| kernel.has_pd_end(*self))
+    }
+
+    pub fn has_irq(&self) -> bool {
+        with_kernel(|kernel| kernel.has_irq_end(*self))
+    }
+
+    pub fn try_notify(&self) -> Result<(), ChannelError> {
+        with_kernel(|kernel| kernel.notify(*self))
+    }
+
+    pub fn irq_ack(&self) -> Result<(), IrqAckError> {
+        with_kernel(|kernel| kernel.irq_ack(*self)).map_err(IrqAckError::from)
+    }
+
+    pub fn try_pp_call(&self, msg_info: MessageInfo) -> Result<MessageInfo, ChannelError> {
+        self.try_pp_call_with_mrs(msg_info, [])
+            .map(|(reply_msg_info, _)| reply_msg_info)
+    }
+
+    pub fn try_pp_call_with_mrs<T: FastMessages>(
+        &self,
+        msg_info: MessageInfo,
+        messages: T,
+    ) -> Result<(MessageInfo, FastMessageRegisters), ChannelError> {
+        with_kernel(|kernel| kernel.pp_call(*self, msg_info, messages.prepare_in()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::cell::RefCell;
+
+    use std::format;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::string::ToString;
+    use std::vec;
+
+    use super::ipc::NUM_MESSAGE_REGISTERS;
+    use super::*;
+    use crate::{get_mr, set_mr, with_msg_regs, Infallible};
+
+    type Log = Rc<RefCell<Vec<String>>>;
+
+    struct Recorder {
+        log: Log,
+    }
+
+    impl Recorder {
+        fn push(&self, event: &str) {
+            self.log
+                .borrow_mut()
+                .push(format!("{}: {event}", pd_name()));
+        }
+    }
+
+    impl Handler for Recorder {
+        type Error = Infallible;
+
+        fn notified(&mut self, channel: Channel) -> Result<(), Self::Error> {
+            self.push(&format!("notified {}", channel.index()));
+            Ok(())
+        }
+    }
+
+    // Replies with the sum of the request's message registers, and the request's count.
+    struct Adder;
+
+    impl Handler for Adder {
+        type Error = Infallible;
+
+        fn protected(
+            &mut self,
+            _channel: Channel,
+            msg_info: MessageInfo,
+        ) -> Result<MessageInfo, Self::Error> {
+            let sum = with_msg_regs(|regs| regs[..msg_info.count()].iter().sum());
+            set_mr(0, sum);
+            set_mr(1, msg_info.count().try_into().unwrap());
+            Ok(MessageInfo::new(msg_info.label(), 2))
+        }
+    }
+
+    struct Faulty;
+
+    impl Handler for Faulty {
+        type Error = &'static str;
+
+        fn notified(&mut self, _channel: Channel) -> Result<(), Self::Error> {
+            Err("bad notification")
+        }
+
+        fn protected(
+            &mut self,
+            _channel: Channel,
+            _msg_info: MessageInfo,
+        ) -> Result<MessageInfo, Self::Error> {
+            Err("bad call")
+        }
+    }
+
+    fn add_recorder(sim: &mut Simulator, log: &Log, name: &'static str, priority: u8) -> PdId {
+        let log = log.clone();
+        sim.add_pd(PdConfig::new(name).priority(priority), move || Recorder {
+            log,
+        })
+    }
+
+    #[test]
+    fn notify() {
+        let log = Log::default();
+        let mut sim = Simulator::new();
+        let low = add_recorder(&mut sim, &log, "low", 100);
+        let high = add_recorder(&mut sim, &log, "high", 200);
+        sim.add_channel(low, 0, high, 1);
+        sim.start().unwrap();
+
+        // Notifying a higher-priority protection domain preempts the sender
+        sim.with_handler(low, |recorder: &mut Recorder| {
+            Channel::new(0).notify();
+            recorder.push("sent");
+        })
+        .unwrap();
+        // Notifying a lower-priority one does not
+        sim.with_handler(high, |recorder: &mut Recorder| {
+            Channel::new(1).notify();
+            recorder.push("sent");
+        })
+        .unwrap();
+        sim.notify(low, 5).unwrap();
+
+        assert_eq!(
+            *log.borrow(),
+            [
+                "high: notified 1",
+                "low: sent",
+                "high: sent",
+                "low: notified 0",
+                "low: notified 5",
+            ]
+        );
+    }
+
+    #[test]
+    fn irq() {
+        let log = Log::default();
+        let mut sim = Simulator::new();
+        let pd = add_recorder(&mut sim, &log, "driver", 100);
+        sim.add_irq(pd, 2, 33);
+        sim.start().unwrap();
+
+        sim.inject_irq(33).unwrap();
+        // Latched until acknowledged
+        sim.inject_irq(33).unwrap();
+        assert_eq!(log.borrow().len(), 1);
+        sim.with_handler(pd, |_: &mut Recorder| Channel::new(2).irq_ack().unwrap())
+            .unwrap();
+        sim.with_handler(pd, |_: &mut Recorder| Channel::new(2).irq_ack().unwrap())
+            .unwrap();
+        sim.inject_irq(33).unwrap();
+        assert_eq!(
+            *log.borrow(),
+            [
+                "driver: notified 2",
+                "driver: notified 2",
+                "driver: notified 2"
+            ]
+        );
+    }
+
+    fn client_server<T: Handler + 'static>(
+        server: impl FnOnce() -> T + 'static,
+    ) -> (Simulator, PdId, PdId) {
+        let mut sim = Simulator::new();
+        let client = add_recorder(&mut sim, &Log::default(), "client", 100);
+        let server = sim.add_pd(PdConfig::new("server").priority(200), server);
+        sim.add_channel(client, 0, server, 3);
+        (sim, client, server)
+    }
+
+    #[test]
+    fn pp_call() {
+        let (mut sim, client, _) = client_server(|| Adder);
+        sim.start().unwrap();
+        let reply = sim
+            .with_handler(client, |_: &mut Recorder| {
+                set_mr(4, 10);
+                set_mr(5, 20);
+                let (reply_msg_info, reply_msg) =
+                    Channel::new(0).pp_call_with_mrs(MessageInfo::new(7, 6), [1, 2, 3, 4]);
+                (
+                    reply_msg_info.label(),
+                    reply_msg_info.count(),
+                    reply_msg[..2].to_vec(),
+                    get_mr(0),
+                )
+            })
+            .unwrap();
+        assert_eq!(reply, (7, 2, vec![40, 6], 40));
+    }
+
+    #[test]
+    fn pp_call_with_oversized_count() {
+        let (mut sim, client, _) = client_server(|| Adder);
+        sim.start().unwrap();
+        let count = sim
+            .with_handler(client, |_: &mut Recorder| {
+                let (_, reply_msg) = Channel::new(0).pp_call_with_mrs(MessageInfo::new(0, 127), []);
+                reply_msg[1]
+            })
+            .unwrap();
+        assert_eq!(count, NUM_MESSAGE_REGISTERS.try_into().unwrap());
+    }
+
+    #[test]
+    fn faulting_callee() {
+        let (mut sim, client, _) = client_server(|| Faulty);
+        sim.start().unwrap();
+        let err = sim
+            .with_handler(client, |_: &mut Recorder| {
+                Channel::new(0).pp_call(MessageInfo::new(0, 0));
+            })
+            .unwrap_err();
+        assert_eq!((err.pd(), err.error()), ("server", "bad call"));
+        // The caller never receives a reply, and so never runs again
+        sim.notify(client, 0).unwrap();
+        assert!(panic::catch_unwind(AssertUnwindSafe(|| {
+            sim.with_handler(client, |_: &mut Recorder| ())
+        }))
+        .is_err());
+    }
+
+    #[test]
+    fn faulting_notification() {
+        let (mut sim, _, server) = client_server(|| Faulty);
+        sim.start().unwrap();
+        let err = sim.notify(server, 3).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "protection domain 'server' faulted: bad notification"
+        );
+    }
+}
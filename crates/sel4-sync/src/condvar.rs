@@ -0,0 +1,63 @@
+//
+// Copyright 2023, Colias Group, LLC
+//
+// SPDX-License-Identifier: MIT
+//
+
+use lock_api::{MutexGuard, RawMutex};
+
+use crate::wait_queue::{state_lock, StateLock, WaitQueue};
+use crate::MutexSyncOps;
+
+/// A condition variable which blocks on the notification provided by its first [`MutexSyncOps`].
+/// The second protects its internal state.
+///
+/// It can be used with any [`lock_api::Mutex`], including those backed by [`GenericRawMutex`].
+/// As with most condition variables, waiters must tolerate spurious wakeups.
+///
+/// [`GenericRawMutex`]: crate::GenericRawMutex
+pub struct GenericCondvar<O> {
+    sync_ops: O,
+    queue: StateLock<O, WaitQueue>,
+}
+
+impl<O> GenericCondvar<O> {
+    pub const fn new(sync_ops: O, state_sync_ops: O) -> Self {
+        Self {
+            sync_ops,
+            queue: state_lock(state_sync_ops, WaitQueue::new()),
+        }
+    }
+}
+
+impl<O: MutexSyncOps> GenericCondvar<O> {
+    pub fn wait<R: RawMutex, T: ?Sized>(&self, guard: &mut MutexGuard<'_, R, T>) {
+        // Enqueue before releasing the mutex, so that a notification sent by a thread which
+        // subsequently acquires the mutex is not lost.
+        self.queue.lock().enqueue();
+        MutexGuard::unlocked(guard, || {
+            self.sync_ops.wait();
+            self.queue.lock().consume(&self.sync_ops);
+        })
+    }
+
+    pub fn wait_while<R: RawMutex, T: ?Sized>(
+        &self,
+        guard: &mut MutexGuard<'_, R, T>,
+        mut condition: impl FnMut(&mut T) -> bool,
+    ) {
+        while condition(&mut *guard) {
+            self.wait(guard);
+        }
+    }
+
+    /// Wakes up one blocked thread, if any. Returns whether a thread was woken.
+    pub fn notify_one(&self) -> bool {
+        self.queue.lock().wake(&self.sync_ops, 1) > 0
+    }
+
+    /// Wakes up all blocked threads. Returns the number of threads woken.
+    pub fn notify_all(&self) -> usize {
+        self.queue.lock().wake(&self.sync_ops, usize::MAX)
+    }
+}
@@ -8,10 +8,19 @@
 
 pub use lock_api;
 
+mod condvar;
 mod mutex;
+mod once_barrier;
+mod rwlock;
+mod semaphore;
+mod wait_queue;
 
+pub use condvar::GenericCondvar;
 pub use mutex::{
     AbstractMutexSyncOps, DeferredNotificationMutexSyncOps, GenericRawMutex,
     IndirectNotificationMutexSyncOps, MutexSyncOps, MutexSyncOpsWithInteriorMutability,
     MutexSyncOpsWithNotification, PanickingMutexSyncOps,
 };
+pub use once_barrier::GenericOnceBarrier;
+pub use rwlock::GenericRawRwLock;
+pub use semaphore::GenericSemaphore;
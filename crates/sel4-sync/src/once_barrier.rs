@@ -0,0 +1,68 @@
+//
+// Copyright 2023, Colias Group, LLC
+//
+// SPDX-License-Identifier: MIT
+//
+
+use crate::wait_queue::{state_lock, StateLock, WaitQueue};
+use crate::MutexSyncOps;
+
+/// A single-use barrier which blocks on the notification provided by its first [`MutexSyncOps`].
+/// The second protects its internal state.
+///
+/// Once `num_threads` threads have called [`wait`](GenericOnceBarrier::wait), all of them are
+/// released, and all subsequent calls return immediately. Unlike a reusable barrier, this only
+/// requires two notifications, regardless of `num_threads`, which makes it suitable for rendezvousing at the end of a
+/// multithreaded root task's initialization.
+pub struct GenericOnceBarrier<O> {
+    sync_ops: O,
+    state: StateLock<O, OnceBarrierState>,
+}
+
+struct OnceBarrierState {
+    remaining: usize,
+    queue: WaitQueue,
+}
+
+impl<O> GenericOnceBarrier<O> {
+    pub const fn new(sync_ops: O, state_sync_ops: O, num_threads: usize) -> Self {
+        Self {
+            sync_ops,
+            state: state_lock(
+                state_sync_ops,
+                OnceBarrierState {
+                    remaining: num_threads,
+                    queue: WaitQueue::new(),
+                },
+            ),
+        }
+    }
+}
+
+impl<O: MutexSyncOps> GenericOnceBarrier<O> {
+    /// Returns `true` for exactly one thread: the one whose arrival released the others.
+    pub fn wait(&self) -> bool {
+        {
+            let mut state = self.state.lock();
+            match state.remaining {
+                0 => return false,
+                1 => {
+                    state.remaining = 0;
+                    state.queue.wake(&self.sync_ops, usize::MAX);
+                    return true;
+                }
+                _ => {
+                    state.remaining -= 1;
+                    state.queue.enqueue();
+                }
+            }
+        }
+        self.sync_ops.wait();
+        self.state.lock().queue.consume(&self.sync_ops);
+        false
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.state.lock().remaining == 0
+    }
+}
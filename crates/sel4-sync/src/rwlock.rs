@@ -0,0 +1,268 @@
+//
+// Copyright 2023, Colias Group, LLC
+//
+// SPDX-License-Identifier: MIT
+//
+
+use crate::wait_queue::{state_lock, StateLock, WaitQueue};
+use crate::MutexSyncOps;
+
+/// A reader-writer lock which blocks readers and writers on separate notifications, provided by
+/// its first two [`MutexSyncOps`]. The third protects its internal state.
+///
+/// New readers queue behind waiting writers. The last reader to leave hands the lock to a waiting
+/// writer, and a releasing writer hands it to all waiting readers, so that neither side starves.
+pub struct GenericRawRwLock<O> {
+    reader_sync_ops: O,
+    writer_sync_ops: O,
+    state: StateLock<O, RwLockState>,
+}
+
+struct RwLockState {
+    readers: usize,
+    writer: bool,
+    waiting_readers: WaitQueue,
+    waiting_writers: WaitQueue,
+}
+
+impl<O> GenericRawRwLock<O> {
+    pub const fn new(reader_sync_ops: O, writer_sync_ops: O, state_sync_ops: O) -> Self {
+        Self {
+            reader_sync_ops,
+            writer_sync_ops,
+            state: state_lock(
+                state_sync_ops,
+                RwLockState {
+                    readers: 0,
+                    writer: false,
+                    waiting_readers: WaitQueue::new(),
+                    waiting_writers: WaitQueue::new(),
+                },
+            ),
+        }
+    }
+}
+
+impl RwLockState {
+    fn can_read(&self) -> bool {
+        !self.writer && self.waiting_writers.waiting() == 0
+    }
+
+    fn can_write(&self) -> bool {
+        !self.writer && self.readers == 0
+    }
+}
+
+impl<O: MutexSyncOps> GenericRawRwLock<O> {
+    fn hand_off(&self, state: &mut RwLockState, prefer_readers: bool) {
+        debug_assert!(state.can_write());
+        if prefer_readers || state.waiting_writers.waiting() == 0 {
+            state.readers += state
+                .waiting_readers
+                .wake(&self.reader_sync_ops, usize::MAX);
+        }
+        if state.readers == 0 && state.waiting_writers.wake(&self.writer_sync_ops, 1) > 0 {
+            state.writer = true;
+        }
+    }
+}
+
+unsafe impl<O: MutexSyncOps> lock_api::RawRwLock for GenericRawRwLock<O> {
+    type GuardMarker = lock_api::GuardNoSend; // TODO
+
+    const INIT: Self = unimplemented!();
+
+    fn lock_shared(&self) {
+        {
+            let mut state = self.state.lock();
+            if state.can_read() {
+                state.readers += 1;
+                return;
+            }
+            state.waiting_readers.enqueue();
+        }
+        // Woken readers have already been counted by the thread which woke them.
+        self.reader_sync_ops.wait();
+        self.state
+            .lock()
+            .waiting_readers
+            .consume(&self.reader_sync_ops);
+    }
+
+    fn try_lock_shared(&self) -> bool {
+        let mut state = self.state.lock();
+        if state.can_read() {
+            state.readers += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    unsafe fn unlock_shared(&self) {
+        let mut state = self.state.lock();
+        state.readers -= 1;
+        if state.readers == 0 {
+            self.hand_off(&mut state, false);
+        }
+    }
+
+    fn lock_exclusive(&self) {
+        {
+            let mut state = self.state.lock();
+            if state.can_write() {
+                state.writer = true;
+                return;
+            }
+            state.waiting_writers.enqueue();
+        }
+        // A woken writer has already been granted the lock by the thread which woke it.
+        self.writer_sync_ops.wait();
+        self.state
+            .lock()
+            .waiting_writers
+            .consume(&self.writer_sync_ops);
+    }
+
+    fn try_lock_exclusive(&self) -> bool {
+        let mut state = self.state.lock();
+        if state.can_write() {
+            state.writer = true;
+            true
+        } else {
+            false
+        }
+    }
+
+    unsafe fn unlock_exclusive(&self) {
+        let mut state = self.state.lock();
+        state.writer = false;
+        self.hand_off(&mut state, true);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+    use std::vec::Vec;
+
+    use lock_api::RawRwLock;
+
+    use super::*;
+    use crate::wait_queue::tests::TestSyncOps;
+
+    type TestRwLock = GenericRawRwLock<TestSyncOps>;
+
+    fn rwlock() -> Arc<TestRwLock> {
+        Arc::new(GenericRawRwLock::new(
+            TestSyncOps::default(),
+            TestSyncOps::default(),
+            TestSyncOps::default(),
+        ))
+    }
+
+    fn wait_until(rwlock: &TestRwLock, f: impl Fn(&RwLockState) -> bool) {
+        while !f(&rwlock.state.lock()) {
+            thread::yield_now();
+        }
+    }
+
+    #[test]
+    fn try_lock() {
+        let rwlock = rwlock();
+        assert!(rwlock.try_lock_exclusive());
+        assert!(!rwlock.try_lock_shared());
+        assert!(!rwlock.try_lock_exclusive());
+        unsafe { rwlock.unlock_exclusive() };
+        assert!(rwlock.try_lock_shared());
+        assert!(rwlock.try_lock_shared());
+        assert!(!rwlock.try_lock_exclusive());
+        unsafe { rwlock.unlock_shared() };
+        unsafe { rwlock.unlock_shared() };
+        assert!(rwlock.try_lock_exclusive());
+    }
+
+    #[test]
+    fn last_reader_hands_off_to_writer() {
+        let rwlock = rwlock();
+        rwlock.lock_shared();
+        let writer = thread::spawn({
+            let rwlock = rwlock.clone();
+            move || {
+                rwlock.lock_exclusive();
+                unsafe { rwlock.unlock_exclusive() };
+            }
+        });
+        wait_until(&rwlock, |state| state.waiting_writers.waiting() == 1);
+        // New readers queue behind the waiting writer
+        assert!(!rwlock.try_lock_shared());
+        unsafe { rwlock.unlock_shared() };
+        writer.join().unwrap();
+        assert_eq!(rwlock.writer_sync_ops.signals(), 1);
+        let state = rwlock.state.lock();
+        assert!(state.can_write() && state.can_read());
+    }
+
+    #[test]
+    fn writer_hands_off_to_all_readers() {
+        let rwlock = rwlock();
+        let holding = Arc::new(AtomicUsize::new(0));
+        rwlock.lock_exclusive();
+        let readers = (0..3)
+            .map(|_| {
+                thread::spawn({
+                    let rwlock = rwlock.clone();
+                    let holding = holding.clone();
+                    move || {
+                        rwlock.lock_shared();
+                        holding.fetch_add(1, Ordering::SeqCst);
+                        while holding.load(Ordering::SeqCst) < 3 {
+                            thread::yield_now();
+                        }
+                        unsafe { rwlock.unlock_shared() };
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        wait_until(&rwlock, |state| state.waiting_readers.waiting() == 3);
+        unsafe { rwlock.unlock_exclusive() };
+        // All readers must hold the lock at once for any of them to finish
+        for reader in readers {
+            reader.join().unwrap();
+        }
+        assert_eq!(rwlock.reader_sync_ops.signals(), 3);
+        assert!(rwlock.try_lock_exclusive());
+    }
+
+    #[test]
+    fn releasing_writer_prefers_readers() {
+        let rwlock = rwlock();
+        rwlock.lock_exclusive();
+        let reader = thread::spawn({
+            let rwlock = rwlock.clone();
+            move || rwlock.lock_shared()
+        });
+        wait_until(&rwlock, |state| state.waiting_readers.waiting() == 1);
+        let writer = thread::spawn({
+            let rwlock = rwlock.clone();
+            move || {
+                rwlock.lock_exclusive();
+                unsafe { rwlock.unlock_exclusive() };
+            }
+        });
+        wait_until(&rwlock, |state| state.waiting_writers.waiting() == 1);
+        unsafe { rwlock.unlock_exclusive() };
+        reader.join().unwrap();
+        {
+            let state = rwlock.state.lock();
+            assert_eq!(state.readers, 1);
+            assert!(!state.writer);
+        }
+        unsafe { rwlock.unlock_shared() };
+        writer.join().unwrap();
+    }
+}
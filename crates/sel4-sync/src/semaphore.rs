@@ -0,0 +1,72 @@
+//
+// Copyright 2023, Colias Group, LLC
+//
+// SPDX-License-Identifier: MIT
+//
+
+use crate::wait_queue::{state_lock, StateLock, WaitQueue};
+use crate::MutexSyncOps;
+
+/// A counting semaphore which blocks on the notification provided by its first [`MutexSyncOps`].
+/// The second protects its internal state.
+pub struct GenericSemaphore<O> {
+    sync_ops: O,
+    state: StateLock<O, SemaphoreState>,
+}
+
+struct SemaphoreState {
+    permits: usize,
+    queue: WaitQueue,
+}
+
+impl<O> GenericSemaphore<O> {
+    pub const fn new(sync_ops: O, state_sync_ops: O, permits: usize) -> Self {
+        Self {
+            sync_ops,
+            state: state_lock(
+                state_sync_ops,
+                SemaphoreState {
+                    permits,
+                    queue: WaitQueue::new(),
+                },
+            ),
+        }
+    }
+}
+
+impl<O: MutexSyncOps> GenericSemaphore<O> {
+    pub fn acquire(&self) {
+        {
+            let mut state = self.state.lock();
+            if state.permits > 0 {
+                state.permits -= 1;
+                return;
+            }
+            state.queue.enqueue();
+        }
+        // A releaser hands its permit directly to the thread it wakes.
+        self.sync_ops.wait();
+        self.state.lock().queue.consume(&self.sync_ops);
+    }
+
+    pub fn try_acquire(&self) -> bool {
+        let mut state = self.state.lock();
+        if state.permits > 0 {
+            state.permits -= 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn release(&self) {
+        let mut state = self.state.lock();
+        if state.queue.wake(&self.sync_ops, 1) == 0 {
+            state.permits += 1;
+        }
+    }
+
+    pub fn available_permits(&self) -> usize {
+        self.state.lock().permits
+    }
+}
@@ -0,0 +1,127 @@
+//
+// Copyright 2023, Colias Group, LLC
+//
+// SPDX-License-Identifier: MIT
+//
+
+use crate::{GenericRawMutex, MutexSyncOps};
+
+// Protects the bookkeeping of the primitives in this crate. Contending threads block on the
+// notification provided by its own `MutexSyncOps`, which must be distinct from those on which the
+// primitive's waiters block.
+pub(crate) type StateLock<O, T> = lock_api::Mutex<GenericRawMutex<O>, T>;
+
+pub(crate) const fn state_lock<O, T>(sync_ops: O, state: T) -> StateLock<O, T> {
+    StateLock::const_new(GenericRawMutex::new(sync_ops), state)
+}
+
+// Threads blocked on a single notification, which behaves as a binary semaphore.
+//
+// Waking several threads at once would collapse into a single signal, so wakeups are instead
+// handed off: a waker records how many threads it is releasing, signals once, and each woken thread
+// passes the signal on until all recorded wakeups have been consumed. All methods must be called
+// with the owning primitive's `StateLock` held.
+#[derive(Debug, Default)]
+pub(crate) struct WaitQueue {
+    waiting: usize,
+    pending: usize,
+}
+
+impl WaitQueue {
+    pub(crate) const fn new() -> Self {
+        Self {
+            waiting: 0,
+            pending: 0,
+        }
+    }
+
+    pub(crate) fn waiting(&self) -> usize {
+        self.waiting
+    }
+
+    // The caller must then drop the state lock, wait on the notification, and call `consume`.
+    pub(crate) fn enqueue(&mut self) {
+        self.waiting += 1;
+    }
+
+    // Returns the number of threads released.
+    pub(crate) fn wake(&mut self, sync_ops: &impl MutexSyncOps, n: usize) -> usize {
+        let n = n.min(self.waiting);
+        if n > 0 {
+            self.waiting -= n;
+            let was_idle = self.pending == 0;
+            self.pending += n;
+            if was_idle {
+                sync_ops.signal();
+            }
+        }
+        n
+    }
+
+    pub(crate) fn consume(&mut self, sync_ops: &impl MutexSyncOps) {
+        self.pending -= 1;
+        if self.pending > 0 {
+            sync_ops.signal();
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    extern crate std;
+
+    use std::sync::{Condvar, Mutex};
+
+    use super::*;
+
+    // Behaves like a notification used as a binary semaphore.
+    #[derive(Default)]
+    pub(crate) struct TestSyncOps {
+        signalled: Mutex<bool>,
+        signals: Mutex<usize>,
+        condvar: Condvar,
+    }
+
+    impl TestSyncOps {
+        pub(crate) fn signals(&self) -> usize {
+            *self.signals.lock().unwrap()
+        }
+    }
+
+    impl MutexSyncOps for TestSyncOps {
+        fn signal(&self) {
+            *self.signals.lock().unwrap() += 1;
+            *self.signalled.lock().unwrap() = true;
+            self.condvar.notify_one();
+        }
+
+        fn wait(&self) {
+            let mut signalled = self
+                .condvar
+                .wait_while(self.signalled.lock().unwrap(), |signalled| !*signalled)
+                .unwrap();
+            *signalled = false;
+        }
+    }
+
+    #[test]
+    fn wake_hands_off_a_single_signal() {
+        let sync_ops = TestSyncOps::default();
+        let mut queue = WaitQueue::new();
+        for _ in 0..3 {
+            queue.enqueue();
+        }
+        assert_eq!(queue.wake(&sync_ops, 2), 2);
+        assert_eq!(queue.waiting(), 1);
+        assert_eq!(sync_ops.signals(), 1);
+        // Already pending wakeups are passed on by the woken threads
+        assert_eq!(queue.wake(&sync_ops, usize::MAX), 1);
+        assert_eq!(sync_ops.signals(), 1);
+        queue.consume(&sync_ops);
+        queue.consume(&sync_ops);
+        assert_eq!(sync_ops.signals(), 3);
+        queue.consume(&sync_ops);
+        assert_eq!(sync_ops.signals(), 3);
+        assert_eq!(queue.wake(&sync_ops, 1), 0);
+    }
+}
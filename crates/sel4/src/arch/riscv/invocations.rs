@@ -6,7 +6,7 @@
 
 use crate::{
     local_cptr::*, AbsoluteCPtr, CapRights, Error, FrameType, InvocationContext, LocalCPtr, Result,
-    VMAttributes,
+    VMAttributes, Word,
 };
 
 impl<T: FrameType, C: InvocationContext> LocalCPtr<T, C> {
@@ -65,6 +65,27 @@ impl<C: InvocationContext> PageTable<C> {
     }
 }
 
+impl<C: InvocationContext> IRQControl<C> {
+    /// Corresponds to `seL4_IRQControl_GetTrigger`.
+    pub fn irq_control_get_trigger(
+        self,
+        irq: Word,
+        trigger: Word,
+        dst: &AbsoluteCPtr,
+    ) -> Result<()> {
+        Error::wrap(self.invoke(|cptr, ipc_buffer| {
+            ipc_buffer.inner_mut().seL4_IRQControl_GetTrigger(
+                cptr.bits(),
+                irq,
+                trigger,
+                dst.root().bits(),
+                dst.path().bits(),
+                dst.path().depth_for_kernel(),
+            )
+        }))
+    }
+}
+
 impl<C: InvocationContext> ASIDControl<C> {
     /// Corresponds to `seL4_RISCV_ASIDControl_MakePool`.
     pub fn asid_control_make_pool(self, untyped: Untyped, dst: &AbsoluteCPtr) -> Result<()> {
@@ -6,7 +6,7 @@
 
 use crate::{
     local_cptr::*, AbsoluteCPtr, CapRights, Error, FrameType, InvocationContext, LocalCPtr, Result,
-    VMAttributes,
+    VMAttributes, Word,
 };
 
 impl<T: FrameType, C: InvocationContext> LocalCPtr<T, C> {
@@ -92,8 +92,80 @@ impl<C: InvocationContext> PageTable<C> {
     }
 }
 
-// TODO
-impl<C: InvocationContext> IRQControl<C> {}
+impl<C: InvocationContext> IRQControl<C> {
+    /// Corresponds to `seL4_IRQControl_GetIOAPIC`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn irq_control_get_ioapic(
+        self,
+        ioapic: Word,
+        pin: Word,
+        level: Word,
+        polarity: Word,
+        vector: Word,
+        dst: &AbsoluteCPtr,
+    ) -> Result<()> {
+        Error::wrap(self.invoke(|cptr, ipc_buffer| {
+            ipc_buffer.inner_mut().seL4_IRQControl_GetIOAPIC(
+                cptr.bits(),
+                dst.root().bits(),
+                dst.path().bits(),
+                dst.path().depth_for_kernel(),
+                ioapic,
+                pin,
+                level,
+                polarity,
+                vector,
+            )
+        }))
+    }
+
+    /// Corresponds to `seL4_IRQControl_GetMSI`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn irq_control_get_msi(
+        self,
+        pci_bus: Word,
+        pci_dev: Word,
+        pci_func: Word,
+        handle: Word,
+        vector: Word,
+        dst: &AbsoluteCPtr,
+    ) -> Result<()> {
+        Error::wrap(self.invoke(|cptr, ipc_buffer| {
+            ipc_buffer.inner_mut().seL4_IRQControl_GetMSI(
+                cptr.bits(),
+                dst.root().bits(),
+                dst.path().bits(),
+                dst.path().depth_for_kernel(),
+                pci_bus,
+                pci_dev,
+                pci_func,
+                handle,
+                vector,
+            )
+        }))
+    }
+}
+
+impl<C: InvocationContext> IOPortControl<C> {
+    /// Corresponds to `seL4_X86_IOPortControl_Issue`.
+    pub fn io_port_control_issue(
+        self,
+        first_port: Word,
+        last_port: Word,
+        dst: &AbsoluteCPtr,
+    ) -> Result<()> {
+        Error::wrap(self.invoke(|cptr, ipc_buffer| {
+            ipc_buffer.inner_mut().seL4_X86_IOPortControl_Issue(
+                cptr.bits(),
+                first_port,
+                last_port,
+                dst.root().bits(),
+                dst.path().bits(),
+                dst.path().depth_for_kernel(),
+            )
+        }))
+    }
+}
 
 impl<C: InvocationContext> ASIDControl<C> {
     /// Corresponds to `seL4_X86_ASIDControl_MakePool`.
@@ -35,6 +35,16 @@ pub(crate) mod cap_type_arch {
     declare_cap_type!(PageDirectory);
     declare_cap_type!(PageTable);
 
+    declare_cap_type! {
+        /// Corresponds to `seL4_X86_IOPortControl`.
+        IOPortControl
+    }
+
+    declare_cap_type! {
+        /// Corresponds to `seL4_X86_IOPort`.
+        IOPort
+    }
+
     pub type VSpace = PML4;
     pub type Granule = _4K;
 }
@@ -50,4 +60,7 @@ pub(crate) mod local_cptr_arch {
     declare_local_cptr_alias!(PDPT);
     declare_local_cptr_alias!(PageDirectory);
     declare_local_cptr_alias!(PageTable);
+
+    declare_local_cptr_alias!(IOPortControl);
+    declare_local_cptr_alias!(IOPort);
 }
@@ -12,13 +12,16 @@ use core::ops::Range;
 use core::slice;
 
 use crate::{
-    newtype_methods, sel4_cfg, sys, ASIDControl, ASIDPool, CNode, CPtr, CapType, IPCBuffer,
-    IRQControl, LocalCPtr, Null, VSpace, GRANULE_SIZE, TCB,
+    newtype_methods, sel4_cfg, sys, ASIDControl, ASIDPool, CNode, CPtr, CapType, DomainSet,
+    IPCBuffer, IRQControl, LocalCPtr, Null, VSpace, GRANULE_SIZE, TCB,
 };
 
 #[sel4_cfg(KERNEL_MCS)]
 use crate::SchedControl;
 
+#[sel4_cfg(any(ARCH_IA32, ARCH_X86_64))]
+use crate::IOPortControl;
+
 /// Corresponds to `seL4_BootInfo`.
 #[derive(Debug)]
 pub struct BootInfo {
@@ -131,6 +134,15 @@ impl BootInfo {
         ASIDControl::from_bits(sys::seL4_RootCapSlot::seL4_CapASIDControl.into())
     }
 
+    pub fn domain() -> DomainSet {
+        DomainSet::from_bits(sys::seL4_RootCapSlot::seL4_CapDomain.into())
+    }
+
+    #[sel4_cfg(any(ARCH_IA32, ARCH_X86_64))]
+    pub fn io_port_control() -> IOPortControl {
+        IOPortControl::from_bits(sys::seL4_RootCapSlot::seL4_CapIOPortControl.into())
+    }
+
     pub fn init_thread_asid_pool() -> ASIDPool {
         ASIDPool::from_bits(sys::seL4_RootCapSlot::seL4_CapInitThreadASIDPool.into())
     }
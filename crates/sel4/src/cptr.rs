@@ -210,6 +210,11 @@ pub mod cap_type {
         ASIDPool
     }
 
+    declare_cap_type! {
+        /// Corresponds to `seL4_DomainSet`.
+        DomainSet
+    }
+
     declare_cap_type! {
         /// Corresponds to the null capability.
         Null
@@ -267,6 +272,7 @@ pub mod local_cptr {
     declare_local_cptr_alias!(IRQHandler);
     declare_local_cptr_alias!(ASIDControl);
     declare_local_cptr_alias!(ASIDPool);
+    declare_local_cptr_alias!(DomainSet);
 
     declare_local_cptr_alias!(Null);
     declare_local_cptr_alias!(Unspecified);
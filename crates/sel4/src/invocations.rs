@@ -274,6 +274,17 @@ impl<C: InvocationContext> IRQControl<C> {
     }
 }
 
+impl<C: InvocationContext> DomainSet<C> {
+    /// Corresponds to `seL4_DomainSet_Set`.
+    pub fn domain_set_set(self, domain: u8, thread: TCB) -> Result<()> {
+        Error::wrap(self.invoke(|cptr, ipc_buffer| {
+            ipc_buffer
+                .inner_mut()
+                .seL4_DomainSet_Set(cptr.bits(), domain, thread.bits())
+        }))
+    }
+}
+
 impl<C: InvocationContext> IRQHandler<C> {
     /// Corresponds to `seL4_IRQHandler_Ack`.
     pub fn irq_handler_ack(self) -> Result<()> {
@@ -355,6 +366,20 @@ impl<C: InvocationContext> AbsoluteCPtr<C> {
         }))
     }
 
+    /// Corresponds to `seL4_CNode_Move`.
+    pub fn move_(self, src: &AbsoluteCPtr) -> Result<()> {
+        Error::wrap(self.invoke(|cptr, path, ipc_buffer| {
+            ipc_buffer.inner_mut().seL4_CNode_Move(
+                cptr.bits(),
+                path.bits(),
+                path.depth_for_kernel(),
+                src.root().bits(),
+                src.path().bits(),
+                src.path().depth_for_kernel(),
+            )
+        }))
+    }
+
     /// Corresponds to `seL4_CNode_Mutate`.
     pub fn mutate(self, src: &AbsoluteCPtr, badge: Word) -> Result<()> {
         Error::wrap(self.invoke(|cptr, path, ipc_buffer| {
@@ -158,6 +158,35 @@ impl<C: InvocationContext> Endpoint<C> {
         }
     }
 
+    pub fn reply_recv_with_mrs<T: FastMessages>(
+        self,
+        info: MessageInfo,
+        messages: T,
+        reply_authority: impl ConveysReplyAuthority,
+    ) -> RecvWithMRs {
+        // NOTE(rustc_wishlist) (see recv_with_mrs)
+        let mut msg = messages.prepare_in_out();
+        let [ref mut mr0, ref mut mr1, ref mut mr2, ref mut mr3] = &mut msg;
+        let (raw_msg_info, badge) = self.invoke(|cptr, ipc_buffer| {
+            ipc_buffer.inner_mut().seL4_ReplyRecvWithMRs(
+                cptr.bits(),
+                info.into_inner(),
+                Some(mr0),
+                Some(mr1),
+                Some(mr2),
+                Some(mr3),
+                reply_authority
+                    .into_reply_authority()
+                    .into_sys_reply_authority(),
+            )
+        });
+        RecvWithMRs {
+            info: MessageInfo::from_inner(raw_msg_info),
+            badge,
+            msg,
+        }
+    }
+
     pub fn call_with_mrs<T: FastMessages>(self, info: MessageInfo, messages: T) -> CallWithMRs {
         // NOTE(rustc_wishlist) (see recv_with_mrs)
         let mut msg = messages.prepare_in_out();
@@ -234,6 +263,38 @@ impl<T: IPCCapType, C: InvocationContext> LocalCPtr<T, C> {
         });
         (MessageInfo::from_inner(raw_msg_info), badge)
     }
+
+    #[sel4_cfg(KERNEL_MCS)]
+    pub fn nb_send_recv_with_mrs<U: IPCCapType, T: FastMessages>(
+        self,
+        info: MessageInfo,
+        messages: T,
+        src: LocalCPtr<U>,
+        reply_authority: impl ConveysReplyAuthority,
+    ) -> RecvWithMRs {
+        // NOTE(rustc_wishlist) (see Endpoint::recv_with_mrs)
+        let mut msg = messages.prepare_in_out();
+        let [ref mut mr0, ref mut mr1, ref mut mr2, ref mut mr3] = &mut msg;
+        let (raw_msg_info, badge) = self.invoke(|cptr, ipc_buffer| {
+            ipc_buffer.inner_mut().seL4_NBSendRecvWithMRs(
+                cptr.bits(),
+                info.into_inner(),
+                src.bits(),
+                Some(mr0),
+                Some(mr1),
+                Some(mr2),
+                Some(mr3),
+                reply_authority
+                    .into_reply_authority()
+                    .into_sys_reply_authority(),
+            )
+        });
+        RecvWithMRs {
+            info: MessageInfo::from_inner(raw_msg_info),
+            badge,
+            msg,
+        }
+    }
 }
 
 /// Corresponds to `seL4_Reply`.
@@ -251,7 +312,7 @@ pub fn r#yield() {
 
 const UNUSED_FOR_IN: Word = 0;
 
-/// The result of [`Endpoint::recv_with_mrs`].
+/// The result of [`Endpoint::recv_with_mrs`] and [`Endpoint::reply_recv_with_mrs`].
 pub struct RecvWithMRs {
     pub info: MessageInfo,
     pub badge: Badge,
@@ -346,6 +346,27 @@ impl seL4_IPCBuffer {
         ret
     }
 
+    pub fn seL4_ReplyRecvWithMRs(
+        &mut self,
+        src: seL4_CPtr,
+        msg_info: seL4_MessageInfo,
+        msg0: Option<&mut seL4_Word>,
+        msg1: Option<&mut seL4_Word>,
+        msg2: Option<&mut seL4_Word>,
+        msg3: Option<&mut seL4_Word>,
+        reply_authority: ReplyAuthority,
+    ) -> (seL4_MessageInfo, seL4_Word) {
+        seL4_ReplyRecvWithMRsWithoutIPCBuffer(
+            src,
+            msg_info,
+            msg0,
+            msg1,
+            msg2,
+            msg3,
+            reply_authority,
+        )
+    }
+
     sel4_cfg_if! {
         if #[cfg(KERNEL_MCS)] {
             pub fn seL4_NBSendRecv(
@@ -379,6 +400,29 @@ impl seL4_IPCBuffer {
                 ret
             }
 
+            pub fn seL4_NBSendRecvWithMRs(
+                &mut self,
+                dest: seL4_CPtr,
+                msg_info: seL4_MessageInfo,
+                src: seL4_CPtr,
+                msg0: Option<&mut seL4_Word>,
+                msg1: Option<&mut seL4_Word>,
+                msg2: Option<&mut seL4_Word>,
+                msg3: Option<&mut seL4_Word>,
+                reply_authority: ReplyAuthority,
+            ) -> (seL4_MessageInfo, seL4_Word) {
+                seL4_NBSendRecvWithMRsWithoutIPCBuffer(
+                    dest,
+                    msg_info,
+                    src,
+                    msg0,
+                    msg1,
+                    msg2,
+                    msg3,
+                    reply_authority,
+                )
+            }
+
             pub fn seL4_NBSendWait(
                 &mut self,
                 dest: seL4_CPtr,
@@ -596,6 +640,73 @@ pub fn seL4_CallWithMRsWithoutIPCBuffer(
     out_msg_info
 }
 
+pub fn seL4_ReplyRecvWithMRsWithoutIPCBuffer(
+    src: seL4_CPtr,
+    msg_info: seL4_MessageInfo,
+    msg0: Option<&mut seL4_Word>,
+    msg1: Option<&mut seL4_Word>,
+    msg2: Option<&mut seL4_Word>,
+    msg3: Option<&mut seL4_Word>,
+    reply_authority: ReplyAuthority,
+) -> (seL4_MessageInfo, seL4_Word) {
+    let mut mr0;
+    let mut mr1;
+    let mut mr2;
+    let mut mr3;
+
+    fill_mrs_from_args!(msg_info, mr0, mr1, mr2, mr3, msg0, msg1, msg2, msg3,);
+
+    let ret = sys_send_recv(
+        syscall_id::ReplyRecv,
+        src,
+        msg_info,
+        &mut mr0,
+        &mut mr1,
+        &mut mr2,
+        &mut mr3,
+        reply_authority_to_sys_arg(reply_authority),
+    );
+
+    empty_mrs_to_args!(mr0, mr1, mr2, mr3, msg0, msg1, msg2, msg3,);
+
+    ret
+}
+
+#[sel4_cfg(KERNEL_MCS)]
+pub fn seL4_NBSendRecvWithMRsWithoutIPCBuffer(
+    dest: seL4_CPtr,
+    msg_info: seL4_MessageInfo,
+    src: seL4_CPtr,
+    msg0: Option<&mut seL4_Word>,
+    msg1: Option<&mut seL4_Word>,
+    msg2: Option<&mut seL4_Word>,
+    msg3: Option<&mut seL4_Word>,
+    reply_authority: ReplyAuthority,
+) -> (seL4_MessageInfo, seL4_Word) {
+    let mut mr0;
+    let mut mr1;
+    let mut mr2;
+    let mut mr3;
+
+    fill_mrs_from_args!(msg_info, mr0, mr1, mr2, mr3, msg0, msg1, msg2, msg3,);
+
+    let ret = sys_nb_send_recv(
+        syscall_id::NBSendRecv,
+        dest,
+        src,
+        msg_info,
+        &mut mr0,
+        &mut mr1,
+        &mut mr2,
+        &mut mr3,
+        reply_authority_to_sys_arg(reply_authority),
+    );
+
+    empty_mrs_to_args!(mr0, mr1, mr2, mr3, msg0, msg1, msg2, msg3,);
+
+    ret
+}
+
 #[sel4_cfg(KERNEL_MCS)]
 pub fn seL4_WaitWithMRsWithoutIPCBuffer(
     src: seL4_CPtr,